@@ -0,0 +1,37 @@
+/* 图片排版 C 接口，实现见 src/ffi.rs */
+#ifndef IMAGE_TYPESETTING_TOOL_H
+#define IMAGE_TYPESETTING_TOOL_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct TypesetSession TypesetSession;
+
+/* 创建排版会话，options_json 可为 NULL；出错返回 NULL */
+TypesetSession *typeset_create_config(const char *options_json);
+
+/* 添加一张编码后的图片，成功返回 0，出错返回 -1 */
+int typeset_add_image(TypesetSession *session, const uint8_t *data, size_t len);
+
+/* 排版并返回页数，出错返回 -1 */
+int typeset_page_count(TypesetSession *session);
+
+/* 输出第 index 页，返回的内存需用 typeset_free_buffer 释放；出错返回 NULL */
+uint8_t *typeset_render_page(TypesetSession *session, size_t index, size_t *out_len);
+
+void typeset_free_buffer(uint8_t *data, size_t len);
+
+void typeset_destroy(TypesetSession *session);
+
+/* 当前线程最近一次出错的原因，无需释放 */
+const char *typeset_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,209 @@
+//! 图片调整
+//!
+//! 缩放前对图片做的裁边与色调调整，以及缩放后的锐化
+
+use image::{imageops, DynamicImage, GenericImageView, Rgba, Rgba32FImage, RgbaImage};
+
+use crate::depth;
+
+/// 锐化半径（单位：mm），约为 300 PPI 下 1 像素
+pub const SHARPEN_RADIUS: f64 = 0.1;
+
+/// 自动裁边时一行或一列中允许与边框颜色不同的像素比例，用于容忍扫描件上的灰尘
+const AUTOCROP_NOISE: f64 = 0.005;
+
+/// 自动裁边：裁去四周接近纯色的边框，边框颜色取左上角像素，透明像素也视为边框
+///
+/// 整张图片均为边框颜色时原样返回
+///
+/// - threshold: 与边框颜色的最大差异 0~1，按通道比较
+pub fn autocrop(image: DynamicImage, threshold: f32) -> DynamicImage {
+    let (w, h) = image.dimensions();
+    if w == 0 || h == 0 {
+        return image;
+    }
+    let rgba = image.to_rgba8();
+    let border = *rgba.get_pixel(0, 0);
+    let limit = (threshold.clamp(0.0, 1.0) * 255.0).round() as i16;
+    let is_border = |pixel: &Rgba<u8>| {
+        pixel.0[3] == 0
+            || pixel
+                .0
+                .iter()
+                .zip(border.0)
+                .all(|(&v, b)| (v as i16 - b as i16).abs() <= limit)
+    };
+    let (x0, y0, x1, y1) = content_bounds(&rgba, is_border);
+    match x1 > x0 && y1 > y0 {
+        true if (x1 - x0, y1 - y0) != (w, h) => image.crop_imm(x0, y0, x1 - x0, y1 - y0),
+        _ => image,
+    }
+}
+
+/// 内容区域的左上角与右下角（不含），逐行逐列从四周向内查找
+fn content_bounds(rgba: &RgbaImage, is_border: impl Fn(&Rgba<u8>) -> bool) -> (u32, u32, u32, u32) {
+    let (w, h) = rgba.dimensions();
+    let plain = |pixels: &mut dyn Iterator<Item = &Rgba<u8>>, len: u32| {
+        let noise = (len as f64 * AUTOCROP_NOISE) as usize;
+        pixels.filter(|pixel| !is_border(pixel)).count() <= noise
+    };
+    let row =
+        |y: u32, x0: u32, x1: u32| plain(&mut (x0..x1).map(|x| rgba.get_pixel(x, y)), x1 - x0);
+    let column =
+        |x: u32, y0: u32, y1: u32| plain(&mut (y0..y1).map(|y| rgba.get_pixel(x, y)), y1 - y0);
+    let y0 = (0..h).find(|&y| !row(y, 0, w)).unwrap_or(h);
+    let y1 = (y0..h).rev().find(|&y| !row(y, 0, w)).map_or(y0, |y| y + 1);
+    let x0 = (0..w).find(|&x| !column(x, y0, y1)).unwrap_or(w);
+    let x1 = (x0..w)
+        .rev()
+        .find(|&x| !column(x, y0, y1))
+        .map_or(x0, |x| x + 1);
+    (x0, y0, x1, y1)
+}
+
+/// 自动色阶忽略的最暗与最亮像素比例
+const AUTO_LEVELS_CLIP: f64 = 0.005;
+
+/// 色调调整：先自动色阶，再调整亮度与对比度，透明度不变
+///
+/// 16 位图片输出 16 位，其余输出 8 位；不做任何调整时原样返回
+///
+/// - brightness: 亮度 -100~100，0 为不调整
+/// - contrast: 对比度 -100~100，0 为不调整，-100 时为纯灰
+/// - auto_levels: 自动色阶，将最暗与最亮处拉伸到纯黑与纯白
+pub fn tone(
+    image: DynamicImage,
+    brightness: f32,
+    contrast: f32,
+    auto_levels: bool,
+) -> DynamicImage {
+    if brightness == 0.0 && contrast == 0.0 && !auto_levels {
+        return image;
+    }
+    let deep = depth::is_deep(&image);
+    let mut rgba = image.into_rgba32f();
+    let (low, high) = match auto_levels {
+        true => levels(&rgba),
+        false => (0.0, 1.0),
+    };
+    let (brightness, contrast) = (brightness / 100.0, 1.0 + contrast / 100.0);
+    for pixel in rgba.pixels_mut() {
+        for v in &mut pixel.0[..3] {
+            let leveled = (*v - low) / (high - low);
+            *v = ((leveled + brightness - 0.5) * contrast + 0.5).clamp(0.0, 1.0);
+        }
+    }
+    let image = DynamicImage::ImageRgba32F(rgba);
+    match deep {
+        true => DynamicImage::ImageRgba16(image.to_rgba16()),
+        false => DynamicImage::ImageRgba8(image.to_rgba8()),
+    }
+}
+
+/// 按亮度直方图求自动色阶的黑场与白场，忽略透明像素
+fn levels(rgba: &Rgba32FImage) -> (f32, f32) {
+    const BINS: usize = 1024;
+    let mut histogram = [0usize; BINS];
+    for pixel in rgba.pixels().filter(|pixel| pixel.0[3] > 0.0) {
+        let [r, g, b, _] = pixel.0;
+        let luma = (0.2126 * r + 0.7152 * g + 0.0722 * b).clamp(0.0, 1.0);
+        histogram[(luma * (BINS - 1) as f32).round() as usize] += 1;
+    }
+    let total: usize = histogram.iter().sum();
+    let clip = (total as f64 * AUTO_LEVELS_CLIP) as usize;
+    // 从两端累计，超过忽略比例处即为黑场与白场
+    let mut count = 0;
+    let low = histogram.iter().position(|&n| {
+        count += n;
+        count > clip
+    });
+    let mut count = 0;
+    let high = histogram.iter().rposition(|&n| {
+        count += n;
+        count > clip
+    });
+    let level = |bin: Option<usize>, default| bin.map_or(default, |i| i as f32 / (BINS - 1) as f32);
+    let (low, high) = (level(low, 0.0), level(high, 1.0));
+    // 几乎单色的图片不做拉伸
+    match high - low > 1.0 / 255.0 {
+        true => (low, high),
+        false => (0.0, 1.0),
+    }
+}
+
+/// USM 锐化：原图加上原图与模糊图之差乘以强度，透明度不变
+///
+/// 16 位图片输出 16 位，其余输出 8 位
+///
+/// - amount: 强度，0 为不处理
+/// - sigma: 高斯模糊半径 像素
+pub fn sharpen(image: DynamicImage, amount: f32, sigma: f32) -> DynamicImage {
+    if amount <= 0.0 || sigma <= 0.0 {
+        return image;
+    }
+    let deep = depth::is_deep(&image);
+    let mut rgba = image.into_rgba32f();
+    let blurred = imageops::blur(&rgba, sigma);
+    for (pixel, blur) in rgba.pixels_mut().zip(blurred.pixels()) {
+        for (v, b) in pixel.0[..3].iter_mut().zip(&blur.0[..3]) {
+            *v = (*v + amount * (*v - b)).clamp(0.0, 1.0);
+        }
+    }
+    let image = DynamicImage::ImageRgba32F(rgba);
+    match deep {
+        true => DynamicImage::ImageRgba16(image.to_rgba16()),
+        false => DynamicImage::ImageRgba8(image.to_rgba8()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_sharpen() {
+        // 灰色边缘两侧反差增大，平坦区域不变
+        let edge = RgbaImage::from_fn(8, 1, |x, _| match x < 4 {
+            true => Rgba([100, 100, 100, 255]),
+            false => Rgba([150, 150, 150, 255]),
+        });
+        let sharpened = sharpen(DynamicImage::ImageRgba8(edge), 1.0, 1.0).into_rgba8();
+        assert!(sharpened.get_pixel(3, 0).0[0] < 100);
+        assert!(sharpened.get_pixel(4, 0).0[0] > 150);
+        assert_eq!(sharpened.get_pixel(4, 0).0[3], 255);
+        let flat = DynamicImage::ImageRgba8(RgbaImage::from_pixel(4, 4, Rgba([90, 90, 90, 255])));
+        let sharpened = sharpen(flat, 1.0, 1.0).into_rgba8();
+        assert_eq!(sharpened.get_pixel(1, 1).0, [90, 90, 90, 255]);
+    }
+
+    #[test]
+    fn test_autocrop() {
+        // 白色边框中有一粒灰尘，内容为 4×3 的灰色区域
+        let mut scan = RgbaImage::from_pixel(400, 300, Rgba([250, 250, 250, 255]));
+        for (x, y) in (100..104).flat_map(|x| (50..53).map(move |y| (x, y))) {
+            scan.put_pixel(x, y, Rgba([80, 80, 80, 255]));
+        }
+        scan.put_pixel(300, 200, Rgba([0, 0, 0, 255]));
+        let cropped = autocrop(DynamicImage::ImageRgba8(scan), 0.1);
+        assert_eq!(cropped.dimensions(), (4, 3));
+        let blank = DynamicImage::ImageRgba8(RgbaImage::from_pixel(8, 8, Rgba([255; 4])));
+        assert_eq!(autocrop(blank, 0.1).dimensions(), (8, 8));
+    }
+
+    #[test]
+    fn test_tone() {
+        // 灰度范围 64~192 的图片自动色阶后拉伸到 0~255
+        let dim = RgbaImage::from_fn(2, 1, |x, _| match x {
+            0 => Rgba([64, 64, 64, 255]),
+            _ => Rgba([192, 192, 192, 255]),
+        });
+        let leveled = tone(DynamicImage::ImageRgba8(dim.clone()), 0.0, 0.0, true).into_rgba8();
+        assert_eq!(leveled.get_pixel(0, 0).0, [0, 0, 0, 255]);
+        assert_eq!(leveled.get_pixel(1, 0).0, [255, 255, 255, 255]);
+        let brighter = tone(DynamicImage::ImageRgba8(dim.clone()), 20.0, 0.0, false).into_rgba8();
+        assert_eq!(brighter.get_pixel(0, 0).0[0], 115);
+        let flat = tone(DynamicImage::ImageRgba8(dim), 0.0, -100.0, false).into_rgba8();
+        assert_eq!(flat.get_pixel(1, 0).0[0], 128);
+    }
+}
@@ -0,0 +1,248 @@
+//! 条形码
+//!
+//! 支持 Code 128 与 EAN-13，绘制在每张图片下方排版时预留的空间内
+
+use image::{imageops, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+
+use crate::{i18n::Msg, layout::Rect, Config, Error, InputSnafu};
+
+/// 条形码类型
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Symbology {
+    /// 任意 ASCII 文字，全为数字时自动压缩
+    #[default]
+    Code128,
+    /// 12 位数字，或含校验位的 13 位数字
+    Ean13,
+}
+
+/// 两侧空白的模块数
+const QUIET_ZONE: u32 = 10;
+
+/// Code 128 各码值的条空宽度，依次为条、空、条、空、条、空
+const CODE128: [&[u8; 6]; 106] = [
+    b"212222", b"222122", b"222221", b"121223", b"121322", b"131222", b"122213", b"122312",
+    b"132212", b"221213", b"221312", b"231212", b"112232", b"122132", b"122231", b"113222",
+    b"123122", b"123221", b"223211", b"221132", b"221231", b"213212", b"223112", b"312131",
+    b"311222", b"321122", b"321221", b"312212", b"322112", b"322211", b"212123", b"212321",
+    b"232121", b"111323", b"131123", b"131321", b"112313", b"132113", b"132311", b"211313",
+    b"231113", b"231311", b"112133", b"112331", b"132131", b"113123", b"113321", b"133121",
+    b"313121", b"211331", b"231131", b"213113", b"213311", b"213131", b"311123", b"311321",
+    b"331121", b"312113", b"312311", b"332111", b"314111", b"221411", b"431111", b"111224",
+    b"111422", b"121124", b"121421", b"141122", b"141221", b"112214", b"112412", b"122114",
+    b"122411", b"142112", b"142211", b"241211", b"221114", b"413111", b"241112", b"134111",
+    b"111242", b"121142", b"121241", b"114212", b"124112", b"124211", b"411212", b"421112",
+    b"421211", b"212141", b"214121", b"412121", b"111143", b"111341", b"131141", b"114113",
+    b"114311", b"411113", b"411311", b"113141", b"114131", b"311141", b"411131", b"211412",
+    b"211214", b"211232",
+];
+
+/// Code 128 终止符
+const CODE128_STOP: &[u8; 7] = b"2331112";
+
+/// Code 128 B 组起始符
+const START_B: usize = 104;
+
+/// Code 128 C 组起始符
+const START_C: usize = 105;
+
+/// EAN 左侧奇校验（L）编码，右侧（R）为其反色，偶校验（G）为 R 的逆序
+const EAN_L: [&[u8; 7]; 10] = [
+    b"0001101", b"0011001", b"0010011", b"0111101", b"0100011", b"0110001", b"0101111", b"0111011",
+    b"0110111", b"0001011",
+];
+
+/// EAN-13 首位数字决定左侧六位使用 L 或 G 编码，1 为 G
+const EAN_PARITY: [u8; 10] = [
+    0b000000, 0b001011, 0b001101, 0b001110, 0b010011, 0b011001, 0b011100, 0b010101, 0b010110,
+    0b011010,
+];
+
+/// 编码为模块序列，true 为条，不含两侧空白
+pub fn encode(value: &str, symbology: Symbology) -> Result<Vec<bool>, Error> {
+    match symbology {
+        Symbology::Code128 => code128(value),
+        Symbology::Ean13 => ean13(value),
+    }
+}
+
+fn code128(value: &str) -> Result<Vec<bool>, Error> {
+    ensure!(
+        !value.is_empty() && value.bytes().all(|b| (32..127).contains(&b)),
+        InputSnafu {
+            reason: Msg::Code128Invalid(value).to_string(),
+        }
+    );
+    // 偶数位纯数字使用 C 组，每两位数字一个码值
+    let digits = value.len() >= 4
+        && value.len().is_multiple_of(2)
+        && value.bytes().all(|b| b.is_ascii_digit());
+    let mut codes = match digits {
+        true => {
+            let mut codes = vec![START_C];
+            for pair in value.as_bytes().chunks(2) {
+                codes.push(((pair[0] - b'0') * 10 + (pair[1] - b'0')) as usize);
+            }
+            codes
+        }
+        false => std::iter::once(START_B)
+            .chain(value.bytes().map(|b| (b - 32) as usize))
+            .collect(),
+    };
+    let checksum = codes
+        .iter()
+        .enumerate()
+        .map(|(i, &code)| i.max(1) * code)
+        .sum::<usize>()
+        % 103;
+    codes.push(checksum);
+    let mut modules = Vec::new();
+    for widths in codes
+        .iter()
+        .map(|&code| &CODE128[code][..])
+        .chain([&CODE128_STOP[..]])
+    {
+        for (i, width) in widths.iter().enumerate() {
+            modules.extend(std::iter::repeat_n(i % 2 == 0, (width - b'0') as usize));
+        }
+    }
+    Ok(modules)
+}
+
+fn ean13(value: &str) -> Result<Vec<bool>, Error> {
+    let digits: Vec<u8> = value.bytes().map(|b| b.wrapping_sub(b'0')).collect();
+    ensure!(
+        matches!(digits.len(), 12 | 13) && digits.iter().all(|&d| d < 10),
+        InputSnafu {
+            reason: Msg::Ean13Digits(value).to_string(),
+        }
+    );
+    let check = ean_check_digit(&digits[..12]);
+    ensure!(
+        digits.len() == 12 || digits[12] == check,
+        InputSnafu {
+            reason: Msg::Ean13Check { value, check }.to_string(),
+        }
+    );
+    let digits: Vec<u8> = digits[..12].iter().copied().chain([check]).collect();
+    let bits = |pattern: &[u8]| pattern.iter().map(|&b| b == b'1').collect::<Vec<_>>();
+    let mut modules = bits(b"101");
+    let parity = EAN_PARITY[digits[0] as usize];
+    for (i, &d) in digits[1..7].iter().enumerate() {
+        let l = bits(EAN_L[d as usize]);
+        match (parity >> (5 - i)) & 1 {
+            0 => modules.extend(l),
+            _ => modules.extend(l.iter().rev().map(|&bar| !bar)),
+        }
+    }
+    modules.extend(bits(b"01010"));
+    for &d in &digits[7..] {
+        modules.extend(bits(EAN_L[d as usize]).iter().map(|&bar| !bar));
+    }
+    modules.extend(bits(b"101"));
+    Ok(modules)
+}
+
+/// EAN 校验位
+fn ean_check_digit(digits: &[u8]) -> u8 {
+    let sum: u32 = digits
+        .iter()
+        .enumerate()
+        .map(|(i, &d)| d as u32 * if i % 2 == 0 { 1 } else { 3 })
+        .sum();
+    ((10 - sum % 10) % 10) as u8
+}
+
+/// 绘制为不超过 max_w × h 像素的图像，含两侧空白，模块按整数像素对齐
+///
+/// max_w 小于模块数时每个模块占 1 像素，图像随之变宽
+pub fn render(modules: &[bool], max_w: u32, h: u32) -> RgbaImage {
+    let n = modules.len() as u32 + 2 * QUIET_ZONE;
+    let scale = (max_w / n).max(1);
+    let mut image = RgbaImage::from_pixel(n * scale, h.max(1), Rgba([255, 255, 255, 255]));
+    for (i, _) in modules.iter().enumerate().filter(|(_, &bar)| bar) {
+        let x = (QUIET_ZONE + i as u32) * scale;
+        for dx in 0..scale {
+            for y in 0..h {
+                image.put_pixel(x + dx, y, Rgba([0, 0, 0, 255]));
+            }
+        }
+    }
+    image
+}
+
+/// 在图片下方预留的空间内绘制条形码，位于说明文字之下，水平居中于图片
+///
+/// - rect: 图片加上单元格样式装饰后的区域
+pub fn draw(
+    canvas: &mut RgbaImage,
+    value: &str,
+    symbology: Symbology,
+    config: &Config,
+    rect: Rect,
+) -> Result<(), Error> {
+    if config.barcode_h_px == 0 {
+        return Ok(());
+    }
+    let barcode = render(&encode(value, symbology)?, rect.w, config.barcode_h_px);
+    let x = rect.x as i64 + (rect.w as i64 - barcode.width() as i64) / 2;
+    let y = rect.y + rect.h + config.caption_h_px + config.barcode_gap_px();
+    imageops::overlay(canvas, &barcode, x, y as i64);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 模块序列转为条空宽度
+    fn widths(modules: &[bool]) -> String {
+        let mut widths = String::new();
+        let mut run = 1;
+        for i in 1..=modules.len() {
+            if i < modules.len() && modules[i] == modules[i - 1] {
+                run += 1;
+            } else {
+                widths.push_str(&run.to_string());
+                run = 1;
+            }
+        }
+        widths
+    }
+
+    #[test]
+    fn test_code128() {
+        assert!(CODE128
+            .iter()
+            .all(|w| w.iter().map(|b| (b - b'0') as u32).sum::<u32>() == 11));
+        // 起始符 B、`A`（33）、校验值 (104 + 33) % 103 = 34、终止符
+        let modules = encode("A", Symbology::Code128).unwrap();
+        assert_eq!(modules.len(), 11 * 3 + 13);
+        assert_eq!(widths(&modules), "2112141113231311232331112");
+        // 纯数字使用 C 组：起始符 C、12、34、校验值 (105 + 12 + 2×34) % 103 = 82
+        let modules = encode("1234", Symbology::Code128).unwrap();
+        assert_eq!(widths(&modules[..11]), "211232");
+        assert_eq!(widths(&modules[33..44]), "121241");
+        assert!(encode("照片", Symbology::Code128).is_err());
+    }
+
+    #[test]
+    fn test_ean13() {
+        assert_eq!(ean_check_digit(&[4, 0, 0, 6, 3, 8, 1, 3, 3, 3, 9, 3]), 1);
+        let modules = encode("400638133393", Symbology::Ean13).unwrap();
+        assert_eq!(modules, encode("4006381333931", Symbology::Ean13).unwrap());
+        assert_eq!(modules.len(), 95);
+        // 首位 4 的校验模式为 LGLLGG，第二位 0 使用 L 编码
+        assert_eq!(widths(&modules[3..10]), "3211");
+        assert!(encode("4006381333932", Symbology::Ean13).is_err());
+        assert!(encode("12345", Symbology::Ean13).is_err());
+
+        let image = render(&modules, 230, 20);
+        assert_eq!(image.dimensions(), (230, 20));
+        assert_eq!(*image.get_pixel(19, 10), Rgba([255, 255, 255, 255]));
+        assert_eq!(*image.get_pixel(20, 10), Rgba([0, 0, 0, 255]));
+    }
+}
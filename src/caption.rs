@@ -0,0 +1,423 @@
+//! 图片说明文字
+//!
+//! 说明文字绘制在每张图片下方，排版时按字号与行数在单元格内预留空间
+
+use image::{Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+use std::{collections::HashMap, fmt, fs, path::Path, str::FromStr};
+
+use crate::{
+    i18n::Msg,
+    layout::Rect,
+    metadata::{PhotoInfo, EXIF_FIELDS},
+    text::Font,
+    Config, Error, IoSnafu,
+};
+
+/// 说明文字颜色
+const CAPTION_COLOR: Rgba<u8> = Rgba([40, 40, 40, 255]);
+
+/// 叠加在图片上时的文字颜色
+const OVERLAY_COLOR: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+/// 叠加在图片上时文字衬底的不透明度
+const OVERLAY_SHADE: f32 = 0.45;
+
+/// `exif` 未指定模板时使用的模板
+const DEFAULT_EXIF_TEMPLATE: &str = "{date} {camera} {exposure}";
+
+/// 行高与字号之比
+pub const LINE_HEIGHT: f64 = 1.25;
+
+/// 说明文字与图片之间的间距与字号之比
+pub const CAPTION_GAP: f64 = 0.3;
+
+/// 说明文字来源
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum CaptionSource {
+    /// 源文件名
+    Filename,
+    /// EXIF 信息模板，如 `{datetime} {camera}`，字段见 [`EXIF_FIELDS`]，另可用 `{filename}`
+    Exif(String),
+}
+
+impl CaptionSource {
+    /// 生成图片的说明文字
+    pub fn text(&self, path: &Path) -> String {
+        let filename = || {
+            path.file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned()
+        };
+        match self {
+            CaptionSource::Filename => filename(),
+            CaptionSource::Exif(template) => {
+                let info = PhotoInfo::open(path);
+                expand(template, |name| match name {
+                    "filename" => Some(filename()),
+                    _ => info.as_ref()?.field(name),
+                })
+            }
+        }
+    }
+}
+
+/// 模板中的字段名，括号不匹配或字段未知时返回错误
+fn template_fields(template: &str) -> Result<Vec<&str>, String> {
+    let mut fields = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| Msg::CaptionBraces(template).to_string())?;
+        let name = &rest[start + 1..start + end];
+        if name != "filename" && !EXIF_FIELDS.contains(&name) {
+            return Err(Msg::CaptionFieldUnknown {
+                field: name,
+                available: EXIF_FIELDS,
+            }
+            .to_string());
+        }
+        fields.push(name);
+        rest = &rest[start + end + 1..];
+    }
+    Ok(fields)
+}
+
+/// 展开模板中的 `{字段}`，缺少的字段为空，并去掉首尾多余的分隔符与空白
+///
+/// - lookup: 按字段名取值
+fn expand(template: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let mut text = template.to_string();
+    for name in template_fields(template).unwrap_or_default() {
+        let value = lookup(name).unwrap_or_default();
+        text = text.replacen(&format!("{{{}}}", name), &value, 1);
+    }
+    text.trim_matches(|c: char| c.is_whitespace() || matches!(c, ',' | '，' | '|' | '-'))
+        .to_string()
+}
+
+impl FromStr for CaptionSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s == "filename" {
+            return Ok(CaptionSource::Filename);
+        }
+        let template = match s.strip_prefix("exif") {
+            Some("") => DEFAULT_EXIF_TEMPLATE,
+            Some(rest) => rest.strip_prefix(':').unwrap_or("").trim(),
+            None => "",
+        };
+        if template.is_empty() {
+            return Err(Msg::CaptionSourceInvalid(s).to_string());
+        }
+        template_fields(template)?;
+        Ok(CaptionSource::Exif(template.to_string()))
+    }
+}
+
+impl fmt::Display for CaptionSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptionSource::Filename => write!(f, "filename"),
+            CaptionSource::Exif(template) => write!(f, "exif:{}", template),
+        }
+    }
+}
+
+/// 说明文字位置
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CaptionPosition {
+    /// 图片下方，排版时预留空间
+    #[default]
+    Below,
+    /// 叠加在图片底部，带半透明衬底
+    Over,
+}
+
+impl TryFrom<String> for CaptionSource {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<CaptionSource> for String {
+    fn from(source: CaptionSource) -> String {
+        source.to_string()
+    }
+}
+
+/// 说明文字文件
+///
+/// CSV 每行 `文件名,说明文字`，扩展名为 `.tsv` 时以制表符分隔；
+/// 说明文字可用引号包含换行，或写作 `\n`。`#` 开头为注释，首行可为表头
+#[derive(Debug, Default)]
+pub struct CaptionFile {
+    captions: HashMap<String, String>,
+}
+
+impl CaptionFile {
+    pub fn load(path: &Path) -> Result<CaptionFile, Error> {
+        let text = fs::read_to_string(path).context(IoSnafu)?;
+        let tsv = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("tsv"));
+        CaptionFile::parse(&text, if tsv { b'\t' } else { b',' })
+    }
+
+    /// 解析说明文字
+    ///
+    /// - delimiter: 分隔符
+    pub fn parse(text: &str, delimiter: u8) -> Result<CaptionFile, Error> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .comment(Some(b'#'))
+            .delimiter(delimiter)
+            .from_reader(text.as_bytes());
+        let mut captions = HashMap::new();
+        for (i, record) in reader.records().enumerate() {
+            let record = record.map_err(|e| Error::Input {
+                reason: Msg::CaptionFileInvalid(e.to_string()).to_string(),
+            })?;
+            let name = record.get(0).unwrap_or_default().trim();
+            let header = ["filename", "file", "文件名"].contains(&name.to_lowercase().as_str());
+            if name.is_empty() || (i == 0 && header) {
+                continue;
+            }
+            // 未加引号的说明文字中的分隔符会被拆成多列，重新拼接
+            let caption = record
+                .iter()
+                .skip(1)
+                .collect::<Vec<_>>()
+                .join(&(delimiter as char).to_string());
+            captions.insert(name.to_string(), caption.trim().replace("\\n", "\n"));
+        }
+        Ok(CaptionFile { captions })
+    }
+
+    /// 按文件名查找说明文字
+    pub fn caption_for(&self, path: &Path) -> Option<&str> {
+        let name = path.file_name()?.to_str()?;
+        self.captions.get(name).map(String::as_str)
+    }
+}
+
+/// 说明文字设置
+#[derive(Debug, Default)]
+pub struct Captions {
+    /// 说明文字来源
+    pub source: Option<CaptionSource>,
+    /// 说明文字文件，其中的条目优先于来源
+    pub file: Option<CaptionFile>,
+    /// 说明文字位置
+    pub position: CaptionPosition,
+}
+
+impl Captions {
+    /// 图片的说明文字，没有时返回 None
+    pub fn text(&self, path: &Path) -> Option<String> {
+        self.file
+            .as_ref()
+            .and_then(|file| file.caption_for(path))
+            .map(str::to_string)
+            .or_else(|| self.source.as_ref().map(|source| source.text(path)))
+    }
+}
+
+/// 按宽度折行，优先在空格处断开，超出行数时截断并以省略号结尾
+///
+/// - size: 字号 像素
+/// - max_w: 最大行宽 像素
+/// - max_lines: 最多行数
+pub fn wrap(font: &Font, text: &str, size: f32, max_w: u32, max_lines: usize) -> Vec<String> {
+    let fits = |line: &str| font.measure(line, size).0 <= max_w;
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for c in paragraph.chars() {
+            line.push(c);
+            if fits(&line) || line.chars().count() == 1 {
+                continue;
+            }
+            line.pop();
+            let next = match line.rfind(' ') {
+                Some(i) if i > 0 => {
+                    let tail = line[i + 1..].to_string();
+                    line.truncate(i);
+                    tail
+                }
+                _ => String::new(),
+            };
+            lines.push(std::mem::replace(&mut line, next));
+            if c != ' ' || !line.is_empty() {
+                line.push(c);
+            }
+        }
+        lines.push(line);
+    }
+    if lines.len() > max_lines.max(1) {
+        lines.truncate(max_lines.max(1));
+        let last = lines.last_mut().unwrap();
+        while !last.is_empty() && !fits(&format!("{}…", last)) {
+            last.pop();
+        }
+        last.push('…');
+    }
+    lines
+}
+
+/// 绘制说明文字
+///
+/// 位于图片下方时相对图片水平居中，不超出单元格，超出单元格宽度时折行；
+/// 叠加在图片上时在图片底部加半透明衬底，按图片宽度折行
+///
+/// - rect: 图片实际绘制区域
+/// - cell: 图片所在单元格区域
+pub fn draw(
+    canvas: &mut RgbaImage,
+    font: &Font,
+    text: &str,
+    config: &Config,
+    position: CaptionPosition,
+    rect: Rect,
+    cell: Rect,
+) {
+    let size = config.caption_size_px as f32;
+    if size <= 0.0 || text.is_empty() {
+        return;
+    }
+    let line_h = size as f64 * LINE_HEIGHT;
+    let gap = (size as f64 * CAPTION_GAP).round() as u32;
+    let max_lines = config.caption_lines as usize;
+    let (lines, top, bounds, color) = match position {
+        // 单元格样式的装饰内有说明文字的位置时，在装饰下边内垂直居中
+        CaptionPosition::Below if config.style.holds_caption() => {
+            let outer = config.outer_rect(rect);
+            let lines = wrap(font, text, size, outer.w, max_lines);
+            let block_h = (lines.len() as f64 * line_h).round() as u32;
+            let top = rect.y + rect.h + config.insets.bottom.saturating_sub(block_h) / 2;
+            (lines, top, outer, CAPTION_COLOR)
+        }
+        CaptionPosition::Below => {
+            let lines = wrap(font, text, size, cell.w, max_lines);
+            (lines, rect.y + rect.h + gap, cell, CAPTION_COLOR)
+        }
+        CaptionPosition::Over => {
+            let lines = wrap(font, text, size, rect.w.saturating_sub(2 * gap), max_lines);
+            let block_h = ((lines.len() as f64 * line_h).round() as u32 + 2 * gap).min(rect.h);
+            let top = rect.y + rect.h - block_h;
+            shade(
+                canvas,
+                Rect {
+                    y: top,
+                    h: block_h,
+                    ..rect
+                },
+            );
+            (lines, top + gap, rect, OVERLAY_COLOR)
+        }
+    };
+    for (i, line) in lines.iter().enumerate() {
+        let (w, _) = font.measure(line, size);
+        let center = rect.x + rect.w / 2;
+        let x = center.saturating_sub(w / 2).clamp(
+            bounds.x,
+            (bounds.x + bounds.w).saturating_sub(w).max(bounds.x),
+        );
+        let y = top + (i as f64 * line_h).round() as u32;
+        font.draw(canvas, line, x as i32, y as i32, size, color);
+    }
+}
+
+/// 将区域调暗，作为叠加文字的衬底
+fn shade(canvas: &mut RgbaImage, area: Rect) {
+    let x_end = (area.x + area.w).min(canvas.width());
+    let y_end = (area.y + area.h).min(canvas.height());
+    for y in area.y..y_end {
+        for x in area.x..x_end {
+            let pixel = canvas.get_pixel_mut(x, y);
+            for c in &mut pixel.0[..3] {
+                *c = (*c as f32 * (1.0 - OVERLAY_SHADE)).round() as u8;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_caption_source() {
+        let source: CaptionSource = "filename".parse().unwrap();
+        assert_eq!(source.text(Path::new("a/b/IMG_01.jpg")), "IMG_01.jpg");
+        assert!("title".parse::<CaptionSource>().is_err());
+        assert_eq!(
+            "exif:{datetime},{camera}".parse(),
+            Ok(CaptionSource::Exif("{datetime},{camera}".to_string()))
+        );
+        assert_eq!(
+            "exif".parse::<CaptionSource>().unwrap().to_string(),
+            format!("exif:{}", DEFAULT_EXIF_TEMPLATE)
+        );
+        assert!("exif:{shutter".parse::<CaptionSource>().is_err());
+        assert!("exif:{model}".parse::<CaptionSource>().is_err());
+    }
+
+    #[test]
+    fn test_expand() {
+        let lookup = |name: &str| match name {
+            "camera" => Some("X100V".to_string()),
+            _ => None,
+        };
+        assert_eq!(expand("{datetime}, {camera}", lookup), "X100V");
+        assert_eq!(expand("{camera} | {iso}", lookup), "X100V");
+        assert_eq!(expand("Shot on {camera}!", lookup), "Shot on X100V!");
+        assert_eq!(expand("{date}", lookup), "");
+    }
+
+    #[test]
+    fn test_caption_file() {
+        let text = "filename,caption\n# 注释\na.jpg,Rosa canina\nb.jpg,\"Quercus, robur\"\nc.jpg,line one\\nline two\n";
+        let file = CaptionFile::parse(text, b',').unwrap();
+        assert_eq!(file.caption_for(Path::new("in/a.jpg")), Some("Rosa canina"));
+        assert_eq!(file.caption_for(Path::new("b.jpg")), Some("Quercus, robur"));
+        assert_eq!(
+            file.caption_for(Path::new("c.jpg")),
+            Some("line one\nline two")
+        );
+        assert_eq!(file.caption_for(Path::new("filename")), None);
+
+        let captions = Captions {
+            source: Some(CaptionSource::Filename),
+            file: Some(CaptionFile::parse("a.jpg\tRose\n", b'\t').unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(captions.text(Path::new("a.jpg")).as_deref(), Some("Rose"));
+        assert_eq!(captions.text(Path::new("d.jpg")).as_deref(), Some("d.jpg"));
+        assert_eq!(Captions::default().text(Path::new("d.jpg")), None);
+    }
+
+    #[test]
+    fn test_wrap() {
+        let Ok(Some(font)) = Font::load(None) else {
+            return;
+        };
+        let (w, _) = font.measure("alpha beta", 20.0);
+        let lines = wrap(&font, "alpha beta gamma", 20.0, w, 3);
+        assert_eq!(lines, vec!["alpha beta", "gamma"]);
+        let lines = wrap(&font, "alpha beta gamma delta epsilon", 20.0, w, 2);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].ends_with('…'));
+        assert!(lines.iter().all(|line| font.measure(line, 20.0).0 <= w));
+    }
+}
@@ -0,0 +1,58 @@
+//! 印在图片旁的编码内容
+//!
+//! 二维码等编码的内容取自文件名，或按文件名从 CSV 中查找
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::caption::CaptionFile;
+
+/// 编码内容来源
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CodeSource {
+    /// 不含扩展名的文件名
+    #[default]
+    FromFilename,
+    /// CSV 文件中每行 `文件名,内容`
+    FromCsv,
+}
+
+/// 编码内容设置
+#[derive(Debug, Default)]
+pub struct CodeValues {
+    pub source: CodeSource,
+    /// 来源为 CSV 时的内容文件，格式同说明文字文件
+    pub file: Option<CaptionFile>,
+}
+
+impl CodeValues {
+    /// 图片对应的编码内容，CSV 中没有该图片时返回 None
+    pub fn value(&self, path: &Path) -> Option<String> {
+        match self.source {
+            CodeSource::FromFilename => path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned()),
+            CodeSource::FromCsv => self.file.as_ref()?.caption_for(path).map(str::to_string),
+        }
+        .filter(|value| !value.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_value() {
+        let path = Path::new("photos/IMG_0001.jpg");
+        let values = CodeValues::default();
+        assert_eq!(values.value(path).as_deref(), Some("IMG_0001"));
+        let values = CodeValues {
+            source: CodeSource::FromCsv,
+            file: Some(CaptionFile::parse("IMG_0001.jpg,https://example.com/1\n", b',').unwrap()),
+        };
+        assert_eq!(values.value(path).as_deref(), Some("https://example.com/1"));
+        assert_eq!(values.value(Path::new("other.jpg")), None);
+    }
+}
@@ -0,0 +1,110 @@
+use image::Rgba;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+use crate::i18n::Msg;
+
+/// 颜色
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Color(pub Rgba<u8>);
+
+/// 预设颜色名称
+const NAMED: &[(&str, [u8; 3])] = &[
+    ("white", [255, 255, 255]),
+    ("black", [0, 0, 0]),
+    ("gray", [128, 128, 128]),
+    ("grey", [128, 128, 128]),
+    ("lightgray", [211, 211, 211]),
+    ("red", [255, 0, 0]),
+    ("green", [0, 128, 0]),
+    ("blue", [0, 0, 255]),
+    ("yellow", [255, 255, 0]),
+];
+
+impl Color {
+    pub const WHITE: Color = Color(Rgba([255, 255, 255, 255]));
+    pub const BLACK: Color = Color(Rgba([0, 0, 0, 255]));
+    pub const TRANSPARENT: Color = Color(Rgba([0, 0, 0, 0]));
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [r, g, b, a] = self.0 .0;
+        match a {
+            255 => write!(f, "#{:02x}{:02x}{:02x}", r, g, b),
+            _ => write!(f, "#{:02x}{:02x}{:02x}{:02x}", r, g, b, a),
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    /// 解析颜色，支持名称、`transparent`、`#rgb`、`#rrggbb` 与带不透明度的 `#rrggbbaa`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_lowercase();
+        if s == "transparent" {
+            return Ok(Color::TRANSPARENT);
+        }
+        if let Some(&(_, [r, g, b])) = NAMED.iter().find(|(name, _)| *name == s) {
+            return Ok(Color(Rgba([r, g, b, 255])));
+        }
+        let hex = s.strip_prefix('#').unwrap_or(&s);
+        let digits: Option<Vec<u8>> = hex
+            .chars()
+            .map(|c| c.to_digit(16).map(|d| d as u8))
+            .collect();
+        let channels = match (digits, hex.len()) {
+            (Some(d), 3) => Some([d[0] * 17, d[1] * 17, d[2] * 17, 255]),
+            (Some(d), 6) => Some([d[0] * 16 + d[1], d[2] * 16 + d[3], d[4] * 16 + d[5], 255]),
+            (Some(d), 8) => Some([
+                d[0] * 16 + d[1],
+                d[2] * 16 + d[3],
+                d[4] * 16 + d[5],
+                d[6] * 16 + d[7],
+            ]),
+            _ => None,
+        };
+        channels
+            .map(|c| Color(Rgba(c)))
+            .ok_or_else(|| Msg::ColorInvalid(&s).to_string())
+    }
+}
+
+impl From<Color> for String {
+    fn from(color: Color) -> Self {
+        color.to_string()
+    }
+}
+
+impl TryFrom<String> for Color {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!("White".parse::<Color>(), Ok(Color::WHITE));
+        assert_eq!("#f00".parse::<Color>(), Ok(Color(Rgba([255, 0, 0, 255]))));
+        assert_eq!(
+            "1e90ff".parse::<Color>(),
+            Ok(Color(Rgba([30, 144, 255, 255])))
+        );
+        assert_eq!(
+            "#00000080".parse::<Color>(),
+            Ok(Color(Rgba([0, 0, 0, 128])))
+        );
+        assert_eq!("transparent".parse::<Color>(), Ok(Color::TRANSPARENT));
+        assert!("#12345".parse::<Color>().is_err());
+        assert!("teal-ish".parse::<Color>().is_err());
+        assert_eq!(Color(Rgba([1, 2, 3, 255])).to_string(), "#010203");
+    }
+}
@@ -0,0 +1,281 @@
+//! 灰度、黑白与 CMYK 输出
+//!
+//! 绘制完成的页面合成到白色背景后转换为灰度，黑白输出再经抖动转换为 1 位；
+//! CMYK 输出按印刷机构提供的 ICC 配置文件分色，没有配置文件时按简单公式分色
+
+use image::{DynamicImage, GrayImage, ImageBuffer, Luma, Rgba};
+use moxcms::{ColorProfile, DataColorSpace, Layout, TransformOptions};
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+use std::{
+    fs,
+    io::{Cursor, Write},
+    path::Path,
+};
+use tiff::encoder::{colortype, Rational, TiffEncoder};
+
+use crate::{depth, flatten, i18n::Msg, icc, icc::Profile, Error, InputSnafu, IoSnafu};
+
+/// CMYK 图像，四个通道依次为 C、M、Y、K，0 为无墨
+pub type CmykImage = ImageBuffer<Rgba<u8>, Vec<u8>>;
+
+/// 输出色彩
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Colorspace {
+    /// 彩色
+    #[default]
+    Rgb,
+    /// 灰度
+    Gray,
+    /// 1 位黑白
+    Bilevel,
+    /// CMYK，仅支持 tiff 格式
+    Cmyk,
+}
+
+/// 黑白输出的抖动方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Dither {
+    /// Floyd–Steinberg 误差扩散，层次细腻
+    #[default]
+    FloydSteinberg,
+    /// 8×8 Bayer 有序抖动，网点规则，适合激光打印机
+    Ordered,
+    /// 不抖动，按 50% 灰度阈值二值化
+    #[value(name = "none")]
+    #[serde(rename = "none")]
+    Threshold,
+}
+
+/// 8×8 Bayer 矩阵
+const BAYER: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+/// 合成到白色背景后转换为灰度，16 位页面输出 16 位灰度
+pub fn to_gray(page: &DynamicImage) -> DynamicImage {
+    let rgba = page.to_rgba32f();
+    let luma = |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+        (y * a + 1.0 - a).clamp(0.0, 1.0)
+    };
+    let (w, h) = rgba.dimensions();
+    match depth::is_deep(page) {
+        true => DynamicImage::ImageLuma16(ImageBuffer::from_fn(w, h, |x, y| {
+            Luma([(luma(x, y) * u16::MAX as f32).round() as u16])
+        })),
+        false => DynamicImage::ImageLuma8(GrayImage::from_fn(w, h, |x, y| {
+            Luma([(luma(x, y) * u8::MAX as f32).round() as u8])
+        })),
+    }
+}
+
+/// 灰度图抖动为黑白，像素值为 0 或 255
+pub fn to_bilevel(gray: &GrayImage, dither: Dither) -> GrayImage {
+    let (w, h) = gray.dimensions();
+    match dither {
+        Dither::Threshold => {
+            GrayImage::from_fn(w, h, |x, y| Luma([bit(gray.get_pixel(x, y).0[0] >= 128)]))
+        }
+        Dither::Ordered => GrayImage::from_fn(w, h, |x, y| {
+            let threshold = BAYER[(y % 8) as usize][(x % 8) as usize] as u32 * 4 + 2;
+            Luma([bit(gray.get_pixel(x, y).0[0] as u32 >= threshold)])
+        }),
+        Dither::FloydSteinberg => {
+            let mut out = GrayImage::new(w, h);
+            // 当前行与下一行累积的误差
+            let (mut current, mut next) =
+                (vec![0.0f32; w as usize + 2], vec![0.0f32; w as usize + 2]);
+            for y in 0..h {
+                for x in 0..w {
+                    let i = x as usize + 1;
+                    let value = gray.get_pixel(x, y).0[0] as f32 + current[i];
+                    let white = value >= 128.0;
+                    out.put_pixel(x, y, Luma([bit(white)]));
+                    let error = value - if white { 255.0 } else { 0.0 };
+                    current[i + 1] += error * 7.0 / 16.0;
+                    next[i - 1] += error * 3.0 / 16.0;
+                    next[i] += error * 5.0 / 16.0;
+                    next[i + 1] += error / 16.0;
+                }
+                std::mem::swap(&mut current, &mut next);
+                next.fill(0.0);
+            }
+            out
+        }
+    }
+}
+
+fn bit(white: bool) -> u8 {
+    match white {
+        true => 255,
+        false => 0,
+    }
+}
+
+/// 以 1 位灰度 PNG 写出黑白图像
+pub fn write_bilevel_png(image: &GrayImage, writer: impl Write) -> Result<(), Error> {
+    let png_error = |e: png::EncodingError| Error::Io {
+        source: std::io::Error::other(e),
+    };
+    let mut encoder = png::Encoder::new(writer, image.width(), image.height());
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::One);
+    let mut writer = encoder.write_header().map_err(png_error)?;
+    // 每行按位打包，白为 1
+    let row_bytes = image.width().div_ceil(8) as usize;
+    let mut data = vec![0u8; row_bytes * image.height() as usize];
+    for (x, y, pixel) in image.enumerate_pixels() {
+        if pixel.0[0] > 0 {
+            data[y as usize * row_bytes + x as usize / 8] |= 0x80 >> (x % 8);
+        }
+    }
+    writer.write_image_data(&data).map_err(png_error)?;
+    writer.finish().map_err(png_error)
+}
+
+/// 读取 CMYK 输出配置文件
+pub fn read_cmyk_profile(path: &Path) -> Result<Vec<u8>, Error> {
+    let icc = fs::read(path).context(IoSnafu)?;
+    let is_cmyk = ColorProfile::new_from_slice(&icc)
+        .is_ok_and(|profile| profile.color_space == DataColorSpace::Cmyk);
+    ensure!(
+        is_cmyk,
+        InputSnafu {
+            reason: Msg::NotCmykProfile(&path.display().to_string()).to_string(),
+        }
+    );
+    Ok(icc)
+}
+
+/// 合成到白色背景后分色为 CMYK
+///
+/// - source: 页面所用的色彩配置文件，None 视为 sRGB
+/// - target: CMYK 配置文件，None 时按 K = 1 - max(R, G, B) 分色
+pub fn to_cmyk(
+    page: &DynamicImage,
+    source: Option<Profile>,
+    target: Option<&[u8]>,
+) -> Result<CmykImage, Error> {
+    let rgb = flatten(&page.to_rgba8());
+    let (w, h) = rgb.dimensions();
+    let Some(target) = target else {
+        return Ok(CmykImage::from_fn(w, h, |x, y| {
+            let [r, g, b] = rgb.get_pixel(x, y).0.map(|v| v as f32 / 255.0);
+            let k = 1.0 - r.max(g).max(b);
+            let ink = |v: f32| match k < 1.0 {
+                true => ((1.0 - v - k) / (1.0 - k) * 255.0).round() as u8,
+                false => 0,
+            };
+            Rgba([ink(r), ink(g), ink(b), (k * 255.0).round() as u8])
+        }));
+    };
+    let failed = |e: moxcms::CmsError| Error::Input {
+        reason: Msg::CmykFailed(e.to_string()).to_string(),
+    };
+    let source = match source {
+        Some(Profile::AdobeRgb) => ColorProfile::new_adobe_rgb(),
+        _ => ColorProfile::new_srgb(),
+    };
+    let target = ColorProfile::new_from_slice(target).map_err(failed)?;
+    let transform = source
+        .create_transform_8bit(
+            Layout::Rgb,
+            &target,
+            Layout::Rgba,
+            TransformOptions::default(),
+        )
+        .map_err(failed)?;
+    let mut cmyk = vec![0u8; w as usize * h as usize * 4];
+    transform
+        .transform(rgb.as_raw(), &mut cmyk)
+        .map_err(failed)?;
+    Ok(CmykImage::from_raw(w, h, cmyk).expect("buffer size matches image dimensions"))
+}
+
+/// 编码为 CMYK TIFF
+///
+/// - ppi: 写入文件的分辨率
+/// - icc: 嵌入的 CMYK 配置文件
+pub fn encode_cmyk_tiff(image: &CmykImage, ppi: f64, icc: Option<&[u8]>) -> Result<Vec<u8>, Error> {
+    let tiff_error = |e: tiff::TiffError| Error::Io {
+        source: std::io::Error::other(e),
+    };
+    let mut bytes = Cursor::new(Vec::new());
+    let mut encoder = TiffEncoder::new(&mut bytes).map_err(tiff_error)?;
+    let mut tiff = encoder
+        .new_image::<colortype::CMYK8>(image.width(), image.height())
+        .map_err(tiff_error)?;
+    tiff.resolution(
+        tiff::tags::ResolutionUnit::Inch,
+        Rational {
+            n: (ppi * 100.0).round() as u32,
+            d: 100,
+        },
+    );
+    tiff.write_data(image.as_raw()).map_err(tiff_error)?;
+    let bytes = bytes.into_inner();
+    match icc {
+        Some(icc) => icc::embed(bytes, image::ImageFormat::Tiff, icc),
+        None => Ok(bytes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bilevel() {
+        // 50% 灰度抖动后约一半为白
+        let gray = GrayImage::from_pixel(16, 16, Luma([128]));
+        for dither in [Dither::FloydSteinberg, Dither::Ordered] {
+            let white = to_bilevel(&gray, dither)
+                .pixels()
+                .filter(|pixel| pixel.0[0] == 255)
+                .count();
+            assert!((120..=136).contains(&white), "{:?}: {}", dither, white);
+        }
+        let image = to_bilevel(&GrayImage::from_pixel(2, 1, Luma([100])), Dither::Threshold);
+        assert!(image.pixels().all(|pixel| pixel.0[0] == 0));
+
+        let page = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            1,
+            1,
+            image::Rgba([255, 0, 0, 0]),
+        ));
+        assert_eq!(to_gray(&page).to_luma8().get_pixel(0, 0).0, [255]);
+
+        let mut png = Vec::new();
+        write_bilevel_png(&to_bilevel(&gray, Dither::Ordered), &mut png).unwrap();
+        let decoded = image::load_from_memory(&png).unwrap().to_luma8();
+        assert_eq!(decoded, to_bilevel(&gray, Dither::Ordered));
+    }
+
+    #[test]
+    fn test_cmyk() {
+        let page = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(3, 1, |x, _| match x {
+            0 => image::Rgba([255, 255, 255, 255]),
+            1 => image::Rgba([0, 0, 0, 255]),
+            _ => image::Rgba([255, 0, 0, 255]),
+        }));
+        let cmyk = to_cmyk(&page, None, None).unwrap();
+        assert_eq!(cmyk.get_pixel(0, 0).0, [0, 0, 0, 0]);
+        assert_eq!(cmyk.get_pixel(1, 0).0, [0, 0, 0, 255]);
+        assert_eq!(cmyk.get_pixel(2, 0).0, [0, 255, 255, 0]);
+        // 解码 CMYK TIFF 时转换回 RGB
+        let tiff = encode_cmyk_tiff(&cmyk, 300.0, None).unwrap();
+        let decoded = image::load_from_memory(&tiff).unwrap().to_rgba8();
+        assert_eq!(decoded, page.to_rgba8());
+    }
+}
@@ -0,0 +1,139 @@
+use clap::{parser::ValueSource, ArgMatches};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use std::{fs, path::Path};
+
+use image_typesetting_tool::i18n::Msg;
+
+use crate::Error;
+
+/// 自动查找的配置文件名
+pub const DEFAULT_CONFIG_FILE: &str = "imgtypeset.toml";
+
+fn config_error(path: &Path, reason: impl std::fmt::Display) -> Error {
+    Error::Config {
+        reason: Msg::ConfigFile {
+            path: &path.display().to_string(),
+            reason: reason.to_string(),
+        }
+        .to_string(),
+    }
+}
+
+/// 读取配置文件
+pub fn load(path: &Path) -> Result<toml::Table, Error> {
+    let text = fs::read_to_string(path).map_err(|e| config_error(path, e))?;
+    text.parse::<toml::Table>()
+        .map_err(|e| config_error(path, e))
+}
+
+/// 反序列化单个字符串或字符串数组，便于配置文件中只写一项
+pub fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+/// 展开预设，预设中的键覆盖顶层的键
+///
+/// 预设定义在 `[profile.NAME]` 表中，未选择预设时忽略全部预设
+///
+/// - table: 配置文件内容
+/// - profile: 选择的预设名称
+/// - path: 配置文件路径，用于错误提示
+pub fn apply_profile(
+    mut table: toml::Table,
+    profile: Option<&str>,
+    path: &Path,
+) -> Result<toml::Table, Error> {
+    let profiles = match table.remove("profile") {
+        Some(toml::Value::Table(profiles)) => profiles,
+        Some(_) => return Err(config_error(path, Msg::PresetsNotTable)),
+        None => toml::Table::new(),
+    };
+    let Some(name) = profile else {
+        return Ok(table);
+    };
+    match profiles.get(name) {
+        Some(toml::Value::Table(entries)) => {
+            table.extend(entries.clone());
+            Ok(table)
+        }
+        Some(_) => Err(config_error(path, Msg::PresetNotTable(name))),
+        None => {
+            let names: Vec<&str> = profiles.keys().map(String::as_str).collect();
+            Err(config_error(
+                path,
+                Msg::PresetMissing {
+                    name,
+                    available: &names,
+                },
+            ))
+        }
+    }
+}
+
+/// 合并配置文件与命令行参数，命令行中显式给出的参数优先
+///
+/// 配置文件的键与命令行参数同名，`-` 与 `_` 均可
+///
+/// - args: 命令行参数
+/// - matches: 命令行解析结果，用于判断参数来源
+/// - table: 配置文件内容
+/// - path: 配置文件路径，用于错误提示
+pub fn merge<T>(args: &T, matches: &ArgMatches, table: toml::Table, path: &Path) -> Result<T, Error>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let mut merged = toml::Table::try_from(args).map_err(|e| config_error(path, e))?;
+    for (key, value) in table {
+        let id = key.replace('-', "_");
+        let from_cli = matches
+            .try_get_raw(&id)
+            .is_ok_and(|_| matches.value_source(&id) == Some(ValueSource::CommandLine));
+        if !from_cli {
+            merged.insert(id, value);
+        }
+    }
+    merged.try_into().map_err(|e| config_error(path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_profile() {
+        let table: toml::Table = r#"
+            nh = 4
+            nv = 3
+
+            [profile.id_photos]
+            nh = 6
+            paper = "a4"
+        "#
+        .parse()
+        .unwrap();
+        let path = Path::new("imgtypeset.toml");
+
+        let plain = apply_profile(table.clone(), None, path).unwrap();
+        assert_eq!(plain.get("nh"), Some(&toml::Value::Integer(4)));
+        assert!(!plain.contains_key("profile"));
+
+        let id = apply_profile(table.clone(), Some("id_photos"), path).unwrap();
+        assert_eq!(id.get("nh"), Some(&toml::Value::Integer(6)));
+        assert_eq!(id.get("nv"), Some(&toml::Value::Integer(3)));
+        assert_eq!(id.get("paper"), Some(&toml::Value::from("a4")));
+
+        assert!(apply_profile(table, Some("missing"), path).is_err());
+    }
+}
@@ -0,0 +1,76 @@
+//! 切割路径
+//!
+//! 为每页输出与页面等大的 SVG，包含每张图片外轮廓的矩形或圆角矩形，
+//! 按实际尺寸（毫米）标注，导入 Cricut、Silhouette 等切割机软件后可直接沿图片边缘切割
+
+use std::fmt::Write;
+
+use crate::layout::Rect;
+
+/// 切割线颜色
+const STROKE: &str = "#ff0000";
+/// 切割线宽度 毫米
+const STROKE_WIDTH: f64 = 0.1;
+
+/// 生成单页切割路径 SVG
+///
+/// - page: 页面宽高 像素
+/// - rects: 每张图片的外轮廓，宽度为 0 的区域（缺失的图片）跳过
+/// - radius: 圆角半径 像素，为 0 时为直角
+pub fn svg(page: (u32, u32), ppc: f64, rects: &[Rect], radius: f32) -> String {
+    let mm = |px: f64| (px / ppc * 1000.0).round() / 100.0;
+    let (w, h) = (mm(page.0 as f64), mm(page.1 as f64));
+    let mut svg = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}mm\" height=\"{1}mm\" viewBox=\"0 0 {0} {1}\">\n\
+         <g fill=\"none\" stroke=\"{2}\" stroke-width=\"{3}\">\n",
+        w, h, STROKE, STROKE_WIDTH
+    );
+    for rect in rects.iter().filter(|rect| rect.w > 0 && rect.h > 0) {
+        let _ = write!(
+            svg,
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"",
+            mm(rect.x as f64),
+            mm(rect.y as f64),
+            mm(rect.w as f64),
+            mm(rect.h as f64)
+        );
+        // 半径超过短边一半时按短边一半处理，正方形即为圆形
+        let radius = (radius as f64).min(rect.w.min(rect.h) as f64 / 2.0);
+        if radius > 0.0 {
+            let _ = write!(svg, " rx=\"{0}\" ry=\"{0}\"", mm(radius));
+        }
+        svg.push_str("/>\n");
+    }
+    svg.push_str("</g>\n</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_svg() {
+        let rects = [
+            Rect {
+                x: 10,
+                y: 20,
+                w: 50,
+                h: 50,
+            },
+            Rect {
+                x: 70,
+                y: 20,
+                w: 0,
+                h: 0,
+            },
+        ];
+        let svg = svg((210, 297), 10.0, &rects, 40.0);
+        assert!(svg.contains("width=\"210mm\" height=\"297mm\" viewBox=\"0 0 210 297\""));
+        assert!(svg
+            .contains("<rect x=\"10\" y=\"20\" width=\"50\" height=\"50\" rx=\"25\" ry=\"25\"/>"));
+        assert_eq!(svg.matches("<rect").count(), 1);
+        assert!(!super::svg((210, 297), 10.0, &rects, 0.0).contains("rx="));
+    }
+}
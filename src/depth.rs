@@ -0,0 +1,78 @@
+//! 16 位输出
+//!
+//! 页面上的装饰均绘制在 8 位画布上，16 位图片另外绘制到 16 位图层，
+//! 输出时图层中与 8 位画布一致的像素保留 16 位数值，其余像素取 8 位画布
+
+use image::{DynamicImage, ImageBuffer, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+/// 16 位 RGBA 图像
+pub type Rgba16Image = ImageBuffer<Rgba<u16>, Vec<u16>>;
+
+/// 输出位深
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Depth {
+    /// 页面中有 16 位图片时输出 16 位
+    #[default]
+    Auto,
+    #[value(name = "8")]
+    #[serde(rename = "8")]
+    Eight,
+    #[value(name = "16")]
+    #[serde(rename = "16")]
+    Sixteen,
+}
+
+/// 图片每个通道是否超过 8 位
+pub fn is_deep(image: &DynamicImage) -> bool {
+    let color = image.color();
+    color.bytes_per_pixel() > color.channel_count()
+}
+
+/// 合并 8 位画布与 16 位图层
+///
+/// - canvas: 绘制完成的 8 位页面
+/// - layer: 16 位图片所在的图层，为空时整页由 8 位画布转换
+pub fn merge(canvas: &RgbaImage, layer: Option<&Rgba16Image>) -> Rgba16Image {
+    let widen = |v: u8| v as u16 * 257;
+    let mut page = Rgba16Image::from_fn(canvas.width(), canvas.height(), |x, y| {
+        Rgba(canvas.get_pixel(x, y).0.map(widen))
+    });
+    let Some(layer) = layer else {
+        return page;
+    };
+    let narrow = DynamicImage::ImageRgba16(layer.clone()).into_rgba8();
+    for ((pixel, deep), (flat, drawn)) in page
+        .pixels_mut()
+        .zip(layer.pixels())
+        .zip(narrow.pixels().zip(canvas.pixels()))
+    {
+        if flat == drawn {
+            *pixel = *deep;
+        }
+    }
+    page
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge() {
+        let mut layer = Rgba16Image::new(3, 1);
+        layer.put_pixel(0, 0, Rgba([1000, 2000, 3000, 65535]));
+        layer.put_pixel(1, 0, Rgba([1000, 2000, 3000, 65535]));
+        let mut canvas = DynamicImage::ImageRgba16(layer.clone()).into_rgba8();
+        // 第二个像素被装饰覆盖，第三个像素没有 16 位图片
+        canvas.put_pixel(1, 0, Rgba([255, 0, 0, 255]));
+        canvas.put_pixel(2, 0, Rgba([255, 255, 255, 255]));
+        let page = merge(&canvas, Some(&layer));
+        assert_eq!(page.get_pixel(0, 0).0, [1000, 2000, 3000, 65535]);
+        assert_eq!(page.get_pixel(1, 0).0, [65535, 0, 0, 65535]);
+        assert_eq!(page.get_pixel(2, 0).0, [65535; 4]);
+        assert!(is_deep(&DynamicImage::new_rgb16(1, 1)));
+        assert!(!is_deep(&DynamicImage::new_luma8(1, 1)));
+    }
+}
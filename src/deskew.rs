@@ -0,0 +1,128 @@
+//! 扫描件纠偏
+//!
+//! 在缩小的灰度图上检测边缘，对边缘点做限定角度范围的 Hough 变换：
+//! 某一角度下水平与竖直方向的投影越集中，说明图中的直线越接近该角度。
+//! 按检测到的角度反向旋转，旋转后露出的角落为透明
+
+use image::{imageops::FilterType, DynamicImage, Rgba};
+use imageproc::{
+    edges::canny,
+    geometric_transformations::{rotate_about_center, Interpolation},
+};
+
+use crate::depth;
+
+/// 检测的最大倾斜角度 度
+const MAX_SKEW: f32 = 10.0;
+/// 检测角度的步长 度
+const STEP: f32 = 0.1;
+/// 检测前将图片缩小到的最大边长 像素
+const DETECT_SIZE: u32 = 1000;
+/// 参与检测的边缘点数上限
+const MAX_POINTS: usize = 50_000;
+/// 边缘点过少时不纠偏
+const MIN_POINTS: usize = 100;
+
+/// 检测图片的倾斜角度（单位：度），顺时针为正
+///
+/// 边缘点过少或倾斜小于检测步长时返回 None
+pub fn detect_skew(image: &DynamicImage) -> Option<f32> {
+    let small = match image.width().max(image.height()) > DETECT_SIZE {
+        true => image.resize(DETECT_SIZE, DETECT_SIZE, FilterType::Triangle),
+        false => image.clone(),
+    }
+    .into_luma8();
+    let edges = canny(&small, 50.0, 150.0);
+    let points: Vec<(f32, f32)> = edges
+        .enumerate_pixels()
+        .filter(|(_, _, pixel)| pixel.0[0] > 0)
+        .map(|(x, y, _)| (x as f32, y as f32))
+        .collect();
+    if points.len() < MIN_POINTS {
+        return None;
+    }
+    let stride = points.len().div_ceil(MAX_POINTS);
+    let points: Vec<(f32, f32)> = points.into_iter().step_by(stride).collect();
+
+    // 投影距离的取值范围为 ±(宽 + 高)
+    let offset = (small.width() + small.height()) as f32;
+    let mut votes = vec![0u32; 2 * offset as usize + 1];
+    let mut score = |angle: f32| {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let mut total = 0u64;
+        // 水平线与竖直线分别投影
+        for project in [
+            |x: f32, y: f32, sin: f32, cos: f32| y * cos - x * sin,
+            |x, y, sin, cos| x * cos + y * sin,
+        ] {
+            votes.fill(0);
+            for &(x, y) in &points {
+                votes[(project(x, y, sin, cos) + offset).round() as usize] += 1;
+            }
+            total += votes.iter().map(|&n| n as u64 * n as u64).sum::<u64>();
+        }
+        total
+    };
+    let steps = (MAX_SKEW / STEP).round() as i32;
+    let (best, _) = (-steps..=steps)
+        .map(|i| (i, score(i as f32 * STEP)))
+        .max_by_key(|&(i, score)| (score, -i.abs()))?;
+    (best != 0).then_some(best as f32 * STEP)
+}
+
+/// 纠偏：检测倾斜角度并反向旋转，尺寸不变，未检测到倾斜时原样返回
+///
+/// 16 位图片输出 16 位，其余输出 8 位
+pub fn deskew(image: DynamicImage) -> DynamicImage {
+    let Some(angle) = detect_skew(&image) else {
+        return image;
+    };
+    let theta = -angle.to_radians();
+    match depth::is_deep(&image) {
+        true => DynamicImage::ImageRgba16(rotate_about_center(
+            &image.into_rgba16(),
+            theta,
+            Interpolation::Bicubic,
+            Rgba([0; 4]),
+        )),
+        false => DynamicImage::ImageRgba8(rotate_about_center(
+            &image.into_rgba8(),
+            theta,
+            Interpolation::Bicubic,
+            Rgba([0; 4]),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+    use imageproc::drawing::draw_filled_rect_mut;
+    use imageproc::rect::Rect;
+
+    #[test]
+    fn test_deskew() {
+        // 白底上的若干黑色横条，顺时针旋转 3 度
+        let mut page = RgbaImage::from_pixel(600, 400, Rgba([255, 255, 255, 255]));
+        for y in (60..340).step_by(40) {
+            draw_filled_rect_mut(
+                &mut page,
+                Rect::at(80, y).of_size(440, 12),
+                Rgba([0, 0, 0, 255]),
+            );
+        }
+        let page = DynamicImage::ImageRgba8(page);
+        assert_eq!(detect_skew(&page), None);
+        let tilted = DynamicImage::ImageRgba8(rotate_about_center(
+            &page.to_rgba8(),
+            3f32.to_radians(),
+            Interpolation::Bilinear,
+            Rgba([255, 255, 255, 255]),
+        ));
+        let angle = detect_skew(&tilted).unwrap();
+        assert!((angle - 3.0).abs() < 0.3, "{}", angle);
+        let straight = deskew(tilted);
+        assert!(detect_skew(&straight).is_none_or(|angle| angle.abs() < 0.3));
+    }
+}
@@ -0,0 +1,512 @@
+//! 页面图形绘制
+
+use image::{DynamicImage, GenericImageView, GrayImage, Luma, Pixel, Rgba, RgbaImage};
+use imageproc::{
+    drawing::{draw_filled_rect_mut, draw_hollow_rect_mut, draw_line_segment_mut},
+    filter::gaussian_blur_f32,
+    rect::Rect,
+};
+
+use crate::{
+    layout::{self, Placement, Span},
+    text::Font,
+    Config,
+};
+
+/// 占位图边框与叉线颜色
+const PLACEHOLDER_STROKE: Rgba<u8> = Rgba([200, 40, 40, 255]);
+
+/// 裁切线颜色
+const CUT_MARK_COLOR: Rgba<u8> = Rgba([0, 0, 0, 255]);
+
+/// 裁切线长度（单位：cm）
+const CUT_MARK_LENGTH: f64 = 0.3;
+
+/// 裁切线与图片角的距离（单位：cm）
+const CUT_MARK_OFFSET: f64 = 0.1;
+
+/// 裁切线线宽（单位：cm）
+const CUT_MARK_WIDTH: f64 = 0.01;
+
+/// 预览中裁切边缘参考线颜色
+const TRIM_GUIDE_COLOR: Rgba<u8> = Rgba([220, 0, 160, 255]);
+
+/// 预览中安全区参考线颜色
+const SAFE_GUIDE_COLOR: Rgba<u8> = Rgba([0, 160, 220, 255]);
+
+/// 调试网格：纸张边距边界颜色
+const DEBUG_BORDER_COLOR: Rgba<u8> = Rgba([230, 30, 30, 255]);
+
+/// 调试网格：单元格边界颜色
+const DEBUG_CELL_COLOR: Rgba<u8> = Rgba([30, 90, 230, 255]);
+
+/// 调试网格：说明文字预留区域边界颜色
+const DEBUG_CAPTION_COLOR: Rgba<u8> = Rgba([20, 170, 60, 255]);
+
+/// 调试网格：图片间距填充颜色
+const DEBUG_MARGIN_COLOR: Rgba<u8> = Rgba([255, 200, 0, 60]);
+
+/// 投影参数
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shadow {
+    /// 向右下方的偏移 像素
+    pub offset: u32,
+    /// 模糊半径 像素
+    pub blur: f32,
+    /// 不透明度 0~1
+    pub opacity: f32,
+}
+
+/// 在图片将要绘制的位置下方绘制柔和投影，需在绘制图片之前调用
+///
+/// 投影形状取自图片的不透明区域，图片外侧的边框同样投影
+///
+/// - image: 将要绘制的图片
+/// - x, y: 图片左上角 像素
+/// - frame: 图片外侧边框宽度 像素
+/// - radius: 图片圆角半径 像素，边框的圆角随之增大
+pub fn drop_shadow(
+    canvas: &mut RgbaImage,
+    image: &DynamicImage,
+    (x, y): (i64, i64),
+    frame: u32,
+    radius: f32,
+    shadow: Shadow,
+) {
+    let (w, h) = image.dimensions();
+    let pad = frame + (shadow.blur * 3.0).ceil() as u32;
+    let frame_radius = if radius > 0.0 {
+        radius + frame as f32
+    } else {
+        0.0
+    };
+    let (frame_w, frame_h) = ((w + 2 * frame) as f32, (h + 2 * frame) as f32);
+    let mut mask = GrayImage::from_fn(w + 2 * pad, h + 2 * pad, |mx, my| {
+        let inner = |v: u32, len: u32| v >= pad && v < pad + len;
+        if inner(mx, w) && inner(my, h) {
+            return Luma([image.get_pixel(mx - pad, my - pad).0[3]]);
+        }
+        // 边框区域
+        let (fx, fy) = (
+            mx as f32 + 0.5 - (pad - frame) as f32,
+            my as f32 + 0.5 - (pad - frame) as f32,
+        );
+        let coverage = match frame {
+            0 => 0.0,
+            _ => rounded_coverage(fx, fy, frame_w, frame_h, frame_radius),
+        };
+        Luma([(coverage * 255.0).round() as u8])
+    });
+    if shadow.blur > 0.0 {
+        mask = gaussian_blur_f32(&mask, shadow.blur);
+    }
+    let (left, top) = (
+        x - pad as i64 + shadow.offset as i64,
+        y - pad as i64 + shadow.offset as i64,
+    );
+    for (mx, my, alpha) in mask.enumerate_pixels() {
+        let (cx, cy) = (left + mx as i64, top + my as i64);
+        if cx < 0 || cy < 0 || cx >= canvas.width() as i64 || cy >= canvas.height() as i64 {
+            continue;
+        }
+        let alpha = (alpha.0[0] as f32 * shadow.opacity.clamp(0.0, 1.0)).round() as u8;
+        if alpha > 0 {
+            canvas
+                .get_pixel_mut(cx as u32, cy as u32)
+                .blend(&Rgba([0, 0, 0, alpha]));
+        }
+    }
+}
+
+/// 像素中心 (x, y) 被圆角矩形覆盖的比例，用于抗锯齿
+///
+/// - w, h: 矩形尺寸，矩形左上角为原点
+/// - radius: 圆角半径
+fn rounded_coverage(x: f32, y: f32, w: f32, h: f32, radius: f32) -> f32 {
+    let radius = radius.min(w / 2.0).min(h / 2.0).max(0.0);
+    let qx = (x - w / 2.0).abs() - (w / 2.0 - radius);
+    let qy = (y - h / 2.0).abs() - (h / 2.0 - radius);
+    let outside = qx.max(0.0).hypot(qy.max(0.0));
+    let distance = outside + qx.max(qy).min(0.0) - radius;
+    (0.5 - distance).clamp(0.0, 1.0)
+}
+
+/// 将图片四角裁为圆角，圆角外透明，边缘抗锯齿
+///
+/// - radius: 圆角半径 像素
+pub fn round_corners(image: &mut RgbaImage, radius: f32) {
+    let (w, h) = (image.width() as f32, image.height() as f32);
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let coverage = rounded_coverage(x as f32 + 0.5, y as f32 + 0.5, w, h, radius);
+        if coverage < 1.0 {
+            pixel.0[3] = (pixel.0[3] as f32 * coverage).round() as u8;
+        }
+    }
+}
+
+/// 在图片区域外侧绘制边框，图片为圆角时边框同为圆角
+///
+/// - rect: 图片区域
+/// - width: 边框宽度 像素
+/// - radius: 图片圆角半径 像素，为 0 时为直角
+pub fn frame(canvas: &mut RgbaImage, rect: layout::Rect, width: u32, radius: f32, color: Rgba<u8>) {
+    if width == 0 {
+        return;
+    }
+    let outer_radius = if radius > 0.0 {
+        radius + width as f32
+    } else {
+        0.0
+    };
+    let (outer_w, outer_h) = ((rect.w + 2 * width) as f32, (rect.h + 2 * width) as f32);
+    let left = rect.x as i64 - width as i64;
+    let top = rect.y as i64 - width as i64;
+    for dy in 0..rect.h + 2 * width {
+        for dx in 0..rect.w + 2 * width {
+            let (cx, cy) = (left + dx as i64, top + dy as i64);
+            if cx < 0 || cy < 0 || cx >= canvas.width() as i64 || cy >= canvas.height() as i64 {
+                continue;
+            }
+            let (px, py) = (dx as f32 + 0.5, dy as f32 + 0.5);
+            let outer = rounded_coverage(px, py, outer_w, outer_h, outer_radius);
+            let inner = rounded_coverage(
+                px - width as f32,
+                py - width as f32,
+                rect.w as f32,
+                rect.h as f32,
+                radius,
+            );
+            let alpha = outer * (1.0 - inner);
+            if alpha > 0.0 {
+                let mut color = color;
+                color.0[3] = (color.0[3] as f32 * alpha).round() as u8;
+                canvas.get_pixel_mut(cx as u32, cy as u32).blend(&color);
+            }
+        }
+    }
+}
+
+/// 平移整页内容，移出页面的部分裁去，空出的部分以 fill 填充
+///
+/// 用于补偿双面打印时背面的套准偏差，16 位页面保持 16 位
+///
+/// - dx, dy: 向右下方的偏移 像素
+pub fn translate(page: DynamicImage, dx: i64, dy: i64, fill: Rgba<u8>) -> DynamicImage {
+    let (w, h) = page.dimensions();
+    match page {
+        DynamicImage::ImageRgba16(page) => {
+            let fill = Rgba(fill.0.map(|v| v as u16 * 257));
+            let mut out = image::ImageBuffer::from_pixel(w, h, fill);
+            image::imageops::replace(&mut out, &page, dx, dy);
+            DynamicImage::ImageRgba16(out)
+        }
+        page => {
+            let mut out = RgbaImage::from_pixel(w, h, fill);
+            image::imageops::replace(&mut out, &page.into_rgba8(), dx, dy);
+            DynamicImage::ImageRgba8(out)
+        }
+    }
+}
+
+/// 生成缺失图片的占位图：浅灰底、红色边框与对角线，中间为文件名
+///
+/// - w, h: 占位图尺寸 像素
+/// - label: 文件名
+/// - font: 字体，为空时不绘制文字
+pub fn placeholder(w: u32, h: u32, label: &str, font: Option<&Font>) -> RgbaImage {
+    let (w, h) = (w.max(1), h.max(1));
+    let mut image = RgbaImage::from_pixel(w, h, Rgba([235, 235, 235, 255]));
+    let stroke = (w.min(h) / 60).max(1);
+    for i in 0..stroke {
+        if w > 2 * i && h > 2 * i {
+            let rect = Rect::at(i as i32, i as i32).of_size(w - 2 * i, h - 2 * i);
+            draw_hollow_rect_mut(&mut image, rect, PLACEHOLDER_STROKE);
+        }
+    }
+    let (fw, fh) = (w as f32 - 1.0, h as f32 - 1.0);
+    draw_line_segment_mut(&mut image, (0.0, 0.0), (fw, fh), PLACEHOLDER_STROKE);
+    draw_line_segment_mut(&mut image, (fw, 0.0), (0.0, fh), PLACEHOLDER_STROKE);
+
+    if let Some(font) = font {
+        let size = font.fit_size(label, h as f32 / 8.0, w * 9 / 10);
+        let (tw, th) = font.measure(label, size);
+        let (x, y) = ((w - tw.min(w)) / 2, (h - th.min(h)) / 2);
+        // 文字衬底，避免与叉线重叠难以辨认
+        let pad = (size / 4.0) as u32;
+        let back = Rect::at(x.saturating_sub(pad) as i32, y.saturating_sub(pad) as i32)
+            .of_size((tw + 2 * pad).max(1), (th + 2 * pad).max(1));
+        draw_filled_rect_mut(&mut image, back, Rgba([255, 255, 255, 255]));
+        font.draw(
+            &mut image,
+            label,
+            x as i32,
+            y as i32,
+            size,
+            PLACEHOLDER_STROKE,
+        );
+    }
+    image
+}
+
+/// 标记有效 DPI 过低的图片：在图片内侧绘制红色边框，左上角为说明文字
+///
+/// - rect: 图片实际绘制区域
+/// - label: 说明文字
+/// - font: 字体，为空时只绘制边框
+pub fn low_dpi_mark(canvas: &mut RgbaImage, rect: layout::Rect, label: &str, font: Option<&Font>) {
+    if rect.w == 0 || rect.h == 0 {
+        return;
+    }
+    let stroke = (rect.w.min(rect.h) / 40).max(1);
+    for i in 0..stroke {
+        if rect.w > 2 * i && rect.h > 2 * i {
+            let outline = Rect::at((rect.x + i) as i32, (rect.y + i) as i32)
+                .of_size(rect.w - 2 * i, rect.h - 2 * i);
+            draw_hollow_rect_mut(canvas, outline, PLACEHOLDER_STROKE);
+        }
+    }
+    if let Some(font) = font {
+        let size = font.fit_size(label, rect.h as f32 / 10.0, rect.w * 9 / 10);
+        let (tw, th) = font.measure(label, size);
+        let pad = (size / 4.0) as u32;
+        let back = Rect::at((rect.x + stroke) as i32, (rect.y + stroke) as i32)
+            .of_size((tw + 2 * pad).max(1), (th + 2 * pad).max(1));
+        draw_filled_rect_mut(canvas, back, PLACEHOLDER_STROKE);
+        font.draw(
+            canvas,
+            label,
+            (rect.x + stroke + pad) as i32,
+            (rect.y + stroke + pad) as i32,
+            size,
+            Rgba([255, 255, 255, 255]),
+        );
+    }
+}
+
+/// 在每张图片四角外侧绘制裁切线，沿图片边缘的延长线向外
+///
+/// 裁切线只画在边距内，不覆盖任何图片及其出血部分
+///
+/// - rects: 图片实际绘制区域，即裁切边缘，宽度为 0 的区域忽略
+/// - ppc: 每厘米像素数
+/// - bleed: 出血 像素，裁切线从出血范围之外开始
+pub fn cut_marks(canvas: &mut RgbaImage, rects: &[layout::Rect], ppc: f64, bleed: u32) {
+    let rects: Vec<&layout::Rect> = rects.iter().filter(|rect| rect.w > 0).collect();
+    let length = (CUT_MARK_LENGTH * ppc).round().max(1.0) as i64;
+    let offset = ((CUT_MARK_OFFSET * ppc).round() as i64).max(bleed as i64);
+    let width = (CUT_MARK_WIDTH * ppc).round().max(1.0) as i64;
+    let bleed = bleed as i64;
+    let inside = |x: i64, y: i64| {
+        rects.iter().any(|rect| {
+            (rect.x as i64 - bleed..(rect.x + rect.w) as i64 + bleed).contains(&x)
+                && (rect.y as i64 - bleed..(rect.y + rect.h) as i64 + bleed).contains(&y)
+        })
+    };
+    let mut plot = |x: i64, y: i64| {
+        if x >= 0
+            && y >= 0
+            && x < canvas.width() as i64
+            && y < canvas.height() as i64
+            && !inside(x, y)
+        {
+            canvas.put_pixel(x as u32, y as u32, CUT_MARK_COLOR);
+        }
+    };
+    for rect in &rects {
+        let (x0, y0) = (rect.x as i64, rect.y as i64);
+        let (x1, y1) = ((rect.x + rect.w) as i64, (rect.y + rect.h) as i64);
+        // 每个角：角的坐标、水平与垂直方向的外侧
+        for (x, y, dx, dy) in [
+            (x0, y0, -1, -1),
+            (x1, y0, 1, -1),
+            (x0, y1, -1, 1),
+            (x1, y1, 1, 1),
+        ] {
+            // 以图片边缘像素为起点，向外跳过间距
+            let (edge_x, edge_y) = (x - (dx + 1) / 2, y - (dy + 1) / 2);
+            for i in offset + 1..offset + 1 + length {
+                for w in 0..width {
+                    plot(edge_x + dx * i, edge_y - dy * w);
+                    plot(edge_x - dx * w, edge_y + dy * i);
+                }
+            }
+        }
+    }
+}
+
+/// 在预览中标出每张图片的裁切边缘与安全区
+///
+/// - rects: 图片实际绘制区域，即裁切边缘，宽度为 0 的区域忽略
+/// - bleed: 出血 像素，为 0 时不标出裁切边缘
+/// - safe: 安全区与裁切边缘的距离 像素，为 0 时不标出
+pub fn guides(canvas: &mut RgbaImage, rects: &[layout::Rect], bleed: u32, safe: u32) {
+    for rect in rects.iter().filter(|rect| rect.w > 0) {
+        if bleed > 0 {
+            let trim = Rect::at(rect.x as i32, rect.y as i32).of_size(rect.w, rect.h);
+            draw_hollow_rect_mut(canvas, trim, TRIM_GUIDE_COLOR);
+        }
+        if safe > 0 && rect.w > 2 * safe && rect.h > 2 * safe {
+            let area = Rect::at((rect.x + safe) as i32, (rect.y + safe) as i32)
+                .of_size(rect.w - 2 * safe, rect.h - 2 * safe);
+            draw_hollow_rect_mut(canvas, area, SAFE_GUIDE_COLOR);
+        }
+    }
+}
+
+/// 绘制调试网格：纸张边距边界、每个单元格边界与说明文字预留区域，图片间距以半透明色填充
+pub fn debug_grid(canvas: &mut RgbaImage, config: &Config) {
+    let outline = |canvas: &mut RgbaImage, rect: layout::Rect, color| {
+        if rect.w > 0 && rect.h > 0 {
+            let rect = Rect::at(rect.x as i32, rect.y as i32).of_size(rect.w, rect.h);
+            draw_hollow_rect_mut(canvas, rect, color);
+        }
+    };
+    let border = config.paper_border_px;
+    let area = layout::Rect {
+        x: border.left,
+        y: border.top,
+        w: config.paper_w_px.saturating_sub(border.horizontal()),
+        h: config.paper_h_px.saturating_sub(border.vertical()),
+    };
+    let cells: Vec<layout::Rect> = (0..config.n_v)
+        .flat_map(|row| (0..config.n_h).map(move |col| (row, col)))
+        .map(|(row, col)| {
+            config.cell_rect(&Placement {
+                index: 0,
+                row,
+                col,
+                span: Span::ONE,
+            })
+        })
+        .collect();
+    // 边距内、单元格外的区域即图片间距
+    let (x0, y0) = config.grid_origin_px;
+    let grid_w = cells.iter().map(|cell| cell.x + cell.w).max().unwrap_or(x0);
+    let grid_h = cells.iter().map(|cell| cell.y + cell.h).max().unwrap_or(y0);
+    for y in y0..grid_h.min(canvas.height()) {
+        for x in x0..grid_w.min(canvas.width()) {
+            let in_cell = cells.iter().any(|cell| {
+                (cell.x..cell.x + cell.w).contains(&x) && (cell.y..cell.y + cell.h).contains(&y)
+            });
+            if !in_cell {
+                canvas.get_pixel_mut(x, y).blend(&DEBUG_MARGIN_COLOR);
+            }
+        }
+    }
+    for &cell in &cells {
+        outline(canvas, cell, DEBUG_CELL_COLOR);
+        if config.caption_h_px > 0 {
+            let caption = layout::Rect {
+                y: cell.y + cell.h.saturating_sub(config.caption_h_px),
+                h: config.caption_h_px.min(cell.h),
+                ..cell
+            };
+            outline(canvas, caption, DEBUG_CAPTION_COLOR);
+        }
+    }
+    outline(canvas, area, DEBUG_BORDER_COLOR);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+
+    #[test]
+    fn test_placeholder() {
+        let image = placeholder(120, 60, "missing.jpg", None);
+        assert_eq!(image.dimensions(), (120, 60));
+        assert_eq!(*image.get_pixel(0, 30), PLACEHOLDER_STROKE);
+        assert_eq!(*image.get_pixel(30, 5), Rgba([235, 235, 235, 255]));
+    }
+
+    #[test]
+    fn test_cut_marks() {
+        let mut canvas = RgbaImage::new(100, 100);
+        let rect = layout::Rect {
+            x: 40,
+            y: 40,
+            w: 20,
+            h: 20,
+        };
+        cut_marks(&mut canvas, &[rect], 10.0, 0);
+        // 左上角水平线与右下角垂直线
+        assert_eq!(*canvas.get_pixel(38, 40), CUT_MARK_COLOR);
+        assert_eq!(*canvas.get_pixel(59, 62), CUT_MARK_COLOR);
+        // 线与图片角之间留有间距，且不覆盖图片
+        assert_eq!(canvas.get_pixel(39, 40).0[3], 0);
+        assert_eq!(canvas.get_pixel(45, 45).0[3], 0);
+        assert_eq!(canvas.get_pixel(30, 40).0[3], 0);
+    }
+
+    #[test]
+    fn test_round_corners() {
+        let mut image = RgbaImage::from_pixel(40, 20, Rgba([255; 4]));
+        round_corners(&mut image, 8.0);
+        assert_eq!(image.get_pixel(0, 0).0[3], 0);
+        assert_eq!(image.get_pixel(39, 19).0[3], 0);
+        assert_eq!(image.get_pixel(20, 0).0[3], 255);
+        assert_eq!(image.get_pixel(0, 10).0[3], 255);
+        // 圆弧边缘为半透明
+        let edge = image.get_pixel(2, 2).0[3];
+        assert!(edge > 0 && edge < 255);
+
+        let mut canvas = RgbaImage::new(60, 40);
+        let rect = layout::Rect {
+            x: 10,
+            y: 10,
+            w: 40,
+            h: 20,
+        };
+        frame(&mut canvas, rect, 2, 0.0, Color::BLACK.0);
+        assert_eq!(*canvas.get_pixel(8, 8), Color::BLACK.0);
+        assert_eq!(*canvas.get_pixel(51, 31), Color::BLACK.0);
+        assert_eq!(canvas.get_pixel(10, 10).0[3], 0);
+        assert_eq!(canvas.get_pixel(7, 20).0[3], 0);
+    }
+
+    #[test]
+    fn test_drop_shadow() {
+        let mut canvas = RgbaImage::new(60, 60);
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(20, 20, Rgba([255; 4])));
+        let shadow = Shadow {
+            offset: 4,
+            blur: 1.0,
+            opacity: 0.5,
+        };
+        drop_shadow(&mut canvas, &image, (20, 20), 0, 0.0, shadow);
+        // 偏移后的中心为半透明黑色，左上方不受影响
+        let center = canvas.get_pixel(34, 34).0[3];
+        assert!((120..=130).contains(&center));
+        assert_eq!(canvas.get_pixel(19, 19).0[3], 0);
+        // 边缘经过模糊
+        let edge = canvas.get_pixel(44, 34).0[3];
+        assert!(edge > 0 && edge < center);
+    }
+
+    #[test]
+    fn test_debug_grid() {
+        let config = Config::from_options(&crate::LayoutOptions {
+            ppc: Some(10.0),
+            ..Default::default()
+        })
+        .unwrap();
+        let mut canvas = RgbaImage::new(config.paper_w_px, config.paper_h_px);
+        debug_grid(&mut canvas, &config);
+        let border = config.paper_border_px;
+        assert_eq!(
+            *canvas.get_pixel(border.left, border.top + 20),
+            DEBUG_BORDER_COLOR
+        );
+        let cell = config.cell_rect(&Placement {
+            index: 0,
+            row: 1,
+            col: 1,
+            span: Span::ONE,
+        });
+        assert_eq!(*canvas.get_pixel(cell.x + 5, cell.y), DEBUG_CELL_COLOR);
+        // 单元格之间的间距被填充，纸张边距之外不变
+        assert!(canvas.get_pixel(cell.x - 1, cell.y + 5).0[3] > 0);
+        assert_eq!(canvas.get_pixel(0, 0).0[3], 0);
+    }
+}
@@ -0,0 +1,289 @@
+//! C 接口
+//!
+//! 供 C/C++/C# 等程序直接调用排版，无需启动子进程。头文件见 `include/image_typesetting_tool.h`。
+//!
+//! 调用顺序：`typeset_create_config` 创建会话，`typeset_add_image` 逐张添加图片，
+//! `typeset_page_count` 获取页数，`typeset_render_page` 输出单页，最后 `typeset_destroy`。
+//! 函数出错或意外中止时返回空指针或负数，原因可通过 `typeset_last_error` 获取。
+
+use std::{
+    cell::RefCell,
+    ffi::{c_char, c_int, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    ptr, slice,
+};
+
+use image::DynamicImage;
+
+use crate::{
+    flatten,
+    i18n::Msg,
+    layout::{Placement, Span},
+    memory::{encode_page, MemoryOptions},
+    panic_reason, Config, Error, Typesetter,
+};
+
+thread_local! {
+    /// 当前线程最近一次出错的原因
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// 记录出错原因
+fn set_error(error: impl ToString) {
+    let message = error.to_string().replace('\0', "");
+    LAST_ERROR.with(|last| *last.borrow_mut() = CString::new(message).ok());
+}
+
+/// 执行接口函数体，出错或意外中止时记录原因并返回 fallback，避免 panic 越过 C 边界
+fn guarded<T>(fallback: T, body: impl FnOnce() -> Result<T, String>) -> T {
+    match panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(value)) => value,
+        Ok(Err(e)) => {
+            set_error(e);
+            fallback
+        }
+        Err(payload) => {
+            set_error(panic_reason(payload.as_ref()));
+            fallback
+        }
+    }
+}
+
+/// 排版会话
+pub struct TypesetSession {
+    options: MemoryOptions,
+    typesetter: Typesetter,
+    images: Vec<DynamicImage>,
+    /// 排版结果，添加图片后重新计算
+    pages: Option<Vec<Vec<Placement>>>,
+}
+
+impl TypesetSession {
+    /// 按当前图片排版
+    fn pages(&mut self) -> Result<&[Vec<Placement>], Error> {
+        if self.pages.is_none() {
+            let spans = vec![Span::ONE; self.images.len()];
+            let groups = vec![0; self.images.len()];
+            self.pages = Some(self.typesetter.layout(&spans, &groups)?);
+        }
+        Ok(self.pages.as_deref().unwrap_or_default())
+    }
+
+    /// 绘制并编码单页
+    fn render_page(&mut self, index: usize) -> Result<Vec<u8>, Error> {
+        let placements = self
+            .pages()?
+            .get(index)
+            .cloned()
+            .ok_or_else(|| Error::Config {
+                reason: Msg::PageOutOfRange(index).to_string(),
+            })?;
+        let page_images: Vec<DynamicImage> = placements
+            .iter()
+            .map(|placement| self.images[placement.index].clone())
+            .collect();
+        let target_hs = vec![self.typesetter.config.target_h_px; placements.len()];
+        let (canvas, _) =
+            self.typesetter
+                .render_page(&page_images, &placements, &target_hs, |_| Ok(()))?;
+        encode_page(&flatten(&canvas), &self.options)
+    }
+}
+
+/// 创建排版会话
+///
+/// - options_json: JSON 参数，UTF-8 编码，可为空指针；键见 [`MemoryOptions`]
+///
+/// 出错时返回空指针
+///
+/// # Safety
+///
+/// options_json 为空指针或以 `\0` 结尾的字符串
+#[no_mangle]
+pub unsafe extern "C" fn typeset_create_config(options_json: *const c_char) -> *mut TypesetSession {
+    guarded(ptr::null_mut(), || {
+        let json = match options_json.is_null() {
+            true => "",
+            false => CStr::from_ptr(options_json)
+                .to_str()
+                .map_err(|e| e.to_string())?,
+        };
+        let options = MemoryOptions::from_json(json).map_err(|e| e.to_string())?;
+        let config = Config::from_options(&options.layout).map_err(|e| e.to_string())?;
+        let session = TypesetSession {
+            typesetter: Typesetter::new(config),
+            options,
+            images: Vec::new(),
+            pages: None,
+        };
+        Ok(Box::into_raw(Box::new(session)))
+    })
+}
+
+/// 添加一张图片，内容为编码后的图片文件，格式按内容识别
+///
+/// 成功返回 0，出错返回 -1
+///
+/// # Safety
+///
+/// session 由 `typeset_create_config` 创建且未释放，data 指向 len 字节的可读内存
+#[no_mangle]
+pub unsafe extern "C" fn typeset_add_image(
+    session: *mut TypesetSession,
+    data: *const u8,
+    len: usize,
+) -> c_int {
+    guarded(-1, || {
+        let session = session
+            .as_mut()
+            .ok_or_else(|| Msg::NullArgument("session").to_string())?;
+        if data.is_null() {
+            return Err(Msg::NullArgument("data").to_string());
+        }
+        let image =
+            image::load_from_memory(slice::from_raw_parts(data, len)).map_err(|e| e.to_string())?;
+        session.images.push(image);
+        session.pages = None;
+        Ok(0)
+    })
+}
+
+/// 按已添加的图片排版，返回页数，出错返回 -1
+///
+/// # Safety
+///
+/// session 由 `typeset_create_config` 创建且未释放
+#[no_mangle]
+pub unsafe extern "C" fn typeset_page_count(session: *mut TypesetSession) -> c_int {
+    guarded(-1, || {
+        let session = session
+            .as_mut()
+            .ok_or_else(|| Msg::NullArgument("session").to_string())?;
+        let pages = session.pages().map_err(|e| e.to_string())?;
+        Ok(pages.len() as c_int)
+    })
+}
+
+/// 输出第 index 页（从 0 开始），返回编码后的图片，长度写入 out_len
+///
+/// 返回的内存需用 `typeset_free_buffer` 释放，出错时返回空指针
+///
+/// # Safety
+///
+/// session 由 `typeset_create_config` 创建且未释放，out_len 指向可写的 `size_t`
+#[no_mangle]
+pub unsafe extern "C" fn typeset_render_page(
+    session: *mut TypesetSession,
+    index: usize,
+    out_len: *mut usize,
+) -> *mut u8 {
+    guarded(ptr::null_mut(), || {
+        let session = session
+            .as_mut()
+            .ok_or_else(|| Msg::NullArgument("session").to_string())?;
+        if out_len.is_null() {
+            return Err(Msg::NullArgument("out_len").to_string());
+        }
+        let bytes = session
+            .render_page(index)
+            .map_err(|e| e.to_string())?
+            .into_boxed_slice();
+        *out_len = bytes.len();
+        Ok(Box::into_raw(bytes) as *mut u8)
+    })
+}
+
+/// 释放 `typeset_render_page` 返回的内存
+///
+/// # Safety
+///
+/// data 与 len 为 `typeset_render_page` 的返回值与输出长度，且只释放一次
+#[no_mangle]
+pub unsafe extern "C" fn typeset_free_buffer(data: *mut u8, len: usize) {
+    guarded((), || {
+        if !data.is_null() {
+            drop(Box::from_raw(ptr::slice_from_raw_parts_mut(data, len)));
+        }
+        Ok(())
+    })
+}
+
+/// 释放排版会话
+///
+/// # Safety
+///
+/// session 由 `typeset_create_config` 创建，且只释放一次
+#[no_mangle]
+pub unsafe extern "C" fn typeset_destroy(session: *mut TypesetSession) {
+    guarded((), || {
+        if !session.is_null() {
+            drop(Box::from_raw(session));
+        }
+        Ok(())
+    })
+}
+
+/// 当前线程最近一次出错的原因，没有时返回空指针
+///
+/// 返回的字符串在下一次出错前有效，无需释放
+#[no_mangle]
+pub extern "C" fn typeset_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageFormat, RgbImage};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_ffi_session() {
+        let mut png = Cursor::new(Vec::new());
+        RgbImage::new(40, 30)
+            .write_to(&mut png, ImageFormat::Png)
+            .unwrap();
+        let png = png.into_inner();
+        let options = CString::new(r#"{ "ppi": 30, "nh": 2, "nv": 1 }"#).unwrap();
+        unsafe {
+            let session = typeset_create_config(options.as_ptr());
+            assert!(!session.is_null());
+            for _ in 0..3 {
+                assert_eq!(typeset_add_image(session, png.as_ptr(), png.len()), 0);
+            }
+            assert_eq!(typeset_add_image(session, b"bad".as_ptr(), 3), -1);
+            assert!(!typeset_last_error().is_null());
+            assert_eq!(typeset_page_count(session), 2);
+
+            let mut len = 0;
+            let page = typeset_render_page(session, 1, &mut len);
+            assert!(!page.is_null());
+            let decoded = image::load_from_memory(slice::from_raw_parts(page, len)).unwrap();
+            assert!(decoded.width() > decoded.height());
+            typeset_free_buffer(page, len);
+
+            assert!(typeset_render_page(session, 2, &mut len).is_null());
+            typeset_destroy(session);
+        }
+    }
+
+    #[test]
+    fn test_ffi_errors() {
+        let last_error = || unsafe { CStr::from_ptr(typeset_last_error()) }.to_string_lossy();
+        // 参数无效时不创建会话
+        let options = CString::new(r#"{ "nh": 0 }"#).unwrap();
+        unsafe {
+            assert!(typeset_create_config(options.as_ptr()).is_null());
+            assert!(!typeset_last_error().is_null());
+            assert_eq!(typeset_page_count(ptr::null_mut()), -1);
+        }
+        // 意外中止时同样记录原因并返回出错值
+        let result = guarded(-1, || -> Result<c_int, String> { panic!("boom") });
+        assert_eq!(result, -1);
+        assert!(last_error().contains("boom"));
+    }
+}
@@ -0,0 +1,90 @@
+use image::{imageops, ImageFormat, RgbaImage};
+use snafu::prelude::*;
+use std::{fmt::Write, fs, path::Path};
+
+use crate::{Error, ImageSnafu, IoSnafu};
+
+/// 缩略图宽度 像素
+const THUMB_WIDTH: u32 = 480;
+
+/// 缩略图目录
+const THUMB_DIR: &str = "thumbs";
+
+/// 画廊中的一页
+pub struct GalleryPage {
+    /// 输出文件名
+    pub file_name: String,
+    /// 缩略图相对路径
+    pub thumb: String,
+    /// 页面包含的图片文件名
+    pub contents: Vec<String>,
+}
+
+/// 生成页面缩略图，返回相对输出目录的路径
+///
+/// - output_dir: 输出目录
+/// - name: 页面名称
+/// - canvas: 页面画布
+pub fn write_thumbnail(output_dir: &str, name: &str, canvas: &RgbaImage) -> Result<String, Error> {
+    let thumb_dir = Path::new(output_dir).join(THUMB_DIR);
+    fs::create_dir_all(&thumb_dir).context(IoSnafu)?;
+    let height =
+        (canvas.height() as u64 * THUMB_WIDTH as u64 / canvas.width().max(1) as u64).max(1) as u32;
+    let thumb = imageops::thumbnail(canvas, THUMB_WIDTH, height);
+    let file_name = format!("{}.png", name);
+    thumb
+        .save_with_format(thumb_dir.join(&file_name), ImageFormat::Png)
+        .context(ImageSnafu)?;
+    Ok(format!("{}/{}", THUMB_DIR, file_name))
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// 生成画廊 HTML
+pub fn render_html(pages: &[GalleryPage]) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Gallery</title>\n\
+         <style>\nbody { font-family: sans-serif; }\n\
+         .page { display: inline-block; vertical-align: top; margin: 1em; }\n\
+         .page img { border: 1px solid #ccc; }\n</style>\n</head>\n<body>\n",
+    );
+    for page in pages {
+        let file_name = escape(&page.file_name);
+        let _ = writeln!(html, "<div class=\"page\">");
+        let _ = writeln!(
+            html,
+            "<a href=\"{0}\"><img src=\"{1}\" width=\"{2}\" alt=\"{0}\"></a>",
+            file_name,
+            escape(&page.thumb),
+            THUMB_WIDTH
+        );
+        let _ = writeln!(html, "<p><a href=\"{0}\">{0}</a></p>\n<ol>", file_name);
+        for name in &page.contents {
+            let _ = writeln!(html, "<li>{}</li>", escape(name));
+        }
+        let _ = writeln!(html, "</ol>\n</div>");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_html() {
+        let html = render_html(&[GalleryPage {
+            file_name: "output_0.png".to_string(),
+            thumb: "thumbs/output_0.png".to_string(),
+            contents: vec!["a&b.jpg".to_string()],
+        }]);
+        assert!(html.contains("<a href=\"output_0.png\"><img src=\"thumbs/output_0.png\""));
+        assert!(html.contains("<li>a&amp;b.jpg</li>"));
+    }
+}
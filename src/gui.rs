@@ -0,0 +1,326 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use eframe::egui;
+use image::DynamicImage;
+use image_typesetting_tool::{draw, flatten, i18n::text, open_image, paper::Border, Error};
+
+use crate::{arrange, scan_sources, typeset, watch::Rendered, Arrangement, Cli, PBData};
+
+/// 预览分辨率 每英寸像素数
+const PREVIEW_PPI: f64 = 30.0;
+
+/// 预览用缩略图的最大边长 像素
+const THUMBNAIL_SIZE: u32 = 256;
+
+/// 输出状态
+enum RenderState {
+    Idle,
+    Running {
+        rx: Receiver<PBData>,
+        current: u64,
+        total: u64,
+        done: Receiver<Result<(), Error>>,
+    },
+    Finished(Result<(), String>),
+}
+
+struct GuiApp {
+    /// 命令行参数，界面中调整的项会覆盖
+    cli: Cli,
+    /// 输入目录
+    folder: Option<PathBuf>,
+    /// 目录选择窗口当前所在目录
+    browsing: Option<PathBuf>,
+    /// 输入图片
+    inputs: Vec<PathBuf>,
+    /// 已加载的缩略图，无法读取时为 None
+    thumbnails: HashMap<PathBuf, Option<DynamicImage>>,
+    /// 每页预览
+    pages: Vec<egui::TextureHandle>,
+    /// 排版参数变化后需要重新生成预览
+    dirty: bool,
+    /// 出错原因
+    error: Option<String>,
+    n_h: u32,
+    n_v: u32,
+    height: f64,
+    border: f64,
+    margin: f64,
+    render: RenderState,
+}
+
+/// 打开图形界面
+///
+/// 可选择输入目录，调整网格数量、边距与目标高度并预览每页排版结果，
+/// 预览与输出使用与命令行相同的排版流程
+pub fn gui(cli: &Cli) -> Result<(), Error> {
+    let config = image_typesetting_tool::Config::from_options(&cli.layout_options())?;
+    let folder = cli.input.first().or(cli.files.first()).map(PathBuf::from);
+    let app = GuiApp {
+        cli: cli.clone(),
+        browsing: None,
+        inputs: Vec::new(),
+        thumbnails: HashMap::new(),
+        pages: Vec::new(),
+        dirty: folder.is_some(),
+        error: None,
+        folder,
+        n_h: config.n_h,
+        n_v: config.n_v,
+        height: config.target_h_px as f64 / config.ppc,
+        border: config.paper_border_px.top as f64 / config.ppc,
+        margin: config.min_margin_h_px as f64 / config.ppc,
+        render: RenderState::Idle,
+    };
+    eframe::run_native(
+        "image-typesetting-tool",
+        eframe::NativeOptions::default(),
+        Box::new(|_| Box::new(app)),
+    )
+    .map_err(|e| Error::Config {
+        reason: e.to_string(),
+    })
+}
+
+impl GuiApp {
+    /// 按界面中的设置生成命令行参数
+    fn options(&self) -> Cli {
+        let mut cli = self.cli.clone();
+        cli.command = None;
+        if let Some(folder) = &self.folder {
+            cli.input = vec![folder.to_string_lossy().into_owned()];
+            cli.files = Vec::new();
+            cli.files_from = None;
+        }
+        cli.nh = Some(self.n_h);
+        cli.nv = Some(self.n_v);
+        cli.height = Some(self.height);
+        cli.border = Some(Border::uniform(self.border));
+        cli.margin = Some(self.margin);
+        cli.quiet = true;
+        cli
+    }
+
+    /// 重新收集输入并生成每页预览
+    fn refresh(&mut self, ctx: &egui::Context) -> Result<(), Error> {
+        let mut cli = self.options();
+        self.inputs = scan_sources(&cli)?;
+        cli.ppc = None;
+        cli.ppi = Some(PREVIEW_PPI);
+        let Arrangement {
+            typesetter,
+            target_hs,
+            pages,
+            ..
+        } = arrange(&cli, &self.inputs, None)?;
+        self.pages.clear();
+        for (i, placements) in pages.iter().enumerate() {
+            let page_target_hs: Vec<u32> = placements
+                .iter()
+                .map(|placement| target_hs[placement.index])
+                .collect();
+            let load = |k: usize| {
+                let path = &self.inputs[placements[k].index];
+                let thumbnail = self.thumbnails.entry(path.clone()).or_insert_with(|| {
+                    open_image(path)
+                        .ok()
+                        .map(|image| image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE))
+                });
+                Ok(thumbnail.clone())
+            };
+            let (mut canvas, rects) =
+                typesetter.render_page_with(placements, &page_target_hs, load, |_| Ok(()))?;
+            let config = &typesetter.config;
+            let safe = (cli.safe_area.unwrap_or(0.0) / 10.0 * config.ppc).round() as u32;
+            draw::guides(&mut canvas, &rects, config.bleed_px, safe);
+            let canvas = flatten(&canvas);
+            let size = [canvas.width() as usize, canvas.height() as usize];
+            let image = egui::ColorImage::from_rgb(size, canvas.as_raw());
+            self.pages.push(ctx.load_texture(
+                format!("page-{i}"),
+                image,
+                egui::TextureOptions::LINEAR,
+            ));
+        }
+        Ok(())
+    }
+
+    /// 在后台按当前设置输出全部页面
+    fn start_render(&mut self) {
+        let cli = self.options();
+        let (tx, rx) = mpsc::channel();
+        let (done_tx, done) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = done_tx.send(typeset(&cli, &mut Rendered::default(), Some(tx)));
+        });
+        self.render = RenderState::Running {
+            rx,
+            current: 0,
+            total: 0,
+            done,
+        };
+    }
+
+    /// 目录选择窗口
+    fn folder_picker(&mut self, ctx: &egui::Context) {
+        let Some(dir) = self.browsing.clone() else {
+            return;
+        };
+        let mut open = true;
+        egui::Window::new(text("选择输入目录", "Choose input folder"))
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label(dir.display().to_string());
+                ui.horizontal(|ui| {
+                    if let Some(parent) = dir.parent() {
+                        if ui.button(text("上一级", "Up")).clicked() {
+                            self.browsing = Some(parent.to_path_buf());
+                        }
+                    }
+                    if ui.button(text("选择此目录", "Use this folder")).clicked() {
+                        self.folder = Some(dir.clone());
+                        self.browsing = None;
+                        self.dirty = true;
+                    }
+                });
+                ui.separator();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for sub in subdirectories(&dir) {
+                        let name = sub.file_name().unwrap_or_default().to_string_lossy();
+                        if ui.selectable_label(false, format!("{name}/")).clicked() {
+                            self.browsing = Some(sub.clone());
+                        }
+                    }
+                });
+            });
+        if !open {
+            self.browsing = None;
+        }
+    }
+}
+
+/// 按名称排序的子目录
+fn subdirectories(dir: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+impl eframe::App for GuiApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        egui::SidePanel::left("settings").show(ctx, |ui| {
+            if ui.button(text("选择输入目录…", "Choose folder…")).clicked() {
+                let start = self.folder.clone().or(std::env::current_dir().ok());
+                self.browsing = start.and_then(|dir| fs::canonicalize(dir).ok());
+            }
+            if let Some(folder) = &self.folder {
+                ui.label(folder.display().to_string());
+                ui.label(format!(
+                    "{} {}",
+                    self.inputs.len(),
+                    text("张图片", "images")
+                ));
+            }
+            ui.separator();
+            let changes = [
+                ui.add(egui::Slider::new(&mut self.n_h, 1..=12).text(text("横向数量", "Columns"))),
+                ui.add(egui::Slider::new(&mut self.n_v, 1..=12).text(text("纵向数量", "Rows"))),
+                ui.add(
+                    egui::Slider::new(&mut self.height, 1.0..=30.0)
+                        .text(text("目标高度 cm", "Height cm")),
+                ),
+                ui.add(
+                    egui::Slider::new(&mut self.border, 0.0..=5.0)
+                        .text(text("纸张边距 cm", "Border cm")),
+                ),
+                ui.add(
+                    egui::Slider::new(&mut self.margin, 0.0..=5.0)
+                        .text(text("图片间距 cm", "Margin cm")),
+                ),
+            ];
+            if changes.iter().any(|response| response.changed()) {
+                self.dirty = true;
+            }
+            ui.checkbox(
+                &mut self.cli.overwrite,
+                text("覆盖已有文件", "Overwrite existing files"),
+            );
+            ui.separator();
+            let running = matches!(self.render, RenderState::Running { .. });
+            let can_render = self.folder.is_some() && !running;
+            if ui
+                .add_enabled(can_render, egui::Button::new(text("输出", "Render")))
+                .clicked()
+            {
+                self.start_render();
+            }
+            match &mut self.render {
+                RenderState::Idle => {}
+                RenderState::Running {
+                    rx,
+                    current,
+                    total,
+                    done,
+                } => {
+                    while let Ok(data) = rx.try_recv() {
+                        match data {
+                            PBData::NewTotal(n) => (*current, *total) = (0, n),
+                            PBData::NextTotal => *current += 1,
+                            _ => {}
+                        }
+                    }
+                    let fraction = *current as f32 / (*total).max(1) as f32;
+                    ui.add(egui::ProgressBar::new(fraction).show_percentage());
+                    if let Ok(result) = done.try_recv() {
+                        self.render = RenderState::Finished(result.map_err(|e| e.to_string()));
+                    }
+                    ctx.request_repaint();
+                }
+                RenderState::Finished(Ok(_)) => {
+                    ui.label(text("输出完成", "Done"));
+                }
+                RenderState::Finished(Err(e)) => {
+                    ui.colored_label(egui::Color32::RED, e.as_str());
+                }
+            }
+            if let Some(error) = &self.error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+        });
+
+        if self.dirty && self.folder.is_some() {
+            self.dirty = false;
+            self.error = self.refresh(ctx).err().map(|e| e.to_string());
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                let width = ui.available_width();
+                for page in &self.pages {
+                    let [w, h] = page.size();
+                    let scale = (width / w as f32).min(1.5);
+                    let size = egui::vec2(w as f32 * scale, h as f32 * scale);
+                    ui.add(egui::Image::new(egui::load::SizedTexture::new(
+                        page.id(),
+                        size,
+                    )));
+                    ui.add_space(8.0);
+                }
+            });
+        });
+
+        self.folder_picker(ctx);
+    }
+}
@@ -0,0 +1,121 @@
+//! 页眉与页脚
+//!
+//! 绘制在纸张边距内，文字中的 `{page}` 替换为页码，`{pages}` 替换为总页数
+
+use image::{Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::{caption::LINE_HEIGHT, text::Font, Config};
+
+/// 页眉页脚颜色
+const PAGE_TEXT_COLOR: Rgba<u8> = Rgba([80, 80, 80, 255]);
+
+/// 水平对齐方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Align {
+    Left,
+    #[default]
+    Center,
+    Right,
+}
+
+/// 单行页眉或页脚
+#[derive(Debug, Clone)]
+pub struct PageText {
+    /// 文字模板
+    pub text: String,
+    pub align: Align,
+}
+
+impl PageText {
+    /// 替换页码，page 从 1 开始
+    pub fn expand(&self, page: usize, pages: usize) -> String {
+        self.text
+            .replace("{pages}", &pages.to_string())
+            .replace("{page}", &page.to_string())
+    }
+}
+
+/// 页眉与页脚设置
+#[derive(Debug, Clone)]
+pub struct HeaderFooter {
+    pub header: Option<PageText>,
+    pub footer: Option<PageText>,
+    /// 字号（单位：pt），超出纸张边距时缩小
+    pub size: f64,
+}
+
+impl HeaderFooter {
+    /// 第 page 页的页眉与页脚文字，page 从 1 开始
+    pub fn texts(&self, page: usize, pages: usize) -> (Option<String>, Option<String>) {
+        let expand = |text: &Option<PageText>| text.as_ref().map(|t| t.expand(page, pages));
+        (expand(&self.header), expand(&self.footer))
+    }
+
+    /// 在纸张上下边距内绘制第 page 页的页眉与页脚，page 从 1 开始
+    pub fn draw(
+        &self,
+        canvas: &mut RgbaImage,
+        font: &Font,
+        config: &Config,
+        page: usize,
+        pages: usize,
+    ) {
+        let border = config.paper_border_px;
+        let max_w = config.paper_w_px.saturating_sub(border.horizontal());
+        let (header, footer) = self.texts(page, pages);
+        let lines = [
+            (header, self.header.as_ref(), 0, border.top),
+            (
+                footer,
+                self.footer.as_ref(),
+                config.paper_h_px.saturating_sub(border.bottom),
+                border.bottom,
+            ),
+        ];
+        for (text, page_text, top, height) in lines {
+            let (Some(text), Some(page_text)) = (text, page_text) else {
+                continue;
+            };
+            let size =
+                (self.size / 72.0 * 2.54 * config.ppc).min(height as f64 / LINE_HEIGHT) as f32;
+            if size < 1.0 {
+                continue;
+            }
+            let size = font.fit_size(&text, size, max_w);
+            let (w, _) = font.measure(&text, size);
+            let x = match page_text.align {
+                Align::Left => border.left,
+                Align::Center => (config.paper_w_px.saturating_sub(w)) / 2,
+                Align::Right => config.paper_w_px.saturating_sub(border.right + w),
+            };
+            // 按行高在边距内垂直居中
+            let y = top + ((height as f64 - size as f64 * LINE_HEIGHT) / 2.0).max(0.0) as u32;
+            font.draw(canvas, &text, x as i32, y as i32, size, PAGE_TEXT_COLOR);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        let text = PageText {
+            text: "Page {page}/{pages}".to_string(),
+            align: Align::Right,
+        };
+        assert_eq!(text.expand(3, 12), "Page 3/12");
+        let header_footer = HeaderFooter {
+            header: None,
+            footer: Some(text),
+            size: 9.0,
+        };
+        assert_eq!(
+            header_footer.texts(1, 2),
+            (None, Some("Page 1/2".to_string()))
+        );
+    }
+}
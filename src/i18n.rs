@@ -0,0 +1,1134 @@
+//! 控制台输出的多语言消息
+
+use std::{
+    fmt,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+/// 界面语言
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Lang {
+    /// 简体中文
+    Zh,
+    /// English
+    En,
+}
+
+static LANG: AtomicU8 = AtomicU8::new(Lang::Zh as u8);
+
+impl Lang {
+    /// 按 `LC_ALL`、`LC_MESSAGES`、`LANG` 的顺序读取系统语言
+    ///
+    /// 未设置时为中文，设置了非中文语言时为英文
+    pub fn from_env() -> Lang {
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|key| std::env::var(key).ok())
+            .find(|value| !value.is_empty());
+        match locale {
+            Some(locale) if !locale.starts_with("zh") => Lang::En,
+            _ => Lang::Zh,
+        }
+    }
+}
+
+/// 设置全局界面语言
+pub fn set_lang(lang: Lang) {
+    LANG.store(lang as u8, Ordering::Relaxed);
+}
+
+/// 当前界面语言
+pub fn lang() -> Lang {
+    match LANG.load(Ordering::Relaxed) {
+        1 => Lang::En,
+        _ => Lang::Zh,
+    }
+}
+
+/// 按当前界面语言选择界面中的短标签
+pub fn text(zh: &'static str, en: &'static str) -> &'static str {
+    match lang() {
+        Lang::Zh => zh,
+        Lang::En => en,
+    }
+}
+
+/// 控制台消息，按当前界面语言格式化
+#[derive(Debug, Clone)]
+pub enum Msg<'a> {
+    /// 自动调整网格
+    GridAdjusted {
+        target_cm: f64,
+        from: (u32, u32),
+        to: (u32, u32),
+    },
+    /// 目标高度超过最大高度
+    HeightClamped { target_cm: f64, max_cm: f64 },
+    /// 随机种子
+    Seed(u64),
+    /// 图片的排版位置
+    Placed {
+        page: &'a str,
+        file: &'a str,
+        row: u32,
+        col: u32,
+        span: (u32, u32),
+    },
+    /// 图片的旋转与缩放
+    Resized {
+        file: &'a str,
+        rotation: u32,
+        percent: f64,
+        from: (u32, u32),
+        to: (u32, u32),
+    },
+    /// 内存预算不足
+    MemoryLow { mb: u64 },
+    /// 无法为图片生成编码
+    CodeSkipped { file: &'a str, reason: &'a str },
+    /// 有效 DPI 过低的图片汇总
+    LowDpiSummary { count: usize, min_dpi: f64 },
+    /// 有效 DPI 过低的单个图片
+    LowDpi { file: &'a str, dpi: f64 },
+    /// 全部完成
+    Done,
+    /// 中断
+    Interrupted,
+    /// 出错
+    Failed,
+    /// 中断时已输出的页数
+    InterruptedAt { written: usize, total: u64 },
+    /// 出错时已输出的页数
+    FailedAt { written: usize, total: u64 },
+    /// 无法读取的文件汇总
+    Skipped { placeholder: bool, count: usize },
+    /// 无法读取的文件及原因
+    SkippedFile { path: &'a str, reason: &'a str },
+    /// 监视模式等待输入变化
+    Watching,
+    /// HTTP 服务已启动
+    Serving(&'a str),
+    /// 交互界面：选中图片
+    TuiStatus {
+        position: usize,
+        total: usize,
+        dropped: usize,
+        path: &'a str,
+    },
+    /// 交互界面：按键说明
+    TuiHelp,
+    /// 进度条：总进度
+    Total,
+    /// 进度条：剩余时间
+    Remaining,
+    /// 进度条：输出
+    Output,
+    /// 进度条：读取图片
+    Reading,
+    /// 进度条：正在读取的文件
+    ReadingFile(&'a str),
+    /// 进度条：读取完成
+    ReadDone,
+    /// 进度条：处理图片
+    Processing,
+    /// 进度条：排版图片
+    Composing,
+    /// 进度条：已暂停
+    Paused,
+    /// 试运行：纸张与网格
+    PlanPaper {
+        size_cm: (f64, f64),
+        size_px: (u32, u32),
+        grid: (u32, u32),
+        pages: usize,
+    },
+    /// 试运行：单元格与目标高度
+    PlanCell {
+        size_cm: (f64, f64),
+        size_px: (u32, u32),
+        target_cm: f64,
+        target_px: u32,
+    },
+    /// 试运行：打印尺寸与有效 DPI
+    PlanImage { size_cm: (f64, f64), dpi: f64 },
+    /// 无法读取
+    Unreadable,
+    /// 无法读取指定文件
+    UnreadableFile { path: &'a str, reason: String },
+    /// 输出目录含有输入文件
+    OutputHasInputs(&'a str),
+    /// 输出目录非空
+    OutputNotEmpty(&'a str),
+    /// 输出文件已存在
+    OutputExists(&'a str),
+    /// 跳过的单元格不少于每页单元格数
+    SkipTooMany { cells: u32 },
+    /// 跳过单元格与成对排版同时使用
+    SkipWithPairs,
+    /// 使用预设但没有配置文件
+    ProfileWithoutConfig(&'a str),
+    /// 没有可用的字体
+    NoFont,
+    /// CMYK 输出格式不是 tiff
+    CmykNeedsTiff,
+    /// 指定 CMYK 配置文件但未输出 CMYK
+    CmykProfileNeedsCmyk,
+    /// 输出格式或色彩空间不支持 16 位
+    DeepUnsupported,
+    /// 页眉页脚没有边距可用
+    HeaderNeedsBorder,
+    /// 图片按原始尺寸超出纸张可用范围
+    OriginalTooLarge(&'a str),
+    /// 起始位置超出网格
+    StartOutOfRange { grid: (u32, u32) },
+    /// 二维码或条形码内容来自 CSV 但未指定文件，参数为选项名
+    CodeNeedsCsv(&'a str),
+    /// 份数文件中的份数无法解析
+    CopiesInvalid { path: &'a str, value: &'a str },
+    /// Code 128 内容含有不支持的字符
+    Code128Invalid(&'a str),
+    /// EAN-13 位数不对
+    Ean13Digits(&'a str),
+    /// EAN-13 校验位错误
+    Ean13Check { value: &'a str, check: u8 },
+    /// 说明文字文件无法解析
+    CaptionFileInvalid(String),
+    /// 不是 CMYK 色彩配置文件
+    NotCmykProfile(&'a str),
+    /// 分色失败
+    CmykFailed(String),
+    /// 色彩配置文件转换失败
+    ProfileConvertFailed(String),
+    /// 无法在该格式中嵌入色彩配置文件
+    ProfileEmbedFailed(&'a str),
+    /// 纸张放不下一张证件照
+    IdPhotoTooLarge { paper: &'a str, size: &'a str },
+    /// 证件照数量超出一页
+    IdPhotoCount {
+        paper: &'a str,
+        size: &'a str,
+        capacity: u32,
+        grid: (u32, u32),
+    },
+    /// 订单文件无法解析
+    OrderInvalid(String),
+    /// 订单中的份数无法解析
+    OrderCopiesInvalid { line: u64, value: &'a str },
+    /// 订单中的尺寸无法解析
+    OrderSizeInvalid { line: u64, value: &'a str },
+    /// 双面排版与成对排版或拼版同时使用
+    DuplexConflict,
+    /// 拼版的横向数量不足或与成对排版同时使用
+    ImposeInvalid,
+    /// 成对排版的横向数量不足
+    PairsNeedColumns,
+    /// 清单中的一行无法解析
+    ManifestLineInvalid { line: usize, text: &'a str },
+    /// 清单无法解析
+    ManifestInvalid(String),
+    /// 清单中的尺寸无法解析
+    ManifestSizeInvalid { name: &'a str, value: &'a str },
+    /// 内存中的图片无法读取，序号从 1 开始
+    MemoryImageUnreadable { index: usize, reason: String },
+    /// 纸张边距过大，没有可打印的区域
+    NoPrintableArea,
+    /// 横向或纵向数量为 0
+    GridEmpty,
+    /// 参数需为正数，参数为选项名
+    OptionNotPositive(&'a str),
+    /// 参数不能为负数，参数为选项名
+    OptionNegative(&'a str),
+    /// 海报重叠宽度过大
+    PosterOverlap { max_cm: f64 },
+    /// 二维码生成失败
+    QrFailed(String),
+    /// 无法监视文件
+    WatchFailed { path: &'a str, reason: String },
+    /// 水印无法读取
+    WatermarkUnreadable { path: &'a str, reason: String },
+    /// 排版结果文件无法解析
+    LayoutInvalid { path: &'a str, reason: String },
+    /// 服务：请求中指定了服务器本地文件
+    RequestLocalPath(&'a str),
+    /// 排版意外中止（程序内部错误）
+    Panicked(&'a str),
+    /// Python 模块：未知的参数
+    OptionUnknown(&'a str),
+    /// Python 模块：参数类型无效
+    OptionTypeInvalid(&'a str),
+    /// 匹配规则无效
+    PatternInvalid { pattern: &'a str, reason: String },
+    /// 输入目录不存在
+    InputDirMissing(&'a str),
+    /// 边距无效
+    BorderInvalid(&'a str),
+    /// 纸张无效
+    PaperInvalid(&'a str),
+    /// 颜色无效
+    ColorInvalid(&'a str),
+    /// 字体无法读取
+    FontUnreadable { path: &'a str, reason: String },
+    /// 字体无法解析
+    FontInvalid(&'a str),
+    /// 模板文件无法读取
+    TemplateUnreadable { path: &'a str, reason: String },
+    /// 模板文件无法解析
+    TemplateInvalid { path: &'a str, reason: String },
+    /// 模板的标签重叠或超出纸张
+    TemplateOverlap { name: &'a str, paper: &'a str },
+    /// 未知的标签模板
+    TemplateUnknown {
+        name: &'a str,
+        presets: &'a [&'a str],
+    },
+    /// 配置文件中的错误
+    ConfigFile { path: &'a str, reason: String },
+    /// 配置文件的 `profile` 不是表
+    PresetsNotTable,
+    /// 预设不是表
+    PresetNotTable(&'a str),
+    /// 未找到预设
+    PresetMissing {
+        name: &'a str,
+        available: &'a [&'a str],
+    },
+    /// 说明文字模板的括号不匹配
+    CaptionBraces(&'a str),
+    /// 说明文字模板中的字段未知
+    CaptionFieldUnknown {
+        field: &'a str,
+        available: &'a [&'a str],
+    },
+    /// 说明文字来源无效
+    CaptionSourceInvalid(&'a str),
+    /// 证件照尺寸无效
+    IdSizeInvalid {
+        value: &'a str,
+        presets: &'a [&'a str],
+    },
+    /// 外部程序无法运行
+    ProgramUnavailable { program: &'a str, reason: String },
+    /// 外部程序执行失败
+    ProgramFailed { program: &'a str, reason: &'a str },
+    /// 监视模式不能从标准输入读取文件列表
+    WatchStdin,
+    /// 编号对照表生成失败
+    NumberTableFailed(String),
+    /// 核对报告：页码标题
+    VerifyPage(usize),
+    /// 核对报告：相邻图片的间距，参数为是否横向
+    VerifyGap(bool),
+    /// 服务：无法监听地址
+    ListenFailed { addr: &'a str, reason: String },
+    /// 服务：任务表不可用
+    JobsUnavailable,
+    /// 服务：任务不存在
+    JobMissing(&'a str),
+    /// 服务：任务正在排版
+    JobRunning,
+    /// 服务：文件名无效
+    FileNameInvalid,
+    /// 服务：输出文件不存在
+    FileMissing(&'a str),
+    /// 服务：未知接口
+    UnknownEndpoint,
+    /// 页码超出范围，页码从 0 开始
+    PageOutOfRange(usize),
+    /// C 接口：参数为空指针
+    NullArgument(&'a str),
+    /// 比例无效
+    PercentInvalid(&'a str),
+    /// 比例不为正数
+    PercentNotPositive(&'a str),
+    /// 大小无效
+    SizeInvalid(&'a str),
+    /// 偏移无效
+    OffsetInvalid(&'a str),
+    /// 目录页：图片位置
+    IndexLocation { page: usize, cell: u32 },
+    /// 目录页：标题
+    IndexTitle,
+}
+
+impl fmt::Display for Msg<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let zh = lang() == Lang::Zh;
+        match *self {
+            Msg::GridAdjusted {
+                target_cm,
+                from,
+                to,
+            } => match zh {
+                true => write!(
+                    f,
+                    "为满足目标高度 {:.2}cm，网格由 {}x{} 调整为 {}x{}",
+                    target_cm, from.0, from.1, to.0, to.1
+                ),
+                false => write!(
+                    f,
+                    "Grid changed from {}x{} to {}x{} to fit target height {:.2}cm",
+                    from.0, from.1, to.0, to.1, target_cm
+                ),
+            },
+            Msg::HeightClamped { target_cm, max_cm } => match zh {
+                true => write!(
+                    f,
+                    "单图片目标高度 {:.2}cm 超过最大高度 {:.2}cm，将设置为最大高度输出",
+                    target_cm, max_cm
+                ),
+                false => write!(
+                    f,
+                    "Target height {:.2}cm exceeds maximum height {:.2}cm, using the maximum",
+                    target_cm, max_cm
+                ),
+            },
+            Msg::Seed(seed) => match zh {
+                true => write!(f, "随机种子：{}，使用 --seed {} 可重现此顺序", seed, seed),
+                false => write!(
+                    f,
+                    "Random seed: {}, use --seed {} to reproduce this order",
+                    seed, seed
+                ),
+            },
+            Msg::Placed {
+                page,
+                file,
+                row,
+                col,
+                span,
+            } => match zh {
+                true => write!(
+                    f,
+                    "{}：{} 第 {} 行第 {} 列，占 {}x{} 格",
+                    page, file, row, col, span.0, span.1
+                ),
+                false => write!(
+                    f,
+                    "{}: {} at row {}, column {}, spanning {}x{} cells",
+                    page, file, row, col, span.0, span.1
+                ),
+            },
+            Msg::Resized {
+                file,
+                rotation,
+                percent,
+                from,
+                to,
+            } => match zh {
+                true => write!(
+                    f,
+                    "{}：{}缩放 {:.1}%（{}x{} → {}x{}）",
+                    file,
+                    match rotation {
+                        0 => String::new(),
+                        degrees => format!("旋转 {}°，", degrees),
+                    },
+                    percent,
+                    from.0,
+                    from.1,
+                    to.0,
+                    to.1
+                ),
+                false => write!(
+                    f,
+                    "{}: {}scaled {:.1}% ({}x{} → {}x{})",
+                    file,
+                    match rotation {
+                        0 => String::new(),
+                        degrees => format!("rotated {}°, ", degrees),
+                    },
+                    percent,
+                    from.0,
+                    from.1,
+                    to.0,
+                    to.1
+                ),
+            },
+            Msg::MemoryLow { mb } => match zh {
+                true => write!(f, "内存预算不足以绘制单页（约需 {} MB），将逐页绘制", mb),
+                false => write!(
+                    f,
+                    "Memory budget is too small for one page (about {} MB needed), rendering one page at a time",
+                    mb
+                ),
+            },
+            Msg::CodeSkipped { file, reason } => match zh {
+                true => write!(f, "未给`{}`加编码：{}", file, reason),
+                false => write!(f, "No code for `{}`: {}", file, reason),
+            },
+            Msg::LowDpiSummary { count, min_dpi } => match zh {
+                true => write!(
+                    f,
+                    "{} 张图片有效 DPI 低于 {:.0}，打印可能模糊：",
+                    count, min_dpi
+                ),
+                false => write!(
+                    f,
+                    "{} image(s) below {:.0} DPI may print blurry:",
+                    count, min_dpi
+                ),
+            },
+            Msg::LowDpi { file, dpi } => write!(f, "{}  {:.0} DPI", file, dpi),
+            Msg::Done => write!(f, "Done!"),
+            Msg::Interrupted => f.write_str(if zh { "已中断" } else { "Interrupted" }),
+            Msg::Failed => f.write_str(if zh { "出错" } else { "Failed" }),
+            Msg::InterruptedAt { written, total } => match zh {
+                true => write!(f, "已中断，已输出 {}/{} 页", written, total),
+                false => write!(f, "Interrupted, {}/{} pages written", written, total),
+            },
+            Msg::FailedAt { written, total } => match zh {
+                true => write!(f, "出错，已输出 {}/{} 页", written, total),
+                false => write!(f, "Failed, {}/{} pages written", written, total),
+            },
+            Msg::Skipped { placeholder, count } => match (zh, placeholder) {
+                (true, true) => write!(f, "以占位图代替 {} 个无法读取的文件：", count),
+                (true, false) => write!(f, "已跳过 {} 个无法读取的文件：", count),
+                (false, true) => write!(f, "Replaced {} unreadable files with placeholders:", count),
+                (false, false) => write!(f, "Skipped {} unreadable files:", count),
+            },
+            Msg::SkippedFile { path, reason } => match zh {
+                true => write!(f, "{}：{}", path, reason),
+                false => write!(f, "{}: {}", path, reason),
+            },
+            Msg::Watching => match zh {
+                true => f.write_str("正在监视输入，按 Ctrl+C 退出"),
+                false => f.write_str("Watching inputs for changes, press Ctrl+C to stop"),
+            },
+            Msg::Serving(addr) => match zh {
+                true => write!(f, "正在监听 http://{}，按 Ctrl+C 退出", addr),
+                false => write!(f, "Listening on http://{}, press Ctrl+C to stop", addr),
+            },
+            Msg::TuiStatus {
+                position,
+                total,
+                dropped,
+                path,
+            } => match zh {
+                true => write!(f, "{}/{}（已移除 {}） {}", position, total, dropped, path),
+                false => write!(f, "{}/{} ({} dropped) {}", position, total, dropped, path),
+            },
+            Msg::TuiHelp => match zh {
+                true => f.write_str(
+                    "方向键/hjkl 选择  Shift+←→/H L 移动  PgUp/PgDn 翻页  d 移除  u 撤销  回车 输出  q 退出",
+                ),
+                false => f.write_str(
+                    "arrows/hjkl select  Shift+←→/H L move  PgUp/PgDn page  d drop  u undo  Enter render  q quit",
+                ),
+            },
+            Msg::Total => f.write_str(if zh { "总进度" } else { "Total" }),
+            Msg::Remaining => f.write_str(if zh { "剩余" } else { "ETA" }),
+            Msg::Output => f.write_str(if zh { "输出" } else { "Output" }),
+            Msg::Reading => f.write_str(if zh { "读取图片" } else { "Reading" }),
+            Msg::ReadingFile(file) => match zh {
+                true => write!(f, "读取：{}", file),
+                false => write!(f, "Reading: {}", file),
+            },
+            Msg::ReadDone => f.write_str(if zh { "读取完成" } else { "Read" }),
+            Msg::Processing => f.write_str(if zh { "处理图片" } else { "Processing" }),
+            Msg::Composing => f.write_str(if zh { "排版图片" } else { "Composing" }),
+            Msg::Paused => match zh {
+                true => f.write_str("已暂停，输入 p 回车继续"),
+                false => f.write_str("Paused, type p and Enter to resume"),
+            },
+            Msg::PlanPaper {
+                size_cm,
+                size_px,
+                grid,
+                pages,
+            } => match zh {
+                true => write!(
+                    f,
+                    "纸张 {:.2}x{:.2}cm [{}x{}px]，网格 {}x{}，共 {} 页",
+                    size_cm.0, size_cm.1, size_px.0, size_px.1, grid.0, grid.1, pages
+                ),
+                false => write!(
+                    f,
+                    "Paper {:.2}x{:.2}cm [{}x{}px], grid {}x{}, {} pages",
+                    size_cm.0, size_cm.1, size_px.0, size_px.1, grid.0, grid.1, pages
+                ),
+            },
+            Msg::PlanCell {
+                size_cm,
+                size_px,
+                target_cm,
+                target_px,
+            } => match zh {
+                true => write!(
+                    f,
+                    "单元格 {:.2}x{:.2}cm [{}x{}px]，目标高度 {:.2}cm [{}px]",
+                    size_cm.0, size_cm.1, size_px.0, size_px.1, target_cm, target_px
+                ),
+                false => write!(
+                    f,
+                    "Cell {:.2}x{:.2}cm [{}x{}px], target height {:.2}cm [{}px]",
+                    size_cm.0, size_cm.1, size_px.0, size_px.1, target_cm, target_px
+                ),
+            },
+            Msg::PlanImage { size_cm, dpi } => match zh {
+                true => write!(f, "{:.2}x{:.2}cm  有效 {:.0} DPI", size_cm.0, size_cm.1, dpi),
+                false => write!(
+                    f,
+                    "{:.2}x{:.2}cm  effective {:.0} DPI",
+                    size_cm.0, size_cm.1, dpi
+                ),
+            },
+            Msg::Unreadable => f.write_str(if zh { "无法读取" } else { "unreadable" }),
+            Msg::UnreadableFile { path, ref reason } => match zh {
+                true => write!(f, "无法读取`{}`：{}", path, reason),
+                false => write!(f, "Cannot read `{}`: {}", path, reason),
+            },
+            Msg::OutputHasInputs(dir) => match zh {
+                true => write!(f, "输出目录`{}`中含有输入文件，拒绝清空", dir),
+                false => write!(
+                    f,
+                    "Output directory `{}` contains input files, refusing to clean it",
+                    dir
+                ),
+            },
+            Msg::OutputNotEmpty(dir) => match zh {
+                true => write!(
+                    f,
+                    "输出目录`{}`非空，使用 --overwrite 覆盖同名文件或 --clean 清空目录",
+                    dir
+                ),
+                false => write!(
+                    f,
+                    "Output directory `{}` is not empty, use --overwrite to replace files or --clean to empty it",
+                    dir
+                ),
+            },
+            Msg::OutputExists(path) => match zh {
+                true => write!(f, "输出文件`{}`已存在，使用 --overwrite 覆盖", path),
+                false => write!(
+                    f,
+                    "Output file `{}` already exists, use --overwrite to replace it",
+                    path
+                ),
+            },
+            Msg::SkipTooMany { cells } => match zh {
+                true => write!(f, "跳过的单元格数量需小于每页单元格数 {}", cells),
+                false => write!(
+                    f,
+                    "The number of skipped cells must be less than the {} cells per page",
+                    cells
+                ),
+            },
+            Msg::SkipWithPairs => f.write_str(match zh {
+                true => "跳过单元格不能与成对排版同时使用",
+                false => "Skipping cells cannot be combined with pairs",
+            }),
+            Msg::ProfileWithoutConfig(name) => match zh {
+                true => write!(f, "未找到配置文件，无法使用预设 `{}`", name),
+                false => write!(f, "No config file found, cannot use profile `{}`", name),
+            },
+            Msg::NoFont => f.write_str(match zh {
+                true => "未找到可用的字体，请使用 --font 指定",
+                false => "No usable font found, specify one with --font",
+            }),
+            Msg::CmykNeedsTiff => f.write_str(match zh {
+                true => "CMYK 输出仅支持 tiff 格式",
+                false => "CMYK output only supports the tiff format",
+            }),
+            Msg::CmykProfileNeedsCmyk => f.write_str(match zh {
+                true => "--cmyk-profile 需配合 --colorspace cmyk 使用",
+                false => "--cmyk-profile requires --colorspace cmyk",
+            }),
+            Msg::DeepUnsupported => f.write_str(match zh {
+                true => "16 位输出仅支持 png 与 tiff 格式，且不支持 CMYK",
+                false => "16-bit output only supports png and tiff, and not CMYK",
+            }),
+            Msg::HeaderNeedsBorder => f.write_str(match zh {
+                true => "页眉与页脚绘制在纸张边距内，请使用 --border 指定边距",
+                false => "Headers and footers are drawn in the paper border, set one with --border",
+            }),
+            Msg::OriginalTooLarge(file) => match zh {
+                true => write!(
+                    f,
+                    "`{}`按原始尺寸超出纸张可用范围，请降低网格数量或提高 --ppi",
+                    file
+                ),
+                false => write!(
+                    f,
+                    "`{}` at its original size exceeds the printable area, use fewer cells or a higher --ppi",
+                    file
+                ),
+            },
+            Msg::StartOutOfRange { grid } => match zh {
+                true => write!(f, "起始位置超出每页 {}×{} 个单元格", grid.0, grid.1),
+                false => write!(
+                    f,
+                    "The start position is outside the {}×{} cells per page",
+                    grid.0, grid.1
+                ),
+            },
+            Msg::CodeNeedsCsv(option) => match zh {
+                true => write!(f, "{0} from-csv 需要以 {0}-csv 指定内容文件", option),
+                false => write!(
+                    f,
+                    "{0} from-csv requires a content file given with {0}-csv",
+                    option
+                ),
+            },
+            Msg::CopiesInvalid { path, value } => match zh {
+                true => write!(f, "份数文件中`{}`的份数无法解析：`{}`", path, value),
+                false => write!(
+                    f,
+                    "Cannot parse the number of copies for `{}` in the copies file: `{}`",
+                    path, value
+                ),
+            },
+            Msg::Code128Invalid(value) => match zh {
+                true => write!(f, "Code 128 只支持 ASCII 可打印字符：`{}`", value),
+                false => write!(
+                    f,
+                    "Code 128 only supports printable ASCII characters: `{}`",
+                    value
+                ),
+            },
+            Msg::Ean13Digits(value) => match zh {
+                true => write!(f, "EAN-13 需要 12 或 13 位数字：`{}`", value),
+                false => write!(f, "EAN-13 requires 12 or 13 digits: `{}`", value),
+            },
+            Msg::Ean13Check { value, check } => match zh {
+                true => write!(f, "EAN-13 校验位错误：`{}`，应为 {}", value, check),
+                false => write!(
+                    f,
+                    "Wrong EAN-13 check digit: `{}`, expected {}",
+                    value, check
+                ),
+            },
+            Msg::CaptionFileInvalid(ref reason) => match zh {
+                true => write!(f, "说明文字文件无法解析：{}", reason),
+                false => write!(f, "Cannot parse the captions file: {}", reason),
+            },
+            Msg::NotCmykProfile(path) => match zh {
+                true => write!(f, "{} 不是 CMYK 色彩配置文件", path),
+                false => write!(f, "{} is not a CMYK color profile", path),
+            },
+            Msg::CmykFailed(ref reason) => match zh {
+                true => write!(f, "无法分色为 CMYK：{}", reason),
+                false => write!(f, "Cannot separate into CMYK: {}", reason),
+            },
+            Msg::ProfileConvertFailed(ref reason) => match zh {
+                true => write!(f, "无法转换色彩配置文件：{}", reason),
+                false => write!(f, "Cannot convert the color profile: {}", reason),
+            },
+            Msg::ProfileEmbedFailed(format) => match zh {
+                true => write!(f, "无法在 {} 文件中嵌入色彩配置文件", format),
+                false => write!(f, "Cannot embed a color profile in {} files", format),
+            },
+            Msg::IdPhotoTooLarge { paper, size } => match zh {
+                true => write!(
+                    f,
+                    "{} cm 的纸张放不下一张 {} mm 的证件照，请减小边距或间距",
+                    paper, size
+                ),
+                false => write!(
+                    f,
+                    "A {} cm paper cannot hold a {} mm ID photo, reduce the border or gap",
+                    paper, size
+                ),
+            },
+            Msg::IdPhotoCount {
+                paper,
+                size,
+                capacity,
+                grid,
+            } => match zh {
+                true => write!(
+                    f,
+                    "{} cm 的纸张每页最多放 {} 张 {} mm 的证件照（{}×{}）",
+                    paper, capacity, size, grid.0, grid.1
+                ),
+                false => write!(
+                    f,
+                    "A {} cm paper holds at most {} ID photos of {} mm ({}×{})",
+                    paper, capacity, size, grid.0, grid.1
+                ),
+            },
+            Msg::OrderInvalid(ref reason) => match zh {
+                true => write!(f, "订单文件无法解析：{}", reason),
+                false => write!(f, "Cannot parse the order file: {}", reason),
+            },
+            Msg::OrderCopiesInvalid { line, value } => match zh {
+                true => write!(f, "订单第{}行的份数无法解析：`{}`", line, value),
+                false => write!(
+                    f,
+                    "Cannot parse the copies on line {} of the order file: `{}`",
+                    line, value
+                ),
+            },
+            Msg::OrderSizeInvalid { line, value } => match zh {
+                true => write!(
+                    f,
+                    "订单第{}行的尺寸无法解析：`{}`，可写作 10cm、150% 或 1.5x",
+                    line, value
+                ),
+                false => write!(
+                    f,
+                    "Cannot parse the size on line {} of the order file: `{}`, write it as 10cm, 150% or 1.5x",
+                    line, value
+                ),
+            },
+            Msg::DuplexConflict => f.write_str(match zh {
+                true => "双面排版不能与成对排版或拼版同时使用",
+                false => "Duplex cannot be combined with pairs or imposition",
+            }),
+            Msg::ImposeInvalid => f.write_str(match zh {
+                true => "拼版需要横向数量至少为 2，且不能与成对排版同时使用",
+                false => "Imposition needs at least 2 columns and cannot be combined with pairs",
+            }),
+            Msg::PairsNeedColumns => f.write_str(match zh {
+                true => "成对排版需要横向数量至少为 2",
+                false => "Pairs need at least 2 columns",
+            }),
+            Msg::ManifestLineInvalid { line, text } => match zh {
+                true => write!(f, "清单第{}行无法解析：`{}`", line, text),
+                false => write!(f, "Cannot parse line {} of the manifest: `{}`", line, text),
+            },
+            Msg::ManifestInvalid(ref reason) => match zh {
+                true => write!(f, "无法解析清单：{}", reason),
+                false => write!(f, "Cannot parse the manifest: {}", reason),
+            },
+            Msg::ManifestSizeInvalid { name, value } => match zh {
+                true => write!(f, "清单中`{}`的尺寸无法解析：`{}`", name, value),
+                false => write!(
+                    f,
+                    "Cannot parse the size of `{}` in the manifest: `{}`",
+                    name, value
+                ),
+            },
+            Msg::MemoryImageUnreadable { index, ref reason } => match zh {
+                true => write!(f, "第 {} 张图片无法读取：{}", index, reason),
+                false => write!(f, "Cannot read image {}: {}", index, reason),
+            },
+            Msg::NoPrintableArea => f.write_str(match zh {
+                true => "纸张边距过大，没有可打印的区域",
+                false => "The paper border leaves no printable area",
+            }),
+            Msg::GridEmpty => f.write_str(match zh {
+                true => "横向与纵向数量需至少为 1",
+                false => "The number of columns and rows must be at least 1",
+            }),
+            Msg::OptionNotPositive(name) => match zh {
+                true => write!(f, "{} 需为正数", name),
+                false => write!(f, "{} must be a positive number", name),
+            },
+            Msg::OptionNegative(name) => match zh {
+                true => write!(f, "{} 不能为负数", name),
+                false => write!(f, "{} must not be negative", name),
+            },
+            Msg::PosterOverlap { max_cm } => match zh {
+                true => write!(f, "重叠宽度需小于可打印区域短边的一半 {:.1} cm", max_cm),
+                false => write!(
+                    f,
+                    "The overlap must be less than half the short side of the printable area, {:.1} cm",
+                    max_cm
+                ),
+            },
+            Msg::QrFailed(ref reason) => match zh {
+                true => write!(f, "无法生成二维码：{}", reason),
+                false => write!(f, "Cannot create the QR code: {}", reason),
+            },
+            Msg::WatchFailed { path, ref reason } => match zh {
+                true => write!(f, "无法监视`{}`：{}", path, reason),
+                false => write!(f, "Cannot watch `{}`: {}", path, reason),
+            },
+            Msg::WatermarkUnreadable { path, ref reason } => match zh {
+                true => write!(f, "无法读取水印`{}`：{}", path, reason),
+                false => write!(f, "Cannot read the watermark `{}`: {}", path, reason),
+            },
+            Msg::LayoutInvalid { path, ref reason } => match zh {
+                true => write!(f, "无法解析`{}`：{}", path, reason),
+                false => write!(f, "Cannot parse `{}`: {}", path, reason),
+            },
+            Msg::RequestLocalPath(key) => match zh {
+                true => write!(f, "请求不能指定服务器本地文件`{}`", key),
+                false => write!(f, "A request cannot set the server-local file `{}`", key),
+            },
+            Msg::Panicked(reason) => match zh {
+                true => write!(f, "排版意外中止：{}", reason),
+                false => write!(f, "Typesetting aborted unexpectedly: {}", reason),
+            },
+            Msg::OptionUnknown(key) => match zh {
+                true => write!(f, "未知参数`{}`", key),
+                false => write!(f, "Unknown option `{}`", key),
+            },
+            Msg::OptionTypeInvalid(key) => match zh {
+                true => write!(f, "参数`{}`的类型无效", key),
+                false => write!(f, "Option `{}` has an invalid type", key),
+            },
+            Msg::PatternInvalid { pattern, ref reason } => match zh {
+                true => write!(f, "无效的匹配规则`{}`：{}", pattern, reason),
+                false => write!(f, "Invalid pattern `{}`: {}", pattern, reason),
+            },
+            Msg::InputDirMissing(dir) => match zh {
+                true => write!(f, "输入目录`{}`不存在或无法读取", dir),
+                false => write!(f, "Input directory `{}` does not exist or cannot be read", dir),
+            },
+            Msg::BorderInvalid(value) => match zh {
+                true => write!(
+                    f,
+                    "无效的边距`{}`，应为一个值或上、右、下、左四个值（单位：cm），如 1.0,0.5,1.0,0.5",
+                    value
+                ),
+                false => write!(
+                    f,
+                    "Invalid border `{}`, expected one value or four values for top, right, bottom and left (in cm), e.g. 1.0,0.5,1.0,0.5",
+                    value
+                ),
+            },
+            Msg::PaperInvalid(value) => match zh {
+                true => write!(
+                    f,
+                    "无效的纸张`{}`，可选 A3、A4、A5、Letter、Legal 或 宽x高（单位：cm，或以 in 结尾表示英寸）",
+                    value
+                ),
+                false => write!(
+                    f,
+                    "Invalid paper `{}`, expected A3, A4, A5, Letter, Legal or WIDTHxHEIGHT (in cm, or inches with an `in` suffix)",
+                    value
+                ),
+            },
+            Msg::ColorInvalid(value) => match zh {
+                true => write!(
+                    f,
+                    "无效的颜色`{}`，可用名称如 white、black、gray、transparent 或 #rrggbb",
+                    value
+                ),
+                false => write!(
+                    f,
+                    "Invalid color `{}`, expected a name such as white, black, gray, transparent or #rrggbb",
+                    value
+                ),
+            },
+            Msg::FontUnreadable { path, ref reason } => match zh {
+                true => write!(f, "无法读取字体`{}`：{}", path, reason),
+                false => write!(f, "Cannot read the font `{}`: {}", path, reason),
+            },
+            Msg::FontInvalid(path) => match zh {
+                true => write!(f, "无法解析字体`{}`", path),
+                false => write!(f, "Cannot parse the font `{}`", path),
+            },
+            Msg::TemplateUnreadable { path, ref reason } => match zh {
+                true => write!(f, "无法读取模板文件`{}`：{}", path, reason),
+                false => write!(f, "Cannot read the template file `{}`: {}", path, reason),
+            },
+            Msg::TemplateInvalid { path, ref reason } => match zh {
+                true => write!(f, "无法解析模板文件`{}`：{}", path, reason),
+                false => write!(f, "Cannot parse the template file `{}`: {}", path, reason),
+            },
+            Msg::TemplateOverlap { name, paper } => match zh {
+                true => write!(f, "模板`{}`的标签重叠或超出纸张 {} cm", name, paper),
+                false => write!(
+                    f,
+                    "Labels of template `{}` overlap or exceed the {} cm paper",
+                    name, paper
+                ),
+            },
+            Msg::TemplateUnknown { name, presets } => match zh {
+                true => write!(
+                    f,
+                    "未知的标签模板`{}`，可选 {} 或 TOML 模板文件路径",
+                    name,
+                    presets.join("、")
+                ),
+                false => write!(
+                    f,
+                    "Unknown label template `{}`, expected {} or the path of a TOML template file",
+                    name,
+                    presets.join(", ")
+                ),
+            },
+            Msg::ConfigFile { path, ref reason } => match zh {
+                true => write!(f, "`{}`：{}", path, reason),
+                false => write!(f, "`{}`: {}", path, reason),
+            },
+            Msg::PresetsNotTable => f.write_str(match zh {
+                true => "`profile` 应为表",
+                false => "`profile` must be a table",
+            }),
+            Msg::PresetNotTable(name) => match zh {
+                true => write!(f, "预设 `{}` 应为表", name),
+                false => write!(f, "Profile `{}` must be a table", name),
+            },
+            Msg::PresetMissing { name, available } => match zh {
+                true => write!(f, "未找到预设 `{}`，可用预设：{}", name, available.join(", ")),
+                false => write!(
+                    f,
+                    "Profile `{}` not found, available profiles: {}",
+                    name,
+                    available.join(", ")
+                ),
+            },
+            Msg::CaptionBraces(template) => match zh {
+                true => write!(f, "模板`{}`中的括号不匹配", template),
+                false => write!(f, "Unbalanced braces in template `{}`", template),
+            },
+            Msg::CaptionFieldUnknown { field, available } => match zh {
+                true => write!(
+                    f,
+                    "未知的字段`{{{}}}`，可选 filename、{}",
+                    field,
+                    available.join("、")
+                ),
+                false => write!(
+                    f,
+                    "Unknown field `{{{}}}`, expected filename, {}",
+                    field,
+                    available.join(", ")
+                ),
+            },
+            Msg::CaptionSourceInvalid(value) => match zh {
+                true => write!(
+                    f,
+                    "无效的说明文字来源`{}`，可选 filename、exif 或 exif:模板",
+                    value
+                ),
+                false => write!(
+                    f,
+                    "Invalid caption source `{}`, expected filename, exif or exif:TEMPLATE",
+                    value
+                ),
+            },
+            Msg::IdSizeInvalid { value, presets } => match zh {
+                true => write!(
+                    f,
+                    "无效的证件照尺寸`{}`，可选 {} 或 宽x高（单位：mm），如 35x45",
+                    value,
+                    presets.join("、")
+                ),
+                false => write!(
+                    f,
+                    "Invalid ID photo size `{}`, expected {} or WIDTHxHEIGHT (in mm), e.g. 35x45",
+                    value,
+                    presets.join(", ")
+                ),
+            },
+            Msg::ProgramUnavailable { program, ref reason } => match zh {
+                true => write!(f, "无法运行`{}`：{}", program, reason),
+                false => write!(f, "Cannot run `{}`: {}", program, reason),
+            },
+            Msg::ProgramFailed { program, reason } => match zh {
+                true => write!(f, "`{}`执行失败：{}", program, reason),
+                false => write!(f, "`{}` failed: {}", program, reason),
+            },
+            Msg::WatchStdin => f.write_str(match zh {
+                true => "--watch 不能与 --files-from - 同时使用",
+                false => "--watch cannot be combined with --files-from -",
+            }),
+            Msg::NumberTableFailed(ref reason) => match zh {
+                true => write!(f, "无法生成编号对照表：{}", reason),
+                false => write!(f, "Cannot write the number table: {}", reason),
+            },
+            Msg::VerifyPage(page) => match zh {
+                true => write!(f, "== 第 {} 页 ==", page),
+                false => write!(f, "== Page {} ==", page),
+            },
+            Msg::VerifyGap(horizontal) => f.write_str(match (zh, horizontal) {
+                (true, true) => "横向间距",
+                (true, false) => "纵向间距",
+                (false, true) => "Horizontal gap",
+                (false, false) => "Vertical gap",
+            }),
+            Msg::ListenFailed { addr, ref reason } => match zh {
+                true => write!(f, "无法监听`{}`：{}", addr, reason),
+                false => write!(f, "Cannot listen on `{}`: {}", addr, reason),
+            },
+            Msg::JobsUnavailable => f.write_str(match zh {
+                true => "任务表不可用",
+                false => "The job table is unavailable",
+            }),
+            Msg::JobMissing(id) => match zh {
+                true => write!(f, "任务`{}`不存在", id),
+                false => write!(f, "Job `{}` does not exist", id),
+            },
+            Msg::JobRunning => f.write_str(match zh {
+                true => "任务正在排版",
+                false => "The job is running",
+            }),
+            Msg::FileNameInvalid => f.write_str(match zh {
+                true => "无效的文件名",
+                false => "Invalid file name",
+            }),
+            Msg::FileMissing(name) => match zh {
+                true => write!(f, "文件`{}`不存在", name),
+                false => write!(f, "File `{}` does not exist", name),
+            },
+            Msg::UnknownEndpoint => f.write_str(match zh {
+                true => "未知接口",
+                false => "Unknown endpoint",
+            }),
+            Msg::PageOutOfRange(index) => match zh {
+                true => write!(f, "页码 {} 超出范围", index),
+                false => write!(f, "Page {} is out of range", index),
+            },
+            Msg::NullArgument(name) => match zh {
+                true => write!(f, "{} 为空", name),
+                false => write!(f, "{} is null", name),
+            },
+            Msg::PercentInvalid(value) => match zh {
+                true => write!(f, "无效的比例`{}`", value),
+                false => write!(f, "Invalid percentage `{}`", value),
+            },
+            Msg::PercentNotPositive(value) => match zh {
+                true => write!(f, "比例必须大于0：`{}`", value),
+                false => write!(f, "The percentage must be greater than 0: `{}`", value),
+            },
+            Msg::SizeInvalid(value) => match zh {
+                true => write!(f, "无效的大小`{}`", value),
+                false => write!(f, "Invalid size `{}`", value),
+            },
+            Msg::OffsetInvalid(value) => match zh {
+                true => write!(f, "无效的偏移`{}`，格式为 X,Y，如 0.5,-0.3", value),
+                false => write!(f, "Invalid offset `{}`, expected X,Y, e.g. 0.5,-0.3", value),
+            },
+            Msg::IndexLocation { page, cell } => match zh {
+                true => write!(f, "第 {} 页 第 {} 格", page, cell),
+                false => write!(f, "p. {} #{}", page, cell),
+            },
+            Msg::IndexTitle => f.write_str(match zh {
+                true => "目录",
+                false => "Index",
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_msg_lang() {
+        let msg = Msg::HeightClamped {
+            target_cm: 6.0,
+            max_cm: 5.5,
+        };
+        set_lang(Lang::En);
+        assert_eq!(
+            msg.to_string(),
+            "Target height 6.00cm exceeds maximum height 5.50cm, using the maximum"
+        );
+        assert_eq!(
+            Msg::SkipWithPairs.to_string(),
+            "Skipping cells cannot be combined with pairs"
+        );
+        assert_eq!(
+            Msg::InputDirMissing("photos").to_string(),
+            "Input directory `photos` does not exist or cannot be read"
+        );
+        let presets = ["1in", "2in"];
+        let id_size = Msg::IdSizeInvalid {
+            value: "big",
+            presets: &presets,
+        };
+        assert!(id_size.to_string().contains("expected 1in, 2in or"));
+        set_lang(Lang::Zh);
+        assert!(msg.to_string().starts_with("单图片目标高度 6.00cm"));
+        assert!(id_size.to_string().contains("可选 1in、2in 或"));
+    }
+}
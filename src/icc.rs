@@ -0,0 +1,362 @@
+//! ICC 色彩配置文件
+//!
+//! 读取图片内嵌的配置文件，将图片转换到输出配置文件，并在输出文件中嵌入该配置文件。
+//! 未嵌入配置文件的图片视为 sRGB
+
+use flate2::{write::ZlibEncoder, Compression};
+use image::{
+    codecs::{jpeg::JpegDecoder, png::PngDecoder, tiff::TiffDecoder, webp::WebPDecoder},
+    io::Reader,
+    DynamicImage, ImageBuffer, ImageDecoder, ImageFormat, Rgba,
+};
+use moxcms::{ColorProfile, DataColorSpace, Layout, TransformOptions};
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+use std::{fs::File, io::BufReader, io::Write, path::Path};
+
+use crate::{depth, i18n::Msg, Error, InputSnafu};
+
+/// 输出色彩配置文件
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Profile {
+    /// sRGB IEC61966-2.1
+    Srgb,
+    /// Adobe RGB (1998)
+    AdobeRgb,
+}
+
+impl Profile {
+    fn color_profile(self) -> ColorProfile {
+        match self {
+            Profile::Srgb => ColorProfile::new_srgb(),
+            Profile::AdobeRgb => ColorProfile::new_adobe_rgb(),
+        }
+    }
+
+    /// 配置文件名称
+    pub fn name(self) -> &'static str {
+        match self {
+            Profile::Srgb => "sRGB IEC61966-2.1",
+            Profile::AdobeRgb => "Adobe RGB (1998)",
+        }
+    }
+
+    /// ICC 配置文件数据
+    pub fn icc(self) -> Vec<u8> {
+        self.color_profile()
+            .encode()
+            .expect("built-in profiles are encodable")
+    }
+}
+
+/// 读取图片内嵌的 ICC 配置文件，支持 PNG、JPEG、TIFF 与 WebP
+pub fn read(path: &Path) -> Option<Vec<u8>> {
+    let format = Reader::open(path)
+        .ok()?
+        .with_guessed_format()
+        .ok()?
+        .format()?;
+    let reader = || File::open(path).map(BufReader::new).ok();
+    match format {
+        ImageFormat::Png => PngDecoder::new(reader()?).ok()?.icc_profile(),
+        ImageFormat::Jpeg => JpegDecoder::new(reader()?).ok()?.icc_profile(),
+        ImageFormat::Tiff => TiffDecoder::new(reader()?).ok()?.icc_profile(),
+        ImageFormat::WebP => WebPDecoder::new(reader()?).ok()?.icc_profile(),
+        _ => None,
+    }
+}
+
+/// 将图片从内嵌配置文件转换到输出配置文件
+///
+/// 无法解析或不是 RGB、灰度的配置文件视为 sRGB。16 位图片输出 16 位，其余输出 8 位
+///
+/// - source: 图片内嵌的 ICC 配置文件
+/// - target: 输出配置文件
+pub fn convert(
+    image: DynamicImage,
+    source: Option<&[u8]>,
+    target: Profile,
+) -> Result<DynamicImage, Error> {
+    let source = source
+        .and_then(|icc| ColorProfile::new_from_slice(icc).ok())
+        .filter(|profile| {
+            matches!(
+                profile.color_space,
+                DataColorSpace::Rgb | DataColorSpace::Gray
+            )
+        });
+    let source = match source {
+        Some(source) => source,
+        None if target == Profile::Srgb => return Ok(image),
+        None => ColorProfile::new_srgb(),
+    };
+    let gray = source.color_space == DataColorSpace::Gray;
+    let layout = match gray {
+        true => Layout::GrayAlpha,
+        false => Layout::Rgba,
+    };
+    let target = target.color_profile();
+    let options = TransformOptions::default();
+    let failed = |e: moxcms::CmsError| Error::Input {
+        reason: Msg::ProfileConvertFailed(e.to_string()).to_string(),
+    };
+    let (w, h) = (image.width(), image.height());
+    let converted = match depth::is_deep(&image) {
+        true => {
+            let transform = source
+                .create_transform_16bit(layout, &target, Layout::Rgba, options)
+                .map_err(failed)?;
+            let src = match gray {
+                true => image.to_luma_alpha16().into_raw(),
+                false => image.to_rgba16().into_raw(),
+            };
+            let mut dst = vec![0u16; w as usize * h as usize * 4];
+            transform.transform(&src, &mut dst).map_err(failed)?;
+            let buffer = ImageBuffer::<Rgba<u16>, _>::from_raw(w, h, dst);
+            buffer.map(DynamicImage::ImageRgba16)
+        }
+        false => {
+            let transform = source
+                .create_transform_8bit(layout, &target, Layout::Rgba, options)
+                .map_err(failed)?;
+            let src = match gray {
+                true => image.to_luma_alpha8().into_raw(),
+                false => image.to_rgba8().into_raw(),
+            };
+            let mut dst = vec![0u8; w as usize * h as usize * 4];
+            transform.transform(&src, &mut dst).map_err(failed)?;
+            let buffer = ImageBuffer::<Rgba<u8>, _>::from_raw(w, h, dst);
+            buffer.map(DynamicImage::ImageRgba8)
+        }
+    };
+    Ok(converted.expect("buffer size matches image dimensions"))
+}
+
+/// 在已编码的图片文件中嵌入 ICC 配置文件，支持 PNG、JPEG、TIFF 与 WebP
+///
+/// - bytes: 编码后的图片文件
+/// - format: 图片格式
+/// - icc: ICC 配置文件
+pub fn embed(bytes: Vec<u8>, format: ImageFormat, icc: &[u8]) -> Result<Vec<u8>, Error> {
+    let embedded = match format {
+        ImageFormat::Png => embed_png(&bytes, icc),
+        ImageFormat::Jpeg => embed_jpeg(&bytes, icc),
+        ImageFormat::Tiff => embed_tiff(&bytes, icc),
+        ImageFormat::WebP => embed_webp(&bytes, icc),
+        _ => None,
+    };
+    embedded.context(InputSnafu {
+        reason: Msg::ProfileEmbedFailed(&format!("{:?}", format)).to_string(),
+    })
+}
+
+/// 在 IHDR 之后插入 iCCP 块
+fn embed_png(bytes: &[u8], icc: &[u8]) -> Option<Vec<u8>> {
+    // 8 字节签名与 25 字节 IHDR 块
+    const IHDR_END: usize = 33;
+    (bytes.get(12..16)? == b"IHDR").then_some(())?;
+    let mut data = b"ICC Profile\0\0".to_vec();
+    let mut encoder = ZlibEncoder::new(&mut data, Compression::default());
+    encoder.write_all(icc).ok()?;
+    encoder.finish().ok()?;
+
+    let mut out = bytes[..IHDR_END].to_vec();
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let chunk_start = out.len();
+    out.extend_from_slice(b"iCCP");
+    out.extend_from_slice(&data);
+    let crc = crc32fast::hash(&out[chunk_start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+    out.extend_from_slice(&bytes[IHDR_END..]);
+    Some(out)
+}
+
+/// 在 SOI 与 JFIF 段之后插入 APP2 段，过大的配置文件分段存放
+fn embed_jpeg(bytes: &[u8], icc: &[u8]) -> Option<Vec<u8>> {
+    // 每段最多 65535 字节，扣除长度、标识与序号
+    const CHUNK: usize = 65535 - 2 - 14;
+    (bytes.get(..2)? == [0xFF, 0xD8]).then_some(())?;
+    let mut at = 2;
+    if bytes.get(2..4)? == [0xFF, 0xE0] {
+        at += 2 + u16::from_be_bytes([*bytes.get(4)?, *bytes.get(5)?]) as usize;
+    }
+    let chunks: Vec<&[u8]> = icc.chunks(CHUNK).collect();
+    (chunks.len() <= u8::MAX as usize).then_some(())?;
+    let mut out = bytes.get(..at)?.to_vec();
+    for (i, chunk) in chunks.iter().enumerate() {
+        out.extend_from_slice(&[0xFF, 0xE2]);
+        out.extend_from_slice(&(chunk.len() as u16 + 16).to_be_bytes());
+        out.extend_from_slice(b"ICC_PROFILE\0");
+        out.extend_from_slice(&[i as u8 + 1, chunks.len() as u8]);
+        out.extend_from_slice(chunk);
+    }
+    out.extend_from_slice(&bytes[at..]);
+    Some(out)
+}
+
+/// 在文件末尾追加配置文件与新的首个 IFD，新 IFD 增加 ICC 标签（34675）
+fn embed_tiff(bytes: &[u8], icc: &[u8]) -> Option<Vec<u8>> {
+    const ICC_TAG: u16 = 34675;
+    let little = match bytes.get(..2)? {
+        b"II" => true,
+        b"MM" => false,
+        _ => return None,
+    };
+    let u16_at = |at: usize| {
+        let b = [*bytes.get(at)?, *bytes.get(at + 1)?];
+        Some(match little {
+            true => u16::from_le_bytes(b),
+            false => u16::from_be_bytes(b),
+        })
+    };
+    let u32_at = |at: usize| {
+        let b: [u8; 4] = bytes.get(at..at + 4)?.try_into().ok()?;
+        Some(match little {
+            true => u32::from_le_bytes(b),
+            false => u32::from_be_bytes(b),
+        })
+    };
+    let put16 = |out: &mut Vec<u8>, v: u16| match little {
+        true => out.extend_from_slice(&v.to_le_bytes()),
+        false => out.extend_from_slice(&v.to_be_bytes()),
+    };
+    let put32 = |out: &mut Vec<u8>, v: u32| match little {
+        true => out.extend_from_slice(&v.to_le_bytes()),
+        false => out.extend_from_slice(&v.to_be_bytes()),
+    };
+    (u16_at(2)? == 42).then_some(())?;
+    let ifd = u32_at(4)? as usize;
+    let count = u16_at(ifd)? as usize;
+    let entries: Vec<&[u8]> = (0..count)
+        .map(|i| bytes.get(ifd + 2 + i * 12..ifd + 14 + i * 12))
+        .collect::<Option<_>>()?;
+    let next = u32_at(ifd + 2 + count * 12)?;
+
+    let mut out = bytes.to_vec();
+    out.resize(out.len().next_multiple_of(2), 0);
+    let icc_offset = out.len() as u32;
+    out.extend_from_slice(icc);
+    out.resize(out.len().next_multiple_of(2), 0);
+    let new_ifd = out.len() as u32;
+    let mut icc_entry = Vec::with_capacity(12);
+    put16(&mut icc_entry, ICC_TAG);
+    put16(&mut icc_entry, 7);
+    put32(&mut icc_entry, icc.len() as u32);
+    put32(&mut icc_entry, icc_offset);
+    // 标签须按升序排列，替换已有的 ICC 标签
+    let tag = |entry: &[u8]| match little {
+        true => u16::from_le_bytes([entry[0], entry[1]]),
+        false => u16::from_be_bytes([entry[0], entry[1]]),
+    };
+    let mut entries: Vec<&[u8]> = entries.into_iter().filter(|e| tag(e) != ICC_TAG).collect();
+    let at = entries.partition_point(|e| tag(e) < ICC_TAG);
+    entries.insert(at, &icc_entry);
+    put16(&mut out, entries.len() as u16);
+    for entry in entries {
+        out.extend_from_slice(entry);
+    }
+    put32(&mut out, next);
+    let mut header = Vec::with_capacity(4);
+    put32(&mut header, new_ifd);
+    out[4..8].copy_from_slice(&header);
+    Some(out)
+}
+
+/// 改写为扩展格式：插入带 ICC 标志的 VP8X 块与 ICCP 块
+fn embed_webp(bytes: &[u8], icc: &[u8]) -> Option<Vec<u8>> {
+    (bytes.get(..4)? == b"RIFF" && bytes.get(8..12)? == b"WEBP").then_some(())?;
+    let chunk = |data: &[u8], fourcc: &[u8]| {
+        let mut out = fourcc.to_vec();
+        out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        out.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            out.push(0);
+        }
+        out
+    };
+    let first = bytes.get(12..16)?;
+    let payload = bytes.get(20..)?;
+    let mut body = b"WEBP".to_vec();
+    match first {
+        b"VP8X" => {
+            let mut vp8x = bytes.get(12..30)?.to_vec();
+            vp8x[8] |= 0x20;
+            body.extend_from_slice(&vp8x);
+            body.extend_from_slice(&chunk(icc, b"ICCP"));
+            body.extend_from_slice(bytes.get(30..)?);
+        }
+        b"VP8L" | b"VP8 " => {
+            let (w, h, alpha) = match first {
+                b"VP8L" => {
+                    let bits = u32::from_le_bytes(payload.get(1..5)?.try_into().ok()?);
+                    (
+                        (bits & 0x3FFF) + 1,
+                        ((bits >> 14) & 0x3FFF) + 1,
+                        bits >> 28 & 1 == 1,
+                    )
+                }
+                _ => {
+                    let dim = |at: usize| {
+                        Some(
+                            u16::from_le_bytes([*payload.get(at)?, *payload.get(at + 1)?]) & 0x3FFF,
+                        )
+                    };
+                    (dim(6)? as u32, dim(8)? as u32, false)
+                }
+            };
+            let mut vp8x = vec![0x20 | if alpha { 0x10 } else { 0 }, 0, 0, 0];
+            vp8x.extend_from_slice(&(w - 1).to_le_bytes()[..3]);
+            vp8x.extend_from_slice(&(h - 1).to_le_bytes()[..3]);
+            body.extend_from_slice(&chunk(&vp8x, b"VP8X"));
+            body.extend_from_slice(&chunk(icc, b"ICCP"));
+            body.extend_from_slice(bytes.get(12..)?);
+        }
+        _ => return None,
+    }
+    let mut out = b"RIFF".to_vec();
+    out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    out.extend_from_slice(&body);
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{codecs::jpeg::JpegEncoder, RgbaImage};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_icc() {
+        // sRGB 纯绿在 Adobe RGB 中饱和度较低，红色通道不为 0
+        let green = DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba([0, 255, 0, 255])));
+        let adobe = convert(green.clone(), None, Profile::AdobeRgb).unwrap();
+        let [r, g, _, a] = adobe.to_rgba8().get_pixel(0, 0).0;
+        assert!(r > 100 && g > 200 && a == 255, "{} {}", r, g);
+        let srgb = Profile::Srgb.icc();
+        let back = convert(adobe, Some(&Profile::AdobeRgb.icc()), Profile::Srgb).unwrap();
+        let [r, g, b, _] = back.to_rgba8().get_pixel(0, 0).0;
+        assert!(r < 8 && g > 247 && b < 8, "{} {} {}", r, g, b);
+
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(3, 2, Rgba([1, 2, 3, 255])));
+        for format in [ImageFormat::Png, ImageFormat::Tiff, ImageFormat::WebP] {
+            let mut bytes = Cursor::new(Vec::new());
+            image.write_to(&mut bytes, format).unwrap();
+            let embedded = embed(bytes.into_inner(), format, &srgb).unwrap();
+            let path = std::env::temp_dir().join(format!("icc-test.{:?}", format));
+            std::fs::write(&path, &embedded).unwrap();
+            assert_eq!(read(&path).as_deref(), Some(&srgb[..]), "{:?}", format);
+            let decoded = image::load_from_memory(&embedded).unwrap();
+            assert_eq!(decoded.to_rgba8(), image.to_rgba8(), "{:?}", format);
+            let _ = std::fs::remove_file(path);
+        }
+        let mut jpeg = Vec::new();
+        image
+            .to_rgb8()
+            .write_with_encoder(JpegEncoder::new(&mut jpeg))
+            .unwrap();
+        let embedded = embed(jpeg, ImageFormat::Jpeg, &srgb).unwrap();
+        let mut decoder = JpegDecoder::new(Cursor::new(&embedded)).unwrap();
+        assert_eq!(decoder.icc_profile().as_deref(), Some(&srgb[..]));
+    }
+}
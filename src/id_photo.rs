@@ -0,0 +1,267 @@
+//! 证件照排版
+//!
+//! 将一张照片按证件照尺寸裁切缩放，在纸张上重复排满，照片之间留出裁切间距
+
+use image::{imageops, imageops::FilterType, DynamicImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+use std::{fmt, str::FromStr};
+
+use crate::{
+    i18n::Msg,
+    layout::Rect,
+    paper::{Orientation, Paper},
+    saliency::{self, Crop},
+    Error, InputSnafu,
+};
+
+/// 证件照尺寸
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct IdSize {
+    /// 宽度 毫米
+    pub width_mm: f64,
+    /// 高度 毫米
+    pub height_mm: f64,
+}
+
+/// 预设证件照尺寸（名称, 宽, 高）毫米
+const PRESETS: &[(&str, f64, f64)] = &[
+    ("cn-1inch", 25.0, 35.0),
+    ("cn-small-1inch", 22.0, 32.0),
+    ("cn-2inch", 35.0, 49.0),
+    ("cn-small-2inch", 33.0, 48.0),
+    ("cn-passport", 33.0, 48.0),
+    ("cn-id-card", 26.0, 32.0),
+    ("us-passport", 51.0, 51.0),
+    ("us-visa", 51.0, 51.0),
+    ("schengen-visa", 35.0, 45.0),
+    ("eu-passport", 35.0, 45.0),
+    ("uk-passport", 35.0, 45.0),
+    ("jp-passport", 35.0, 45.0),
+    ("jp-resume", 30.0, 40.0),
+    ("ca-passport", 50.0, 70.0),
+];
+
+impl fmt::Display for IdSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.width_mm, self.height_mm)
+    }
+}
+
+impl From<IdSize> for String {
+    fn from(size: IdSize) -> Self {
+        size.to_string()
+    }
+}
+
+impl TryFrom<String> for IdSize {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl FromStr for IdSize {
+    type Err = String;
+
+    /// 解析证件照尺寸，支持预设名称或自定义 `宽x高`（单位：mm），如 35x45
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.trim().to_ascii_lowercase();
+        if let Some(&(_, width_mm, height_mm)) = PRESETS.iter().find(|(name, _, _)| *name == lower)
+        {
+            return Ok(IdSize {
+                width_mm,
+                height_mm,
+            });
+        }
+        let parsed = lower
+            .trim_end_matches("mm")
+            .split_once(['x', '*'])
+            .and_then(|(w, h)| {
+                Some((w.trim().parse::<f64>().ok()?, h.trim().parse::<f64>().ok()?))
+            });
+        match parsed {
+            Some((width_mm, height_mm)) if width_mm > 0.0 && height_mm > 0.0 => Ok(IdSize {
+                width_mm,
+                height_mm,
+            }),
+            _ => Err(Msg::IdSizeInvalid {
+                value: s,
+                presets: &PRESETS.iter().map(|(name, _, _)| *name).collect::<Vec<_>>(),
+            }
+            .to_string()),
+        }
+    }
+}
+
+/// 证件照排版参数
+#[derive(Debug, Clone, Copy)]
+pub struct IdSheet {
+    /// 纸张
+    pub paper: Paper,
+    /// 照片尺寸
+    pub size: IdSize,
+    /// 照片之间的间距（单位：cm）
+    pub gap_cm: f64,
+    /// 纸张边距（单位：cm）
+    pub border_cm: f64,
+    /// 每厘米像素数
+    pub ppc: f64,
+}
+
+impl IdSheet {
+    /// 按方向调整纸张，未指定方向时取能放下更多照片的方向，数量相同时保持原方向
+    pub fn oriented(self, orientation: Option<Orientation>) -> IdSheet {
+        let oriented = |orientation| IdSheet {
+            paper: self.paper.oriented(orientation),
+            ..self
+        };
+        match orientation {
+            Some(orientation) => oriented(orientation),
+            None => [
+                oriented(Orientation::Portrait),
+                oriented(Orientation::Landscape),
+            ]
+            .into_iter()
+            .rev()
+            .max_by_key(|sheet| {
+                let (cols, rows) = sheet.grid();
+                (cols * rows, sheet.paper == self.paper)
+            })
+            .unwrap_or(self),
+        }
+    }
+
+    /// 每页可放下的列数与行数
+    pub fn grid(&self) -> (u32, u32) {
+        let fit = |paper_cm: f64, photo_mm: f64| {
+            let n =
+                (paper_cm - 2.0 * self.border_cm + self.gap_cm) / (photo_mm / 10.0 + self.gap_cm);
+            n.max(0.0).floor() as u32
+        };
+        (
+            fit(self.paper.width_cm, self.size.width_mm),
+            fit(self.paper.height_cm, self.size.height_mm),
+        )
+    }
+
+    /// 检查纸张能否放下照片，返回实际照片数量
+    ///
+    /// - count: 照片数量，None 为排满一页
+    pub fn check(&self, count: Option<u32>) -> Result<u32, Error> {
+        let (cols, rows) = self.grid();
+        let capacity = cols * rows;
+        ensure!(
+            capacity > 0,
+            InputSnafu {
+                reason: Msg::IdPhotoTooLarge {
+                    paper: &self.paper.to_string(),
+                    size: &self.size.to_string(),
+                }
+                .to_string(),
+            }
+        );
+        let count = count.unwrap_or(capacity);
+        ensure!(
+            (1..=capacity).contains(&count),
+            InputSnafu {
+                reason: Msg::IdPhotoCount {
+                    paper: &self.paper.to_string(),
+                    size: &self.size.to_string(),
+                    capacity,
+                    grid: (cols, rows),
+                }
+                .to_string(),
+            }
+        );
+        Ok(count)
+    }
+
+    /// 绘制一页，照片按网格居中排列，返回页面与每张照片的区域
+    ///
+    /// - photo: 原始照片
+    /// - count: 照片数量，None 为排满一页
+    /// - crop: 宽高比与证件照不同时的裁切位置
+    pub fn render(
+        &self,
+        photo: &DynamicImage,
+        count: Option<u32>,
+        crop: Crop,
+        filter: FilterType,
+    ) -> Result<(RgbaImage, Vec<Rect>), Error> {
+        let count = self.check(count)?;
+        let (cols, _) = self.grid();
+        let px = |cm: f64| (cm * self.ppc).round() as u32;
+        let (w, h) = (
+            px(self.size.width_mm / 10.0),
+            px(self.size.height_mm / 10.0),
+        );
+        let gap = px(self.gap_cm);
+        let (page_w, page_h) = (px(self.paper.width_cm), px(self.paper.height_cm));
+        // 照片不足一行时只占用实际列数，整体居中
+        let used_cols = cols.min(count);
+        let used_rows = count.div_ceil(cols);
+        let grid_w = used_cols * w + (used_cols - 1) * gap;
+        let grid_h = used_rows * h + (used_rows - 1) * gap;
+        let (x0, y0) = (
+            page_w.saturating_sub(grid_w) / 2,
+            page_h.saturating_sub(grid_h) / 2,
+        );
+
+        let fitted = saliency::resize_to_fill(photo, w, h, filter, crop).into_rgba8();
+        let mut canvas = RgbaImage::from_pixel(page_w, page_h, image::Rgba([255; 4]));
+        let rects: Vec<Rect> = (0..count)
+            .map(|i| Rect {
+                x: x0 + (i % cols) * (w + gap),
+                y: y0 + (i / cols) * (h + gap),
+                w,
+                h,
+            })
+            .collect();
+        for rect in &rects {
+            imageops::overlay(&mut canvas, &fitted, rect.x as i64, rect.y as i64);
+        }
+        Ok((canvas, rects))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_id_sheet() {
+        // 4×6 英寸相纸横放可排 8 张 35×45 mm
+        let sheet = IdSheet {
+            paper: "10.16x15.24".parse().unwrap(),
+            size: "35x45".parse().unwrap(),
+            gap_cm: 0.2,
+            border_cm: 0.3,
+            ppc: 20.0,
+        }
+        .oriented(None);
+        assert_eq!(sheet.grid(), (4, 2));
+        assert!(sheet.paper.width_cm > sheet.paper.height_cm);
+        let photo =
+            DynamicImage::ImageRgba8(RgbaImage::from_pixel(60, 80, image::Rgba([9, 9, 9, 255])));
+        let (canvas, rects) = sheet
+            .render(&photo, Some(3), Crop::Center, FilterType::Triangle)
+            .unwrap();
+        assert_eq!(rects.len(), 3);
+        assert_eq!((rects[0].w, rects[0].h), (70, 90));
+        assert_eq!(canvas.get_pixel(rects[2].x, rects[2].y).0, [9, 9, 9, 255]);
+        assert!(sheet
+            .render(&photo, Some(9), Crop::Center, FilterType::Triangle)
+            .is_err());
+        assert!("35".parse::<IdSize>().is_err());
+        assert_eq!(
+            "US-Passport".parse::<IdSize>(),
+            Ok(IdSize {
+                width_mm: 51.0,
+                height_mm: 51.0
+            })
+        );
+    }
+}
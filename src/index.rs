@@ -0,0 +1,179 @@
+//! 目录页
+//!
+//! 按文件名列出每张图片所在的页码与格号，排在全部页面之前
+
+use image::{Rgba, RgbaImage};
+use std::path::PathBuf;
+
+use crate::{caption::LINE_HEIGHT, i18n::Msg, layout::Placement, text::Font, Config};
+
+/// 目录文字颜色
+const INDEX_COLOR: Rgba<u8> = Rgba([40, 40, 40, 255]);
+
+/// 目录字号（单位：pt）
+const INDEX_SIZE: f64 = 9.0;
+
+/// 标题字号与目录字号之比
+const TITLE_SCALE: f64 = 1.6;
+
+/// 每栏最小宽度（单位：cm）
+const COLUMN_MIN_CM: f64 = 8.0;
+
+/// 目录中的一项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// 文件名
+    pub name: String,
+    /// 页码 从 1 开始
+    pub page: usize,
+    /// 格号 从 1 开始，按行从左到右计数
+    pub cell: u32,
+}
+
+impl IndexEntry {
+    /// 位置说明，如 `第 3 页 第 5 格`
+    fn location(&self) -> String {
+        Msg::IndexLocation {
+            page: self.page,
+            cell: self.cell,
+        }
+        .to_string()
+    }
+}
+
+/// 收集全部图片的位置，按文件名自然排序
+///
+/// - pages: 每页图片位置
+/// - inputs: 全部输入图片
+/// - n_h: 横向图片数量
+pub fn entries(pages: &[Vec<Placement>], inputs: &[PathBuf], n_h: u32) -> Vec<IndexEntry> {
+    let mut entries: Vec<IndexEntry> = pages
+        .iter()
+        .enumerate()
+        .flat_map(|(i, placements)| {
+            placements.iter().map(move |placement| IndexEntry {
+                name: inputs[placement.index]
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned(),
+                page: i + 1,
+                cell: placement.row * n_h + placement.col + 1,
+            })
+        })
+        .collect();
+    entries.sort_by(|a, b| natord::compare(&a.name, &b.name).then(a.page.cmp(&b.page)));
+    entries
+}
+
+/// 绘制目录页，一页放不下时分为多页
+///
+/// 页面尺寸与边距与图片页面相同，按纸张宽度分栏
+pub fn render(entries: &[IndexEntry], font: &Font, config: &Config) -> Vec<RgbaImage> {
+    let size = (INDEX_SIZE / 72.0 * 2.54 * config.ppc) as f32;
+    let title_size = size * TITLE_SCALE as f32;
+    let line_h = (size as f64 * LINE_HEIGHT).ceil().max(1.0) as u32;
+    let border = config.paper_border_px;
+    let area_w = config.paper_w_px.saturating_sub(border.horizontal()).max(1);
+    let title_h = (title_size as f64 * LINE_HEIGHT * 1.5).ceil() as u32;
+    let area_h = config
+        .paper_h_px
+        .saturating_sub(border.vertical() + title_h);
+    let gutter = line_h;
+    let n_columns = ((area_w as f64 / (COLUMN_MIN_CM * config.ppc)) as u32).max(1);
+    let column_w = (area_w + gutter) / n_columns - gutter;
+    let per_column = (area_h / line_h).max(1) as usize;
+    let per_page = per_column * n_columns as usize;
+    let title = Msg::IndexTitle.to_string();
+
+    entries
+        .chunks(per_page)
+        .map(|chunk| {
+            let mut canvas =
+                RgbaImage::from_pixel(config.paper_w_px, config.paper_h_px, config.background.0);
+            font.draw(
+                &mut canvas,
+                &title,
+                border.left as i32,
+                border.top as i32,
+                title_size,
+                INDEX_COLOR,
+            );
+            for (i, entry) in chunk.iter().enumerate() {
+                let x = border.left + (i / per_column) as u32 * (column_w + gutter);
+                let y = border.top + title_h + (i % per_column) as u32 * line_h;
+                let location = entry.location();
+                let (location_w, _) = font.measure(&location, size);
+                font.draw(
+                    &mut canvas,
+                    &location,
+                    (x + column_w.saturating_sub(location_w)) as i32,
+                    y as i32,
+                    size,
+                    INDEX_COLOR,
+                );
+                let name = truncate(
+                    font,
+                    &entry.name,
+                    size,
+                    column_w.saturating_sub(location_w + gutter),
+                );
+                font.draw(&mut canvas, &name, x as i32, y as i32, size, INDEX_COLOR);
+            }
+            canvas
+        })
+        .collect()
+}
+
+/// 超出宽度的文件名截去中间部分，保留扩展名
+fn truncate(font: &Font, name: &str, size: f32, max_w: u32) -> String {
+    if font.measure(name, size).0 <= max_w {
+        return name.to_string();
+    }
+    let chars: Vec<char> = name.chars().collect();
+    let (mut head, mut tail) = (chars.len() / 2, chars.len() - chars.len() / 2);
+    loop {
+        let text: String = chars[..head]
+            .iter()
+            .chain(['…'].iter())
+            .chain(&chars[chars.len() - tail..])
+            .collect();
+        if font.measure(&text, size).0 <= max_w || head + tail == 0 {
+            return text;
+        }
+        if head >= tail {
+            head -= 1;
+        } else {
+            tail -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::Span;
+
+    #[test]
+    fn test_entries() {
+        let inputs: Vec<PathBuf> = ["a/img10.jpg", "b/img2.jpg", "img1.jpg"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        let placement = |index, row, col| Placement {
+            index,
+            row,
+            col,
+            span: Span::ONE,
+        };
+        let pages = vec![
+            vec![placement(0, 0, 0), placement(1, 0, 1)],
+            vec![placement(2, 1, 2)],
+        ];
+        let entries = entries(&pages, &inputs, 3);
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["img1.jpg", "img2.jpg", "img10.jpg"]);
+        assert_eq!((entries[0].page, entries[0].cell), (2, 6));
+        assert_eq!((entries[1].page, entries[1].cell), (1, 2));
+    }
+}
@@ -0,0 +1,390 @@
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+use std::{
+    collections::HashSet,
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+use walkdir::WalkDir;
+
+use crate::{i18n::Msg, manifest::Scale, metadata, Error, InputSnafu, IoSnafu};
+
+/// 输入排序方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SortKey {
+    /// 按文件名
+    Name,
+    /// 按文件名，其中的数字按数值比较，如 img2 在 img10 之前
+    Natural,
+    /// 按修改时间
+    Mtime,
+    /// 按 EXIF 拍摄时间，没有拍摄时间的排在最后
+    ExifDate,
+    /// 按文件大小
+    Size,
+}
+
+/// 输入文件过滤规则
+///
+/// 匹配相对于输入目录的路径，`*` 可跨越目录层级
+#[derive(Debug, Clone, Default)]
+pub struct InputFilter {
+    /// 包含规则，为空时包含全部文件
+    include: Option<GlobSet>,
+    /// 排除规则
+    exclude: Option<GlobSet>,
+}
+
+fn build_globs(patterns: &[String]) -> Result<Option<GlobSet>, Error> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern).map_err(|e| Error::Input {
+            reason: Msg::PatternInvalid {
+                pattern,
+                reason: e.to_string(),
+            }
+            .to_string(),
+        })?;
+        builder.add(glob);
+    }
+    builder.build().map(Some).map_err(|e| Error::Input {
+        reason: e.to_string(),
+    })
+}
+
+impl InputFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<InputFilter, Error> {
+        Ok(InputFilter {
+            include: build_globs(include)?,
+            exclude: build_globs(exclude)?,
+        })
+    }
+
+    /// 判断文件是否应作为输入
+    ///
+    /// - path: 相对于输入目录的路径
+    pub fn matches(&self, path: &Path) -> bool {
+        self.include.as_ref().is_none_or(|set| set.is_match(path))
+            && !self.exclude.as_ref().is_some_and(|set| set.is_match(path))
+    }
+}
+
+/// 列出输入目录中的文件
+///
+/// - input_dir: 输入目录
+/// - recursive: 是否包含子目录
+/// - filter: 文件过滤规则
+pub fn scan_inputs(
+    input_dir: &str,
+    recursive: bool,
+    filter: &InputFilter,
+) -> Result<Vec<PathBuf>, Error> {
+    let path = Path::new(input_dir);
+    ensure!(
+        path.is_dir(),
+        InputSnafu {
+            reason: Msg::InputDirMissing(&path.display().to_string()).to_string(),
+        }
+    );
+
+    let mut walker = WalkDir::new(path).min_depth(1).follow_links(true);
+    if !recursive {
+        walker = walker.max_depth(1);
+    }
+    let mut inputs: Vec<PathBuf> = Vec::new();
+    for entry in walker {
+        let entry = entry.map_err(io::Error::from).context(IoSnafu)?;
+        let file_path = entry.path();
+        let relative = file_path.strip_prefix(path).unwrap_or(file_path);
+        if entry.file_type().is_file() && filter.matches(relative) {
+            inputs.push(file_path.to_path_buf());
+        }
+    }
+    // 目录遍历顺序因系统而异，统一按路径排序
+    inputs.sort();
+    Ok(inputs)
+}
+
+/// 排序输入文件，键相同时按文件名排序
+pub fn sort_inputs(inputs: &mut [PathBuf], key: SortKey) {
+    let name = |path: &PathBuf| path.file_name().unwrap_or_default().to_owned();
+    match key {
+        SortKey::Name => inputs.sort_by_cached_key(|path| (name(path), path.clone())),
+        SortKey::Natural => inputs.sort_by(|a, b| {
+            natord::compare(&name(a).to_string_lossy(), &name(b).to_string_lossy())
+                .then_with(|| a.cmp(b))
+        }),
+        SortKey::Mtime => inputs.sort_by_cached_key(|path| {
+            let mtime = fs::metadata(path).and_then(|meta| meta.modified()).ok();
+            (mtime, name(path))
+        }),
+        SortKey::ExifDate => inputs.sort_by_cached_key(|path| {
+            let timestamp = metadata::capture_timestamp(path);
+            (timestamp.is_none(), timestamp, name(path))
+        }),
+        SortKey::Size => inputs.sort_by_cached_key(|path| {
+            let size = fs::metadata(path).map(|meta| meta.len()).unwrap_or(0);
+            (size, name(path))
+        }),
+    }
+}
+
+/// 按种子打乱输入顺序，相同种子与输入得到相同结果
+///
+/// 使用 SplitMix64 生成随机数，结果不受依赖库版本影响
+pub fn shuffle_inputs(inputs: &mut [PathBuf], seed: u64) {
+    let mut state = seed;
+    let mut next = || {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    };
+    // Fisher-Yates
+    for i in (1..inputs.len()).rev() {
+        let j = (next() % (i as u64 + 1)) as usize;
+        inputs.swap(i, j);
+    }
+}
+
+/// 排列清单中的一项
+#[derive(Debug, Clone, PartialEq)]
+pub enum OrderEntry {
+    /// 图片，可附带尺寸覆盖
+    Image(PathBuf, Option<Scale>),
+    /// 空白单元格
+    Blank,
+    /// 换页
+    PageBreak,
+}
+
+/// 读取排列清单
+///
+/// 每行一个图片路径，相对路径相对于清单所在目录；
+/// 空行为空白单元格，`---` 为换页，`#` 开头为注释。
+/// 扩展名为 .csv 时按订单格式读取，见 [`parse_order_csv`]
+pub fn read_order(path: &Path) -> Result<Vec<OrderEntry>, Error> {
+    let text = fs::read_to_string(path).context(IoSnafu)?;
+    let base = path.parent().unwrap_or(Path::new(""));
+    if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"))
+    {
+        return parse_order_csv(&text, base);
+    }
+    let mut entries: Vec<OrderEntry> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .map(|line| match line {
+            "" => OrderEntry::Blank,
+            "---" => OrderEntry::PageBreak,
+            line => OrderEntry::Image(base.join(line), None),
+        })
+        .collect();
+    // 忽略末尾空行
+    while entries.last() == Some(&OrderEntry::Blank) {
+        entries.pop();
+    }
+    Ok(entries)
+}
+
+/// 解析 CSV 订单
+///
+/// 每行 `文件,份数,尺寸`，份数与尺寸可省略，份数默认 1，为 0 时跳过该行；
+/// 尺寸同图片清单，如 `10cm`、`150%`。首行可为表头，`#` 开头为注释。
+/// 每张图片按份数重复，尺寸与上一行不同时换页，使每页的照片尺寸一致
+///
+/// - base: 相对路径的基准目录
+pub fn parse_order_csv(text: &str, base: &Path) -> Result<Vec<OrderEntry>, Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .comment(Some(b'#'))
+        .trim(csv::Trim::All)
+        .from_reader(text.as_bytes());
+    let mut entries = Vec::new();
+    let mut prev_scale = None;
+    for (i, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| Error::Input {
+            reason: Msg::OrderInvalid(e.to_string()).to_string(),
+        })?;
+        let field = |n: usize| record.get(n).unwrap_or_default();
+        let header =
+            ["file", "filename", "文件", "文件名"].contains(&field(0).to_lowercase().as_str());
+        if field(0).is_empty() || (i == 0 && header) {
+            continue;
+        }
+        let line = record.position().map_or(i as u64 + 1, |pos| pos.line());
+        let copies = match field(1) {
+            "" => 1,
+            copies => copies.parse::<usize>().ok().context(InputSnafu {
+                reason: Msg::OrderCopiesInvalid {
+                    line,
+                    value: copies,
+                }
+                .to_string(),
+            })?,
+        };
+        let scale = match field(2) {
+            "" => None,
+            size => Some(Scale::parse(size).context(InputSnafu {
+                reason: Msg::OrderSizeInvalid { line, value: size }.to_string(),
+            })?),
+        };
+        if copies == 0 {
+            continue;
+        }
+        if !entries.is_empty() && scale != prev_scale {
+            entries.push(OrderEntry::PageBreak);
+        }
+        prev_scale = scale;
+        let image = OrderEntry::Image(base.join(field(0)), scale);
+        entries.extend(std::iter::repeat_n(image, copies));
+    }
+    Ok(entries)
+}
+
+/// 按顺序合并多个输入来源，目录展开为其中的文件，重复的文件只保留首次出现
+///
+/// 直接指定的文件不受过滤规则影响
+///
+/// - sources: 输入目录或文件
+/// - recursive: 是否包含子目录
+/// - filter: 目录中文件的过滤规则
+pub fn collect_inputs(
+    sources: &[&str],
+    recursive: bool,
+    filter: &InputFilter,
+) -> Result<Vec<PathBuf>, Error> {
+    let mut seen = HashSet::new();
+    let mut inputs = Vec::new();
+    for &source in sources {
+        let path = Path::new(source);
+        let files = if path.is_file() {
+            vec![path.to_path_buf()]
+        } else {
+            scan_inputs(source, recursive, filter)?
+        };
+        inputs.extend(files.into_iter().filter(|file| seen.insert(file.clone())));
+    }
+    Ok(inputs)
+}
+
+/// 读取输入列表，忽略空行
+///
+/// - reader: 列表内容
+/// - nul: 是否以 NUL 分隔，否则按行分隔
+pub fn read_file_list(mut reader: impl Read, nul: bool) -> Result<Vec<String>, Error> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text).context(IoSnafu)?;
+    let entries: Vec<&str> = if nul {
+        text.split('\0').collect()
+    } else {
+        text.lines().collect()
+    };
+    Ok(entries
+        .into_iter()
+        .filter(|entry| !entry.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input_filter() {
+        let patterns = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let filter =
+            InputFilter::new(&patterns(&["*.jpg", "*.png"]), &patterns(&["raw/*"])).unwrap();
+        assert!(filter.matches(Path::new("a.jpg")));
+        assert!(filter.matches(Path::new("2024/05/b.png")));
+        assert!(!filter.matches(Path::new("c.tif")));
+        assert!(!filter.matches(Path::new("raw/d.jpg")));
+
+        assert!(InputFilter::default().matches(Path::new("any.file")));
+        assert!(InputFilter::new(&patterns(&["a[.jpg"]), &[]).is_err());
+    }
+
+    #[test]
+    fn test_read_file_list() {
+        let list = read_file_list("a.jpg\r\n\nb c.png\n".as_bytes(), false).unwrap();
+        assert_eq!(list, vec!["a.jpg", "b c.png"]);
+        let list = read_file_list("a\nb.jpg\0c.jpg\0".as_bytes(), true).unwrap();
+        assert_eq!(list, vec!["a\nb.jpg", "c.jpg"]);
+    }
+
+    #[test]
+    fn test_sort_inputs() {
+        let mut inputs: Vec<PathBuf> = ["b/img10.jpg", "a/img2.jpg", "img1.jpg"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        sort_inputs(&mut inputs, SortKey::Name);
+        assert_eq!(inputs[0], PathBuf::from("img1.jpg"));
+        assert_eq!(inputs[1], PathBuf::from("b/img10.jpg"));
+        sort_inputs(&mut inputs, SortKey::Natural);
+        assert_eq!(inputs[1], PathBuf::from("a/img2.jpg"));
+        assert_eq!(inputs[2], PathBuf::from("b/img10.jpg"));
+    }
+
+    #[test]
+    fn test_shuffle_inputs() {
+        let sorted: Vec<PathBuf> = (0..20).map(|i| PathBuf::from(format!("{i}.jpg"))).collect();
+        let mut a = sorted.clone();
+        let mut b = sorted.clone();
+        shuffle_inputs(&mut a, 42);
+        shuffle_inputs(&mut b, 42);
+        assert_eq!(a, b);
+        assert_ne!(a, sorted);
+        a.sort();
+        b = sorted.clone();
+        b.sort();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_read_order() {
+        let dir = std::env::temp_dir().join("image-typesetting-tool-order");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("order.txt");
+        fs::write(&path, "# 封面\ncover.jpg\n\n---\n/abs/p1.jpg\n\n").unwrap();
+        let entries = read_order(&path).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                OrderEntry::Image(dir.join("cover.jpg"), None),
+                OrderEntry::Blank,
+                OrderEntry::PageBreak,
+                OrderEntry::Image(PathBuf::from("/abs/p1.jpg"), None),
+            ]
+        );
+
+        let text = "file,copies,size\na.jpg,2,10cm\n# 取消\nb.jpg,0,10cm\nc.jpg,,10cm\nd.jpg,1\n";
+        let base = Path::new("order");
+        let a = OrderEntry::Image(base.join("a.jpg"), Some(Scale::Height(10.0)));
+        let c = OrderEntry::Image(base.join("c.jpg"), Some(Scale::Height(10.0)));
+        assert_eq!(
+            parse_order_csv(text, base).unwrap(),
+            vec![
+                a.clone(),
+                a,
+                c,
+                OrderEntry::PageBreak,
+                OrderEntry::Image(base.join("d.jpg"), None),
+            ]
+        );
+        assert!(parse_order_csv("a.jpg,two\n", base).is_err());
+        assert!(parse_order_csv("a.jpg,1,big\n", base).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
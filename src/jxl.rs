@@ -0,0 +1,69 @@
+//! JPEG XL 编解码
+//!
+//! 调用 libjxl 提供的 `djxl`/`cjxl` 命令行工具，需要在 PATH 中可用
+
+use image::{DynamicImage, ImageFormat, RgbaImage};
+use snafu::prelude::*;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{self, Command},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{i18n::Msg, CodecSnafu, Error, ImageSnafu};
+
+/// 是否为 JPEG XL 文件
+pub fn is_jxl(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("jxl"))
+}
+
+/// 生成唯一的临时 PNG 路径
+fn temp_png() -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let n = COUNTER.fetch_add(1, Ordering::SeqCst);
+    env::temp_dir().join(format!("image-typesetting-{}-{}.png", process::id(), n))
+}
+
+fn run(command: &mut Command) -> Result<(), Error> {
+    let program = command.get_program().to_string_lossy().to_string();
+    let output = command.output().map_err(|e| Error::Codec {
+        reason: Msg::ProgramUnavailable {
+            program: &program,
+            reason: e.to_string(),
+        }
+        .to_string(),
+    })?;
+    ensure!(
+        output.status.success(),
+        CodecSnafu {
+            reason: Msg::ProgramFailed {
+                program: &program,
+                reason: String::from_utf8_lossy(&output.stderr).trim(),
+            }
+            .to_string(),
+        }
+    );
+    Ok(())
+}
+
+/// 解码 JPEG XL 图片
+pub fn decode(path: &Path) -> Result<DynamicImage, Error> {
+    let tmp = temp_png();
+    let result = run(Command::new("djxl").arg(path).arg(&tmp))
+        .and_then(|_| image::open(&tmp).context(ImageSnafu));
+    let _ = fs::remove_file(&tmp);
+    result
+}
+
+/// 以 JPEG XL 格式保存画布
+pub fn encode(canvas: &RgbaImage, output_path: &Path) -> Result<(), Error> {
+    let tmp = temp_png();
+    let result = canvas
+        .save_with_format(&tmp, ImageFormat::Png)
+        .context(ImageSnafu)
+        .and_then(|_| run(Command::new("cjxl").arg(&tmp).arg(output_path)));
+    let _ = fs::remove_file(&tmp);
+    result
+}
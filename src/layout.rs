@@ -0,0 +1,785 @@
+/// 图片占用的单元格数量
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    /// 横向占用单元格数
+    pub cols: u32,
+    /// 纵向占用单元格数
+    pub rows: u32,
+}
+
+impl Span {
+    pub const ONE: Span = Span { cols: 1, rows: 1 };
+}
+
+/// 单张图片在页面中的位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Placement {
+    /// 输入图片索引
+    pub index: usize,
+    /// 起始行号
+    pub row: u32,
+    /// 起始列号
+    pub col: u32,
+    /// 占用单元格数
+    pub span: Span,
+}
+
+/// 页面上的矩形区域 像素
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+}
+
+/// 页面单元格占用表
+struct Page {
+    n_h: u32,
+    n_v: u32,
+    order: FillOrder,
+    occupied: Vec<bool>,
+    placements: Vec<Placement>,
+}
+
+impl Page {
+    fn new(n_h: u32, n_v: u32, order: FillOrder) -> Page {
+        Page {
+            n_h,
+            n_v,
+            order,
+            occupied: vec![false; (n_h * n_v) as usize],
+            placements: Vec::new(),
+        }
+    }
+
+    fn fits(&self, row: u32, col: u32, span: Span) -> bool {
+        if row + span.rows > self.n_v || col + span.cols > self.n_h {
+            return false;
+        }
+        (row..row + span.rows)
+            .all(|r| (col..col + span.cols).all(|c| !self.occupied[(r * self.n_h + c) as usize]))
+    }
+
+    /// 按填充顺序寻找第一个可放置的位置
+    fn try_place(&mut self, index: usize, span: Span) -> bool {
+        for idx in 0..(self.n_h * self.n_v) as usize {
+            let (row, col, leftward) = self.order.cell(self.n_h, self.n_v, idx);
+            // 从右向左填充时该单元格为图片的右边缘
+            let col = match leftward {
+                true => match (col + 1).checked_sub(span.cols) {
+                    Some(col) => col,
+                    None => continue,
+                },
+                false => col,
+            };
+            if !self.fits(row, col, span) {
+                continue;
+            }
+            for r in row..row + span.rows {
+                for c in col..col + span.cols {
+                    self.occupied[(r * self.n_h + c) as usize] = true;
+                }
+            }
+            self.placements.push(Placement {
+                index,
+                row,
+                col,
+                span,
+            });
+            return true;
+        }
+        false
+    }
+}
+
+/// 将图片依次排入页面网格
+///
+/// 每张图片按其占用的单元格数放入当前页第一个可用位置，放不下或与前一张分组不同时新开一页。
+/// 超出整页的占用会被限制为整页。
+///
+/// - spans: 每张图片占用的单元格数
+/// - groups: 每张图片的分组编号
+/// - n_h: 横向单元格数量
+/// - n_v: 纵向单元格数量
+/// - order: 填充顺序
+pub fn pack(
+    spans: &[Span],
+    groups: &[usize],
+    n_h: u32,
+    n_v: u32,
+    order: FillOrder,
+) -> Vec<Vec<Placement>> {
+    let mut pages: Vec<Page> = Vec::new();
+    for (index, span) in spans.iter().enumerate() {
+        let span = Span {
+            cols: span.cols.clamp(1, n_h),
+            rows: span.rows.clamp(1, n_v),
+        };
+        let same_group = index > 0 && groups[index] == groups[index - 1];
+        let placed = match pages.last_mut() {
+            Some(page) if same_group => page.try_place(index, span),
+            _ => false,
+        };
+        if !placed {
+            let mut page = Page::new(n_h, n_v, order);
+            page.try_place(index, span);
+            pages.push(page);
+        }
+    }
+
+    pages.into_iter().map(|page| page.placements).collect()
+}
+
+/// 图片旋转方式
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    clap::ValueEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Rotation {
+    /// 逆时针旋转 90° 后在单元格中更大时旋转
+    #[default]
+    Auto,
+    /// 不旋转
+    #[value(name = "none")]
+    #[serde(rename = "none")]
+    Keep,
+    /// 顺时针旋转 90°
+    Cw,
+    /// 逆时针旋转 90°
+    Ccw,
+    /// 旋转 180°
+    #[value(name = "180")]
+    #[serde(rename = "180")]
+    Flip,
+}
+
+impl Rotation {
+    /// 顺时针旋转角度，自动旋转时在图片方向与单元格方向不同时旋转
+    ///
+    /// 排版时按旋转后的尺寸选择自动旋转，见 [`crate::Config::rotation_for`]
+    ///
+    /// - dimensions: 图片原始尺寸
+    /// - portrait_cell: 单元格是否为竖向
+    pub fn degrees(self, (w, h): (u32, u32), portrait_cell: bool) -> u32 {
+        match self {
+            Rotation::Auto if w != h && (h > w) != portrait_cell => 270,
+            Rotation::Auto | Rotation::Keep => 0,
+            Rotation::Cw => 90,
+            Rotation::Ccw => 270,
+            Rotation::Flip => 180,
+        }
+    }
+}
+
+/// 图片在单元格中的对齐位置
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    clap::ValueEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    #[default]
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    /// 图片左上角相对可用区域左上角的偏移
+    ///
+    /// - slack: 可用区域比图片多出的宽度与高度
+    pub fn offset(self, (slack_w, slack_h): (u32, u32)) -> (u32, u32) {
+        let x = match self {
+            Anchor::TopLeft | Anchor::Left | Anchor::BottomLeft => 0,
+            Anchor::Top | Anchor::Center | Anchor::Bottom => slack_w / 2,
+            Anchor::TopRight | Anchor::Right | Anchor::BottomRight => slack_w,
+        };
+        let y = match self {
+            Anchor::TopLeft | Anchor::Top | Anchor::TopRight => 0,
+            Anchor::Left | Anchor::Center | Anchor::Right => slack_h / 2,
+            Anchor::BottomLeft | Anchor::Bottom | Anchor::BottomRight => slack_h,
+        };
+        (x, y)
+    }
+}
+
+/// 图片缩放方式
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    clap::ValueEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Fit {
+    /// 保持宽高比完整放入单元格
+    #[default]
+    Contain,
+    /// 保持宽高比填满单元格，居中裁去超出部分
+    Cover,
+    /// 拉伸填满单元格，不保持宽高比
+    Stretch,
+    /// 按原始像素尺寸放置，超出单元格的部分居中裁去
+    Original,
+}
+
+/// 装订线所在一侧
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    clap::ValueEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum GutterSide {
+    /// 每页均在左侧
+    #[default]
+    Left,
+    /// 奇数页在左侧，偶数页在右侧，用于双面打印
+    Alternating,
+}
+
+/// 缩放滤镜
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    clap::ValueEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Filter {
+    /// 最近邻，最快，有锯齿
+    Nearest,
+    /// 线性插值，较快
+    Triangle,
+    /// Catmull-Rom 三次插值
+    #[value(name = "catmullrom")]
+    #[serde(rename = "catmullrom")]
+    CatmullRom,
+    /// Lanczos 窗口 3，最清晰，最慢
+    #[default]
+    Lanczos3,
+}
+
+impl Filter {
+    pub fn filter_type(self) -> image::imageops::FilterType {
+        use image::imageops::FilterType;
+        match self {
+            Filter::Nearest => FilterType::Nearest,
+            Filter::Triangle => FilterType::Triangle,
+            Filter::CatmullRom => FilterType::CatmullRom,
+            Filter::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
+}
+
+/// 成对排版方式
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum PairMode {
+    /// 每张图片与其副本左右相邻
+    Adjacent,
+    /// 左半页与右半页内容相同，沿中线裁开得到两叠相同的图片
+    Halves,
+}
+
+/// 单元格填充顺序
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    clap::ValueEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum FillOrder {
+    /// 先行后列，每行从左到右
+    #[default]
+    Rows,
+    /// 先列后行，每列从上到下
+    Columns,
+    /// 蛇形，奇数行从左到右，偶数行从右到左
+    Snake,
+    /// 先行后列，每行从右到左，用于日漫、阿拉伯文等从右向左阅读的版面
+    Rtl,
+}
+
+impl FillOrder {
+    /// 第 row 行第 col 列的单元格在填充顺序中的序号
+    pub fn position(self, n_h: u32, n_v: u32, (row, col): (u32, u32)) -> Option<u32> {
+        (0..n_h * n_v).find(|&idx| {
+            let (r, c, _) = self.cell(n_h, n_v, idx as usize);
+            (r, c) == (row, col)
+        })
+    }
+
+    /// 第 idx 个单元格的行列号，以及该行是否从右向左填充
+    fn cell(self, n_h: u32, n_v: u32, idx: usize) -> (u32, u32, bool) {
+        let (row, col) = match self {
+            FillOrder::Columns => {
+                let (col, row) = row_and_col_from_index(n_v as usize, idx);
+                (row, col)
+            }
+            _ => row_and_col_from_index(n_h as usize, idx),
+        };
+        match self {
+            FillOrder::Rtl => (row, n_h - 1 - col, true),
+            FillOrder::Snake if !row.is_multiple_of(2) => (row, n_h - 1 - col, true),
+            _ => (row, col, false),
+        }
+    }
+}
+
+/// 成对排入页面网格，每张图片放置两次
+///
+/// 横向单元格数量需至少为 2
+///
+/// - spans: 每张图片占用的单元格数
+/// - groups: 每张图片的分组编号
+/// - n_h: 横向单元格数量
+/// - n_v: 纵向单元格数量
+/// - mode: 成对排版方式
+/// - order: 填充顺序
+pub fn pack_pairs(
+    spans: &[Span],
+    groups: &[usize],
+    n_h: u32,
+    n_v: u32,
+    mode: PairMode,
+    order: FillOrder,
+) -> Vec<Vec<Placement>> {
+    let half = (n_h / 2).max(1);
+    let (pages, offset) = match mode {
+        PairMode::Adjacent => {
+            // 先按双倍宽度排版，再拆分为左右两份
+            let doubled: Vec<Span> = spans
+                .iter()
+                .map(|span| Span {
+                    cols: (span.cols * 2).min(n_h - n_h % 2).max(2),
+                    rows: span.rows,
+                })
+                .collect();
+            (pack(&doubled, groups, n_h, n_v, order), None)
+        }
+        PairMode::Halves => (pack(spans, groups, half, n_v, order), Some(half)),
+    };
+
+    pages
+        .into_iter()
+        .map(|page| {
+            page.into_iter()
+                .flat_map(|placement| {
+                    let (first, shift) = match offset {
+                        Some(half) => (placement, half),
+                        None => {
+                            let span = Span {
+                                cols: placement.span.cols / 2,
+                                ..placement.span
+                            };
+                            (Placement { span, ..placement }, span.cols)
+                        }
+                    };
+                    let second = Placement {
+                        col: first.col + shift,
+                        ..first
+                    };
+                    [first, second]
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// 拼版方式
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Imposition {
+    /// 骑马钉小册子：每张纸左右各放一页，双面打印后对折装订即按顺序阅读
+    Booklet,
+}
+
+/// 骑马钉拼版，将按半页排版的结果两两合并为整页
+///
+/// 页数补足为 4 的倍数，补充的页面留空。输出页面依次为每张纸的正面与反面，
+/// 如 8 页时为 (8,1)、(2,7)、(6,3)、(4,5)
+///
+/// - pages: 按半页宽度排版的结果
+/// - half: 半页的横向单元格数量，右半页的列号依此偏移
+pub fn impose_booklet(mut pages: Vec<Vec<Placement>>, half: u32) -> Vec<Vec<Placement>> {
+    if pages.is_empty() {
+        return pages;
+    }
+    let n = pages.len().next_multiple_of(4);
+    pages.resize(n, Vec::new());
+    (0..n / 2)
+        .map(|side| {
+            // 正面左侧为后面的页，反面左侧为前面的页
+            let (left, right) = match side % 2 {
+                0 => (n - 1 - side, side),
+                _ => (side, n - 1 - side),
+            };
+            let mut page = pages[left].clone();
+            page.extend(pages[right].iter().map(|placement| Placement {
+                col: placement.col + half,
+                ..*placement
+            }));
+            page
+        })
+        .collect()
+}
+
+/// 跳过第一页开头的若干单元格后排版，用于已用去部分标签的标签纸
+///
+/// 跳过的单元格按单张图片占位，排版后去掉
+///
+/// - skip: 跳过的单元格数量
+/// - pack: 排版方式，参数为每张图片占用的单元格数与分组编号
+pub fn skip_cells(
+    spans: &[Span],
+    groups: &[usize],
+    skip: usize,
+    pack: impl FnOnce(&[Span], &[usize]) -> Vec<Vec<Placement>>,
+) -> Vec<Vec<Placement>> {
+    if skip == 0 {
+        return pack(spans, groups);
+    }
+    let group = groups.first().copied().unwrap_or(0);
+    let spans: Vec<Span> = std::iter::repeat_n(Span::ONE, skip)
+        .chain(spans.iter().copied())
+        .collect();
+    let groups: Vec<usize> = std::iter::repeat_n(group, skip)
+        .chain(groups.iter().copied())
+        .collect();
+    pack(&spans, &groups)
+        .into_iter()
+        .map(|page| {
+            page.into_iter()
+                .filter(|placement| placement.index >= skip)
+                .map(|placement| Placement {
+                    index: placement.index - skip,
+                    ..placement
+                })
+                .collect::<Vec<_>>()
+        })
+        .filter(|page| !page.is_empty())
+        .collect()
+}
+
+/// 双面排版，将按正面图片排版的结果展开为正反面交替的页面
+///
+/// 图片两两一组为正面与背面。背面页的列序左右镜像，沿长边翻转双面打印后
+/// 每张背面与其正面重合；最后一组缺少背面时对应单元格留空
+///
+/// - pages: 仅正面图片的排版结果，索引为组号
+/// - n_h: 横向单元格数量
+/// - n: 图片总数
+pub fn duplex(pages: Vec<Vec<Placement>>, n_h: u32, n: usize) -> Vec<Vec<Placement>> {
+    pages
+        .into_iter()
+        .flat_map(|page| {
+            let front = page
+                .iter()
+                .map(|placement| Placement {
+                    index: placement.index * 2,
+                    ..*placement
+                })
+                .collect();
+            let back = page
+                .iter()
+                .filter(|placement| placement.index * 2 + 1 < n)
+                .map(|placement| Placement {
+                    index: placement.index * 2 + 1,
+                    col: n_h.saturating_sub(placement.col + placement.span.cols),
+                    ..*placement
+                })
+                .collect();
+            [front, back]
+        })
+        .collect()
+}
+
+/// 将按位置排版的结果映射回图片
+///
+/// 排版时每个位置占用一个条目，空白位置只占位不绘制。
+/// 映射后去掉空白位置，全为空白的页面也一并去掉。
+///
+/// - pages: 按位置排版的结果
+/// - slots: 每个位置对应的图片索引，None 为空白
+pub fn fill_slots(pages: Vec<Vec<Placement>>, slots: &[Option<usize>]) -> Vec<Vec<Placement>> {
+    pages
+        .into_iter()
+        .map(|page| {
+            page.into_iter()
+                .filter_map(|placement| {
+                    slots[placement.index].map(|index| Placement { index, ..placement })
+                })
+                .collect::<Vec<_>>()
+        })
+        .filter(|page| !page.is_empty())
+        .collect()
+}
+
+/// 通过索引获取当前行列号
+///
+/// 先行后列
+///
+/// - nh: 横向数量
+/// - idx: 当前索引
+pub fn row_and_col_from_index(nh: usize, idx: usize) -> (u32, u32) {
+    let row = (idx / nh) as u32;
+    let col = (idx % nh) as u32;
+
+    (row, col)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anchor_offset() {
+        assert_eq!(Anchor::TopLeft.offset((10, 6)), (0, 0));
+        assert_eq!(Anchor::Center.offset((10, 6)), (5, 3));
+        assert_eq!(Anchor::Bottom.offset((10, 6)), (5, 6));
+        assert_eq!(Anchor::Right.offset((10, 6)), (10, 3));
+    }
+
+    #[test]
+    fn test_rotation_degrees() {
+        // 横向单元格中竖图逆时针旋转，竖向单元格中横图逆时针旋转
+        assert_eq!(Rotation::Auto.degrees((30, 40), false), 270);
+        assert_eq!(Rotation::Auto.degrees((40, 30), false), 0);
+        assert_eq!(Rotation::Auto.degrees((40, 30), true), 270);
+        assert_eq!(Rotation::Auto.degrees((30, 30), true), 0);
+        assert_eq!(Rotation::Keep.degrees((30, 40), false), 0);
+        assert_eq!(Rotation::Cw.degrees((40, 30), false), 90);
+        assert_eq!(Rotation::Flip.degrees((40, 30), true), 180);
+    }
+
+    #[test]
+    fn test_row_and_col_from_index() {
+        assert!(row_and_col_from_index(4, 0) == (0, 0));
+        assert!(row_and_col_from_index(4, 3) == (0, 3));
+        assert!(row_and_col_from_index(4, 11) == (2, 3));
+    }
+
+    #[test]
+    fn test_fill_order() {
+        let cells = |order, spans: &[Span], n_h, n_v| -> Vec<(u32, u32)> {
+            pack(spans, &vec![0; spans.len()], n_h, n_v, order)[0]
+                .iter()
+                .map(|p| (p.row, p.col))
+                .collect()
+        };
+        assert_eq!(
+            cells(FillOrder::Columns, &[Span::ONE; 3], 2, 2),
+            vec![(0, 0), (1, 0), (0, 1)]
+        );
+        assert_eq!(
+            cells(FillOrder::Snake, &[Span::ONE; 5], 3, 2),
+            vec![(0, 0), (0, 1), (0, 2), (1, 2), (1, 1)]
+        );
+        assert_eq!(FillOrder::Columns.position(2, 3, (1, 1)), Some(4));
+        assert_eq!(FillOrder::Snake.position(3, 2, (1, 2)), Some(3));
+        assert_eq!(FillOrder::Rtl.position(3, 2, (2, 0)), None);
+        // 从右向左时跨列的图片以右边缘对齐可用单元格
+        let wide = Span { cols: 2, rows: 1 };
+        assert_eq!(
+            cells(FillOrder::Rtl, &[Span::ONE, wide], 3, 1),
+            vec![(0, 2), (0, 0)]
+        );
+    }
+
+    #[test]
+    fn test_pack_single_cells() {
+        let pages = pack(&[Span::ONE; 10], &[0; 10], 2, 2, FillOrder::Rows);
+        let sizes: Vec<usize> = pages.iter().map(|page| page.len()).collect();
+        assert_eq!(sizes, vec![4, 4, 2]);
+        assert_eq!(
+            (pages[1][0].index, pages[1][0].row, pages[1][0].col),
+            (4, 0, 0)
+        );
+        assert_eq!((pages[2][1].row, pages[2][1].col), (0, 1));
+    }
+
+    #[test]
+    fn test_pack_mixed_cells() {
+        let big = Span { cols: 2, rows: 2 };
+        let pages = pack(
+            &[Span::ONE, big, Span::ONE, Span::ONE],
+            &[0; 4],
+            4,
+            3,
+            FillOrder::Rows,
+        );
+        assert_eq!(pages.len(), 1);
+        assert_eq!((pages[0][1].row, pages[0][1].col), (0, 1));
+        assert_eq!((pages[0][2].row, pages[0][2].col), (0, 3));
+        assert_eq!((pages[0][3].row, pages[0][3].col), (1, 0));
+
+        // 超出整页的占用被限制为整页
+        let pages = pack(
+            &[Span::ONE, Span { cols: 9, rows: 9 }],
+            &[0; 2],
+            4,
+            3,
+            FillOrder::Rows,
+        );
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[1][0].span, Span { cols: 4, rows: 3 });
+    }
+
+    #[test]
+    fn test_pack_pairs() {
+        let cells = |page: &[Placement]| -> Vec<(usize, u32, u32)> {
+            page.iter().map(|p| (p.index, p.row, p.col)).collect()
+        };
+        let pages = pack_pairs(
+            &[Span::ONE; 3],
+            &[0; 3],
+            4,
+            1,
+            PairMode::Adjacent,
+            FillOrder::Rows,
+        );
+        assert_eq!(
+            cells(&pages[0]),
+            vec![(0, 0, 0), (0, 0, 1), (1, 0, 2), (1, 0, 3)]
+        );
+        assert_eq!(cells(&pages[1]), vec![(2, 0, 0), (2, 0, 1)]);
+
+        let pages = pack_pairs(
+            &[Span::ONE; 3],
+            &[0; 3],
+            4,
+            1,
+            PairMode::Halves,
+            FillOrder::Rows,
+        );
+        assert_eq!(
+            cells(&pages[0]),
+            vec![(0, 0, 0), (0, 0, 2), (1, 0, 1), (1, 0, 3)]
+        );
+        assert_eq!(pages[1].len(), 2);
+    }
+
+    #[test]
+    fn test_impose_booklet() {
+        // 5 页补足为 8 页，每页一张图片
+        let pages = pack(&[Span::ONE; 5], &[0; 5], 1, 1, FillOrder::Rows);
+        let sides = impose_booklet(pages, 1);
+        let cells: Vec<Vec<(usize, u32)>> = sides
+            .iter()
+            .map(|page| page.iter().map(|p| (p.index, p.col)).collect())
+            .collect();
+        assert_eq!(
+            cells,
+            vec![
+                vec![(0, 1)],
+                vec![(1, 0)],
+                vec![(2, 1)],
+                vec![(3, 0), (4, 1)],
+            ]
+        );
+        assert!(impose_booklet(Vec::new(), 1).is_empty());
+    }
+
+    #[test]
+    fn test_skip_cells() {
+        let pages = skip_cells(&[Span::ONE; 3], &[0; 3], 3, |spans, groups| {
+            pack(spans, groups, 2, 2, FillOrder::Rows)
+        });
+        let cells: Vec<Vec<(usize, u32, u32)>> = pages
+            .iter()
+            .map(|page| page.iter().map(|p| (p.index, p.row, p.col)).collect())
+            .collect();
+        assert_eq!(cells, vec![vec![(0, 1, 1)], vec![(1, 0, 0), (2, 0, 1)]]);
+    }
+
+    #[test]
+    fn test_duplex() {
+        let pages = pack(
+            &[Span::ONE, Span { cols: 2, rows: 1 }],
+            &[0; 2],
+            3,
+            1,
+            FillOrder::Rows,
+        );
+        let sides = duplex(pages, 3, 3);
+        let cells: Vec<Vec<(usize, u32)>> = sides
+            .iter()
+            .map(|page| page.iter().map(|p| (p.index, p.col)).collect())
+            .collect();
+        assert_eq!(cells, vec![vec![(0, 0), (2, 1)], vec![(1, 2)]]);
+    }
+
+    #[test]
+    fn test_pack_groups() {
+        let pages = pack(&[Span::ONE; 5], &[0, 0, 1, 1, 1], 2, 2, FillOrder::Rows);
+        let sizes: Vec<usize> = pages.iter().map(|page| page.len()).collect();
+        assert_eq!(sizes, vec![2, 3]);
+        assert_eq!(pages[1][0].index, 2);
+    }
+
+    #[test]
+    fn test_fill_slots() {
+        let slots = [Some(0), None, Some(1), None, None];
+        let pages = fill_slots(
+            pack(&[Span::ONE; 5], &[0, 0, 0, 1, 1], 2, 2, FillOrder::Rows),
+            &slots,
+        );
+        assert_eq!(pages.len(), 1);
+        assert_eq!(
+            pages[0]
+                .iter()
+                .map(|p| (p.index, p.row, p.col))
+                .collect::<Vec<_>>(),
+            vec![(0, 0, 0), (1, 1, 0)]
+        );
+    }
+}
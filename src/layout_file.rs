@@ -0,0 +1,354 @@
+//! 排版结果文件 layout.json
+//!
+//! 记录每页每张图片的来源、实际绘制位置、旋转与截取区域，供裁切设备、检查脚本等下游工具使用，
+//! 也可由 render 子命令按记录重新绘制
+
+use image::{DynamicImage, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    color::Color,
+    i18n::Msg,
+    layout::{Placement, Rect, Rotation},
+    Config, Error, IoSnafu,
+};
+
+/// 排版结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LayoutFile {
+    /// 每厘米像素数
+    pub ppc: f64,
+    /// 纸张宽度 像素
+    pub paper_w_px: u32,
+    /// 纸张高度 像素
+    pub paper_h_px: u32,
+    /// 横向单元格数量
+    pub n_h: u32,
+    /// 纵向单元格数量
+    pub n_v: u32,
+    /// 图片旋转方式，仅用于未记录旋转角度的旧文件
+    #[serde(default)]
+    pub rotation: Rotation,
+    /// 页面背景色，缺省时透明
+    #[serde(default)]
+    pub background: Option<Color>,
+    pub pages: Vec<PageLayout>,
+}
+
+/// 单页排版结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PageLayout {
+    /// 输出文件名（不含扩展名）
+    pub name: String,
+    pub images: Vec<PlacedImage>,
+}
+
+/// 单张图片的位置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlacedImage {
+    /// 源文件路径
+    pub path: PathBuf,
+    /// 起始单元格序号，先行后列
+    pub cell: u32,
+    /// 左上角横坐标 像素
+    pub x: u32,
+    /// 左上角纵坐标 像素
+    pub y: u32,
+    /// 绘制宽度 像素
+    pub w: u32,
+    /// 绘制高度 像素
+    pub h: u32,
+    /// 左上角横坐标 厘米
+    pub x_cm: f64,
+    /// 左上角纵坐标 厘米
+    pub y_cm: f64,
+    /// 绘制宽度 厘米
+    pub w_cm: f64,
+    /// 绘制高度 厘米
+    pub h_cm: f64,
+    /// 所在单元格区域 像素，缺省时为绘制区域
+    #[serde(default)]
+    pub cell_rect: Option<Rect>,
+    /// 顺时针旋转角度，缺省时按旋转方式与绘制区域的方向推算
+    #[serde(default)]
+    pub rotation: Option<u32>,
+    /// 旋转后的图片中缩放至绘制尺寸的区域 像素，缺省时为整张图片
+    #[serde(default)]
+    pub crop: Option<Rect>,
+}
+
+impl PlacedImage {
+    /// 绘制区域 像素
+    pub fn rect(&self) -> Rect {
+        Rect {
+            x: self.x,
+            y: self.y,
+            w: self.w,
+            h: self.h,
+        }
+    }
+}
+
+/// 保留两位小数
+fn round_cm(px: u32, ppc: f64) -> f64 {
+    (px as f64 / ppc * 100.0).round() / 100.0
+}
+
+impl LayoutFile {
+    pub fn new(config: &Config) -> LayoutFile {
+        LayoutFile {
+            ppc: config.ppc,
+            paper_w_px: config.paper_w_px,
+            paper_h_px: config.paper_h_px,
+            n_h: config.n_h,
+            n_v: config.n_v,
+            rotation: config.rotation,
+            background: Some(config.background),
+            pages: Vec::new(),
+        }
+    }
+
+    /// 添加一页
+    ///
+    /// - name: 输出文件名
+    /// - placements: 本页图片位置
+    /// - paths: 本页图片源文件，与 placements 一一对应
+    /// - rects: 本页图片实际绘制区域，与 placements 一一对应
+    /// - cells: 本页图片所在单元格区域，与 placements 一一对应
+    /// - framings: 本页图片的旋转角度与缩放前截取的区域，与 placements 一一对应
+    pub fn push_page(
+        &mut self,
+        name: &str,
+        placements: &[Placement],
+        paths: &[PathBuf],
+        rects: &[Rect],
+        cells: &[Rect],
+        framings: &[Option<(u32, Rect)>],
+    ) {
+        let ppc = self.ppc;
+        let images = placements
+            .iter()
+            .zip(paths.iter().zip(rects))
+            .zip(cells.iter().zip(framings))
+            .map(|((placement, (path, rect)), (cell, framing))| PlacedImage {
+                path: path.clone(),
+                cell: placement.row * self.n_h + placement.col,
+                x: rect.x,
+                y: rect.y,
+                w: rect.w,
+                h: rect.h,
+                x_cm: round_cm(rect.x, ppc),
+                y_cm: round_cm(rect.y, ppc),
+                w_cm: round_cm(rect.w, ppc),
+                h_cm: round_cm(rect.h, ppc),
+                cell_rect: Some(*cell),
+                rotation: framing.map(|(rotation, _)| rotation),
+                crop: framing.map(|(_, crop)| crop),
+            })
+            .collect();
+        self.pages.push(PageLayout {
+            name: name.to_string(),
+            images,
+        });
+    }
+
+    /// 按记录的位置重新绘制一页
+    ///
+    /// 图片按记录的旋转角度旋转、截取记录的区域后缩放至记录的尺寸，
+    /// 单元格样式、圆角、边框等装饰按 config 绘制，宽高为 0 的条目跳过
+    ///
+    /// - page: 页面
+    /// - config: 排版参数
+    /// - load: 读取图片，返回 None 时跳过该图片
+    pub fn render_page(
+        &self,
+        page: &PageLayout,
+        config: &Config,
+        mut load: impl FnMut(&Path) -> Result<Option<DynamicImage>, Error>,
+    ) -> Result<RgbaImage, Error> {
+        let background = self.background.map_or(Rgba([0, 0, 0, 0]), |color| color.0);
+        let mut canvas = RgbaImage::from_pixel(self.paper_w_px, self.paper_h_px, background);
+        for placed in &page.images {
+            if placed.w == 0 || placed.h == 0 {
+                continue;
+            }
+            let Some(image) = load(&placed.path)? else {
+                continue;
+            };
+            // 未记录旋转角度时，绘制区域的方向即单元格方向
+            let rotation = placed.rotation.unwrap_or_else(|| {
+                self.rotation
+                    .degrees((image.width(), image.height()), placed.h > placed.w)
+            });
+            let photo = placed.rect();
+            let area = config.image_area(&placed.cell_rect.unwrap_or(photo));
+            config.draw_placed(&mut canvas, image, rotation, placed.crop, photo, area);
+        }
+        Ok(canvas)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn load(path: &Path) -> Result<LayoutFile, Error> {
+        let text = fs::read_to_string(path).context(IoSnafu)?;
+        serde_json::from_str(&text).map_err(|e| Error::Input {
+            reason: Msg::LayoutInvalid {
+                path: &path.display().to_string(),
+                reason: e.to_string(),
+            }
+            .to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        layout::{Fit, Span},
+        style::CellStyle,
+        LayoutOptions, RenderEvent, Typesetter,
+    };
+    use image::Rgb;
+
+    #[test]
+    fn test_layout_file_round_trip() {
+        let mut layout = LayoutFile {
+            ppc: 100.0,
+            paper_w_px: 2970,
+            paper_h_px: 2100,
+            n_h: 4,
+            n_v: 3,
+            rotation: Rotation::Cw,
+            background: None,
+            pages: Vec::new(),
+        };
+        let placement = Placement {
+            index: 0,
+            row: 1,
+            col: 2,
+            span: Span::ONE,
+        };
+        let rect = Rect {
+            x: 150,
+            y: 80,
+            w: 500,
+            h: 333,
+        };
+        let crop = Rect {
+            x: 10,
+            y: 0,
+            w: 900,
+            h: 600,
+        };
+        layout.push_page(
+            "output_0",
+            &[placement],
+            &[PathBuf::from("a.jpg")],
+            &[rect],
+            &[rect],
+            &[Some((90, crop))],
+        );
+        let image = &layout.pages[0].images[0];
+        assert_eq!(image.cell, 6);
+        assert_eq!((image.x_cm, image.h_cm), (1.5, 3.33));
+        assert_eq!((image.rotation, image.crop), (Some(90), Some(crop)));
+
+        let parsed: LayoutFile = serde_json::from_str(&layout.to_json()).unwrap();
+        assert_eq!(parsed, layout);
+    }
+
+    #[test]
+    fn test_render_page_matches_typesetter() {
+        // 横竖不同、内容不对称的图片，旋转、截取或背景不一致时绘制结果不同
+        let gradient = |w: u32, h: u32| {
+            DynamicImage::ImageRgb8(image::RgbImage::from_fn(w, h, |x, y| {
+                Rgb([
+                    (x * 255 / w) as u8,
+                    (y * 255 / h) as u8,
+                    ((x + y) % 256) as u8,
+                ])
+            }))
+        };
+        let images = [gradient(120, 40), gradient(30, 90), gradient(64, 48)];
+        let config = Config::from_options(&LayoutOptions {
+            ppc: Some(10.0),
+            nh: Some(2),
+            nv: Some(2),
+            fit: Some(Fit::Cover),
+            style: Some(CellStyle::Polaroid),
+            frame: Some(0.2),
+            corner_radius: Some(0.3),
+            background: Some("#336699".parse().unwrap()),
+            ..Default::default()
+        })
+        .unwrap();
+        let typesetter = Typesetter::new(config);
+        let expected = typesetter.render_pages(&images).unwrap();
+
+        let spans = vec![Span::ONE; images.len()];
+        let groups = vec![0; images.len()];
+        let pages = typesetter.layout(&spans, &groups).unwrap();
+        let mut layout = LayoutFile::new(&typesetter.config);
+        let config = &typesetter.config;
+        for (i, placements) in pages.iter().enumerate() {
+            let page_images: Vec<DynamicImage> = placements
+                .iter()
+                .map(|placement| images[placement.index].clone())
+                .collect();
+            let target_hs = vec![config.target_h_px; placements.len()];
+            let mut framings = vec![None; placements.len()];
+            let (_, rects) = typesetter
+                .render_page(&page_images, placements, &target_hs, |event| {
+                    if let RenderEvent::Resized {
+                        index,
+                        rotation,
+                        crop,
+                        ..
+                    } = event
+                    {
+                        framings[index] = Some((rotation, crop));
+                    }
+                    Ok(())
+                })
+                .unwrap();
+            let paths: Vec<PathBuf> = placements
+                .iter()
+                .map(|placement| PathBuf::from(placement.index.to_string()))
+                .collect();
+            let cells: Vec<Rect> = placements
+                .iter()
+                .map(|placement| config.cell_rect(placement))
+                .collect();
+            layout.push_page(
+                &i.to_string(),
+                placements,
+                &paths,
+                &rects,
+                &cells,
+                &framings,
+            );
+        }
+        let layout: LayoutFile = serde_json::from_str(&layout.to_json()).unwrap();
+        assert!(layout.pages[0]
+            .images
+            .iter()
+            .any(|image| image.rotation == Some(270)));
+        for (page, expected) in layout.pages.iter().zip(&expected) {
+            let canvas = layout
+                .render_page(page, config, |path| {
+                    let index: usize = path.to_string_lossy().parse().unwrap();
+                    Ok(Some(images[index].clone()))
+                })
+                .unwrap();
+            assert!(canvas == *expected, "page {} differs", page.name);
+        }
+    }
+}
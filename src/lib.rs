@@ -0,0 +1,1784 @@
+//! 图片排版
+//!
+//! 将一组图片按固定网格排入纸张，输出可直接打印的页面。
+//! 命令行工具之外，也可在其他程序中通过 [`Typesetter`] 直接获取排版结果。
+
+use color::Color;
+use depth::{Depth, Rgba16Image};
+use icc::Profile;
+use image::{imageops, DynamicImage, Rgb, RgbImage, RgbaImage};
+use imageproc::drawing::draw_filled_rect_mut;
+use layout::{
+    Anchor, FillOrder, Filter, Fit, GutterSide, Imposition, PairMode, Placement, Rect, Rotation,
+    Span,
+};
+use paper::{Border, Orientation, Paper};
+use saliency::Crop;
+use serde::Deserialize;
+use snafu::prelude::*;
+use std::path::Path;
+use style::{CellStyle, Insets};
+use template::{LabelShape, LabelTemplate};
+
+pub mod adjust;
+pub mod barcode;
+pub mod caption;
+pub mod code;
+pub mod color;
+pub mod colorspace;
+pub mod cut_path;
+pub mod depth;
+pub mod deskew;
+pub mod draw;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod ffi;
+pub mod gallery;
+pub mod header;
+pub mod i18n;
+pub mod icc;
+pub mod id_photo;
+pub mod index;
+pub mod input;
+#[cfg(feature = "jxl")]
+pub mod jxl;
+pub mod layout;
+pub mod layout_file;
+pub mod linear;
+pub mod manifest;
+pub mod memory;
+pub mod metadata;
+pub mod number;
+pub mod output;
+pub mod page;
+pub mod paper;
+pub mod pdf;
+pub mod plan;
+pub mod poster;
+#[cfg(feature = "python")]
+mod python;
+pub mod qr;
+pub mod saliency;
+pub mod skin;
+pub mod style;
+pub mod template;
+pub mod text;
+pub mod verify;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
+pub mod watermark;
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub))]
+pub enum Error {
+    #[snafu(display("I/O error: {}", source))]
+    Io { source: std::io::Error },
+    #[snafu(display("Image error: {}", source))]
+    Image { source: image::ImageError },
+    #[snafu(display("Input error: {}", reason))]
+    Input { reason: String },
+    #[snafu(display("Config error: {}", reason))]
+    Config { reason: String },
+    #[snafu(display("PDF error: {}", source))]
+    Pdf { source: printpdf::Error },
+    #[snafu(display("PDF error: {}", source))]
+    PdfPages { source: printpdf::lopdf::Error },
+    #[snafu(display("Interrupted"))]
+    Interrupted,
+    #[cfg(feature = "jxl")]
+    #[snafu(display("Codec error: {}", reason))]
+    Codec { reason: String },
+}
+
+/// 排版参数，未设置的项使用默认值
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LayoutOptions {
+    /// 纸张 默认 A4 横向
+    pub paper: Option<Paper>,
+    /// 纸张方向
+    pub orientation: Option<Orientation>,
+    /// 标签纸模板，设置后纸张、数量与单元格位置均取自模板
+    pub template: Option<LabelTemplate>,
+    /// 单张图片目标高度（单位：cm） 默认 5
+    pub height: Option<f64>,
+    /// 纸张四边的边距（单位：cm） 默认 0.8
+    pub border: Option<Border>,
+    /// 图片之间的间距（单位：cm） 默认 0.3
+    pub margin: Option<f64>,
+    /// 装订线宽度（单位：cm），在装订一侧的纸张边距外额外留出
+    pub gutter: Option<f64>,
+    /// 装订线所在一侧 默认左侧
+    pub gutter_side: Option<GutterSide>,
+    /// 每厘米像素数 默认 118.11
+    pub ppc: Option<f64>,
+    /// 每英寸像素数，优先于 ppc
+    pub ppi: Option<f64>,
+    /// 横向图片数量
+    pub nh: Option<u32>,
+    /// 纵向图片数量
+    pub nv: Option<u32>,
+    /// 整体缩放比例
+    pub scale: Option<f64>,
+    /// 缩放时同时缩放图片间距
+    pub scale_margins: bool,
+    /// 目标高度放不下时自动减少横向/纵向数量
+    pub auto_grid: bool,
+    /// 剩余空间平均分配到图片间距，使网格占满可用区域
+    pub justify: bool,
+    /// 说明文字字号（单位：pt），设置后在每张图片下方预留说明文字的空间
+    pub caption_size: Option<f64>,
+    /// 说明文字最多行数 默认 1
+    pub caption_lines: Option<u32>,
+    /// 说明文字叠加在图片上，不预留空间
+    pub caption_overlay: bool,
+    /// 出血（单位：mm），图片向四周放大超出单元格，裁切后边缘不留白
+    pub bleed: Option<f64>,
+    /// 图片边框宽度（单位：mm），绘制在图片外侧
+    pub frame: Option<f64>,
+    /// 图片边框颜色 默认黑色
+    pub frame_color: Option<Color>,
+    /// 图片圆角半径（单位：mm）
+    pub corner_radius: Option<f64>,
+    /// 单元格样式 默认仅图片
+    pub style: Option<CellStyle>,
+    /// 图片旋转方式 默认按单元格方向自动旋转
+    pub rotate: Option<Rotation>,
+    /// 图片在单元格中的对齐位置 默认居中
+    pub align: Option<Anchor>,
+    /// 图片缩放方式 默认完整放入单元格
+    pub fit: Option<Fit>,
+    /// 不放大图片，小图按原始像素尺寸输出
+    pub no_upscale: bool,
+    /// 铺满时的裁切位置 默认保留细节最多的区域
+    pub crop: Option<Crop>,
+    /// 缩放滤镜 默认 Lanczos3
+    pub filter: Option<Filter>,
+    /// 在线性 RGB 中缩放，避免高反差细节变暗
+    pub linear_light: bool,
+    /// 缩放后 USM 锐化强度，0 为不锐化
+    pub sharpen: Option<f64>,
+    /// 自动纠偏
+    pub deskew: bool,
+    /// 自动裁边阈值 0~1，None 为不裁边
+    pub autocrop: Option<f64>,
+    /// 亮度 -100~100 默认 0
+    pub brightness: Option<f64>,
+    /// 对比度 -100~100 默认 0
+    pub contrast: Option<f64>,
+    /// 自动色阶
+    pub auto_levels: bool,
+    /// 输出位深 默认按图片自动选择
+    pub depth: Option<Depth>,
+    /// 色彩配置文件，设置后图片由内嵌配置文件转换到该配置文件，并在输出中嵌入
+    pub color_profile: Option<Profile>,
+    /// 页面背景色 默认白色
+    pub background: Option<Color>,
+    /// 单元格衬底颜色，填充图片未占满的区域
+    pub matte: Option<Color>,
+    /// 在每张图片下方绘制投影
+    pub shadow: bool,
+    /// 投影偏移（单位：mm） 默认 1
+    pub shadow_offset: Option<f64>,
+    /// 投影模糊半径（单位：mm） 默认 1.5
+    pub shadow_blur: Option<f64>,
+    /// 投影不透明度 0~1 默认 0.4
+    pub shadow_opacity: Option<f64>,
+    /// 二维码边长（单位：mm），设置后在每张图片右侧预留二维码的空间
+    pub qr_size: Option<f64>,
+    /// 条形码高度（单位：mm），设置后在每张图片下方预留条形码的空间
+    pub barcode_height: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// 每厘米像素数
+    pub ppc: f64,
+    /// 纸张宽度 像素
+    pub paper_w_px: u32,
+    /// 纸张高度 像素
+    pub paper_h_px: u32,
+    /// 纸张四边的外边距 像素
+    pub paper_border_px: Insets,
+    /// 第一个单元格左上角位置 像素，未使用模板时为纸张外边距
+    pub grid_origin_px: (u32, u32),
+    /// 单独横向移动的一行（行号, 偏移像素，向右为正），用于居中不完整的最后一行
+    pub row_shift_px: Option<(u32, i64)>,
+    /// 装订线宽度 像素
+    pub gutter_px: u32,
+    /// 装订线所在一侧
+    pub gutter_side: GutterSide,
+    /// 纵向最小边距 像素
+    pub min_margin_v_px: u32,
+    /// 横向最小边距 像素
+    pub min_margin_h_px: u32,
+    /// 单图片目标高度 像素
+    pub target_h_px: u32,
+    /// 单图片最大高度 像素
+    pub max_h_px: u32,
+    /// 单图片最大宽度 像素
+    pub max_w_px: u32,
+    /// 横向图片数量
+    pub n_h: u32,
+    /// 纵向图片数量
+    pub n_v: u32,
+    /// 说明文字字号 像素，0 表示不显示说明文字
+    pub caption_size_px: f64,
+    /// 说明文字最多行数
+    pub caption_lines: u32,
+    /// 每张图片下方为说明文字预留的高度 像素
+    pub caption_h_px: u32,
+    /// 出血 单边 像素
+    pub bleed_px: u32,
+    /// 图片边框宽度 像素
+    pub frame_px: u32,
+    /// 图片边框颜色
+    pub frame_color: Color,
+    /// 图片圆角半径 像素
+    pub corner_radius_px: f32,
+    /// 单元格样式
+    pub style: CellStyle,
+    /// 单元格样式的装饰在图片四周占用的宽度 像素
+    pub insets: Insets,
+    /// 页面背景色
+    pub background: Color,
+    /// 单元格衬底颜色
+    pub matte: Option<Color>,
+    /// 投影，像素
+    pub shadow: Option<draw::Shadow>,
+    /// 二维码边长 像素，0 为不预留
+    pub qr_px: u32,
+    /// 条形码高度 像素，0 为不预留
+    pub barcode_h_px: u32,
+    /// 图片旋转方式
+    pub rotation: Rotation,
+    /// 图片在单元格中的对齐位置
+    pub align: Anchor,
+    /// 图片缩放方式
+    pub fit: Fit,
+    /// 不放大图片
+    pub no_upscale: bool,
+    /// 铺满时的裁切位置
+    pub crop: Crop,
+    /// 缩放滤镜
+    pub filter: Filter,
+    /// 在线性 RGB 中缩放
+    pub linear_light: bool,
+    /// 缩放后锐化强度，0 为不锐化
+    pub sharpen: f32,
+    /// 自动纠偏
+    pub deskew: bool,
+    /// 自动裁边阈值，None 为不裁边
+    pub autocrop: Option<f32>,
+    /// 亮度 -100~100
+    pub brightness: f32,
+    /// 对比度 -100~100
+    pub contrast: f32,
+    /// 自动色阶
+    pub auto_levels: bool,
+    /// 输出位深
+    pub depth: Depth,
+    /// 色彩配置文件，None 为不做色彩管理
+    pub color_profile: Option<Profile>,
+    /// 生成参数时调整了网格或目标高度等的提示，由调用方决定如何输出
+    pub warnings: Vec<i18n::Msg<'static>>,
+}
+
+impl Config {
+    /// 按排版参数计算像素尺寸与网格，参数无效（数量为 0、分辨率或缩放比例不为正数、
+    /// 纸张边距过大等）时返回 [`Error::Config`]
+    pub fn from_options(options: &LayoutOptions) -> Result<Config, Error> {
+        let template = options.template.as_ref();
+        // 纸张尺寸
+        let paper = match (template, options.orientation) {
+            (Some(template), _) => template.paper,
+            (None, Some(orientation)) => options.paper.unwrap_or_default().oriented(orientation),
+            (None, None) => options.paper.unwrap_or_default(),
+        };
+        // 默认数量按纸张方向确定，长边放 4 张，短边放 3 张
+        let (default_n_h, default_n_v) = if paper.width_cm >= paper.height_cm {
+            (4, 3)
+        } else {
+            (3, 4)
+        };
+        // 横向图片数量
+        let mut n_h: u32 = options.nh.unwrap_or(default_n_h);
+        // 纵向图片数量
+        let mut n_v: u32 = options.nv.unwrap_or(default_n_v);
+        // 单图片目标高度 厘米
+        let mut target_h_cm: f64 = options.height.unwrap_or(5.0);
+        // 纸张四边的外边距 厘米
+        let paper_border_cm = options.border.unwrap_or(Border::uniform(0.8));
+        // 纵向最小边距 厘米
+        let mut min_margin_v_cm: f64 = options.margin.unwrap_or(0.3);
+        // 横向最小边距 厘米
+        let mut min_margin_h_cm: f64 = options.margin.unwrap_or(0.3);
+        // 每厘米像素数，默认从ppi计算，否则取ppc或默认值118.11=300ppi
+        let ppc: f64 = match options.ppi {
+            Some(ppi) => ppi / 2.54,
+            None => options.ppc.unwrap_or(118.11),
+        };
+        // 装订线 厘米，模板中的标签位置固定，不留装订线
+        let gutter_cm = match template {
+            Some(_) => 0.0,
+            None => options.gutter.unwrap_or(0.0).max(0.0),
+        };
+        // 可用区域 厘米
+        let area_w_cm = paper.width_cm - paper_border_cm.left - paper_border_cm.right - gutter_cm;
+        let area_h_cm = paper.height_cm - paper_border_cm.top - paper_border_cm.bottom;
+        // 参数检查：数量与各个除数需为正数
+        let invalid = |msg: i18n::Msg| ConfigSnafu {
+            reason: msg.to_string(),
+        };
+        let positive = |value: f64| value.is_finite() && value > 0.0;
+        ensure!(n_h > 0 && n_v > 0, invalid(i18n::Msg::GridEmpty));
+        ensure!(
+            positive(ppc),
+            invalid(i18n::Msg::OptionNotPositive("ppc/ppi"))
+        );
+        ensure!(
+            positive(target_h_cm),
+            invalid(i18n::Msg::OptionNotPositive("height"))
+        );
+        ensure!(
+            options.scale.is_none_or(positive),
+            invalid(i18n::Msg::OptionNotPositive("scale"))
+        );
+        ensure!(
+            min_margin_h_cm.is_finite() && min_margin_h_cm >= 0.0,
+            invalid(i18n::Msg::OptionNegative("margin"))
+        );
+        ensure!(
+            positive(area_w_cm) && positive(area_h_cm),
+            invalid(i18n::Msg::NoPrintableArea)
+        );
+        // 单图片最大高度 厘米
+        let mut max_h_cm = (area_h_cm - (n_v - 1) as f64 * min_margin_v_cm) / n_v as f64;
+        // 单图片最大宽度 厘米
+        let mut max_w_cm = (area_w_cm - (n_h - 1) as f64 * min_margin_h_cm) / n_h as f64;
+        // 整体缩放，缩小单元格后重新计算每页可容纳的数量
+        if let (Some(scale), None) = (options.scale, template) {
+            target_h_cm *= scale;
+            max_h_cm *= scale;
+            max_w_cm *= scale;
+            if options.scale_margins {
+                min_margin_v_cm *= scale;
+                min_margin_h_cm *= scale;
+            }
+            let fit_count = |area: f64, cell: f64, gap: f64| {
+                (((area + gap) / (cell + gap)).floor() as u32).max(1)
+            };
+            n_h = fit_count(area_w_cm, max_w_cm, min_margin_h_cm);
+            n_v = fit_count(area_h_cm, max_h_cm, min_margin_v_cm);
+            max_h_cm = max_h_cm.min(area_h_cm);
+            max_w_cm = max_w_cm.min(area_w_cm);
+        }
+        let mut warnings = Vec::new();
+        // 自动调整网格，减少数量直至单元格能容纳目标高度
+        // 图片均旋转为横图，单元格宽度也不应小于目标高度
+        if options.auto_grid && template.is_none() {
+            let (n_h_before, n_v_before) = (n_h, n_v);
+            while n_v > 1 && target_h_cm > max_h_cm {
+                n_v -= 1;
+                max_h_cm = (area_h_cm - (n_v - 1) as f64 * min_margin_v_cm) / n_v as f64;
+            }
+            while n_h > 1 && target_h_cm > max_w_cm {
+                n_h -= 1;
+                max_w_cm = (area_w_cm - (n_h - 1) as f64 * min_margin_h_cm) / n_h as f64;
+            }
+            if (n_h, n_v) != (n_h_before, n_v_before) {
+                warnings.push(i18n::Msg::GridAdjusted {
+                    target_cm: target_h_cm,
+                    from: (n_h_before, n_v_before),
+                    to: (n_h, n_v),
+                });
+            }
+        }
+        // 两端对齐：剩余空间平均分配到图片间距，只有一行或一列时居中
+        let mut justify_offset_cm = (0.0, 0.0);
+        if options.justify && template.is_none() {
+            let spread = |area: f64, cell: f64, gap: &mut f64, n: u32| {
+                let leftover = (area - n as f64 * cell - (n - 1) as f64 * *gap).max(0.0);
+                match n {
+                    1 => leftover / 2.0,
+                    _ => {
+                        *gap += leftover / (n - 1) as f64;
+                        0.0
+                    }
+                }
+            };
+            justify_offset_cm = (
+                spread(area_w_cm, max_w_cm, &mut min_margin_h_cm, n_h),
+                spread(area_h_cm, max_h_cm, &mut min_margin_v_cm, n_v),
+            );
+        }
+        // 模板中的标签即单元格，标签之间的空隙即间距
+        if let Some(template) = template {
+            n_h = template.cols;
+            n_v = template.rows;
+            max_w_cm = template.width / 10.0;
+            max_h_cm = template.height / 10.0;
+            min_margin_h_cm = (template.pitch_x - template.width) / 10.0;
+            min_margin_v_cm = (template.pitch_y - template.height) / 10.0;
+            target_h_cm = options.height.unwrap_or(max_h_cm);
+        }
+        // 纸张尺寸 像素
+        let paper_w_px = (paper.width_cm * ppc).ceil() as u32;
+        let paper_h_px = (paper.height_cm * ppc).ceil() as u32;
+        // 纸张四边的外边距 像素
+        let border_px = |cm: f64| (cm.max(0.0) * ppc).round() as u32;
+        let paper_border_px = Insets {
+            left: border_px(paper_border_cm.left),
+            top: border_px(paper_border_cm.top),
+            right: border_px(paper_border_cm.right),
+            bottom: border_px(paper_border_cm.bottom),
+        };
+        let gutter_px = (gutter_cm * ppc).round() as u32;
+        let grid_origin_px = match template {
+            Some(template) => (
+                (template.left / 10.0 * ppc).round() as u32,
+                (template.top / 10.0 * ppc).round() as u32,
+            ),
+            None => (
+                paper_border_px.left + gutter_px + (justify_offset_cm.0 * ppc).round() as u32,
+                paper_border_px.top + (justify_offset_cm.1 * ppc).round() as u32,
+            ),
+        };
+        // 纵向最小边距 像素
+        let min_margin_v_px = (min_margin_v_cm * ppc).round() as u32;
+        // 横向最小边距 像素
+        let min_margin_h_px = (min_margin_h_cm * ppc).round() as u32;
+        // 单图片目标高度 像素
+        let mut target_h_px = (target_h_cm * ppc).round() as u32;
+        // 单图片最大高度 像素
+        let max_h_px = (max_h_cm * ppc).round() as u32;
+        // 单图片最大宽度 像素
+        let max_w_px = (max_w_cm * ppc).round() as u32;
+        // 说明文字 pt 换算为像素，预留间距与各行高度
+        let caption_size_px = options.caption_size.unwrap_or(0.0) / 72.0 * 2.54 * ppc;
+        let caption_lines = options.caption_lines.unwrap_or(1).max(1);
+        let caption_h_px = match options.caption_size {
+            Some(_) if !options.caption_overlay => (caption_size_px
+                * (caption::CAPTION_GAP + caption_lines as f64 * caption::LINE_HEIGHT))
+                .ceil() as u32,
+            _ => 0,
+        };
+        // 单元格样式的装饰，可将说明文字写在装饰内
+        let style = options.style.unwrap_or_default();
+        let insets = style.insets(max_h_px, caption_h_px);
+        let caption_h_px = match style.holds_caption() {
+            true => 0,
+            false => caption_h_px,
+        };
+
+        // 条形码 mm 换算为像素，另加与上方内容的间距
+        let barcode_h_px =
+            (options.barcode_height.unwrap_or(0.0).max(0.0) / 10.0 * ppc).round() as u32;
+        let barcode_reserve_px = match barcode_h_px {
+            0 => 0,
+            h => h + h / 4,
+        };
+
+        // 验证config
+        let image_max_h_px = max_h_px
+            .saturating_sub(caption_h_px + insets.vertical() + barcode_reserve_px)
+            .max(1);
+        if target_h_px > image_max_h_px {
+            warnings.push(i18n::Msg::HeightClamped {
+                target_cm: target_h_cm,
+                max_cm: image_max_h_px as f64 / ppc,
+            });
+            target_h_px = image_max_h_px;
+        };
+
+        Ok(Config {
+            ppc,
+            paper_w_px,
+            paper_h_px,
+            paper_border_px,
+            grid_origin_px,
+            row_shift_px: None,
+            gutter_px,
+            gutter_side: options.gutter_side.unwrap_or_default(),
+            min_margin_v_px,
+            min_margin_h_px,
+            target_h_px,
+            max_h_px,
+            max_w_px,
+            n_h,
+            n_v,
+            caption_size_px,
+            caption_lines,
+            caption_h_px,
+            bleed_px: (options.bleed.unwrap_or(0.0).max(0.0) / 10.0 * ppc).round() as u32,
+            frame_px: (options.frame.unwrap_or(0.0).max(0.0) / 10.0 * ppc).round() as u32,
+            frame_color: options.frame_color.unwrap_or(Color::BLACK),
+            // 圆形标签默认将图片裁为圆形
+            corner_radius_px: match (options.corner_radius, template.map(|t| t.shape)) {
+                (None, Some(LabelShape::Round)) => (max_w_px.min(max_h_px) / 2) as f32,
+                (radius, _) => (radius.unwrap_or(0.0).max(0.0) / 10.0 * ppc) as f32,
+            },
+            style,
+            insets,
+            background: options.background.unwrap_or(Color::WHITE),
+            matte: options.matte,
+            shadow: options.shadow.then(|| draw::Shadow {
+                offset: (options.shadow_offset.unwrap_or(1.0).max(0.0) / 10.0 * ppc).round() as u32,
+                blur: (options.shadow_blur.unwrap_or(1.5).max(0.0) / 10.0 * ppc) as f32,
+                opacity: options.shadow_opacity.unwrap_or(0.4).clamp(0.0, 1.0) as f32,
+            }),
+            qr_px: (options.qr_size.unwrap_or(0.0).max(0.0) / 10.0 * ppc).round() as u32,
+            barcode_h_px,
+            rotation: options.rotate.unwrap_or_default(),
+            align: options.align.unwrap_or_default(),
+            // 标签默认铺满
+            fit: match template {
+                Some(_) => options.fit.unwrap_or(Fit::Cover),
+                None => options.fit.unwrap_or_default(),
+            },
+            no_upscale: options.no_upscale,
+            crop: options.crop.unwrap_or_default(),
+            filter: options.filter.unwrap_or_default(),
+            linear_light: options.linear_light,
+            sharpen: options.sharpen.unwrap_or(0.0).max(0.0) as f32,
+            deskew: options.deskew,
+            autocrop: options
+                .autocrop
+                .map(|threshold| threshold.clamp(0.0, 1.0) as f32),
+            brightness: options.brightness.unwrap_or(0.0).clamp(-100.0, 100.0) as f32,
+            contrast: options.contrast.unwrap_or(0.0).clamp(-100.0, 100.0) as f32,
+            auto_levels: options.auto_levels,
+            depth: options.depth.unwrap_or_default(),
+            color_profile: options.color_profile,
+            warnings,
+        })
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config::from_options(&LayoutOptions::default()).expect("默认排版参数有效")
+    }
+}
+
+impl Config {
+    /// 计算图片按目标高度输出时占用的单元格数
+    ///
+    /// - target_h_px: 目标高度 像素
+    /// - dimensions: 图片原始尺寸
+    pub fn span_for(&self, target_h_px: u32, dimensions: (u32, u32)) -> Span {
+        let (w, h) = self.oriented(dimensions);
+        let width_px = target_h_px as u64 * w as u64 / h.max(1) as u64
+            + self.insets.horizontal() as u64
+            + self.code_w_px() as u64;
+        let cells = |len: u64, cell: u32, gap: u32| {
+            (len + gap as u64).div_ceil(cell as u64 + gap as u64).max(1) as u32
+        };
+        let height_px = target_h_px as u64
+            + self.caption_h_px as u64
+            + self.insets.vertical() as u64
+            + self.code_h_px() as u64;
+        Span {
+            cols: cells(width_px, self.max_w_px, self.min_margin_h_px),
+            rows: cells(height_px, self.max_h_px, self.min_margin_v_px),
+        }
+    }
+
+    /// 计算图片绘制后的尺寸 像素，与绘制时的旋转、缩放一致
+    ///
+    /// - placement: 图片位置
+    /// - target_h_px: 目标高度 像素
+    /// - dimensions: 图片原始尺寸
+    pub fn fitted_size(
+        &self,
+        placement: &Placement,
+        target_h_px: u32,
+        dimensions: (u32, u32),
+    ) -> (u32, u32) {
+        let (w, h) = self.oriented(dimensions);
+        let area = self.image_area(&self.cell_rect(placement));
+        let (box_w, box_h) = self.fit_box(area, target_h_px, (w, h));
+        match self.fit {
+            Fit::Contain => {
+                let (w, h) = (w.max(1) as f64, h.max(1) as f64);
+                let ratio = (box_w as f64 / w).min(box_h as f64 / h);
+                (
+                    ((w * ratio).round() as u32).max(1),
+                    ((h * ratio).round() as u32).max(1),
+                )
+            }
+            Fit::Cover | Fit::Stretch => (box_w, box_h),
+            Fit::Original => (w.clamp(1, area.w), h.clamp(1, area.h)),
+        }
+    }
+
+    /// 计算图片按绘制尺寸打印时的有效 DPI，按放大较多的方向计算
+    ///
+    /// - placement: 图片位置
+    /// - target_h_px: 目标高度 像素
+    /// - dimensions: 图片原始尺寸
+    pub fn effective_dpi(
+        &self,
+        placement: &Placement,
+        target_h_px: u32,
+        dimensions: (u32, u32),
+    ) -> f64 {
+        let (w, h) = self.oriented(dimensions);
+        let (fitted_w, fitted_h) = match self.fit {
+            // 按原始尺寸放置时只裁切，不缩放
+            Fit::Original => (w, h),
+            _ => self.fitted_size(placement, target_h_px, dimensions),
+        };
+        let ratio = (fitted_w as f64 / w.max(1) as f64).max(fitted_h as f64 / h.max(1) as f64);
+        self.ppc * 2.54 / ratio
+    }
+
+    /// 按缩放方式缩放图片时的目标区域 像素
+    ///
+    /// 不放大图片时区域缩小至图片原始尺寸对应的大小
+    ///
+    /// - area: 图片可用的区域
+    /// - target_h_px: 目标高度 像素
+    /// - dimensions: 图片旋转后的尺寸
+    fn fit_box(&self, area: Rect, target_h_px: u32, dimensions: (u32, u32)) -> (u32, u32) {
+        let (box_w, box_h) = (area.w, target_h_px.min(area.h));
+        if !self.no_upscale {
+            return (box_w, box_h);
+        }
+        let (w, h) = (dimensions.0.max(1), dimensions.1.max(1));
+        match self.fit {
+            Fit::Cover => {
+                let ratio = (box_w as f64 / w as f64).max(box_h as f64 / h as f64);
+                match ratio > 1.0 {
+                    true => (
+                        ((box_w as f64 / ratio).round() as u32).max(1),
+                        ((box_h as f64 / ratio).round() as u32).max(1),
+                    ),
+                    false => (box_w, box_h),
+                }
+            }
+            _ => (box_w.min(w), box_h.min(h)),
+        }
+    }
+
+    /// 单元格中图片可用的区域 像素，扣除单元格样式的装饰、说明文字与条形码预留的高度及二维码预留的宽度
+    pub fn image_area(&self, cell: &Rect) -> Rect {
+        Rect {
+            x: cell.x + self.insets.left,
+            y: cell.y + self.insets.top,
+            w: cell
+                .w
+                .saturating_sub(self.insets.horizontal() + self.code_w_px())
+                .max(1),
+            h: cell
+                .h
+                .saturating_sub(self.caption_h_px + self.insets.vertical() + self.code_h_px())
+                .max(1),
+        }
+    }
+
+    /// 二维码与图片之间的间距 像素
+    pub fn qr_gap_px(&self) -> u32 {
+        self.qr_px / 8
+    }
+
+    /// 图片右侧为二维码预留的宽度 像素
+    pub fn code_w_px(&self) -> u32 {
+        match self.qr_px {
+            0 => 0,
+            qr_px => qr_px + self.qr_gap_px(),
+        }
+    }
+
+    /// 单元格是否为竖向，宽高相等时视为横向
+    pub fn portrait_cells(&self) -> bool {
+        self.max_h_px > self.max_w_px
+    }
+
+    /// 图片按旋转方式应旋转的角度，顺时针
+    ///
+    /// 自动旋转时比较旋转前后在单个单元格中按目标高度缩放的尺寸，取较大者
+    pub fn rotation_for(&self, dimensions: (u32, u32)) -> u32 {
+        if self.rotation != Rotation::Auto {
+            return self.rotation.degrees(dimensions, self.portrait_cells());
+        }
+        let area = self.image_area(&Rect {
+            x: 0,
+            y: 0,
+            w: self.max_w_px,
+            h: self.max_h_px,
+        });
+        let (area_w, area_h) = (area.w as f64, self.target_h_px.min(area.h) as f64);
+        let (w, h) = (dimensions.0.max(1) as f64, dimensions.1.max(1) as f64);
+        let upright = (area_w / w).min(area_h / h);
+        let turned = (area_w / h).min(area_h / w);
+        match turned > upright {
+            true => 270,
+            false => 0,
+        }
+    }
+
+    /// 图片旋转后的尺寸
+    pub fn oriented(&self, dimensions: (u32, u32)) -> (u32, u32) {
+        match self.rotation_for(dimensions) {
+            90 | 270 => (dimensions.1, dimensions.0),
+            _ => dimensions,
+        }
+    }
+
+    /// 条形码与上方内容之间的间距 像素
+    pub fn barcode_gap_px(&self) -> u32 {
+        self.barcode_h_px / 4
+    }
+
+    /// 图片下方为条形码预留的高度 像素
+    pub fn code_h_px(&self) -> u32 {
+        match self.barcode_h_px {
+            0 => 0,
+            barcode_h_px => barcode_h_px + self.barcode_gap_px(),
+        }
+    }
+
+    /// 单元格中图片可用的最大高度 像素
+    pub fn image_max_h(&self, cell: &Rect) -> u32 {
+        self.image_area(cell).h
+    }
+
+    /// 图片加上单元格样式的装饰后的区域 像素
+    ///
+    /// - rect: 图片实际绘制区域
+    pub fn outer_rect(&self, rect: Rect) -> Rect {
+        Rect {
+            x: rect.x.saturating_sub(self.insets.left),
+            y: rect.y.saturating_sub(self.insets.top),
+            w: rect.w + self.insets.horizontal(),
+            h: rect.h + self.insets.vertical(),
+        }
+    }
+
+    /// 绘制前的预处理：纠偏、裁边与色调调整
+    fn prepare(&self, mut image: DynamicImage) -> DynamicImage {
+        // 先纠偏再裁边，纠偏露出的透明角落一并裁去
+        if self.deskew {
+            image = deskew::deskew(image);
+        }
+        if let Some(threshold) = self.autocrop {
+            image = adjust::autocrop(image, threshold);
+        }
+        adjust::tone(image, self.brightness, self.contrast, self.auto_levels)
+    }
+
+    /// 是否在线性光下缩放
+    fn scales_linear(&self) -> bool {
+        self.linear_light && self.fit != Fit::Original
+    }
+
+    /// 缩放后的处理：线性光时转换回 sRGB，再锐化
+    ///
+    /// - deep: 原图是否为 16 位
+    fn resized(&self, image: DynamicImage, deep: bool) -> DynamicImage {
+        let image = match self.scales_linear() {
+            true => linear::to_srgb(&image, deep),
+            false => image,
+        };
+        let sigma = (adjust::SHARPEN_RADIUS / 10.0 * self.ppc) as f32;
+        adjust::sharpen(image, self.sharpen, sigma)
+    }
+
+    /// 截取图片中的 crop 区域并缩放至 size，原始尺寸放置时只截取不缩放
+    ///
+    /// - image: 旋转后的图片
+    /// - deep: 原图是否为 16 位
+    fn fit_crop(
+        &self,
+        image: &DynamicImage,
+        crop: Rect,
+        (w, h): (u32, u32),
+        deep: bool,
+    ) -> DynamicImage {
+        let cropped = |image: &DynamicImage| image.crop_imm(crop.x, crop.y, crop.w, crop.h);
+        if self.fit == Fit::Original {
+            return cropped(image);
+        }
+        let filter = self.filter.filter_type();
+        let whole = (crop.x, crop.y, crop.w, crop.h) == (0, 0, image.width(), image.height());
+        let fitted = match whole {
+            true => image.resize_exact(w, h, filter),
+            false => cropped(image).resize_exact(w, h, filter),
+        };
+        self.resized(fitted, deep)
+    }
+
+    /// 为缩放后的图片加上出血与单元格样式的装饰，得到实际放置的图片
+    ///
+    /// - image: 旋转后、缩放前的图片，出血时从中重新缩放裁切
+    /// - fitted: 缩放后的图片
+    /// - deep: 原图是否为 16 位
+    fn dress(&self, image: &DynamicImage, fitted: DynamicImage, deep: bool) -> DynamicImage {
+        // 出血：放大至四周各超出 bleed_px，居中裁切
+        let bleed = self.bleed_px;
+        let bleed_w = fitted.width() + self.insets.horizontal() + 2 * bleed;
+        let bleed_h = fitted.height() + self.insets.vertical() + 2 * bleed;
+        let filter = self.filter.filter_type();
+        match (bleed, self.style) {
+            (0, style) => style.decorate(fitted, self.insets),
+            (_, CellStyle::Plain) if self.fit == Fit::Stretch => {
+                self.resized(image.resize_exact(bleed_w, bleed_h, filter), deep)
+            }
+            (_, CellStyle::Plain) => self.resized(
+                saliency::resize_to_fill(image, bleed_w, bleed_h, filter, self.crop),
+                deep,
+            ),
+            (_, style) => style
+                .decorate(fitted, self.insets)
+                .resize_to_fill(bleed_w, bleed_h, filter),
+        }
+    }
+
+    /// 加上装饰与出血后的图片左上角位置 像素
+    fn dressed_origin(&self, photo: Rect) -> (i64, i64) {
+        let outer = self.outer_rect(photo);
+        let bleed = self.bleed_px as i64;
+        (outer.x as i64 - bleed, outer.y as i64 - bleed)
+    }
+
+    /// 将装饰后的图片绘制到画布，加上圆角、衬底、阴影与边框
+    ///
+    /// - image: 装饰后的图片
+    /// - photo: 图片绘制区域
+    /// - area: 单元格中图片可用的区域，衬底填满此区域
+    fn place(&self, canvas: &mut RgbaImage, mut image: DynamicImage, photo: Rect, area: Rect) {
+        let bleed = self.bleed_px;
+        // 圆角：出血部分的圆角半径随之增大，裁切后与设定一致
+        if self.corner_radius_px > 0.0 {
+            let mut rgba = image.into_rgba8();
+            draw::round_corners(&mut rgba, self.corner_radius_px + bleed as f32);
+            image = DynamicImage::ImageRgba8(rgba);
+        }
+        let (x, y) = self.dressed_origin(photo);
+        // 衬底填满图片可用的区域
+        if let Some(matte) = self.matte {
+            let matte_area =
+                imageproc::rect::Rect::at(area.x as i32, area.y as i32).of_size(area.w, area.h);
+            draw_filled_rect_mut(canvas, matte_area, matte.0);
+        }
+        if let Some(shadow) = self.shadow {
+            let frame = self.frame_px.saturating_sub(bleed);
+            let radius = self.corner_radius_px + bleed as f32;
+            draw::drop_shadow(canvas, &image, (x, y), frame, radius, shadow);
+        }
+        imageops::overlay(canvas, &image, x, y);
+        draw::frame(
+            canvas,
+            self.outer_rect(photo),
+            self.frame_px,
+            self.corner_radius_px,
+            self.frame_color.0,
+        );
+    }
+
+    /// 按记录的旋转角度与截取区域重新绘制一张图片
+    ///
+    /// 与排版时的绘制过程相同，只是位置与缩放尺寸取记录值，用于重现或调整排版结果
+    ///
+    /// - rotation: 顺时针旋转角度
+    /// - crop: 旋转后的图片中缩放至绘制尺寸的区域，None 时为整张图片
+    /// - photo: 图片绘制区域
+    /// - area: 单元格中图片可用的区域
+    pub fn draw_placed(
+        &self,
+        canvas: &mut RgbaImage,
+        image: DynamicImage,
+        rotation: u32,
+        crop: Option<Rect>,
+        photo: Rect,
+        area: Rect,
+    ) {
+        let deep = depth::is_deep(&image);
+        let mut image = rotate(self.prepare(image), rotation);
+        if self.scales_linear() {
+            image = linear::to_linear(&image);
+        }
+        let crop = crop.unwrap_or(Rect {
+            x: 0,
+            y: 0,
+            w: image.width(),
+            h: image.height(),
+        });
+        let fitted = self.fit_crop(&image, crop, (photo.w, photo.h), deep);
+        let image = self.dress(&image, fitted, deep);
+        self.place(canvas, image, photo, area);
+    }
+
+    /// 第 number 页（从 1 开始）的排版参数，与本参数相同时返回 None
+    ///
+    /// 装订线交替时偶数页的左右边距互换，装订线在右侧，网格随之左移，
+    /// 两端对齐等在边距内的偏移保持不变
+    pub fn for_page(&self, number: usize) -> Option<Config> {
+        match self.gutter_side {
+            GutterSide::Alternating if self.gutter_px > 0 && number.is_multiple_of(2) => {
+                let border = self.paper_border_px;
+                let x = self.grid_origin_px.0 as i64 - border.left as i64 - self.gutter_px as i64
+                    + border.right as i64;
+                Some(Config {
+                    paper_border_px: Insets {
+                        left: border.right,
+                        right: border.left,
+                        ..border
+                    },
+                    grid_origin_px: (x.max(0) as u32, self.grid_origin_px.1),
+                    ..self.clone()
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// 居中不完整的页面，返回调整后的排版参数，无需调整时返回 None
+    ///
+    /// 最后一行未排满时该行横向居中（从左或从右填充均可），未用完全部行时整体纵向居中，
+    /// 跨越多行的图片延伸到最后一行时该行不移动
+    pub fn centered(&self, placements: &[Placement]) -> Option<Config> {
+        let rows = placements.iter().map(|p| p.row + p.span.rows).max()?;
+        let last = rows - 1;
+        let in_last: Vec<&Placement> = placements
+            .iter()
+            .filter(|p| p.row + p.span.rows > last)
+            .collect();
+        let start = in_last.iter().map(|p| p.col).min()?;
+        let end = in_last.iter().map(|p| p.col + p.span.cols).max()?;
+        // 右侧与左侧空余列数之差，为正时向右移动
+        let spare_cols = match in_last.iter().all(|p| p.row == last) {
+            true => self.n_h.saturating_sub(end) as i64 - start as i64,
+            false => 0,
+        };
+        let spare_rows = self.n_v.saturating_sub(rows);
+        if spare_cols == 0 && spare_rows == 0 {
+            return None;
+        }
+        let half = |n: i64, cell: u32, gap: u32| n * (cell + gap) as i64 / 2;
+        Some(Config {
+            grid_origin_px: (
+                self.grid_origin_px.0,
+                self.grid_origin_px.1
+                    + half(spare_rows as i64, self.max_h_px, self.min_margin_v_px) as u32,
+            ),
+            row_shift_px: (spare_cols != 0)
+                .then(|| (last, half(spare_cols, self.max_w_px, self.min_margin_h_px))),
+            ..self.clone()
+        })
+    }
+
+    /// 计算图片所在单元格区域 像素
+    pub fn cell_rect(&self, placement: &Placement) -> Rect {
+        let (origin_x, origin_y) = self.grid_origin_px;
+        let x = origin_x + placement.col * (self.max_w_px + self.min_margin_h_px);
+        let x = match self.row_shift_px {
+            Some((row, shift)) if row == placement.row => (x as i64 + shift).max(0) as u32,
+            _ => x,
+        };
+        let y = origin_y + placement.row * (self.max_h_px + self.min_margin_v_px);
+        let w =
+            placement.span.cols * self.max_w_px + (placement.span.cols - 1) * self.min_margin_h_px;
+        let h =
+            placement.span.rows * self.max_h_px + (placement.span.rows - 1) * self.min_margin_v_px;
+        Rect { x, y, w, h }
+    }
+}
+
+/// 绘制进度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderEvent {
+    /// 确定一张图片的旋转与缩放
+    Resized {
+        /// 图片在本页中的序号
+        index: usize,
+        /// 顺时针旋转角度，0 为未旋转
+        rotation: u32,
+        /// 旋转后的图片中缩放至绘制尺寸的区域 像素
+        crop: Rect,
+        /// 原始尺寸 像素
+        from: (u32, u32),
+        /// 缩放后尺寸 像素
+        to: (u32, u32),
+    },
+    /// 完成一张图片的预处理
+    Processed,
+    /// 完成一张图片的排版
+    Composed,
+}
+
+/// 排版器
+///
+/// 负责将图片排入页面并绘制
+pub struct Typesetter {
+    pub config: Config,
+    /// 成对排版方式，每张图片放置两次
+    pub pairs: Option<PairMode>,
+    /// 拼版方式
+    pub impose: Option<Imposition>,
+    /// 双面排版，图片两两一组为正面与背面
+    pub duplex: bool,
+    /// 第一页开头跳过的单元格数量
+    pub skip_cells: u32,
+    /// 单元格填充顺序
+    pub fill_order: FillOrder,
+}
+
+impl Typesetter {
+    pub fn new(config: Config) -> Typesetter {
+        Typesetter {
+            config,
+            pairs: None,
+            impose: None,
+            duplex: false,
+            skip_cells: 0,
+            fill_order: FillOrder::Rows,
+        }
+    }
+
+    /// 将图片排入页面网格，并按拼版方式合并页面
+    ///
+    /// - spans: 每张图片占用的单元格数
+    /// - groups: 每张图片的分组编号
+    pub fn layout(&self, spans: &[Span], groups: &[usize]) -> Result<Vec<Vec<Placement>>, Error> {
+        Ok(self.impose(self.pack(spans, groups)?))
+    }
+
+    /// 将图片排入页面网格，拼版时按半页宽度排版，需再经 [`Typesetter::impose`] 合并
+    ///
+    /// - spans: 每张图片占用的单元格数
+    /// - groups: 每张图片的分组编号
+    pub fn pack(&self, spans: &[Span], groups: &[usize]) -> Result<Vec<Vec<Placement>>, Error> {
+        let Config { n_h, n_v, .. } = self.config;
+        let skip = self.skip_cells as usize;
+        let cells = match self.impose {
+            Some(_) => n_h / 2 * n_v,
+            None => n_h * n_v,
+        };
+        ensure!(
+            skip == 0 || skip < cells as usize,
+            InputSnafu {
+                reason: i18n::Msg::SkipTooMany { cells }.to_string(),
+            }
+        );
+        ensure!(
+            skip == 0 || self.pairs.is_none(),
+            InputSnafu {
+                reason: i18n::Msg::SkipWithPairs.to_string(),
+            }
+        );
+        let pack = |spans: &[Span], groups: &[usize], n_h: u32| {
+            layout::skip_cells(spans, groups, skip, |spans, groups| {
+                layout::pack(spans, groups, n_h, n_v, self.fill_order)
+            })
+        };
+        if self.duplex {
+            ensure!(
+                self.pairs.is_none() && self.impose.is_none(),
+                InputSnafu {
+                    reason: i18n::Msg::DuplexConflict.to_string(),
+                }
+            );
+            let fronts: Vec<Span> = spans.iter().step_by(2).copied().collect();
+            let groups: Vec<usize> = groups.iter().step_by(2).copied().collect();
+            let pages = pack(&fronts, &groups, n_h);
+            return Ok(layout::duplex(pages, n_h, spans.len()));
+        }
+        if self.impose.is_some() {
+            ensure!(
+                n_h >= 2 && self.pairs.is_none(),
+                InputSnafu {
+                    reason: i18n::Msg::ImposeInvalid.to_string(),
+                }
+            );
+            return Ok(pack(spans, groups, n_h / 2));
+        }
+        match self.pairs {
+            Some(mode) => {
+                ensure!(
+                    n_h >= 2,
+                    InputSnafu {
+                        reason: i18n::Msg::PairsNeedColumns.to_string(),
+                    }
+                );
+                Ok(layout::pack_pairs(
+                    spans,
+                    groups,
+                    n_h,
+                    n_v,
+                    mode,
+                    self.fill_order,
+                ))
+            }
+            None => Ok(pack(spans, groups, n_h)),
+        }
+    }
+
+    /// 按拼版方式合并 [`Typesetter::pack`] 的结果，未指定拼版时原样返回
+    pub fn impose(&self, pages: Vec<Vec<Placement>>) -> Vec<Vec<Placement>> {
+        match self.impose {
+            Some(Imposition::Booklet) => layout::impose_booklet(pages, self.config.n_h / 2),
+            None => pages,
+        }
+    }
+
+    /// 绘制单页
+    ///
+    /// - images: 本页图片
+    /// - placements: 本页图片位置，与 images 一一对应
+    /// - target_hs: 本页图片目标高度 像素，与 images 一一对应
+    /// - on_event: 进度回调，返回错误时中止绘制
+    ///
+    /// 返回画布与每张图片的实际绘制区域
+    pub fn render_page(
+        &self,
+        images: &[DynamicImage],
+        placements: &[Placement],
+        target_hs: &[u32],
+        on_event: impl FnMut(RenderEvent) -> Result<(), Error>,
+    ) -> Result<(RgbaImage, Vec<Rect>), Error> {
+        self.render_page_with(
+            placements,
+            target_hs,
+            |i| Ok(Some(images[i].clone())),
+            on_event,
+        )
+    }
+
+    /// 逐张读取并绘制单页
+    ///
+    /// 每张图片读取、缩放、绘制后立即释放，同一时刻只保留一张原图
+    ///
+    /// - placements: 本页图片位置
+    /// - target_hs: 本页图片目标高度 像素，与 placements 一一对应
+    /// - load: 按本页内的序号读取图片，返回 None 时该单元格留空
+    /// - on_event: 进度回调，返回错误时中止绘制
+    ///
+    /// 返回画布与每张图片的实际绘制区域，留空的单元格区域宽高为 0
+    pub fn render_page_with(
+        &self,
+        placements: &[Placement],
+        target_hs: &[u32],
+        load: impl FnMut(usize) -> Result<Option<DynamicImage>, Error>,
+        on_event: impl FnMut(RenderEvent) -> Result<(), Error>,
+    ) -> Result<(RgbaImage, Vec<Rect>), Error> {
+        let (canvas, _, rects) =
+            self.render_layers(placements, target_hs, load, on_event, false)?;
+        Ok((canvas, rects))
+    }
+
+    /// 逐张读取并绘制单页，同时保留 16 位图片
+    ///
+    /// 参数同 [`Typesetter::render_page_with`]，另外返回 16 位图片所在的图层，
+    /// 位深为 8 或本页没有 16 位图片时图层为空，用 [`depth::merge`] 与画布合并
+    pub fn render_page_deep(
+        &self,
+        placements: &[Placement],
+        target_hs: &[u32],
+        load: impl FnMut(usize) -> Result<Option<DynamicImage>, Error>,
+        on_event: impl FnMut(RenderEvent) -> Result<(), Error>,
+    ) -> Result<(RgbaImage, Option<Rgba16Image>, Vec<Rect>), Error> {
+        let keep_layer = self.config.depth != Depth::Eight;
+        self.render_layers(placements, target_hs, load, on_event, keep_layer)
+    }
+
+    /// 绘制单页，keep_layer 为真时将 16 位图片另外绘制到 16 位图层
+    fn render_layers(
+        &self,
+        placements: &[Placement],
+        target_hs: &[u32],
+        mut load: impl FnMut(usize) -> Result<Option<DynamicImage>, Error>,
+        mut on_event: impl FnMut(RenderEvent) -> Result<(), Error>,
+        keep_layer: bool,
+    ) -> Result<(RgbaImage, Option<Rgba16Image>, Vec<Rect>), Error> {
+        let cfg = &self.config;
+        let mut canvas = RgbaImage::from_pixel(cfg.paper_w_px, cfg.paper_h_px, cfg.background.0);
+        let mut layer: Option<Rgba16Image> = None;
+        let mut rects = Vec::with_capacity(placements.len());
+        for (i, (placement, &target_h)) in placements.iter().zip(target_hs).enumerate() {
+            let cell = cfg.cell_rect(placement);
+            let Some(mut image) = load(i)? else {
+                rects.push(Rect { w: 0, h: 0, ..cell });
+                on_event(RenderEvent::Processed)?;
+                on_event(RenderEvent::Composed)?;
+                continue;
+            };
+            let deep = depth::is_deep(&image);
+            image = cfg.prepare(image);
+            let from = (image.width(), image.height());
+            // 按旋转方式与单元格方向旋转
+            let rotation = cfg.rotation_for(from);
+            image = rotate(image, rotation);
+            // resize 统一高度
+            let area = cfg.image_area(&cell);
+            let (box_w, box_h) = cfg.fit_box(area, target_h, (image.width(), image.height()));
+            // 线性光：缩放前转换为线性 RGB，缩放后转换回 sRGB
+            if cfg.scales_linear() {
+                image = linear::to_linear(&image);
+            }
+            let full = Rect {
+                x: 0,
+                y: 0,
+                w: image.width(),
+                h: image.height(),
+            };
+            let crop = match cfg.fit {
+                Fit::Cover => saliency::fill_window(&image, box_w, box_h, cfg.crop),
+                Fit::Original => {
+                    let (w, h) = (full.w.min(area.w), full.h.min(area.h));
+                    Rect {
+                        x: (full.w - w) / 2,
+                        y: (full.h - h) / 2,
+                        w,
+                        h,
+                    }
+                }
+                Fit::Contain | Fit::Stretch => full,
+            };
+            let fitted = match cfg.fit {
+                Fit::Contain => {
+                    cfg.resized(image.resize(box_w, box_h, cfg.filter.filter_type()), deep)
+                }
+                _ => cfg.fit_crop(&image, crop, (box_w, box_h), deep),
+            };
+            let (w, h) = (fitted.width(), fitted.height());
+            on_event(RenderEvent::Resized {
+                index: i,
+                rotation,
+                crop,
+                from,
+                to: (w, h),
+            })?;
+            // 按对齐位置放入可用区域，单元格样式：为图片加上装饰，得到实际放置的图片
+            let (dx, dy) = cfg
+                .align
+                .offset((area.w.saturating_sub(w), area.h.saturating_sub(h)));
+            let photo = Rect {
+                x: area.x + dx,
+                y: area.y + dy,
+                w,
+                h,
+            };
+            let image = cfg.dress(&image, fitted, deep);
+            // 16 位图层中的图片不做圆角，圆角处与画布不一致，合并时取画布
+            if keep_layer && depth::is_deep(&image) {
+                let (x, y) = cfg.dressed_origin(photo);
+                let layer =
+                    layer.get_or_insert_with(|| Rgba16Image::new(cfg.paper_w_px, cfg.paper_h_px));
+                imageops::overlay(layer, &image.to_rgba16(), x, y);
+            }
+            on_event(RenderEvent::Processed)?;
+            cfg.place(&mut canvas, image, photo, area);
+            rects.push(photo);
+            on_event(RenderEvent::Composed)?;
+        }
+
+        Ok((canvas, layer, rects))
+    }
+
+    /// 估算绘制单页所需的内存 字节
+    ///
+    /// 包括画布，以及同一时刻最多保留的一张原图与其旋转副本
+    ///
+    /// - dimensions: 本页图片原始尺寸
+    pub fn page_memory(&self, dimensions: &[(u32, u32)]) -> u64 {
+        let canvas = self.config.paper_w_px as u64 * self.config.paper_h_px as u64 * 4;
+        let largest = dimensions
+            .iter()
+            .map(|&(w, h)| w as u64 * h as u64 * 4)
+            .max()
+            .unwrap_or(0);
+        canvas + 2 * largest
+    }
+
+    /// 按默认目标高度排版全部图片，返回每页画布
+    pub fn render_pages(&self, images: &[DynamicImage]) -> Result<Vec<RgbaImage>, Error> {
+        let spans = vec![Span::ONE; images.len()];
+        let groups = vec![0; images.len()];
+        self.layout(&spans, &groups)?
+            .iter()
+            .map(|placements| {
+                let page_images: Vec<DynamicImage> = placements
+                    .iter()
+                    .map(|placement| images[placement.index].clone())
+                    .collect();
+                let target_hs = vec![self.config.target_h_px; placements.len()];
+                self.render_page(&page_images, placements, &target_hs, |_| Ok(()))
+                    .map(|(canvas, _)| canvas)
+            })
+            .collect()
+    }
+}
+
+/// 将透明画布合成到白色背景上
+pub fn flatten(canvas: &RgbaImage) -> RgbImage {
+    RgbImage::from_fn(canvas.width(), canvas.height(), |x, y| {
+        let [r, g, b, a] = canvas.get_pixel(x, y).0;
+        let blend = |c: u8| ((c as u32 * a as u32 + 255 * (255 - a as u32)) / 255) as u8;
+        Rgb([blend(r), blend(g), blend(b)])
+    })
+}
+
+/// 读取图片，按扩展名选择解码方式
+pub fn open_image(path: &Path) -> Result<DynamicImage, Error> {
+    #[cfg(feature = "jxl")]
+    if jxl::is_jxl(path) {
+        return jxl::decode(path);
+    }
+    let image = image::open(path).context(ImageSnafu)?;
+    Ok(apply_orientation(image, metadata::orientation(path)))
+}
+
+/// 读取图片并转换到色彩配置文件，profile 为 None 时同 [`open_image`]
+pub fn open_image_as(path: &Path, profile: Option<Profile>) -> Result<DynamicImage, Error> {
+    let image = open_image(path)?;
+    match profile {
+        Some(profile) => icc::convert(image, icc::read(path).as_deref(), profile),
+        None => Ok(image),
+    }
+}
+
+/// 顺时针旋转图片，degrees 为 90 的倍数
+pub fn rotate(image: DynamicImage, degrees: u32) -> DynamicImage {
+    match degrees % 360 {
+        90 => image.rotate90(),
+        180 => image.rotate180(),
+        270 => image.rotate270(),
+        _ => image,
+    }
+}
+
+/// 按 EXIF 方向（1~8）变换图片，使其按拍摄时的方向显示
+pub fn apply_orientation(image: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+/// 读取图片尺寸，按 EXIF 方向交换宽高
+pub fn image_dimensions(path: &Path) -> Result<(u32, u32), Error> {
+    #[cfg(feature = "jxl")]
+    if jxl::is_jxl(path) {
+        let image = jxl::decode(path)?;
+        return Ok((image.width(), image.height()));
+    }
+    let (w, h) = image::image_dimensions(path).context(ImageSnafu)?;
+    match metadata::orientation(path) {
+        5..=8 => Ok((h, w)),
+        _ => Ok((w, h)),
+    }
+}
+
+/// 意外中止（panic）的原因，供服务、C 接口与 Python 模块报告错误
+pub fn panic_reason(payload: &(dyn std::any::Any + Send)) -> String {
+    let reason = payload
+        .downcast_ref::<&str>()
+        .map(|reason| reason.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_default();
+    i18n::Msg::Panicked(&reason).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit() {
+        let config = Config::from_options(&LayoutOptions {
+            ppc: Some(10.0),
+            nh: Some(2),
+            nv: Some(2),
+            fit: Some(Fit::Cover),
+            ..Default::default()
+        })
+        .unwrap();
+        let placement = Placement {
+            index: 0,
+            row: 0,
+            col: 0,
+            span: Span::ONE,
+        };
+        let area = config.image_area(&config.cell_rect(&placement));
+        let size = config.fitted_size(&placement, 40, (80, 30));
+        assert_eq!(size, (area.w, 40));
+        let typesetter = Typesetter::new(config.clone());
+        let image = DynamicImage::new_rgba8(80, 30);
+        let (_, rects) = typesetter
+            .render_page(
+                std::slice::from_ref(&image),
+                &[placement],
+                &[40],
+                |_| Ok(()),
+            )
+            .unwrap();
+        assert_eq!((rects[0].w, rects[0].h), size);
+
+        let config = Config {
+            fit: Fit::Original,
+            ..config
+        };
+        assert_eq!(config.fitted_size(&placement, 40, (80, 30)), (80, 30));
+        let typesetter = Typesetter::new(config.clone());
+        let (_, rects) = typesetter
+            .render_page(&[image], &[placement], &[40], |_| Ok(()))
+            .unwrap();
+        assert_eq!((rects[0].w, rects[0].h), (80, 30));
+
+        // 不放大：完整放入时保持原始尺寸，填满时按较大的放大比例缩小区域
+        let config = Config {
+            fit: Fit::Contain,
+            no_upscale: true,
+            ..config
+        };
+        assert_eq!(config.fitted_size(&placement, 40, (20, 10)), (20, 10));
+        assert_eq!(config.fitted_size(&placement, 40, (800, 300)).1, 40);
+        let config = Config {
+            fit: Fit::Cover,
+            ..config
+        };
+        let (w, h) = config.fitted_size(&placement, 40, (20, 10));
+        assert!(w == 20 && h <= 10);
+        let typesetter = Typesetter::new(config.clone());
+        let image = DynamicImage::new_rgba8(20, 10);
+        let (_, rects) = typesetter
+            .render_page(&[image], &[placement], &[40], |_| Ok(()))
+            .unwrap();
+        assert_eq!((rects[0].w, rects[0].h), (w, h));
+
+        assert!((config.effective_dpi(&placement, 40, (20, 10)) - 25.4).abs() < 1e-9);
+        let config = Config {
+            no_upscale: false,
+            ..config
+        };
+        assert!(config.effective_dpi(&placement, 40, (20, 10)) < 25.4 / 3.0);
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        let invalid = |options: LayoutOptions| {
+            matches!(Config::from_options(&options), Err(Error::Config { .. }))
+        };
+        assert!(invalid(LayoutOptions {
+            nh: Some(0),
+            ..Default::default()
+        }));
+        assert!(invalid(LayoutOptions {
+            nv: Some(0),
+            ..Default::default()
+        }));
+        assert!(invalid(LayoutOptions {
+            ppi: Some(0.0),
+            ..Default::default()
+        }));
+        assert!(invalid(LayoutOptions {
+            scale: Some(-1.0),
+            ..Default::default()
+        }));
+        assert!(invalid(LayoutOptions {
+            border: Some(Border::uniform(20.0)),
+            ..Default::default()
+        }));
+    }
+
+    #[test]
+    fn test_warnings() {
+        assert!(Config::default().warnings.is_empty());
+        let options = LayoutOptions {
+            orientation: Some(Orientation::Portrait),
+            nv: Some(4),
+            height: Some(8.0),
+            ..Default::default()
+        };
+        let config = Config::from_options(&options).unwrap();
+        assert!(matches!(
+            config.warnings[..],
+            [i18n::Msg::HeightClamped { .. }]
+        ));
+        let config = Config::from_options(&LayoutOptions {
+            auto_grid: true,
+            ..options
+        })
+        .unwrap();
+        assert!(matches!(
+            config.warnings[..],
+            [i18n::Msg::GridAdjusted { to: (_, 3), .. }]
+        ));
+    }
+
+    #[test]
+    fn test_skip_cells() {
+        let typesetter = Typesetter {
+            skip_cells: 2,
+            ..Typesetter::new(Config::default())
+        };
+        let pages = typesetter.pack(&[Span::ONE; 3], &[0; 3]).unwrap();
+        assert_eq!((pages[0][0].row, pages[0][0].col), (0, 2));
+        let cells = typesetter.config.n_h * typesetter.config.n_v;
+        let reason = |typesetter: Typesetter| match typesetter.pack(&[Span::ONE], &[0]) {
+            Err(Error::Input { reason }) => reason,
+            _ => panic!("expected an input error"),
+        };
+        let too_many = reason(Typesetter {
+            skip_cells: cells,
+            ..Typesetter::new(Config::default())
+        });
+        assert!(too_many.contains(&cells.to_string()));
+        let with_pairs = reason(Typesetter {
+            pairs: Some(PairMode::Adjacent),
+            ..typesetter
+        });
+        assert_ne!(too_many, with_pairs);
+    }
+
+    #[test]
+    fn test_rotation_for() {
+        let config = Config::from_options(&LayoutOptions {
+            ppc: Some(10.0),
+            nh: Some(2),
+            nv: Some(2),
+            ..Default::default()
+        })
+        .unwrap();
+        // 单元格约 139×95 像素，目标高度 50 像素：竖图旋转后更大
+        assert_eq!(config.rotation_for((30, 40)), 270);
+        assert_eq!(config.rotation_for((40, 30)), 0);
+        // 目标高度限制下，细长的横图旋转后反而更小
+        assert_eq!(config.rotation_for((100, 10)), 0);
+        let config = Config {
+            rotation: Rotation::Keep,
+            ..config
+        };
+        assert_eq!(config.rotation_for((30, 40)), 0);
+    }
+
+    #[test]
+    fn test_apply_orientation() {
+        let mut image = RgbaImage::new(2, 1);
+        image.put_pixel(0, 0, image::Rgba([255; 4]));
+        let image = DynamicImage::ImageRgba8(image);
+        // 左上角的白点变换后的位置
+        let expected = [
+            (0, 0),
+            (1, 0),
+            (1, 0),
+            (0, 0),
+            (0, 0),
+            (0, 0),
+            (0, 1),
+            (0, 1),
+        ];
+        for (orientation, position) in (1..=8).zip(expected) {
+            let oriented = apply_orientation(image.clone(), orientation).into_rgba8();
+            let white = oriented
+                .enumerate_pixels()
+                .find(|(_, _, pixel)| pixel.0[0] == 255)
+                .map(|(x, y, _)| (x, y));
+            assert_eq!(white, Some(position), "orientation {}", orientation);
+        }
+    }
+
+    #[test]
+    fn test_render_pages() {
+        let config = Config::from_options(&LayoutOptions {
+            ppc: Some(10.0),
+            nh: Some(2),
+            nv: Some(2),
+            ..Default::default()
+        })
+        .unwrap();
+        let (paper_w, paper_h) = (config.paper_w_px, config.paper_h_px);
+        let typesetter = Typesetter::new(config);
+        let images = vec![DynamicImage::new_rgba8(40, 30); 5];
+        let pages = typesetter.render_pages(&images).unwrap();
+        assert_eq!(pages.len(), 2);
+        assert!(pages
+            .iter()
+            .all(|page| page.dimensions() == (paper_w, paper_h)));
+    }
+
+    #[test]
+    fn test_fitted_size() {
+        let config = Config::from_options(&LayoutOptions {
+            ppc: Some(10.0),
+            ..Default::default()
+        })
+        .unwrap();
+        let placement = Placement {
+            index: 0,
+            row: 0,
+            col: 0,
+            span: Span::ONE,
+        };
+        let typesetter = Typesetter::new(config);
+        let image = DynamicImage::new_rgba8(300, 400);
+        let (_, rects) = typesetter
+            .render_page(&[image], &[placement], &[50], |_| Ok(()))
+            .unwrap();
+        assert_eq!(
+            typesetter.config.fitted_size(&placement, 50, (300, 400)),
+            (rects[0].w, rects[0].h)
+        );
+    }
+
+    #[test]
+    fn test_justify() {
+        let options = LayoutOptions {
+            ppc: Some(10.0),
+            nh: Some(3),
+            nv: Some(1),
+            scale: Some(0.5),
+            justify: true,
+            ..Default::default()
+        };
+        let config = Config::from_options(&options).unwrap();
+        let plain = Config::from_options(&LayoutOptions {
+            justify: false,
+            ..options.clone()
+        })
+        .unwrap();
+        assert!(config.min_margin_h_px > plain.min_margin_h_px);
+        // 最右一列贴近右边距，唯一一行上下居中
+        let last = config.cell_rect(&Placement {
+            index: 0,
+            row: 0,
+            col: config.n_h - 1,
+            span: Span::ONE,
+        });
+        let right = config.paper_w_px - config.paper_border_px.right;
+        assert!(right.abs_diff(last.x + last.w) <= config.n_h);
+        let bottom = config.paper_h_px - config.paper_border_px.bottom;
+        let top = config.grid_origin_px.1 - config.paper_border_px.top;
+        assert!(top > 0 && top.abs_diff(bottom - last.y - last.h) <= 1);
+    }
+
+    #[test]
+    fn test_centered() {
+        let config = Config::from_options(&LayoutOptions {
+            ppc: Some(10.0),
+            nh: Some(4),
+            nv: Some(3),
+            ..Default::default()
+        })
+        .unwrap();
+        let at = |row, col| Placement {
+            index: 0,
+            row,
+            col,
+            span: Span::ONE,
+        };
+        // 两张图片居中到第二行的中间两列
+        let centered = config.centered(&[at(0, 0), at(0, 1)]).unwrap();
+        assert_eq!(centered.cell_rect(&at(0, 0)), config.cell_rect(&at(1, 1)));
+        // 只有最后一行横向居中
+        let centered = config
+            .centered(&[
+                at(0, 0),
+                at(0, 1),
+                at(0, 2),
+                at(0, 3),
+                at(1, 0),
+                at(1, 1),
+                at(1, 2),
+            ])
+            .unwrap();
+        assert_eq!(
+            centered.cell_rect(&at(0, 0)).x,
+            config.cell_rect(&at(0, 0)).x
+        );
+        assert!(centered.cell_rect(&at(1, 0)).x > config.cell_rect(&at(1, 0)).x);
+        // 从右向左填充的最后一行向左移动
+        let centered = config.centered(&[at(0, 3), at(0, 2)]).unwrap();
+        assert_eq!(centered.cell_rect(&at(0, 3)), config.cell_rect(&at(1, 2)));
+        let full: Vec<Placement> = (0..12).map(|i| at(i / 4, i % 4)).collect();
+        assert!(config.centered(&full).is_none());
+    }
+
+    #[test]
+    fn test_gutter() {
+        let options = LayoutOptions {
+            ppc: Some(10.0),
+            gutter: Some(1.5),
+            ..Default::default()
+        };
+        let config = Config::from_options(&options).unwrap();
+        let plain = Config::from_options(&LayoutOptions {
+            gutter: None,
+            ..options.clone()
+        })
+        .unwrap();
+        assert_eq!(config.grid_origin_px, (23, 8));
+        assert!(config.max_w_px < plain.max_w_px);
+        assert!(config.for_page(2).is_none());
+        // 交替时偶数页网格移到纸张边距处，右侧留出装订线
+        let config = Config::from_options(&LayoutOptions {
+            gutter_side: Some(GutterSide::Alternating),
+            ..options.clone()
+        })
+        .unwrap();
+        assert_eq!(config.for_page(2).unwrap().grid_origin_px, (8, 8));
+        assert!(config.for_page(3).is_none());
+        // 两端对齐居中的偏移在偶数页保留，网格只随装订线移动
+        let config = Config::from_options(&LayoutOptions {
+            gutter_side: Some(GutterSide::Alternating),
+            nh: Some(1),
+            nv: Some(1),
+            scale: Some(0.5),
+            justify: true,
+            ..options
+        })
+        .unwrap();
+        let offset = config.grid_origin_px.0 - 23;
+        assert!(offset > 0);
+        let even = config.for_page(2).unwrap();
+        assert_eq!(even.grid_origin_px, (8 + offset, config.grid_origin_px.1));
+        let cell = |config: &Config| {
+            config.cell_rect(&Placement {
+                index: 0,
+                row: 0,
+                col: 0,
+                span: Span::ONE,
+            })
+        };
+        let (odd_cell, even_cell) = (cell(&config), cell(&even));
+        assert_eq!(odd_cell.x - even_cell.x, 15);
+        let right_gap = config.paper_w_px - even_cell.x - even_cell.w - 8 - 15;
+        assert!(right_gap.abs_diff(odd_cell.x - 8 - 15) <= 1);
+    }
+
+    #[test]
+    fn test_bleed() {
+        let config = Config::from_options(&LayoutOptions {
+            ppc: Some(10.0),
+            bleed: Some(3.0),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(config.bleed_px, 3);
+        let placement = Placement {
+            index: 0,
+            row: 0,
+            col: 0,
+            span: Span::ONE,
+        };
+        let typesetter = Typesetter::new(config);
+        let color = image::Rgba([9, 9, 9, 255]);
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(40, 30, color));
+        let (canvas, rects) = typesetter
+            .render_page(&[image], &[placement], &[30], |_| Ok(()))
+            .unwrap();
+        // 绘制区域不变，图片向四周超出 3 像素
+        let Rect { x, y, w, h } = rects[0];
+        assert_eq!((w, h), (40, 30));
+        assert_eq!(*canvas.get_pixel(x - 3, y - 3), color);
+        assert_eq!(*canvas.get_pixel(x + w + 2, y + h + 2), color);
+        assert_eq!(*canvas.get_pixel(x - 4, y), Color::WHITE.0);
+    }
+}
@@ -0,0 +1,85 @@
+//! 线性光缩放
+//!
+//! sRGB 数值与亮度不成正比，直接缩放会使细线、网点等高反差细节变暗。
+//! 缩放前转换为线性 RGB，缩放后再转换回 sRGB，透明度不做转换
+
+use image::{DynamicImage, Rgba32FImage};
+
+/// sRGB 编码值转换为线性值，均为 0~1
+fn decode(v: f32) -> f32 {
+    match v <= 0.04045 {
+        true => v / 12.92,
+        false => ((v + 0.055) / 1.055).powf(2.4),
+    }
+}
+
+/// 线性值转换为 sRGB 编码值，均为 0~1
+fn encode(v: f32) -> f32 {
+    let v = v.clamp(0.0, 1.0);
+    match v <= 0.0031308 {
+        true => v * 12.92,
+        false => 1.055 * v.powf(1.0 / 2.4) - 0.055,
+    }
+}
+
+/// 转换为线性 RGB 浮点图像
+pub fn to_linear(image: &DynamicImage) -> DynamicImage {
+    let mut linear = image.to_rgba32f();
+    for pixel in linear.pixels_mut() {
+        for v in &mut pixel.0[..3] {
+            *v = decode(*v);
+        }
+    }
+    DynamicImage::ImageRgba32F(linear)
+}
+
+/// 线性 RGB 浮点图像转换回 sRGB
+///
+/// - deep: 为真时输出 16 位，否则输出 8 位
+pub fn to_srgb(image: &DynamicImage, deep: bool) -> DynamicImage {
+    let mut srgb: Rgba32FImage = image.to_rgba32f();
+    for pixel in srgb.pixels_mut() {
+        for v in &mut pixel.0[..3] {
+            *v = encode(*v);
+        }
+    }
+    let srgb = DynamicImage::ImageRgba32F(srgb);
+    match deep {
+        true => DynamicImage::ImageRgba16(srgb.to_rgba16()),
+        false => DynamicImage::ImageRgba8(srgb.to_rgba8()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{imageops::FilterType, Rgba, RgbaImage};
+
+    #[test]
+    fn test_linear_resize() {
+        for v in 0..=255u8 {
+            let round_trip = to_srgb(
+                &to_linear(&DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                    1,
+                    1,
+                    Rgba([v, v, v, 255]),
+                ))),
+                false,
+            );
+            assert_eq!(round_trip.to_rgba8().get_pixel(0, 0).0[0], v);
+        }
+        // 黑白相间的细线缩小后，线性光下为中灰 188，直接缩放为 128
+        let lines = RgbaImage::from_fn(2, 2, |x, _| match x {
+            0 => Rgba([0, 0, 0, 255]),
+            _ => Rgba([255, 255, 255, 255]),
+        });
+        let image = DynamicImage::ImageRgba8(lines);
+        let direct = image.resize_exact(1, 1, FilterType::Triangle).to_rgba8();
+        let linear = to_srgb(
+            &to_linear(&image).resize_exact(1, 1, FilterType::Triangle),
+            false,
+        );
+        assert_eq!(direct.get_pixel(0, 0).0[0], 128);
+        assert_eq!(linear.to_rgba8().get_pixel(0, 0).0[0], 188);
+    }
+}
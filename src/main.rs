@@ -1,48 +1,505 @@
-use clap::Parser;
-use image::{
-    imageops::{self, FilterType},
-    DynamicImage, GenericImageView, ImageBuffer, RgbaImage,
+use clap::{ArgAction, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use image::RgbaImage;
+use image_typesetting_tool::{
+    barcode::Symbology,
+    caption::{self, CaptionFile, CaptionPosition, CaptionSource, Captions},
+    code::{CodeSource, CodeValues},
+    color::Color,
+    colorspace::{self, Colorspace, Dither},
+    cut_path,
+    depth::Depth,
+    draw,
+    gallery::{self, GalleryPage},
+    header::{Align, HeaderFooter, PageText},
+    i18n::{self, Lang, Msg},
+    icc::Profile,
+    id_photo::{IdSheet, IdSize},
+    image_dimensions, index,
+    input::{
+        collect_inputs, read_file_list, read_order, shuffle_inputs, sort_inputs, InputFilter,
+        OrderEntry, SortKey,
+    },
+    layout::{Anchor, FillOrder, Filter, Fit, GutterSide, Imposition, PairMode, Rect, Rotation},
+    layout_file::LayoutFile,
+    manifest::Manifest,
+    number::{self, Corner},
+    open_image, open_image_as,
+    output::{embed_profile, link_uri, save_atomic, save_image, save_page, OutputFormat},
+    page::{render_page, unreadable, OnError, PageEvent, PageSource, PageStyle, RenderedPage},
+    paper::{Border, Orientation, Paper},
+    pdf::{PageLink, PdfBook},
+    plan::{
+        self, copy_names, copy_sequences, date_groups, dry_run_report, image_copies,
+        low_dpi_images, page_names, ArrangeOptions, Arrangement, GroupBy, Plan,
+    },
+    poster::Poster,
+    saliency::Crop,
+    style::CellStyle,
+    template::LabelTemplate,
+    text::Font,
+    verify,
+    watermark::{Watermark, WatermarkPos, WatermarkScope},
+    Config, Error, InputSnafu, InterruptedSnafu, IoSnafu, LayoutOptions, RenderEvent, Typesetter,
 };
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
 use snafu::prelude::*;
 use std::thread::{self, JoinHandle};
 use std::{
     fs,
+    io::{self, BufRead, IsTerminal},
+    iter,
     path::{Path, PathBuf},
-    sync::mpsc::{self, Sender},
+    sync::atomic::{AtomicBool, Ordering},
+    sync::mpsc::{self, Receiver, Sender},
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
-use utils::BatchIter;
+use watch::Rendered;
 
-mod utils;
+mod config_file;
+#[cfg(feature = "gui")]
+mod gui;
+mod serve;
+mod tui;
+mod watch;
 
-#[derive(Debug, Snafu)]
-enum Error {
-    #[snafu(display("I/O error: {}", source))]
-    Io { source: std::io::Error },
-    #[snafu(display("Image error: {}", source))]
-    Image { source: image::ImageError },
-    #[snafu(display("Input error: {}", reason))]
-    Input { reason: String },
+/// PDF 输出文件名
+const PDF_NAME: &str = "output.pdf";
+/// 证件照默认纸张 6x4 英寸相纸
+const ID_PAPER: Paper = Paper {
+    width_cm: 15.24,
+    height_cm: 10.16,
+};
+
+/// 排版结果输出文件名
+const LAYOUT_NAME: &str = "layout.json";
+
+/// 草稿模式的最大分辨率
+const DRAFT_PPI: f64 = 100.0;
+
+/// 中断退出码 128 + SIGINT
+const EXIT_INTERRUPTED: i32 = 130;
+
+/// 出错退出码
+const EXIT_FAILURE: i32 = 1;
+
+/// Ctrl+C 中断标志
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// 键盘暂停标志
+static PAUSED: AtomicBool = AtomicBool::new(false);
+
+/// 安装 Ctrl+C 处理器
+///
+/// 首次中断仅设置标志，由主流程在图片之间检查并收尾；
+/// 再次中断则立即退出。
+fn install_interrupt_handler() {
+    let _ = ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            std::process::exit(EXIT_INTERRUPTED);
+        }
+    });
+}
+
+/// 流水线检查点：暂停时在此等待，中断时返回错误
+fn checkpoint() -> Result<(), Error> {
+    while PAUSED.load(Ordering::SeqCst) && !INTERRUPTED.load(Ordering::SeqCst) {
+        thread::sleep(Duration::from_millis(100));
+    }
+    ensure!(!INTERRUPTED.load(Ordering::SeqCst), InterruptedSnafu);
+    Ok(())
 }
 
-#[derive(Clone, Parser)]
+/// 将单页绘制进度转发到进度线程，并在每个事件后经过检查点
+fn page_progress(tx: &Sender<PBData>, event: PageEvent) -> Result<(), Error> {
+    let data = match event {
+        PageEvent::Read(input) => PBData::NextRead(
+            input
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned()),
+        ),
+        PageEvent::Render(RenderEvent::Resized { .. }) => return checkpoint(),
+        PageEvent::Render(RenderEvent::Processed) => PBData::NextProcess,
+        PageEvent::Render(RenderEvent::Composed) => {
+            let _ = tx.send(PBData::NextComp);
+            PBData::NextTotal
+        }
+        PageEvent::Message(msg) => PBData::Println(msg.to_string()),
+        PageEvent::Warn(msg) => PBData::Warn(msg.to_string()),
+    };
+    let _ = tx.send(data);
+    checkpoint()
+}
+
+/// 监听键盘输入，`p` 回车切换暂停/继续
+///
+/// 仅在标准输入为终端时启用
+fn spawn_pause_listener(tx: Sender<PBData>) {
+    if !io::stdin().is_terminal() {
+        return;
+    }
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            if line.trim().eq_ignore_ascii_case("p") {
+                let paused = !PAUSED.fetch_xor(true, Ordering::SeqCst);
+                if tx.send(PBData::Paused(paused)).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+}
+
+#[derive(Clone, Parser, Serialize, Deserialize)]
 #[command(version, about, long_about = None)]
+#[serde(deny_unknown_fields)]
 struct Cli {
-    /// 输入目录 默认 input
+    #[command(subcommand)]
+    #[serde(skip)]
+    command: Option<Command>,
+    /// 配置文件，可设置所有命令行参数，命令行参数优先
+    /// 未指定时自动读取当前目录下的 imgtypeset.toml
+    #[arg(long, value_name = "FILE")]
+    #[serde(skip)]
+    config: Option<String>,
+    /// 使用配置文件中 `[profile.NAME]` 定义的预设
+    #[arg(long, value_name = "NAME")]
+    #[serde(skip)]
+    profile: Option<String>,
+    /// 输入目录，可多次指定 默认 input
     #[arg(short, long, value_name = "DIR")]
-    input: String,
-    /// 输出目录 默认 output
+    #[serde(deserialize_with = "config_file::one_or_many")]
+    input: Vec<String>,
+    /// 输入文件或目录，排在 -i 指定的目录之后
+    #[arg(value_name = "FILE")]
+    #[serde(deserialize_with = "config_file::one_or_many")]
+    files: Vec<String>,
+    /// 从文件读取输入列表，每行一个路径，`-` 为标准输入
+    #[arg(long, value_name = "FILE")]
+    files_from: Option<String>,
+    /// 输入列表以 NUL 分隔，配合 `find -print0` 使用
+    #[arg(short = '0', long, requires = "files_from")]
+    null: bool,
+    /// 输入排序方式 默认保持输入顺序，目录内按路径排序
+    #[arg(long, value_name = "KEY")]
+    sort: Option<SortKey>,
+    /// 倒序排列输入
+    #[arg(long)]
+    reverse: bool,
+    /// 随机打乱输入顺序，在排序之后进行
+    #[arg(long)]
+    shuffle: bool,
+    /// 随机种子，相同种子得到相同顺序 默认随机并打印
+    #[arg(long, value_name = "N", requires = "shuffle")]
+    seed: Option<u64>,
+    /// 无法读取图片时的处理方式
+    #[arg(long, value_name = "POLICY", default_value = "skip")]
+    on_error: OnError,
+    /// 遇到无法读取的图片立即退出，同 --on-error abort
+    #[arg(long, conflicts_with = "on_error")]
+    strict: bool,
+    /// 界面语言，默认按 LANG 环境变量选择
+    #[arg(long, value_name = "LANG")]
+    lang: Option<Lang>,
+    /// 进度显示方式
+    #[arg(long, value_name = "MODE", default_value = "bar")]
+    progress: ProgressMode,
+    /// 不显示进度条，等同于 --progress none
+    #[arg(long)]
+    no_progress: bool,
+    /// 安静模式，不显示进度与提示信息，只输出警告和错误
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+    /// 输出更详细的信息，-v 显示每张图片的旋转与缩放，-vv 另外显示排版位置
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
+    /// 仅计算并打印排版结果，不读取图片内容、不输出文件
+    #[arg(long)]
+    dry_run: bool,
+    /// 监视输入，文件新增或修改时重新排版
+    #[arg(long, conflicts_with = "dry_run")]
+    watch: bool,
+    /// 文字所用字体文件 默认查找系统字体
+    #[arg(long, value_name = "FILE")]
+    font: Option<String>,
+    /// 为每张图片绘制说明文字：`filename` 为源文件名，
+    /// `exif:模板` 按 EXIF 信息生成，如 `exif:{datetime} {camera}`，
+    /// 可用字段 datetime、date、camera、lens、exposure、shutter、aperture、iso、focal、filename
+    #[arg(long, value_name = "SOURCE")]
+    captions: Option<CaptionSource>,
+    /// 说明文字位置
+    #[arg(long, value_name = "POSITION", default_value = "below")]
+    caption_position: CaptionPosition,
+    /// 说明文字文件，CSV 每行 `文件名,说明文字`（.tsv 以制表符分隔），优先于 --captions
+    #[arg(long, value_name = "FILE")]
+    captions_file: Option<String>,
+    /// 说明文字字号（单位：pt）
+    #[arg(long, value_name = "pt", default_value_t = 8.0)]
+    caption_size: f64,
+    /// 说明文字最多行数，超出图片所在单元格宽度时折行
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    caption_lines: u32,
+    /// 页眉文字，绘制在纸张上边距内，`{page}` 为页码，`{pages}` 为总页数
+    #[arg(long, value_name = "TEXT")]
+    header: Option<String>,
+    /// 页脚文字，绘制在纸张下边距内，如 `第 {page}/{pages} 页`
+    #[arg(long, value_name = "TEXT")]
+    footer: Option<String>,
+    /// 页眉与页脚所用字体文件 默认同 --font
+    #[arg(long, value_name = "FILE")]
+    header_font: Option<String>,
+    /// 页眉与页脚字号（单位：pt），超出纸张边距时自动缩小
+    #[arg(long, value_name = "pt", default_value_t = 9.0)]
+    header_size: f64,
+    /// 页眉对齐方式
+    #[arg(long, value_name = "ALIGN", default_value = "center")]
+    header_align: Align,
+    /// 页脚对齐方式
+    #[arg(long, value_name = "ALIGN", default_value = "center")]
+    footer_align: Align,
+    /// 在每张图片四角外侧绘制裁切线，便于用裁纸刀裁切
+    #[arg(long)]
+    cut_marks: bool,
+    /// 出血（单位：mm），图片向四周放大超出所在位置，裁切后边缘不留白；
+    /// 裁切线随之外移，图片间距应不小于出血的两倍
+    #[arg(long, value_name = "mm")]
+    bleed: Option<f64>,
+    /// 图片边框宽度（单位：mm），绘制在每张图片外侧，图片间距应不小于边框宽度的两倍
+    #[arg(long, value_name = "mm")]
+    frame: Option<f64>,
+    /// 图片边框颜色，如 black、gray、#rrggbb 默认黑色
+    #[arg(long, value_name = "COLOR", requires = "frame")]
+    frame_color: Option<Color>,
+    /// 图片旋转方式：auto 在图片方向与单元格方向不同时旋转，none 不旋转，
+    /// cw、ccw、180 按指定角度旋转全部图片
+    #[arg(long, value_name = "ROTATE", default_value = "auto")]
+    rotate: Rotation,
+    /// 图片在单元格中的对齐位置，如 center、top-left、bottom
+    #[arg(long, value_name = "ANCHOR", default_value = "center")]
+    align: Anchor,
+    /// 图片缩放方式：contain 完整放入，cover 填满并按 --crop 裁切，stretch 拉伸填满，
+    /// original 按原始像素尺寸放置 默认 contain，使用 --template 时默认 cover
+    #[arg(long, value_name = "FIT")]
+    fit: Option<Fit>,
+    /// cover 与出血裁切的位置：detail 保留细节最多的区域，skin 以最大肤色区域为中心
+    /// （颜色启发式，不识别人脸，适合纯色背景的证件照；找不到时同 detail），center 居中
+    #[arg(long, value_name = "CROP", default_value = "detail")]
+    crop: Crop,
+    /// 按原始尺寸输出：每张图片按 --ppi 换算打印尺寸，不做任何重采样，
+    /// 按需占用多个单元格，超出纸张可用范围时报错
+    #[arg(long, conflicts_with_all = ["fit", "manifest", "bleed"])]
+    true_size: bool,
+    /// 不放大图片，小图按原始像素尺寸输出，不足目标高度时留白
+    #[arg(long)]
+    no_upscale: bool,
+    /// 缩放滤镜：nearest、triangle、catmullrom、lanczos3 默认 lanczos3，--draft 时为 triangle
+    #[arg(long, value_name = "FILTER")]
+    filter: Option<Filter>,
+    /// 在线性 RGB 中缩放，避免细线、网点等高反差细节缩小后变暗，较慢且占用更多内存
+    #[arg(long)]
+    linear_light: bool,
+    /// 输出位深：auto 在页面中有 16 位图片时输出 16 位，16 仅支持 png 与 tiff 格式
+    #[arg(long, value_name = "DEPTH", default_value = "auto")]
+    depth: Depth,
+    /// 输出色彩：rgb、gray（灰度）、bilevel（1 位黑白，png 输出为 1 位 PNG）、
+    /// cmyk（仅支持 tiff 格式，用于胶印）
+    #[arg(long, value_name = "COLORSPACE", default_value = "rgb")]
+    colorspace: Colorspace,
+    /// 黑白输出的抖动方式：floyd-steinberg、ordered（Bayer 有序抖动）、none（直接二值化）
+    #[arg(long, value_name = "DITHER", default_value = "floyd-steinberg")]
+    dither: Dither,
+    /// CMYK 输出所用的 ICC 配置文件，如印刷厂提供的 coated_fogra39.icc，
+    /// 分色结果按该配置文件计算并嵌入输出；未指定时按简单公式分色
+    #[arg(long, value_name = "FILE")]
+    cmyk_profile: Option<PathBuf>,
+    /// 色彩配置文件：srgb、adobe-rgb。图片由内嵌配置文件（没有时视为 sRGB）转换到该配置文件，
+    /// 并嵌入 png、jpeg、tiff、webp 与 pdf 输出；灰度与黑白输出不嵌入。未设置时不做色彩管理
+    #[arg(long, value_name = "PROFILE")]
+    color_profile: Option<Profile>,
+    /// 缩放后 USM 锐化，可指定强度 默认 0.5
+    #[arg(long, value_name = "AMOUNT", num_args = 0..=1, default_missing_value = "0.5")]
+    sharpen: Option<f64>,
+    /// 缩放前自动纠偏：检测扫描件的倾斜角度（10 度以内）并旋转摆正，露出的角落为透明
+    #[arg(long)]
+    deskew: bool,
+    /// 缩放前裁去四周接近纯色的边框（如扫描件的白边），可指定与边框颜色的最大差异 0~1 默认 0.1。
+    /// 排版按裁切前的宽高比估算
+    #[arg(long, value_name = "THRESHOLD", num_args = 0..=1, default_missing_value = "0.1")]
+    autocrop: Option<f64>,
+    /// 缩放前调整亮度，-100~100
+    #[arg(long, value_name = "PERCENT", allow_negative_numbers = true)]
+    brightness: Option<f64>,
+    /// 缩放前调整对比度，-100~100
+    #[arg(long, value_name = "PERCENT", allow_negative_numbers = true)]
+    contrast: Option<f64>,
+    /// 缩放前自动色阶，将每张图片最暗与最亮处拉伸到纯黑与纯白，适合偏暗、发灰的扫描件
+    #[arg(long)]
+    auto_levels: bool,
+    /// 草稿模式：使用较快的缩放滤镜，并将分辨率降至不超过 100 PPI，用于快速预览排版
+    #[arg(long)]
+    draft: bool,
+    /// 有效 DPI 低于此值的图片按 --low-dpi 处理，0 为不检查
+    #[arg(long, value_name = "DPI", default_value_t = 150.0)]
+    min_dpi: f64,
+    /// 有效 DPI 过低时的处理方式
+    #[arg(long, value_name = "POLICY", default_value = "warn")]
+    low_dpi: LowDpi,
+    /// 单元格样式，polaroid 为拍立得相框，说明文字写在相框下边内
+    #[arg(long, value_name = "STYLE", default_value = "plain")]
+    style: CellStyle,
+    /// 页面背景色，如 white、#f0f0f0、transparent 默认白色
+    #[arg(long, value_name = "COLOR")]
+    background: Option<Color>,
+    /// 单元格衬底颜色，填充图片宽高比与单元格不同时留下的空白
+    #[arg(long, value_name = "COLOR")]
+    matte: Option<Color>,
+    /// 图片圆角半径（单位：mm），用于贴纸与卡片
+    #[arg(long, value_name = "mm")]
+    corner_radius: Option<f64>,
+    /// 在每张图片下方绘制柔和的投影
+    #[arg(long)]
+    shadow: bool,
+    /// 投影向右下方的偏移（单位：mm） 默认 1
+    #[arg(long, value_name = "mm", requires = "shadow")]
+    shadow_offset: Option<f64>,
+    /// 投影模糊半径（单位：mm） 默认 1.5
+    #[arg(long, value_name = "mm", requires = "shadow")]
+    shadow_blur: Option<f64>,
+    /// 投影不透明度，0~1 默认 0.4
+    #[arg(long, value_name = "OPACITY", requires = "shadow")]
+    shadow_opacity: Option<f64>,
+    /// 水印图片，半透明叠加在页面或每张图片上，用于交给客户确认的样张
+    #[arg(long, value_name = "FILE")]
+    watermark: Option<String>,
+    /// 水印不透明度，0~1
+    #[arg(
+        long,
+        value_name = "OPACITY",
+        default_value_t = 0.2,
+        requires = "watermark"
+    )]
+    watermark_opacity: f32,
+    /// 水印位置，center 居中放置一个，tile 平铺
+    #[arg(
+        long,
+        value_name = "POS",
+        default_value = "center",
+        requires = "watermark"
+    )]
+    watermark_pos: WatermarkPos,
+    /// 水印作用范围，page 整页，image 每张图片
+    #[arg(
+        long,
+        value_name = "SCOPE",
+        default_value = "page",
+        requires = "watermark"
+    )]
+    watermark_scope: WatermarkScope,
+    /// 在每张图片右侧绘制二维码，内容取自文件名或 --qr-csv，排版时预留二维码的宽度
+    #[arg(long, value_name = "SOURCE")]
+    qr: Option<CodeSource>,
+    /// 二维码内容文件，CSV 每行 `文件名,内容`，如存档编号或网址
+    #[arg(long, value_name = "FILE", requires = "qr")]
+    qr_csv: Option<String>,
+    /// 二维码边长（单位：mm）
+    #[arg(long, value_name = "mm", default_value_t = 12.0, requires = "qr")]
+    qr_size: f64,
+    /// 在每张图片下方绘制条形码，内容取自文件名或 --barcode-csv，排版时预留条形码的高度
+    #[arg(long, value_name = "SOURCE")]
+    barcode: Option<CodeSource>,
+    /// 条形码内容文件，CSV 每行 `文件名,内容`，如货号
+    #[arg(long, value_name = "FILE", requires = "barcode")]
+    barcode_csv: Option<String>,
+    /// 条形码类型
+    #[arg(
+        long,
+        value_name = "TYPE",
+        default_value = "code128",
+        requires = "barcode"
+    )]
+    barcode_type: Symbology,
+    /// 条形码高度（单位：mm）
+    #[arg(long, value_name = "mm", default_value_t = 8.0, requires = "barcode")]
+    barcode_height: f64,
+    /// 在每张图片一角印上编号（1、2、3…），按页序与页内顺序递增，便于客户按编号挑选加印
+    #[arg(long)]
+    number_images: bool,
+    /// 编号所在的角
+    #[arg(
+        long,
+        value_name = "CORNER",
+        default_value = "bottom-right",
+        requires = "number_images"
+    )]
+    number_corner: Corner,
+    /// 同时在输出目录写入 numbers.csv，列出每个编号对应的文件
+    #[arg(long, requires = "number_images")]
+    numbers_csv: bool,
+    /// 安全区（单位：mm），在图形界面预览中标出距裁切边缘的安全范围
+    #[arg(long, value_name = "mm")]
+    safe_area: Option<f64>,
+    /// 在输出上绘制调试网格：纸张边距、单元格边界与图片间距，便于调整边距与网格数量
+    #[arg(long)]
+    debug_grid: bool,
+    /// 在全部页面之前加入目录页，按文件名列出每张图片所在的页码与格号
+    #[arg(long)]
+    index: bool,
+    /// 排列清单，每行一个图片路径，按顺序排入单元格，替代输入目录与排序
+    /// 空行留空一个单元格，`---` 换页；
+    /// 扩展名为 .csv 时为订单，每行 `文件,份数,尺寸`，尺寸同 --manifest，尺寸变化处换页
+    #[arg(long, value_name = "FILE")]
+    order: Option<String>,
+    /// 同时读取子目录中的图片
+    #[arg(short, long)]
+    recursive: bool,
+    /// 仅包含匹配的文件，如 '*.jpg'，可多次指定
+    /// 匹配相对于输入目录的路径
+    #[arg(long, value_name = "GLOB")]
+    #[serde(deserialize_with = "config_file::one_or_many")]
+    include: Vec<String>,
+    /// 排除匹配的文件，可多次指定
+    #[arg(long, value_name = "GLOB")]
+    #[serde(deserialize_with = "config_file::one_or_many")]
+    exclude: Vec<String>,
+    /// 输出目录 默认 output，目录非空时需指定 --overwrite 或 --clean，否则不输出并以状态码 1 退出
     #[arg(short, long, value_name = "DIR")]
     output: Option<String>,
+    /// 输出目录非空时覆盖同名文件，保留其他文件
+    #[arg(long)]
+    overwrite: bool,
+    /// 输出前清空输出目录
+    #[arg(long, conflicts_with = "overwrite")]
+    clean: bool,
+    /// 纸张，可选 A3、A4、A5、Letter、Legal（横向）或自定义 宽x高（单位：cm，以 in 结尾时为英寸） 默认 A4
+    #[arg(long, value_name = "PAPER")]
+    paper: Option<Paper>,
+    /// 纸张方向 默认横向；竖向时横向/纵向数量默认值随之互换
+    #[arg(long, value_name = "ORIENTATION")]
+    orientation: Option<Orientation>,
+    /// 标签纸模板，图片按模板中每个标签的位置与尺寸排版，默认铺满，圆形标签裁为圆形
+    /// 可选 avery-5160、avery-5163、avery-5167、avery-22807、avery-l7160、avery-l7163、round-24，
+    /// 或 TOML 模板文件：paper、cols、rows、width、height、left、top、pitch_x、pitch_y（单位：mm）、shape
+    #[arg(long, value_name = "NAME|FILE", conflicts_with_all = ["paper", "orientation", "nh", "nv"])]
+    template: Option<LabelTemplate>,
     /// 单张图片最大高度（单位：cm）
     #[arg(long, value_name = "cm")]
     height: Option<f64>,
-    /// 纸张边距（单位：cm）
+    /// 纸张边距（单位：cm），一个值为四边相同，四个值依次为上、右、下、左，如 1.0,0.5,1.0,0.5
     #[arg(long, value_name = "cm")]
-    border: Option<f64>,
+    border: Option<Border>,
     /// 图片之间的间距（单位：cm）
     #[arg(long, value_name = "cm")]
     margin: Option<f64>,
+    /// 装订线宽度（单位：cm），在装订一侧的纸张边距外额外留出，避免装订后内容被遮挡
+    #[arg(long, value_name = "cm", conflicts_with = "template")]
+    gutter: Option<f64>,
+    /// 装订线所在一侧，alternating 为奇数页在左、偶数页在右，用于双面打印
+    #[arg(long, value_name = "SIDE", requires = "gutter")]
+    gutter_side: Option<GutterSide>,
     /// PPC 每厘米像素数 默认118.11PPC=300PPI
     /// PPC与PPI同时设置时，PPI优先
     #[arg(long, value_name = "PPC")]
@@ -56,31 +513,303 @@ struct Cli {
     /// 纵向图片数量
     #[arg(long, value_name = "COUNT")]
     nv: Option<u32>,
+    /// 图片清单文件，每行 `文件名 尺寸` 为单张图片指定缩放比例或打印高度
+    /// 尺寸如 150%、1.5x、10cm，放大后的图片会占用多个单元格；
+    /// .toml 文件可用 `"合影.jpg" = { width = 15 }` 指定打印宽度（单位：cm）
+    #[arg(long, value_name = "FILE")]
+    manifest: Option<String>,
+    /// 整体缩放比例，如 80% 或 0.8，按比例缩小单元格与目标高度并重新计算每页数量
+    #[arg(long, value_name = "PERCENT", value_parser = parse_percent)]
+    scale: Option<f64>,
+    /// 缩放时同时缩放图片间距
+    #[arg(long, requires = "scale")]
+    scale_margins: bool,
+    /// 目标高度放不下时自动减少横向/纵向数量，而不是将图片缩小到最大高度
+    #[arg(long)]
+    auto_grid: bool,
+    /// 两端对齐：单元格未占满可用区域时，将剩余空间平均分配到图片间距，只有一行或一列时居中
+    #[arg(long, conflicts_with = "template")]
+    justify: bool,
+    /// 输出实际打印尺寸报告 verify.txt，列出每张图片及间距的厘米尺寸
+    #[arg(long)]
+    verify: bool,
+    /// 分组方式，分组变化时另起一页
+    #[arg(long, value_name = "KEY")]
+    group_by: Option<GroupBy>,
+    /// 输出格式
+    #[arg(long, value_name = "FORMAT", default_value = "png")]
+    format: OutputFormat,
+    /// JPEG 输出质量 1-100
+    #[arg(long, value_name = "Q", default_value_t = 90,
+        value_parser = clap::value_parser!(u8).range(1..=100))]
+    quality: u8,
+    /// PDF 中每张图片的链接地址模板，`{name}` 为文件名，`{path}` 为源文件路径
+    /// 默认链接到源文件
+    #[arg(long, value_name = "TEMPLATE")]
+    pdf_link: Option<String>,
+    /// 同时输出排版结果 layout.json，记录每张图片的来源、单元格与绘制位置
+    #[arg(long)]
+    export_layout: bool,
+    /// 同时输出静态 HTML 画廊 index.html，包含每页缩略图及内容列表
+    #[arg(long)]
+    export_html: bool,
+    /// 同时为每页输出切割路径 SVG，包含每张图片的外轮廓（圆角时为圆角矩形），
+    /// 按实际尺寸标注，供 Cricut、Silhouette 等切割机沿图片边缘切割
+    #[arg(long)]
+    cut_path: bool,
+    /// 每张图片放置两份，便于裁切成两叠相同的图片
+    #[arg(long, value_name = "MODE")]
+    pairs: Option<PairMode>,
+    /// 拼版方式，booklet 为骑马钉小册子：左右半页各排一页，页数补足为 4 的倍数，
+    /// 双面打印（短边翻转）后对折装订即按顺序阅读，横向数量为奇数时最右一列留空
+    #[arg(long, value_name = "MODE", conflicts_with = "pairs")]
+    impose: Option<Imposition>,
+    /// 双面排版：输入图片两两一组为正面与背面，输出正反面交替的页面，
+    /// 背面列序左右镜像，沿长边翻转双面打印后裁切即正反对齐，适合卡片、单词卡
+    #[arg(long, conflicts_with_all = ["pairs", "impose"])]
+    duplex: bool,
+    /// 背面整体偏移 X,Y（单位：mm），向右下为正，补偿打印机双面套准偏差
+    #[arg(long, value_name = "X,Y", value_parser = parse_offset, allow_hyphen_values = true,
+        requires = "duplex")]
+    duplex_offset: Option<(f64, f64)>,
+    /// 单元格填充顺序：rows 先行后列，columns 先列后行，snake 蛇形，rtl 每行从右到左
+    #[arg(long, value_name = "ORDER", default_value = "rows")]
+    fill_order: FillOrder,
+    /// 第一页开头跳过的单元格数量，按填充顺序计数，用于已用去部分标签的标签纸
+    #[arg(long, value_name = "N", conflicts_with_all = ["pairs", "start_row", "start_col"])]
+    skip_cells: Option<u32>,
+    /// 第一页从第几行开始放置（从 1 开始）
+    #[arg(long, value_name = "ROW",
+        value_parser = clap::value_parser!(u32).range(1..), conflicts_with = "pairs")]
+    start_row: Option<u32>,
+    /// 第一页从第几列开始放置（从 1 开始，从左数）
+    #[arg(long, value_name = "COL",
+        value_parser = clap::value_parser!(u32).range(1..), conflicts_with = "pairs")]
+    start_col: Option<u32>,
+    /// 居中最后一页：最后一行未排满时该行横向居中，未用完全部行时整体纵向居中
+    #[arg(long, conflicts_with_all = ["duplex", "impose", "skip_cells", "start_row", "start_col"])]
+    center_last: bool,
+    /// 水平翻转整页输出，用于热转印纸与部分丝网印刷菲林，目录页不翻转
+    #[arg(long)]
+    mirror: bool,
+    /// 反相整页输出，用于制作接触印相（如蓝晒）的数码负片，目录页不反相；
+    /// 配合 --colorspace gray 输出灰度负片，配合 --mirror 使药面朝下
+    #[arg(long)]
+    invert: bool,
+    /// 输出份数，整套页面重复输出；单张图片的份数见 --image-copies
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    copies: u32,
+    /// 每张图片排入的份数，如每张照片各打印 3 张
+    #[arg(long, value_name = "N", default_value_t = 1,
+        value_parser = clap::value_parser!(u32).range(1..))]
+    image_copies: u32,
+    /// 单张图片的份数文件，CSV 每行 `文件名,份数`，未列出的图片使用 --image-copies，份数为 0 时跳过
+    #[arg(long, value_name = "FILE")]
+    image_copies_csv: Option<String>,
+    /// 多份输出时逐份排序（1,2,3,1,2,3），默认逐页排序（1,1,2,2,3,3）
+    #[arg(long)]
+    collate: bool,
+    /// 同时绘制的页数 默认为 CPU 核心数
+    #[arg(short, long, value_name = "N")]
+    jobs: Option<usize>,
+    /// 绘制时的内存预算，如 512M、2G，超出时减少同时绘制的页数
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    max_memory: Option<u64>,
 }
 
-struct Config {
-    /// 每厘米像素数
-    pub ppc: f64,
-    /// 纸张外边距 单边 像素
-    pub paper_border_px: u32,
-    /// 纵向最小边距 像素
-    pub min_margin_v_px: u32,
-    /// 横向最小边距 像素
-    pub min_margin_h_px: u32,
-    /// 单图片目标高度 像素
-    pub target_h_px: u32,
-    /// 单图片最大高度 像素
-    pub max_h_px: u32,
-    /// 单图片最大宽度 像素
-    pub max_w_px: u32,
-    /// 横向图片数量
-    pub n_h: u32,
-    /// 纵向图片数量
-    pub n_v: u32,
+#[derive(Clone, Subcommand)]
+enum Command {
+    /// 按排版结果文件重新绘制页面，可先手动调整其中的图片与位置
+    ///
+    /// 输出目录、格式、单元格样式、边框与说明文字等沿用主命令参数，排版时的参数需一并传入，
+    /// 如 `-o out --frame 0.2 render --manifest layout.json`
+    Render {
+        /// 排版结果文件，由 --export-layout 生成
+        #[arg(long, value_name = "FILE")]
+        manifest: String,
+    },
+    /// 启动本地 HTTP 服务，通过 REST 接口上传图片、提交排版参数、查询进度并下载结果
+    ///
+    /// 主命令参数作为每个任务的默认参数，请求中的参数与配置文件格式相同
+    Serve {
+        /// 监听地址
+        #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8080")]
+        addr: String,
+        /// 任务文件存放目录 默认系统临时目录
+        #[arg(long, value_name = "DIR")]
+        workspace: Option<String>,
+    },
+    /// 证件照：将一张照片按证件照尺寸裁切，重复排满一张相纸
+    ///
+    /// 纸张默认 6x4in，边距默认 0.3 cm，照片间距沿用 --margin 默认 0.2 cm，
+    /// 方向未指定时取能放下更多照片的方向，如 `-o out id photo.jpg --size 35x45`
+    Id {
+        /// 照片文件
+        photo: PathBuf,
+        /// 证件照尺寸，可选 cn-1inch、cn-2inch、us-passport、schengen-visa、jp-resume 等预设
+        /// 或自定义 宽x高（单位：mm）
+        #[arg(long, value_name = "SIZE", default_value = "35x45")]
+        size: IdSize,
+        /// 照片数量 默认排满一页
+        #[arg(long, value_name = "COUNT")]
+        count: Option<u32>,
+    },
+    /// 海报：将一张大图放大后分割到 N×M 张纸上，打印后拼接
+    ///
+    /// 纸张默认 A4，边距默认 1 cm，方向未指定时取拼接后宽高比最接近图片的方向；
+    /// 相邻页面重叠一段相同内容，重叠边界在边距内画出对齐标记，如 `-o out poster big.jpg --cols 3 --rows 2`
+    Poster {
+        /// 图片文件
+        image: PathBuf,
+        /// 横向页数
+        #[arg(long, value_name = "N", default_value_t = 2,
+            value_parser = clap::value_parser!(u32).range(1..))]
+        cols: u32,
+        /// 纵向页数
+        #[arg(long, value_name = "M", default_value_t = 2,
+            value_parser = clap::value_parser!(u32).range(1..))]
+        rows: u32,
+        /// 相邻页面的重叠宽度（单位：cm）
+        #[arg(long, value_name = "cm", default_value_t = 1.0)]
+        overlap: f64,
+    },
+    /// 在终端界面中预览每页网格，调整顺序或移除图片后再输出
+    Tui,
+    /// 打开图形界面，选择目录、调整网格与边距并预览每页
+    #[cfg(feature = "gui")]
+    Gui,
+}
+
+impl Cli {
+    /// 每厘米像素数，PPI 优先
+    fn ppc(&self) -> f64 {
+        match self.ppi {
+            Some(ppi) => ppi / 2.54,
+            None => self.ppc.unwrap_or(118.11),
+        }
+    }
+
+    /// 实际使用的进度显示方式
+    fn progress_mode(&self) -> ProgressMode {
+        match self.no_progress {
+            true => ProgressMode::None,
+            false => self.progress,
+        }
+    }
+
+    /// 排版参数
+    fn layout_options(&self) -> LayoutOptions {
+        LayoutOptions {
+            paper: self.paper,
+            orientation: self.orientation,
+            template: self.template.clone(),
+            height: self.height,
+            border: self.border,
+            margin: self.margin,
+            gutter: self.gutter,
+            gutter_side: self.gutter_side,
+            ppc: self.ppc,
+            ppi: match self.draft {
+                true => Some(
+                    self.ppi
+                        .or(self.ppc.map(|ppc| ppc * 2.54))
+                        .map_or(DRAFT_PPI, |ppi| ppi.min(DRAFT_PPI)),
+                ),
+                false => self.ppi,
+            },
+            nh: self.nh,
+            nv: self.nv,
+            scale: self.scale,
+            scale_margins: self.scale_margins,
+            auto_grid: self.auto_grid,
+            justify: self.justify,
+            caption_size: (self.captions.is_some() || self.captions_file.is_some())
+                .then_some(self.caption_size),
+            caption_lines: Some(self.caption_lines),
+            caption_overlay: self.caption_position == CaptionPosition::Over,
+            bleed: self.bleed,
+            frame: self.frame,
+            frame_color: self.frame_color,
+            corner_radius: self.corner_radius,
+            style: Some(self.style),
+            rotate: Some(self.rotate),
+            align: Some(self.align),
+            fit: match self.true_size {
+                true => Some(Fit::Original),
+                false => self.fit,
+            },
+            no_upscale: self.no_upscale,
+            crop: Some(self.crop),
+            filter: match self.draft {
+                true => Some(self.filter.unwrap_or(Filter::Triangle)),
+                false => self.filter,
+            },
+            linear_light: self.linear_light,
+            sharpen: self.sharpen,
+            deskew: self.deskew,
+            autocrop: self.autocrop,
+            brightness: self.brightness,
+            contrast: self.contrast,
+            auto_levels: self.auto_levels,
+            // 只有 PNG 与 TIFF 支持 16 位，CMYK 输出为 8 位
+            depth: Some(match (self.format, self.colorspace) {
+                (_, Colorspace::Cmyk) => Depth::Eight,
+                (OutputFormat::Png | OutputFormat::Tiff, _) => self.depth,
+                _ => Depth::Eight,
+            }),
+            color_profile: self.color_profile,
+            background: self.background,
+            matte: self.matte,
+            shadow: self.shadow,
+            shadow_offset: self.shadow_offset,
+            shadow_blur: self.shadow_blur,
+            shadow_opacity: self.shadow_opacity,
+            qr_size: self.qr.map(|_| self.qr_size),
+            barcode_height: self.barcode.map(|_| self.barcode_height),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum LowDpi {
+    /// 在结束时列出
+    Warn,
+    /// 排版前报错退出
+    Reject,
+    /// 在图片上标出，并在结束时列出
+    Mark,
+}
+
+#[derive(Clone, Copy, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum ProgressMode {
+    /// 终端进度条
+    Bar,
+    /// 每个事件输出一行 JSON 到标准错误，供其他程序解析
+    Json,
+    /// 不显示进度，只逐行输出提示信息
+    None,
+}
+
+/// 解析百分比，支持 `80%` 与 `0.8`
+fn parse_percent(s: &str) -> Result<f64, String> {
+    let value = match s.trim().strip_suffix('%') {
+        Some(v) => v.trim().parse::<f64>().map(|v| v / 100.0),
+        None => s.trim().parse::<f64>(),
+    }
+    .map_err(|_| Msg::PercentInvalid(s).to_string())?;
+    if value > 0.0 && value.is_finite() {
+        Ok(value)
+    } else {
+        Err(Msg::PercentNotPositive(s).to_string())
+    }
 }
 
 enum PBData {
     Stop,
+    NewTotal(u64),
+    NextTotal,
     NewOutput(u64),
     NextOutput,
     NewRead(u64),
@@ -93,184 +822,1151 @@ enum PBData {
     NextComp,
     SetComp(u64),
     Println(String),
+    /// 警告信息，安静模式下仍然输出
+    Warn(String),
+    Paused(bool),
 }
 
-impl Config {
-    pub fn from_cli_default(cli: &Cli) -> Config {
-        // 横向图片数量
-        let n_h: u32 = cli.nh.unwrap_or(4);
-        // 纵向图片数量
-        let n_v: u32 = cli.nv.unwrap_or(3);
-        // 单图片目标高度 厘米
-        let target_h_cm: f64 = cli.height.unwrap_or(5.0);
-        // 纸张外边距 单边 厘米
-        let paper_border_cm: f64 = cli.border.unwrap_or(0.8);
-        // 纵向最小边距 厘米
-        let min_margin_v_cm: f64 = cli.margin.unwrap_or(0.3);
-        // 横向最小边距 厘米
-        let min_margin_h_cm: f64 = cli.margin.unwrap_or(0.3);
-        // 每厘米像素数，默认从ppi计算，否则取ppc或默认值118.11=300ppi
-        let ppc: f64 = match cli.ppi {
-            Some(ppi) => ppi / 2.54,
-            None => cli.ppc.unwrap_or(118.11),
-        };
-        // 纸张外边距 单边 像素
-        let paper_border_px = (paper_border_cm * ppc).round() as u32;
-        // 纵向最小边距 像素
-        let min_margin_v_px = (min_margin_v_cm * ppc).round() as u32;
-        // 横向最小边距 像素
-        let min_margin_h_px = (min_margin_h_cm * ppc).round() as u32;
-        // 单图片目标高度 像素
-        let mut target_h_px = (target_h_cm * ppc).round() as u32;
-        // 单图片最大高度 像素
-        let max_h_px = ((21.0 - 2.0 * paper_border_cm - (n_v - 1) as f64 * min_margin_v_cm)
-            / n_v as f64
-            * ppc)
-            .round() as u32;
-        // 单图片最大宽度 像素
-        let max_w_px = ((29.7 - 2.0 * paper_border_cm - (n_h - 1) as f64 * min_margin_h_cm)
-            / n_h as f64
-            * ppc)
-            .round() as u32;
-
-        // 验证config
-        if target_h_px > max_h_px {
-            println!("单图片目标高度超过最大高度，将设置为最大高度输出");
-            target_h_px = max_h_px;
+/// 解析内存大小，支持 `K`、`M`、`G` 后缀（1024 进制），无后缀为字节
+fn parse_size(s: &str) -> Result<u64, String> {
+    let upper = s.trim().to_ascii_uppercase();
+    let upper = upper.strip_suffix('B').unwrap_or(&upper);
+    let (value, unit) = match upper.char_indices().last() {
+        Some((i, 'K')) => (&upper[..i], 1u64 << 10),
+        Some((i, 'M')) => (&upper[..i], 1 << 20),
+        Some((i, 'G')) => (&upper[..i], 1 << 30),
+        _ => (upper, 1),
+    };
+    match value.trim().parse::<f64>() {
+        Ok(v) if v > 0.0 && v.is_finite() => Ok((v * unit as f64) as u64),
+        _ => Err(Msg::SizeInvalid(s).to_string()),
+    }
+}
+
+/// 解析偏移量 `X,Y`
+fn parse_offset(s: &str) -> Result<(f64, f64), String> {
+    s.split_once(',')
+        .and_then(|(x, y)| Some((x.trim().parse().ok()?, y.trim().parse().ok()?)))
+        .filter(|(x, y): &(f64, f64)| x.is_finite() && y.is_finite())
+        .ok_or_else(|| Msg::OffsetInvalid(s).to_string())
+}
+
+/// 解析命令行参数并合并配置文件
+fn parse_cli() -> Result<Cli, Error> {
+    // 参数的格式错误在解析时输出，需先按命令行或环境变量设置界面语言
+    i18n::set_lang(lang_arg().unwrap_or_else(Lang::from_env));
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let path = match &cli.config {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let path = PathBuf::from(config_file::DEFAULT_CONFIG_FILE);
+            if !path.is_file() {
+                return match &cli.profile {
+                    Some(name) => Err(Error::Config {
+                        reason: Msg::ProfileWithoutConfig(name).to_string(),
+                    }),
+                    None => Ok(cli),
+                };
+            }
+            path
+        }
+    };
+    let table = config_file::load(&path)?;
+    let table = config_file::apply_profile(table, cli.profile.as_deref(), &path)?;
+    merge_config(cli, &matches, table, &path)
+}
+
+/// 命令行中 `--lang` 指定的界面语言，未指定或无效时返回 None
+fn lang_arg() -> Option<Lang> {
+    let args: Vec<String> = std::env::args_os()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+    args.iter().enumerate().find_map(|(i, arg)| {
+        let value = match arg.strip_prefix("--lang")? {
+            "" => args.get(i + 1)?.as_str(),
+            rest => rest.strip_prefix('=')?,
         };
+        Lang::from_str(value, true).ok()
+    })
+}
+
+/// 合并配置文件与命令行参数
+///
+/// 子命令、配置文件与预设不在配置文件中设置，合并后沿用命令行中的值
+fn merge_config(
+    cli: Cli,
+    matches: &ArgMatches,
+    table: toml::Table,
+    path: &Path,
+) -> Result<Cli, Error> {
+    let merged = config_file::merge(&cli, matches, table, path)?;
+    Ok(Cli {
+        command: cli.command,
+        config: cli.config,
+        profile: cli.profile,
+        ..merged
+    })
+}
+
+/// 按命令行参数收集、排序输入文件
+fn scan_sources(cli: &Cli) -> Result<Vec<PathBuf>, Error> {
+    let filter = InputFilter::new(&cli.include, &cli.exclude)?;
+    let listed = match cli.files_from.as_deref() {
+        Some("-") => read_file_list(io::stdin().lock(), cli.null)?,
+        Some(path) => read_file_list(fs::File::open(path).context(IoSnafu)?, cli.null)?,
+        None => Vec::new(),
+    };
+    let mut sources: Vec<&str> = cli
+        .input
+        .iter()
+        .chain(&cli.files)
+        .chain(&listed)
+        .map(String::as_str)
+        .collect();
+    if sources.is_empty() {
+        sources.push("input");
+    }
+    let mut inputs = collect_inputs(&sources, cli.recursive, &filter)?;
+    if let Some(key) = cli.sort {
+        sort_inputs(&mut inputs, key);
+    }
+    if cli.shuffle {
+        let seed = cli.seed.unwrap_or_else(|| {
+            let seed = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_nanos() as u64);
+            if !cli.quiet {
+                println!("{}", Msg::Seed(seed));
+            }
+            seed
+        });
+        shuffle_inputs(&mut inputs, seed);
+    }
+    if cli.reverse {
+        inputs.reverse();
+    }
+    Ok(inputs)
+}
 
-        Config {
-            ppc,
-            paper_border_px,
-            min_margin_v_px,
-            min_margin_h_px,
-            target_h_px,
-            max_h_px,
-            max_w_px,
-            n_h,
-            n_v,
+fn process_with_pb() -> Result<(), Error> {
+    let cli = parse_cli()?;
+    i18n::set_lang(cli.lang.unwrap_or_else(Lang::from_env));
+    match &cli.command {
+        Some(Command::Render { manifest }) => return render_manifest(&cli, Path::new(manifest)),
+        Some(Command::Serve { addr, workspace }) => {
+            let workspace = match workspace {
+                Some(dir) => PathBuf::from(dir),
+                None => std::env::temp_dir().join("image-typesetting-tool"),
+            };
+            return serve::serve(&cli, addr, &workspace);
         }
+        Some(Command::Id { photo, size, count }) => return id_photo(&cli, photo, *size, *count),
+        Some(Command::Poster {
+            image,
+            cols,
+            rows,
+            overlap,
+        }) => return poster(&cli, image, (*cols, *rows), *overlap),
+        Some(Command::Tui) => return tui::tui(&cli),
+        #[cfg(feature = "gui")]
+        Some(Command::Gui) => return gui::gui(&cli),
+        None => {}
     }
+    if cli.watch {
+        return watch::watch(&cli);
+    }
+    typeset(&cli, &mut Rendered::default(), None)
 }
 
-fn scan_inputs(input_dir: &str) -> Result<Vec<PathBuf>, Error> {
-    let path = Path::new(input_dir);
-    let mut inputs: Vec<PathBuf> = Vec::new();
-    let entries = match fs::read_dir(path) {
-        Ok(entries) => entries,
-        Err(_) => {
-            return Err(Error::Input {
-                reason: format!("输入目录`{}`不存在或无法读取", path.display()),
+/// 收集输入、排版并输出全部页面
+///
+/// - rendered: 上一轮的输出，内容未变化的页面不再重绘
+/// - progress: 接收进度事件，未指定时按命令行参数显示进度
+fn typeset(
+    cli: &Cli,
+    rendered: &mut Rendered,
+    progress: Option<Sender<PBData>>,
+) -> Result<(), Error> {
+    let order = match &cli.order {
+        Some(path) => Some(read_order(Path::new(path))?),
+        None => None,
+    };
+    // 无法读取的文件按策略处理，跳过时排列清单中的对应位置留空
+    let on_error = if cli.strict {
+        OnError::Abort
+    } else {
+        cli.on_error
+    };
+    let mut skipped: Vec<(PathBuf, String)> = Vec::new();
+    let mut readable = |path: &Path| match image_dimensions(path) {
+        Ok(_) => Ok(true),
+        Err(e) if on_error == OnError::Abort => Err(unreadable(path, e)),
+        Err(e) => {
+            skipped.push((path.to_path_buf(), e.to_string()));
+            Ok(on_error == OnError::Placeholder)
+        }
+    };
+    let copies_csv = match &cli.image_copies_csv {
+        Some(path) => Some(CaptionFile::load(Path::new(path))?),
+        None => None,
+    };
+    let copies_of = |path: &Path| image_copies(path, cli.image_copies, copies_csv.as_ref());
+    // 每张图片按份数重复，同一图片的多份相邻排列
+    let order = match order {
+        Some(order) => {
+            let mut entries = Vec::with_capacity(order.len());
+            for entry in order {
+                match entry {
+                    OrderEntry::Image(path, _) if !readable(&path)? => {
+                        entries.push(OrderEntry::Blank)
+                    }
+                    OrderEntry::Image(path, scale) => {
+                        let n = copies_of(&path)?;
+                        entries.extend(iter::repeat_n(OrderEntry::Image(path, scale), n));
+                    }
+                    entry => entries.push(entry),
+                }
+            }
+            Some(entries)
+        }
+        None => None,
+    };
+    let inputs: Vec<PathBuf> = match &order {
+        Some(order) => order
+            .iter()
+            .filter_map(|entry| match entry {
+                OrderEntry::Image(path, _) => Some(path.clone()),
+                _ => None,
             })
+            .collect(),
+        None => {
+            let mut inputs = Vec::new();
+            for input in scan_sources(cli)? {
+                if readable(&input)? {
+                    let n = copies_of(&input)?;
+                    inputs.extend(iter::repeat_n(input, n));
+                }
+            }
+            inputs
         }
     };
-
-    for entry in entries {
-        let entry = entry.context(IoSnafu)?;
-        let file_path = entry.path();
-        if file_path.is_file() {
-            inputs.push(file_path);
+    let captions = load_captions(cli)?;
+    let header_footer = match (&cli.header, &cli.footer) {
+        (None, None) => None,
+        (header, footer) => Some(HeaderFooter {
+            header: header.as_ref().map(|text| PageText {
+                text: text.clone(),
+                align: cli.header_align,
+            }),
+            footer: footer.as_ref().map(|text| PageText {
+                text: text.clone(),
+                align: cli.footer_align,
+            }),
+            size: cli.header_size,
+        }),
+    };
+    let needs_font = on_error == OnError::Placeholder
+        || captions.is_some()
+        || cli.index
+        || cli.number_images
+        || cli.low_dpi == LowDpi::Mark
+        || (header_footer.is_some() && cli.header_font.is_none());
+    let font = match needs_font {
+        true => Font::load(cli.font.as_deref().map(Path::new))?,
+        false => None,
+    };
+    let header_font = match (&header_footer, &cli.header_font) {
+        (Some(_), Some(path)) => Some(Font::open(Path::new(path))?),
+        _ => None,
+    };
+    ensure!(
+        (captions.is_none() && !cli.index && !cli.number_images || font.is_some())
+            && (header_footer.is_none() || header_font.is_some() || font.is_some()),
+        InputSnafu {
+            reason: Msg::NoFont.to_string(),
         }
+    );
+    ensure!(
+        cli.colorspace != Colorspace::Cmyk || matches!(cli.format, OutputFormat::Tiff),
+        InputSnafu {
+            reason: Msg::CmykNeedsTiff.to_string(),
+        }
+    );
+    ensure!(
+        cli.cmyk_profile.is_none() || cli.colorspace == Colorspace::Cmyk,
+        InputSnafu {
+            reason: Msg::CmykProfileNeedsCmyk.to_string(),
+        }
+    );
+    let cmyk_profile = match &cli.cmyk_profile {
+        Some(path) => Some(colorspace::read_cmyk_profile(path)?),
+        None => None,
+    };
+    let qr = code_values(cli.qr, cli.qr_csv.as_deref(), "--qr")?;
+    let barcode = code_values(cli.barcode, cli.barcode_csv.as_deref(), "--barcode")?;
+    let watermark = match &cli.watermark {
+        Some(path) => Some(Watermark::open(
+            Path::new(path),
+            cli.watermark_opacity,
+            cli.watermark_pos,
+            cli.watermark_scope,
+        )?),
+        None => None,
+    };
+    let style = PageStyle {
+        on_error,
+        font: font.as_ref(),
+        captions: captions.as_ref(),
+        header_footer: header_footer.as_ref(),
+        header_font: header_font.as_ref().or(font.as_ref()),
+        watermark: watermark.as_ref(),
+        qr: qr.as_ref(),
+        barcode: barcode.as_ref().map(|values| (values, cli.barcode_type)),
+        number_corner: cli.number_images.then_some(cli.number_corner),
+        cut_marks: cli.cut_marks,
+        debug_grid: cli.debug_grid,
+        low_dpi: None,
+        colorspace: cli.colorspace,
+        dither: cli.dither,
+        cmyk_profile: cmyk_profile.as_deref(),
+        back_offset: None,
+        center_last: cli.center_last,
+        mirror: cli.mirror,
+        invert: cli.invert,
+        verbose: cli.verbose,
+    };
+    let skipped = Mutex::new(skipped);
+    let Arrangement {
+        typesetter,
+        target_hs,
+        dates,
+        pages,
+        ..
+    } = arrange(cli, &inputs, order.as_deref())?;
+    let config = &typesetter.config;
+    ensure!(
+        cli.depth != Depth::Sixteen || config.depth == Depth::Sixteen,
+        InputSnafu {
+            reason: Msg::DeepUnsupported.to_string(),
+        }
+    );
+    ensure!(
+        header_footer.as_ref().is_none_or(|header_footer| {
+            (header_footer.header.is_none() || config.paper_border_px.top > 0)
+                && (header_footer.footer.is_none() || config.paper_border_px.bottom > 0)
+        }),
+        InputSnafu {
+            reason: Msg::HeaderNeedsBorder.to_string(),
+        }
+    );
+    let page_names = page_names(&pages, &dates, cli.group_by);
+    let copy_sequences = copy_sequences(pages.len(), cli.copies, cli.collate);
+    let copy_names = copy_names(&page_names, &copy_sequences, cli.group_by);
+    if cli.dry_run {
+        for warning in &config.warnings {
+            eprintln!("{}", warning);
+        }
+        let plan = Plan {
+            config,
+            pages: &pages,
+            page_names: &page_names,
+            inputs: &inputs,
+            target_hs: &target_hs,
+        };
+        print!("{}", dry_run_report(&plan));
+        return Ok(());
     }
-    Ok(inputs)
-}
-
-fn load_images(inputs: &[PathBuf], tx: Sender<PBData>) -> Result<Vec<DynamicImage>, Error> {
-    let images: Result<Vec<_>, _> = inputs
+    // 有效 DPI 检查
+    let low_dpi = low_dpi_images(config, &pages, &inputs, &target_hs, cli.min_dpi);
+    let low_dpi_report = (!low_dpi.is_empty()).then(|| {
+        let mut report = Msg::LowDpiSummary {
+            count: low_dpi.len(),
+            min_dpi: cli.min_dpi,
+        }
+        .to_string();
+        for (&index, &dpi) in &low_dpi {
+            let file = inputs[index].display().to_string();
+            report.push_str(&format!("\n  {}", Msg::LowDpi { file: &file, dpi }));
+        }
+        report
+    });
+    if let (LowDpi::Reject, Some(report)) = (cli.low_dpi, &low_dpi_report) {
+        return Err(Error::Input {
+            reason: report.clone(),
+        });
+    }
+    let style = PageStyle {
+        low_dpi: (cli.low_dpi == LowDpi::Mark).then_some(&low_dpi),
+        back_offset: cli.duplex_offset.map(|(x, y)| {
+            let px = |mm: f64| (mm / 10.0 * config.ppc).round() as i64;
+            (px(x), px(y))
+        }),
+        ..style
+    };
+    // 准备输出
+    let output_dir = cli.output.clone().unwrap_or("output".to_string());
+    prepare_output_dir(&output_dir, cli, &inputs)?;
+    let overwrite = cli.overwrite;
+    let output_files: Vec<Vec<String>> = copy_names
         .iter()
-        .map(|input| {
-            let _ = tx.send(PBData::NextRead(
-                input
-                    .file_name()
-                    .and_then(|name| name.to_str())
-                    .and_then(|name| Some(format!("读取：{name}"))),
-            ));
-            image::open(input).context(ImageSnafu)
+        .map(|names| match cli.format {
+            OutputFormat::Pdf => Vec::new(),
+            format => names
+                .iter()
+                .map(|name| format!("{}/{}.{}", output_dir, name, format.extension()))
+                .collect(),
         })
         .collect();
-    Ok(images?)
-}
-
-fn draw_canvas(
-    images: &[DynamicImage],
-    cfg: &Config,
-    tx: Sender<PBData>,
-) -> Result<RgbaImage, Error> {
-    // 图像预处理
-    let images: Vec<DynamicImage> = images
+    let first_numbers = number::first_numbers(&pages);
+    let signatures: Vec<u64> = pages
         .iter()
-        .map(|image| {
-            let _ = tx.send(PBData::NextProcess);
-            // 判断图片方向 旋转
-            let (width, height) = image.dimensions();
-            let image = if height > width {
-                image.rotate270()
-            } else {
-                image.clone()
-            };
-            // resize 统一高度
-            image.resize(cfg.max_w_px, cfg.target_h_px, FilterType::Lanczos3)
+        .enumerate()
+        .map(|(i, placements)| {
+            // 总页数变化时页眉页脚随之变化，前面页面的图片数变化时编号随之变化
+            let page_texts = header_footer
+                .as_ref()
+                .map(|header_footer| header_footer.texts(i + 1, pages.len()));
+            let first_number = cli.number_images.then_some(first_numbers[i]);
+            watch::page_signature(placements, &inputs, &target_hs, (page_texts, first_number))
         })
         .collect();
+    // PDF 与各类汇总文件包含全部页面，需要完整重绘
+    let incremental = !matches!(cli.format, OutputFormat::Pdf)
+        && !cli.verify
+        && !cli.export_layout
+        && !cli.export_html;
+    let todo: Vec<usize> = (0..pages.len())
+        .filter(|&i| !(incremental && rendered.unchanged(i, signatures[i], &output_files[i])))
+        .collect();
+    // 初始化进度条功能
+    let n_input = todo.iter().map(|&i| pages[i].len()).sum::<usize>() as u64;
+    let n_batch = todo.len() as u64;
+    let (handle, tx) = match progress {
+        Some(tx) => (None, tx),
+        None => {
+            let (handle, tx) = init_pb_thread(cli.progress_mode(), cli.quiet);
+            if !cli.watch {
+                spawn_pause_listener(tx.clone());
+            }
+            (Some(handle), tx)
+        }
+    };
+    let _ = tx.send(PBData::NewTotal(n_input));
+    let _ = tx.send(PBData::NewOutput(n_batch));
+    for warning in &config.warnings {
+        let _ = tx.send(PBData::Warn(warning.to_string()));
+    }
+    if cli.verbose > 1 {
+        for (name, placements) in page_names.iter().zip(&pages) {
+            for placement in placements {
+                let input = inputs[placement.index].file_name().unwrap_or_default();
+                let msg = Msg::Placed {
+                    page: name,
+                    file: &input.to_string_lossy(),
+                    row: placement.row + 1,
+                    col: placement.col + 1,
+                    span: (placement.span.cols, placement.span.rows),
+                };
+                let _ = tx.send(PBData::Println(msg.to_string()));
+            }
+        }
+    }
 
-    // 布局
-    let mut canvas: RgbaImage = ImageBuffer::new(
-        (cfg.ppc * 29.7).ceil() as u32,
-        (cfg.ppc * 21.0).ceil() as u32,
-    );
-    images.iter().enumerate().for_each(|(i, image)| {
-        let _ = tx.send(PBData::NextComp);
-        let (row, col) = row_and_col_from_index(cfg.n_h as usize, i);
-        let x = cfg.paper_border_px + col * (cfg.max_w_px + cfg.min_margin_h_px);
-        let y = cfg.paper_border_px + row * (cfg.max_h_px + cfg.min_margin_v_px);
-        imageops::overlay(&mut canvas, image, x as i64, y as i64);
-    });
+    // 分批绘制
+    let mut n_written = 0;
+    let mut verify_report = String::new();
+    let mut gallery_pages = Vec::new();
+    let mut layout_file = LayoutFile::new(config);
+    let mut book = PdfBook::new("output");
+    book.set_profile(config.color_profile);
+    // 目录页排在全部页面之前，PDF 中的页码随之后移
+    let index_pages = match (cli.index, font.as_ref()) {
+        (true, Some(font)) => {
+            let entries = index::entries(&pages, &inputs, config.n_h);
+            index::render(&entries, font, config)
+        }
+        _ => Vec::new(),
+    };
+    for (i, canvas) in index_pages.iter().enumerate() {
+        match cli.format {
+            OutputFormat::Pdf => book.add_page(canvas, config.ppc, &format!("index_{}", i), &[]),
+            format => {
+                let output_path = format!("{}/index_{}.{}", output_dir, i, format.extension());
+                save_atomic(&output_path, overwrite, |path| {
+                    save_image(canvas, path, format, cli.quality)
+                })?;
+            }
+        }
+    }
+    let n_index = index_pages.len();
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(cli.jobs.unwrap_or(0))
+        .build()
+        .map_err(|e| Error::Config {
+            reason: e.to_string(),
+        })?;
+    let mut jobs = pool.current_num_threads();
+    if let Some(budget) = cli.max_memory {
+        // 按最耗内存的一页估算可同时绘制的页数
+        let mut page_memory = 0;
+        for placements in &pages {
+            let dimensions = placements
+                .iter()
+                .map(|placement| image_dimensions(&inputs[placement.index]).unwrap_or((0, 0)))
+                .collect::<Vec<_>>();
+            page_memory = page_memory.max(typesetter.page_memory(&dimensions));
+        }
+        let fit = (budget / page_memory.max(1)) as usize;
+        if fit == 0 {
+            let msg = Msg::MemoryLow {
+                mb: page_memory.div_ceil(1 << 20),
+            };
+            let _ = tx.send(PBData::Warn(msg.to_string()));
+        }
+        jobs = jobs.min(fit).max(1);
+    }
+    let result = (|| {
+        // 每轮并行绘制 jobs 页，再按页序依次输出
+        for chunk in todo.chunks(jobs) {
+            let n = chunk.iter().map(|&i| pages[i].len()).sum::<usize>() as u64;
+            let _ = tx.send(PBData::NewRead(n));
+            let _ = tx.send(PBData::NewProcess(n));
+            let _ = tx.send(PBData::NewComp(n));
+            let _ = tx.send(PBData::SetRead(0));
+            let _ = tx.send(PBData::SetProcess(0));
+            let _ = tx.send(PBData::SetComp(0));
 
-    Ok(canvas)
+            let rendered = pool.install(|| {
+                chunk
+                    .par_iter()
+                    .map(|&i| {
+                        let page = PageSource {
+                            placements: &pages[i],
+                            inputs: &inputs,
+                            target_hs: &target_hs,
+                            number: (i + 1, pages.len()),
+                            first_image: first_numbers[i],
+                        };
+                        render_page(&typesetter, page, &style, &skipped, |event| {
+                            page_progress(&tx, event)
+                        })
+                    })
+                    .collect::<Result<Vec<_>, Error>>()
+            })?;
+            for (&i, page) in chunk.iter().zip(rendered) {
+                let RenderedPage {
+                    inputs: batch_inputs,
+                    canvas,
+                    output,
+                    cmyk,
+                    rects,
+                    cells,
+                    framings,
+                } = page;
+                match cli.format {
+                    OutputFormat::Pdf => {
+                        let links: Vec<PageLink> = batch_inputs
+                            .iter()
+                            .zip(&rects)
+                            .filter(|(_, rect)| rect.w > 0)
+                            .map(|(input, &rect)| PageLink {
+                                // 镜像输出时链接区域随页面翻转
+                                rect: match cli.mirror {
+                                    true => Rect {
+                                        x: canvas.width() - rect.x - rect.w,
+                                        ..rect
+                                    },
+                                    false => rect,
+                                },
+                                uri: link_uri(input, cli.pdf_link.as_deref()),
+                            })
+                            .collect();
+                        book.add_page(&canvas, config.ppc, &page_names[i], &links);
+                    }
+                    format => {
+                        let output_paths = &output_files[i];
+                        let output_path = &output_paths[0];
+                        save_atomic(output_path, overwrite, |path| {
+                            match (&cmyk, &output) {
+                                (Some(cmyk), _) => {
+                                    let tiff = colorspace::encode_cmyk_tiff(
+                                        cmyk,
+                                        config.ppc * 2.54,
+                                        cmyk_profile.as_deref(),
+                                    )?;
+                                    fs::write(path, tiff).context(IoSnafu)
+                                }
+                                (None, Some(output)) => save_page(
+                                    output,
+                                    &canvas,
+                                    path,
+                                    format,
+                                    cli.quality,
+                                    cli.colorspace,
+                                ),
+                                (None, None) => save_image(&canvas, path, format, cli.quality),
+                            }?;
+                            match (config.color_profile, cli.colorspace) {
+                                (Some(profile), Colorspace::Rgb) => {
+                                    embed_profile(path, format, profile)
+                                }
+                                _ => Ok(()),
+                            }
+                        })?;
+                        // 其余份数直接复制已输出的文件
+                        for copy_path in &output_paths[1..] {
+                            save_atomic(copy_path, overwrite, |path| {
+                                fs::copy(output_path, path).map(|_| ()).context(IoSnafu)
+                            })?;
+                        }
+                    }
+                }
+                let names: Vec<String> = batch_inputs
+                    .iter()
+                    .map(|input| {
+                        input
+                            .file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy()
+                            .into()
+                    })
+                    .collect();
+                if cli.export_layout {
+                    layout_file.push_page(
+                        &page_names[i],
+                        &pages[i],
+                        &batch_inputs,
+                        &rects,
+                        &cells,
+                        &framings,
+                    );
+                }
+                if cli.cut_path {
+                    // 切割外轮廓，镜像输出时随页面翻转
+                    let outlines: Vec<Rect> = rects
+                        .iter()
+                        .map(|&rect| match rect.w {
+                            0 => rect,
+                            _ => config.outer_rect(rect),
+                        })
+                        .map(|rect| match cli.mirror {
+                            true if rect.w > 0 => Rect {
+                                x: canvas.width() - rect.x - rect.w,
+                                ..rect
+                            },
+                            _ => rect,
+                        })
+                        .collect();
+                    let svg = cut_path::svg(
+                        canvas.dimensions(),
+                        config.ppc,
+                        &outlines,
+                        config.corner_radius_px,
+                    );
+                    let svg_path = format!("{}/{}.svg", output_dir, page_names[i]);
+                    save_atomic(&svg_path, overwrite, |path| {
+                        fs::write(path, &svg).context(IoSnafu)
+                    })?;
+                }
+                if cli.verify {
+                    verify_report.push_str(&verify::page_report(i, &names, &rects, config.ppc));
+                }
+                if cli.export_html {
+                    let file_name = match cli.format {
+                        OutputFormat::Pdf => {
+                            let page = n_index + copy_sequences[i][0] + 1;
+                            format!("{}#page={}", PDF_NAME, page)
+                        }
+                        format => format!("{}.{}", copy_names[i][0], format.extension()),
+                    };
+                    gallery_pages.push(GalleryPage {
+                        file_name,
+                        thumb: gallery::write_thumbnail(&output_dir, &page_names[i], &canvas)?,
+                        contents: names,
+                    });
+                }
+                n_written += 1;
+                let _ = tx.send(PBData::NextOutput);
+                checkpoint()?;
+            }
+        }
+        Ok(())
+    })();
+
+    if let OutputFormat::Pdf = cli.format {
+        // 按份数排列已完成的页面
+        let mut order: Vec<(usize, usize)> = copy_sequences[..n_written]
+            .iter()
+            .enumerate()
+            .flat_map(|(i, seqs)| seqs.iter().map(move |&seq| (seq, i)))
+            .collect();
+        order.sort_unstable();
+        let order: Vec<usize> = if cli.copies > 1 {
+            (0..n_index)
+                .chain(order.into_iter().map(|(_, i)| n_index + i))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let pdf_path = format!("{}/{}", output_dir, PDF_NAME);
+        save_atomic(&pdf_path, overwrite, |path| book.save(path, &order))?;
+    }
+    if cli.verify {
+        let verify_path = format!("{}/verify.txt", output_dir);
+        save_atomic(&verify_path, overwrite, |path| {
+            fs::write(path, &verify_report).context(IoSnafu)
+        })?;
+    }
+    if cli.numbers_csv {
+        let numbers_path = format!("{}/numbers.csv", output_dir);
+        let numbers = number::csv(&pages, &inputs)?;
+        save_atomic(&numbers_path, overwrite, |path| {
+            fs::write(path, &numbers).context(IoSnafu)
+        })?;
+    }
+    if cli.export_layout {
+        let layout_path = format!("{}/{}", output_dir, LAYOUT_NAME);
+        save_atomic(&layout_path, overwrite, |path| {
+            fs::write(path, layout_file.to_json()).context(IoSnafu)
+        })?;
+    }
+    if cli.export_html {
+        let html_path = format!("{}/index.html", output_dir);
+        save_atomic(&html_path, overwrite, |path| {
+            fs::write(path, gallery::render_html(&gallery_pages)).context(IoSnafu)
+        })?;
+    }
+    if result.is_ok() {
+        rendered.update(signatures, output_files);
+    }
+    let summary = match &result {
+        Ok(_) => Msg::Done,
+        Err(Error::Interrupted) => Msg::InterruptedAt {
+            written: n_written,
+            total: n_batch,
+        },
+        Err(_) => Msg::FailedAt {
+            written: n_written,
+            total: n_batch,
+        },
+    };
+    let _ = tx.send(PBData::Println(summary.to_string()));
+    let skipped = skipped.into_inner().unwrap_or_default();
+    if !skipped.is_empty() {
+        let mut report = Msg::Skipped {
+            placeholder: on_error == OnError::Placeholder,
+            count: skipped.len(),
+        }
+        .to_string();
+        for (path, reason) in &skipped {
+            let path = path.display().to_string();
+            let msg = Msg::SkippedFile {
+                path: &path,
+                reason,
+            };
+            report.push_str(&format!("\n  {}", msg));
+        }
+        let _ = tx.send(PBData::Warn(report));
+    }
+    if let Some(report) = low_dpi_report {
+        let _ = tx.send(PBData::Warn(report));
+    }
+    let _ = tx.send(PBData::Stop);
+    if let Some(handle) = handle {
+        let _ = handle.join();
+    }
+    result
 }
 
-fn process_with_pb() -> Result<(), Error> {
-    let cli = Cli::parse();
+/// 按命令行参数排版
+fn arrange(
+    cli: &Cli,
+    inputs: &[PathBuf],
+    order: Option<&[OrderEntry]>,
+) -> Result<Arrangement, Error> {
+    let manifest = match &cli.manifest {
+        Some(path) => Manifest::load(Path::new(path))?,
+        None => Manifest::default(),
+    };
+    let typesetter = Typesetter {
+        config: Config::from_options(&cli.layout_options())?,
+        pairs: cli.pairs,
+        impose: cli.impose,
+        duplex: cli.duplex,
+        skip_cells: 0,
+        fill_order: cli.fill_order,
+    };
+    let options = ArrangeOptions {
+        true_size: cli.true_size,
+        group_by: cli.group_by,
+        skip_cells: cli.skip_cells,
+        start: (cli.start_row, cli.start_col),
+    };
+    plan::arrange(typesetter, &manifest, &options, inputs, order)
+}
 
-    let inputs = scan_inputs(&cli.input)?;
-    let config = Config::from_cli_default(&cli);
-    // 准备输出
-    let output_dir = cli.output.unwrap_or("output".to_string());
-    let _ = fs::remove_dir_all(&output_dir);
-    fs::create_dir_all(&output_dir).context(IoSnafu)?;
-    // 初始化进度条功能
-    let n_input = inputs.len() as u64;
-    let n_batch = (n_input as f64 / 12 as f64).ceil() as u64;
-    let (handle, tx) = init_pb_thread();
-    let _ = tx.send(PBData::NewOutput(n_batch));
+/// 按排版结果文件重新绘制并输出全部页面
+fn render_manifest(cli: &Cli, manifest: &Path) -> Result<(), Error> {
+    let layout = LayoutFile::load(manifest)?;
+    let inputs: Vec<PathBuf> = layout
+        .pages
+        .iter()
+        .flat_map(|page| page.images.iter().map(|image| image.path.clone()))
+        .collect();
+    let output_dir = cli.output.clone().unwrap_or("output".to_string());
+    prepare_output_dir(&output_dir, cli, &inputs)?;
+    // 单元格样式、边框等装饰与说明文字沿用命令行参数
+    let config = Config::from_options(&cli.layout_options())?;
+    let captions = load_captions(cli)?;
+    let font = match captions {
+        Some(_) => Font::load(cli.font.as_deref().map(Path::new))?,
+        None => None,
+    };
+    ensure!(
+        captions.is_none() || font.is_some(),
+        InputSnafu {
+            reason: Msg::NoFont.to_string(),
+        }
+    );
 
-    // 分批绘制
-    let batch_size = (config.n_h * config.n_v) as usize;
-    let batch_inputs_iter = BatchIter::new(inputs.into_iter(), batch_size);
-    for (i, batch_inputs) in batch_inputs_iter.enumerate() {
-        let n = batch_inputs.len() as u64;
-        let _ = tx.send(PBData::NewRead(n));
-        let _ = tx.send(PBData::NewProcess(n));
-        let _ = tx.send(PBData::NewComp(n));
-        let _ = tx.send(PBData::SetRead(0));
-        let _ = tx.send(PBData::SetProcess(0));
-        let _ = tx.send(PBData::SetComp(0));
-
-        let images = load_images(&batch_inputs, tx.clone())?;
-        let canvas = draw_canvas(&images, &config, tx.clone())?;
-        let output_path = format!("{}/output_{}.png", output_dir, i);
-        canvas.save(output_path).context(ImageSnafu)?;
-        let _ = tx.send(PBData::NextOutput);
-    }
-
-    let _ = tx.send(PBData::Println("Done!".to_string()));
+    let (handle, tx) = init_pb_thread(cli.progress_mode(), cli.quiet);
+    let _ = tx.send(PBData::NewTotal(inputs.len() as u64));
+    let _ = tx.send(PBData::NewOutput(layout.pages.len() as u64));
+    for warning in &config.warnings {
+        let _ = tx.send(PBData::Warn(warning.to_string()));
+    }
+    let mut book = PdfBook::new("output");
+    let result = (|| {
+        for page in &layout.pages {
+            let mut canvas = layout.render_page(page, &config, |path| {
+                checkpoint()?;
+                let _ = tx.send(PBData::NextTotal);
+                match open_image(path) {
+                    Ok(image) => Ok(Some(image)),
+                    Err(e) if cli.strict || cli.on_error == OnError::Abort => {
+                        Err(unreadable(path, e))
+                    }
+                    Err(_) => Ok(None),
+                }
+            })?;
+            if let (Some(captions), Some(font)) = (&captions, &font) {
+                for placed in page.images.iter().filter(|placed| placed.w > 0) {
+                    let Some(text) = captions.text(&placed.path) else {
+                        continue;
+                    };
+                    let rect = placed.rect();
+                    let cell = placed.cell_rect.unwrap_or(rect);
+                    let position = captions.position;
+                    caption::draw(&mut canvas, font, &text, &config, position, rect, cell);
+                }
+            }
+            match cli.format {
+                OutputFormat::Pdf => book.add_page(&canvas, layout.ppc, &page.name, &[]),
+                format => {
+                    let output_path =
+                        format!("{}/{}.{}", output_dir, page.name, format.extension());
+                    save_atomic(&output_path, cli.overwrite, |path| {
+                        save_image(&canvas, path, format, cli.quality)
+                    })?;
+                }
+            }
+            let _ = tx.send(PBData::NextOutput);
+        }
+        Ok(())
+    })();
+    if let (OutputFormat::Pdf, Ok(_)) = (cli.format, &result) {
+        let pdf_path = format!("{}/{}", output_dir, PDF_NAME);
+        save_atomic(&pdf_path, cli.overwrite, |path| book.save(path, &[]))?;
+    }
+    let summary = match &result {
+        Ok(_) => Msg::Done,
+        Err(Error::Interrupted) => Msg::Interrupted,
+        Err(_) => Msg::Failed,
+    };
+    let _ = tx.send(PBData::Println(summary.to_string()));
     let _ = tx.send(PBData::Stop);
     let _ = handle.join();
+    result
+}
+
+/// 读取说明文字设置，未启用时返回 None
+fn load_captions(cli: &Cli) -> Result<Option<Captions>, Error> {
+    Ok(match (&cli.captions, &cli.captions_file) {
+        (None, None) => None,
+        (source, file) => Some(Captions {
+            source: source.clone(),
+            file: match file {
+                Some(path) => Some(CaptionFile::load(Path::new(path))?),
+                None => None,
+            },
+            position: cli.caption_position,
+        }),
+    })
+}
+
+/// 证件照排版，照片按最大肤色区域裁切（找不到时按细节量），输出单页
+fn id_photo(cli: &Cli, photo: &Path, size: IdSize, count: Option<u32>) -> Result<(), Error> {
+    let sheet = IdSheet {
+        paper: cli.paper.unwrap_or(ID_PAPER),
+        size,
+        gap_cm: cli.margin.unwrap_or(0.2),
+        border_cm: cli.border.map_or(0.3, |border| border.max()),
+        ppc: cli.ppc(),
+    }
+    .oriented(cli.orientation);
+    sheet.check(count)?;
+    let output_dir = cli.output.clone().unwrap_or("output".to_string());
+    prepare_output_dir(&output_dir, cli, &[photo.to_path_buf()])?;
+
+    let image = open_image_as(photo, cli.color_profile).map_err(|e| unreadable(photo, e))?;
+    let filter = cli.filter.unwrap_or_default().filter_type();
+    let (mut canvas, rects) = sheet.render(&image, count, Crop::Skin, filter)?;
+    if cli.cut_marks {
+        draw::cut_marks(&mut canvas, &rects, sheet.ppc, 0);
+    }
+    save_sheets(cli, &output_dir, &[("id".to_string(), canvas)], sheet.ppc)
+}
+
+/// 海报拼接，图片分割为每页一张纸
+///
+/// - grid: 横向与纵向页数
+/// - overlap: 相邻页面的重叠宽度（单位：cm）
+fn poster(cli: &Cli, image: &Path, (cols, rows): (u32, u32), overlap: f64) -> Result<(), Error> {
+    let dimensions = image_dimensions(image).map_err(|e| unreadable(image, e))?;
+    let poster = Poster {
+        paper: cli.paper.unwrap_or(Paper::A4_LANDSCAPE),
+        cols,
+        rows,
+        overlap_cm: overlap,
+        border_cm: cli.border.map_or(1.0, |border| border.max()),
+        ppc: cli.ppc(),
+    }
+    .oriented(cli.orientation, dimensions);
+    let output_dir = cli.output.clone().unwrap_or("output".to_string());
+    prepare_output_dir(&output_dir, cli, &[image.to_path_buf()])?;
+
+    let source = open_image_as(image, cli.color_profile).map_err(|e| unreadable(image, e))?;
+    let filter = cli.filter.unwrap_or_default().filter_type();
+    let pages: Vec<(String, RgbaImage)> = poster
+        .render(&source, filter)?
+        .into_iter()
+        .enumerate()
+        .map(|(i, page)| (format!("output_{}", i), page))
+        .collect();
+    save_sheets(cli, &output_dir, &pages, poster.ppc)
+}
+
+/// 保存子命令生成的页面，PDF 输出时合并为一个文件
+///
+/// - pages: 每页的文件名（不含扩展名）与内容
+fn save_sheets(
+    cli: &Cli,
+    output_dir: &str,
+    pages: &[(String, RgbaImage)],
+    ppc: f64,
+) -> Result<(), Error> {
+    match cli.format {
+        OutputFormat::Pdf => {
+            let mut book = PdfBook::new("output");
+            book.set_profile(cli.color_profile);
+            for (name, canvas) in pages {
+                book.add_page(canvas, ppc, name, &[]);
+            }
+            let pdf_path = format!("{}/{}", output_dir, PDF_NAME);
+            save_atomic(&pdf_path, cli.overwrite, |path| book.save(path, &[]))?;
+        }
+        format => {
+            for (name, canvas) in pages {
+                checkpoint()?;
+                let output_path = format!("{}/{}.{}", output_dir, name, format.extension());
+                save_atomic(&output_path, cli.overwrite, |path| {
+                    save_image(canvas, path, format, cli.quality)?;
+                    match cli.color_profile {
+                        Some(profile) => embed_profile(path, format, profile),
+                        None => Ok(()),
+                    }
+                })?;
+            }
+        }
+    }
+    if !cli.quiet {
+        println!("{}", Msg::Done);
+    }
     Ok(())
 }
 
-fn init_pb_thread() -> (JoinHandle<()>, Sender<PBData>) {
+/// 读取二维码或条形码的内容设置，未启用时返回 None
+///
+/// - option: 启用该编码的命令行参数，用于错误提示
+fn code_values(
+    source: Option<CodeSource>,
+    csv: Option<&str>,
+    option: &str,
+) -> Result<Option<CodeValues>, Error> {
+    let Some(source) = source else {
+        return Ok(None);
+    };
+    ensure!(
+        source != CodeSource::FromCsv || csv.is_some(),
+        InputSnafu {
+            reason: Msg::CodeNeedsCsv(option).to_string(),
+        }
+    );
+    Ok(Some(CodeValues {
+        source,
+        file: match csv {
+            Some(path) => Some(CaptionFile::load(Path::new(path))?),
+            None => None,
+        },
+    }))
+}
+
+/// 准备输出目录
+///
+/// 目录不存在时创建；非空时除非指定 --overwrite 或 --clean，否则拒绝输出。
+/// --clean 仅在目录中不含输入文件时清空目录。
+fn prepare_output_dir(output_dir: &str, cli: &Cli, inputs: &[PathBuf]) -> Result<(), Error> {
+    let dir = Path::new(output_dir);
+    let non_empty = fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_some());
+    if non_empty && cli.clean {
+        let canonical = fs::canonicalize(dir).context(IoSnafu)?;
+        let contains_input = inputs
+            .iter()
+            .any(|input| fs::canonicalize(input).is_ok_and(|input| input.starts_with(&canonical)));
+        ensure!(
+            !contains_input,
+            InputSnafu {
+                reason: Msg::OutputHasInputs(&dir.display().to_string()).to_string(),
+            }
+        );
+        fs::remove_dir_all(dir).context(IoSnafu)?;
+    } else if non_empty && !cli.overwrite {
+        return Err(Error::Input {
+            reason: Msg::OutputNotEmpty(&dir.display().to_string()).to_string(),
+        });
+    }
+    fs::create_dir_all(dir).context(IoSnafu)
+}
+
+/// 输出 JSON 进度，每个事件一行写入标准错误
+///
+/// 格式为 `{"stage": 阶段, "current": 当前, "total": 总数, "file": 文件名}`，
+/// 消息为 `{"stage": "message", "message": 内容}`
+fn json_progress_loop(rx: Receiver<PBData>) {
+    // total/output/read/process/compose 各阶段的 (当前, 总数)
+    let mut stages = [(0u64, 0u64); 5];
+    const NAMES: [&str; 5] = ["total", "output", "read", "process", "compose"];
+    let emit = |value: serde_json::Value| eprintln!("{}", value);
+    let progress = |stages: &[(u64, u64); 5], i: usize, file: Option<&str>| {
+        emit(json!({
+            "stage": NAMES[i],
+            "current": stages[i].0,
+            "total": stages[i].1,
+            "file": file,
+        }))
+    };
+    while let Ok(data) = rx.recv() {
+        let (i, file) = match data {
+            PBData::Stop => {
+                emit(json!({ "stage": "done" }));
+                break;
+            }
+            PBData::Println(message) => {
+                emit(json!({ "stage": "message", "message": message }));
+                continue;
+            }
+            PBData::Warn(message) => {
+                emit(json!({ "stage": "warning", "message": message }));
+                continue;
+            }
+            PBData::Paused(paused) => {
+                emit(json!({ "stage": "paused", "paused": paused }));
+                continue;
+            }
+            PBData::NewTotal(n) => {
+                stages[0] = (0, n);
+                (0, None)
+            }
+            PBData::NextTotal => {
+                stages[0].0 += 1;
+                (0, None)
+            }
+            PBData::NewOutput(n) => {
+                stages[1] = (0, n);
+                (1, None)
+            }
+            PBData::NextOutput => {
+                stages[1].0 += 1;
+                (1, None)
+            }
+            PBData::NewRead(n) => {
+                stages[2].1 = n;
+                continue;
+            }
+            PBData::NextRead(file) => {
+                stages[2].0 += 1;
+                (2, file)
+            }
+            PBData::SetRead(n) => {
+                stages[2].0 = n;
+                continue;
+            }
+            PBData::NewProcess(n) => {
+                stages[3].1 = n;
+                continue;
+            }
+            PBData::NextProcess => {
+                stages[3].0 += 1;
+                (3, None)
+            }
+            PBData::SetProcess(n) => {
+                stages[3].0 = n;
+                continue;
+            }
+            PBData::NewComp(n) => {
+                stages[4].1 = n;
+                continue;
+            }
+            PBData::NextComp => {
+                stages[4].0 += 1;
+                (4, None)
+            }
+            PBData::SetComp(n) => {
+                stages[4].0 = n;
+                continue;
+            }
+        };
+        progress(&stages, i, file.as_deref());
+    }
+}
+
+/// 不显示进度，只逐行输出提示与警告
+///
+/// - quiet: 是否同时忽略提示信息
+fn plain_progress_loop(rx: Receiver<PBData>, quiet: bool) {
+    while let Ok(data) = rx.recv() {
+        match data {
+            PBData::Stop => break,
+            PBData::Println(message) if !quiet => println!("{message}"),
+            PBData::Warn(message) => eprintln!("{message}"),
+            _ => {}
+        }
+    }
+}
+
+/// 启动进度显示线程
+///
+/// - mode: 进度显示方式
+/// - quiet: 安静模式，不显示进度与提示信息
+fn init_pb_thread(mode: ProgressMode, quiet: bool) -> (JoinHandle<()>, Sender<PBData>) {
     let (tx, rx) = mpsc::channel::<PBData>();
+    match mode {
+        _ if quiet => return (thread::spawn(move || plain_progress_loop(rx, true)), tx),
+        ProgressMode::Json => return (thread::spawn(move || json_progress_loop(rx)), tx),
+        ProgressMode::None => return (thread::spawn(move || plain_progress_loop(rx, false)), tx),
+        ProgressMode::Bar => {}
+    }
     let handle = thread::spawn(move || {
         let m = MultiProgress::new();
         let sty = ProgressStyle::with_template(
@@ -278,19 +1974,28 @@ fn init_pb_thread() -> (JoinHandle<()>, Sender<PBData>) {
         )
         .unwrap()
         .progress_chars("##-");
+        let sty_total = ProgressStyle::with_template(&format!(
+            "[{{elapsed_precise}}] {{bar:40.green/blue}} {{pos:>7}}/{{len:7}} {} {{eta_precise}} {{msg}}",
+            Msg::Remaining
+        ))
+        .unwrap()
+        .progress_chars("##-");
 
+        let pb_total = m.add(ProgressBar::new(0));
+        pb_total.set_style(sty_total);
+        pb_total.set_message(Msg::Total.to_string());
         let pb_output = m.add(ProgressBar::new(0));
         pb_output.set_style(sty.clone());
-        pb_output.set_message("输出");
+        pb_output.set_message(Msg::Output.to_string());
         let pb_read = m.add(ProgressBar::new(0));
         pb_read.set_style(sty.clone());
-        pb_read.set_message("读取图片");
+        pb_read.set_message(Msg::Reading.to_string());
         let pb_process = m.add(ProgressBar::new(0));
         pb_process.set_style(sty.clone());
-        pb_process.set_message("处理图片");
+        pb_process.set_message(Msg::Processing.to_string());
         let pb_comp = m.add(ProgressBar::new(0));
         pb_comp.set_style(sty);
-        pb_comp.set_message("排版图片");
+        pb_comp.set_message(Msg::Composing.to_string());
 
         // event loop
         loop {
@@ -299,8 +2004,16 @@ fn init_pb_thread() -> (JoinHandle<()>, Sender<PBData>) {
                     m.remove(&pb_read);
                     m.remove(&pb_process);
                     m.remove(&pb_comp);
+                    pb_total.finish();
                     break;
                 }
+                Ok(PBData::NewTotal(n)) => {
+                    pb_total.set_length(n);
+                    pb_total.reset();
+                }
+                Ok(PBData::NextTotal) => pb_total.inc(1),
+                Ok(PBData::Paused(true)) => pb_total.set_message(Msg::Paused.to_string()),
+                Ok(PBData::Paused(false)) => pb_total.set_message(Msg::Total.to_string()),
                 Ok(PBData::NewOutput(n)) => {
                     pb_output.set_length(n);
                     pb_output.reset();
@@ -318,15 +2031,15 @@ fn init_pb_thread() -> (JoinHandle<()>, Sender<PBData>) {
                 Ok(PBData::NextRead(msg)) => {
                     pb_read.inc(1);
                     if pb_read.position() == pb_read.length().unwrap_or(0) {
-                        pb_read.finish_with_message("读取完成");
+                        pb_read.finish_with_message(Msg::ReadDone.to_string());
                         continue;
                     };
-                    if let Some(msg) = msg {
-                        pb_read.set_message(msg);
+                    if let Some(name) = msg {
+                        pb_read.set_message(Msg::ReadingFile(&name).to_string());
                     };
                 }
                 Ok(PBData::SetRead(n)) => pb_read.set_position(n),
-                Ok(PBData::Println(s)) => {
+                Ok(PBData::Println(s)) | Ok(PBData::Warn(s)) => {
                     let _ = m.println(s);
                 }
                 Ok(PBData::NextProcess) => pb_process.inc(1),
@@ -342,34 +2055,74 @@ fn init_pb_thread() -> (JoinHandle<()>, Sender<PBData>) {
 }
 
 fn main() -> Result<(), Error> {
-    if let Err(e) = process_with_pb() {
-        eprintln!("{e}");
+    install_interrupt_handler();
+    match process_with_pb() {
+        Err(Error::Interrupted) => std::process::exit(EXIT_INTERRUPTED),
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(EXIT_FAILURE);
+        }
+        Ok(_) => {}
     };
 
     Ok(())
 }
 
-/// 通过索引获取当前行列号
-///
-/// 先行后列
-///
-/// - nh: 横向数量
-/// - idx: 当前索引
-fn row_and_col_from_index(nh: usize, idx: usize) -> (u32, u32) {
-    let row = (idx / nh) as u32;
-    let col = (idx % nh) as u32;
-
-    (row, col)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn test_row_and_col_from_index() {
-        assert!(row_and_col_from_index(4, 0) == (0, 0));
-        assert!(row_and_col_from_index(4, 3) == (0, 3));
-        assert!(row_and_col_from_index(4, 11) == (2, 3));
+    fn test_parse_size() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("4k"), Ok(4096));
+        assert_eq!(parse_size("1.5G"), Ok(3 << 29));
+        assert_eq!(parse_size("256MB"), Ok(256 << 20));
+        assert!(parse_size("0M").is_err());
+        assert!(parse_size("lots").is_err());
+    }
+
+    #[test]
+    fn test_merge_config() {
+        let args = [
+            "image-typesetting-tool",
+            "--profile",
+            "big",
+            "poster",
+            "big.jpg",
+        ];
+        let matches = Cli::command().try_get_matches_from(args).unwrap();
+        let cli = Cli::from_arg_matches(&matches).unwrap();
+        let table: toml::Table = "nh = 4\nborder = 1.5\n".parse().unwrap();
+        let merged = merge_config(cli, &matches, table, Path::new("imgtypeset.toml")).unwrap();
+        assert!(matches!(merged.command, Some(Command::Poster { .. })));
+        assert_eq!(merged.profile.as_deref(), Some("big"));
+        assert_eq!(merged.nh, Some(4));
+    }
+
+    #[test]
+    fn test_prepare_output_dir() {
+        let dir = std::env::temp_dir().join("image-typesetting-tool-output-dir");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("output_0.png"), b"").unwrap();
+        let output_dir = dir.to_string_lossy();
+        let cli = Cli::parse_from(["image-typesetting-tool"]);
+        assert!(matches!(
+            prepare_output_dir(&output_dir, &cli, &[]),
+            Err(Error::Input { .. })
+        ));
+        let cli = Cli::parse_from(["image-typesetting-tool", "--overwrite"]);
+        assert!(prepare_output_dir(&output_dir, &cli, &[]).is_ok());
+        let cli = Cli::parse_from(["image-typesetting-tool", "--clean"]);
+        assert!(prepare_output_dir(&output_dir, &cli, &[]).is_ok());
+        assert!(!dir.join("output_0.png").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_parse_offset() {
+        assert_eq!(parse_offset("0.5,-0.3"), Ok((0.5, -0.3)));
+        assert!(parse_offset("1").is_err());
     }
 }
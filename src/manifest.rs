@@ -0,0 +1,204 @@
+use snafu::prelude::*;
+use std::{collections::HashMap, fs, path::Path};
+use toml::Value;
+
+use crate::{i18n::Msg, Error, InputSnafu, IoSnafu};
+
+/// 单张图片的尺寸覆盖
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scale {
+    /// 相对默认目标高度的缩放比例
+    Factor(f64),
+    /// 指定打印高度（单位：cm）
+    Height(f64),
+    /// 指定打印宽度（单位：cm），按图片宽高比换算为高度
+    Width(f64),
+}
+
+impl Scale {
+    /// 解析尺寸描述，支持 `150%`、`1.5x`、`10cm`
+    pub fn parse(s: &str) -> Option<Scale> {
+        let s = s.trim().to_ascii_lowercase();
+        let (value, make): (&str, fn(f64) -> Scale) = if let Some(v) = s.strip_suffix('%') {
+            (v, |v| Scale::Factor(v / 100.0))
+        } else if let Some(v) = s.strip_suffix('x') {
+            (v, Scale::Factor)
+        } else if let Some(v) = s.strip_suffix("cm") {
+            (v, Scale::Height)
+        } else {
+            return None;
+        };
+        match value.trim().parse::<f64>() {
+            Ok(v) if v > 0.0 && v.is_finite() => Some(make(v)),
+            _ => None,
+        }
+    }
+
+    /// 计算覆盖后的目标高度 像素
+    ///
+    /// - default_h_px: 默认目标高度 像素
+    /// - ppc: 每厘米像素数
+    /// - dimensions: 图片旋转后的尺寸，无法读取时指定宽度的覆盖使用默认目标高度
+    pub fn target_h_px(&self, default_h_px: u32, ppc: f64, dimensions: Option<(u32, u32)>) -> u32 {
+        match *self {
+            Scale::Factor(factor) => (default_h_px as f64 * factor).round() as u32,
+            Scale::Height(cm) => (cm * ppc).round() as u32,
+            Scale::Width(cm) => match dimensions {
+                Some((w, h)) => (cm * ppc * h as f64 / w.max(1) as f64).round() as u32,
+                None => default_h_px,
+            },
+        }
+    }
+
+    /// 解析 TOML 中的尺寸覆盖
+    ///
+    /// 字符串同 [`Scale::parse`]，表中 `scale`、`height`、`width` 三选一，
+    /// `scale` 可为数字或字符串，`height`、`width` 单位为 cm
+    fn from_toml(value: &Value) -> Option<Scale> {
+        let number = |value: &Value| match value {
+            Value::Float(v) => Some(*v),
+            Value::Integer(v) => Some(*v as f64),
+            _ => None,
+        };
+        let scale = match value {
+            Value::String(s) => return Scale::parse(s),
+            Value::Table(table) if table.len() == 1 => match table.iter().next()? {
+                (key, Value::String(s)) if key == "scale" => return Scale::parse(s),
+                (key, value) if key == "scale" => Scale::Factor(number(value)?),
+                (key, value) if key == "height" => Scale::Height(number(value)?),
+                (key, value) if key == "width" => Scale::Width(number(value)?),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let (Scale::Factor(v) | Scale::Height(v) | Scale::Width(v)) = scale;
+        (v > 0.0 && v.is_finite()).then_some(scale)
+    }
+}
+
+/// 图片清单
+///
+/// 每行一个 `文件名 尺寸`，`#` 开头为注释；
+/// 扩展名为 .toml 时每个键为文件名，值为尺寸字符串或 `{ height = 10 }` 形式的表
+#[derive(Debug, Default)]
+pub struct Manifest {
+    scales: HashMap<String, Scale>,
+}
+
+impl Manifest {
+    pub fn load(path: &Path) -> Result<Manifest, Error> {
+        let text = fs::read_to_string(path).context(IoSnafu)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => Manifest::parse_toml(&text),
+            _ => Manifest::parse(&text),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Manifest, Error> {
+        let mut scales = HashMap::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parsed = line
+                .rsplit_once(char::is_whitespace)
+                .and_then(|(name, size)| Some((name.trim(), Scale::parse(size)?)));
+            match parsed {
+                Some((name, scale)) => {
+                    scales.insert(name.to_string(), scale);
+                }
+                None => {
+                    return Err(Error::Input {
+                        reason: Msg::ManifestLineInvalid {
+                            line: i + 1,
+                            text: line,
+                        }
+                        .to_string(),
+                    })
+                }
+            }
+        }
+        Ok(Manifest { scales })
+    }
+
+    pub fn parse_toml(text: &str) -> Result<Manifest, Error> {
+        let table = text.parse::<toml::Table>().map_err(|e| Error::Input {
+            reason: Msg::ManifestInvalid(e.to_string()).to_string(),
+        })?;
+        let mut scales = HashMap::new();
+        for (name, value) in &table {
+            let scale = Scale::from_toml(value).context(InputSnafu {
+                reason: Msg::ManifestSizeInvalid {
+                    name,
+                    value: &value.to_string(),
+                }
+                .to_string(),
+            })?;
+            scales.insert(name.clone(), scale);
+        }
+        Ok(Manifest { scales })
+    }
+
+    /// 按文件名查找尺寸覆盖
+    pub fn scale_for(&self, path: &Path) -> Option<Scale> {
+        let name = path.file_name()?.to_str()?;
+        self.scales.get(name).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_scale() {
+        assert_eq!(Scale::parse("150%"), Some(Scale::Factor(1.5)));
+        assert_eq!(Scale::parse("2x"), Some(Scale::Factor(2.0)));
+        assert_eq!(Scale::parse("10cm"), Some(Scale::Height(10.0)));
+        assert_eq!(Scale::parse("10"), None);
+        assert_eq!(Scale::parse("-1x"), None);
+    }
+
+    #[test]
+    fn test_parse_manifest() {
+        let manifest =
+            Manifest::parse("# 合影放大\ngroup photo.jpg 10cm\n\nsmall.png 50%\n").unwrap();
+        assert_eq!(
+            manifest.scale_for(Path::new("in/group photo.jpg")),
+            Some(Scale::Height(10.0))
+        );
+        assert_eq!(
+            manifest.scale_for(Path::new("small.png")),
+            Some(Scale::Factor(0.5))
+        );
+        assert_eq!(manifest.scale_for(Path::new("other.png")), None);
+        assert!(Manifest::parse("missing-size.jpg").is_err());
+    }
+
+    #[test]
+    fn test_parse_toml_manifest() {
+        let manifest = Manifest::parse_toml(
+            "\"group photo.jpg\" = { width = 15 }\n\"a.jpg\" = \"2x\"\n\"b.jpg\" = { scale = 1.5 }\n\"c.jpg\" = { height = 8.5 }\n",
+        )
+        .unwrap();
+        let scale = manifest.scale_for(Path::new("group photo.jpg")).unwrap();
+        assert_eq!(scale, Scale::Width(15.0));
+        assert_eq!(scale.target_h_px(500, 100.0, Some((3000, 2000))), 1000);
+        assert_eq!(scale.target_h_px(500, 100.0, None), 500);
+        assert_eq!(
+            manifest.scale_for(Path::new("a.jpg")),
+            Some(Scale::Factor(2.0))
+        );
+        assert_eq!(
+            manifest.scale_for(Path::new("b.jpg")),
+            Some(Scale::Factor(1.5))
+        );
+        assert_eq!(
+            manifest.scale_for(Path::new("c.jpg")),
+            Some(Scale::Height(8.5))
+        );
+        assert!(Manifest::parse_toml("\"a.jpg\" = { width = -1 }").is_err());
+        assert!(Manifest::parse_toml("\"a.jpg\" = { width = 1, height = 2 }").is_err());
+    }
+}
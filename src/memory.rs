@@ -0,0 +1,114 @@
+//! 内存中排版，不读写文件，用于浏览器等没有文件系统的环境
+
+use std::io::Cursor;
+
+use image::{codecs::jpeg::JpegEncoder, ImageFormat};
+use serde::Deserialize;
+use snafu::prelude::*;
+
+use crate::{flatten, i18n::Msg, Config, Error, ImageSnafu, LayoutOptions, Typesetter};
+
+/// 页面编码格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PageFormat {
+    #[default]
+    Png,
+    Jpeg,
+}
+
+/// 内存排版参数，排版相关的键与 [`LayoutOptions`] 相同
+#[derive(Debug, Clone, Deserialize)]
+pub struct MemoryOptions {
+    #[serde(flatten)]
+    pub layout: LayoutOptions,
+    /// 页面编码格式 默认 png
+    #[serde(default)]
+    pub format: PageFormat,
+    /// JPEG 质量 默认 90
+    #[serde(default = "default_quality")]
+    pub quality: u8,
+}
+
+fn default_quality() -> u8 {
+    90
+}
+
+impl MemoryOptions {
+    /// 解析 JSON 参数，空字符串使用默认值
+    pub fn from_json(json: &str) -> Result<MemoryOptions, Error> {
+        let json = if json.trim().is_empty() { "{}" } else { json };
+        serde_json::from_str(json).map_err(|e| Error::Config {
+            reason: e.to_string(),
+        })
+    }
+}
+
+/// 排版编码后的图片，返回每页编码后的图片
+///
+/// - images: 编码后的图片文件内容，格式按内容识别
+/// - options: JSON 参数，见 [`MemoryOptions`]
+pub fn typeset_bytes(images: &[&[u8]], options: &str) -> Result<Vec<Vec<u8>>, Error> {
+    let options = MemoryOptions::from_json(options)?;
+    let images = images
+        .iter()
+        .enumerate()
+        .map(|(i, bytes)| {
+            image::load_from_memory(bytes).map_err(|e| Error::Input {
+                reason: Msg::MemoryImageUnreadable {
+                    index: i + 1,
+                    reason: e.to_string(),
+                }
+                .to_string(),
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    let typesetter = Typesetter::new(Config::from_options(&options.layout)?);
+    typesetter
+        .render_pages(&images)?
+        .iter()
+        .map(|canvas| encode_page(&flatten(canvas), &options))
+        .collect()
+}
+
+/// 按参数编码单页
+pub(crate) fn encode_page(
+    page: &image::RgbImage,
+    options: &MemoryOptions,
+) -> Result<Vec<u8>, Error> {
+    let mut bytes = Cursor::new(Vec::new());
+    match options.format {
+        PageFormat::Png => page.write_to(&mut bytes, ImageFormat::Png),
+        PageFormat::Jpeg => page.write_with_encoder(JpegEncoder::new_with_quality(
+            &mut bytes,
+            options.quality.clamp(1, 100),
+        )),
+    }
+    .context(ImageSnafu)?;
+    Ok(bytes.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbImage;
+
+    #[test]
+    fn test_typeset_bytes() {
+        let mut png = Cursor::new(Vec::new());
+        RgbImage::new(40, 30)
+            .write_to(&mut png, ImageFormat::Png)
+            .unwrap();
+        let png = png.into_inner();
+        let images = vec![png.as_slice(); 3];
+        let options = r#"{ "ppi": 30, "nh": 2, "nv": 1, "format": "jpeg" }"#;
+        let pages = typeset_bytes(&images, options).unwrap();
+        assert_eq!(pages.len(), 2);
+        let page = image::load_from_memory(&pages[0]).unwrap();
+        assert_eq!(image::guess_format(&pages[0]).unwrap(), ImageFormat::Jpeg);
+        assert!(page.width() > page.height());
+
+        assert!(typeset_bytes(&[b"not an image"], "").is_err());
+        assert!(MemoryOptions::from_json(r#"{ "nh": "two" }"#).is_err());
+    }
+}
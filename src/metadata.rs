@@ -0,0 +1,172 @@
+use std::{fs::File, io::BufReader, path::Path};
+
+use exif::{In, Tag, Value};
+
+/// 读取图片的 EXIF 信息，无法读取时返回 None
+fn read_exif(path: &Path) -> Option<exif::Exif> {
+    let file = File::open(path).ok()?;
+    exif::Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()
+}
+
+/// 读取 EXIF 拍摄时间，优先 DateTimeOriginal
+fn capture_datetime(exif: &exif::Exif) -> Option<exif::DateTime> {
+    [Tag::DateTimeOriginal, Tag::DateTimeDigitized, Tag::DateTime]
+        .iter()
+        .find_map(|&tag| match &exif.get_field(tag, In::PRIMARY)?.value {
+            Value::Ascii(values) => exif::DateTime::from_ascii(values.first()?).ok(),
+            _ => None,
+        })
+}
+
+/// 读取拍摄日期，格式 `YYYY-MM-DD`
+pub fn capture_date(path: &Path) -> Option<String> {
+    let datetime = capture_datetime(&read_exif(path)?)?;
+    Some(format!(
+        "{:04}-{:02}-{:02}",
+        datetime.year, datetime.month, datetime.day
+    ))
+}
+
+/// 读取拍摄时间，格式 `YYYY-MM-DD HH:MM:SS`，可直接按字符串排序
+pub fn capture_timestamp(path: &Path) -> Option<String> {
+    let datetime = capture_datetime(&read_exif(path)?)?;
+    Some(format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        datetime.year,
+        datetime.month,
+        datetime.day,
+        datetime.hour,
+        datetime.minute,
+        datetime.second
+    ))
+}
+
+/// 读取 EXIF 方向（1~8），没有或无效时为 1
+pub fn orientation(path: &Path) -> u32 {
+    read_exif(path)
+        .and_then(|exif| {
+            exif.get_field(Tag::Orientation, In::PRIMARY)?
+                .value
+                .get_uint(0)
+        })
+        .filter(|orientation| (1..=8).contains(orientation))
+        .unwrap_or(1)
+}
+
+/// 可用于说明文字的 EXIF 字段
+pub const EXIF_FIELDS: &[&str] = &[
+    "datetime", "date", "camera", "lens", "exposure", "shutter", "aperture", "iso", "focal",
+];
+
+/// 图片的 EXIF 信息
+pub struct PhotoInfo(exif::Exif);
+
+impl PhotoInfo {
+    /// 读取图片的 EXIF 信息，无法读取时返回 None
+    pub fn open(path: &Path) -> Option<PhotoInfo> {
+        read_exif(path).map(PhotoInfo)
+    }
+
+    fn ascii(&self, tag: Tag) -> Option<String> {
+        match &self.0.get_field(tag, In::PRIMARY)?.value {
+            Value::Ascii(values) => {
+                let text = String::from_utf8_lossy(values.first()?);
+                let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+                (!text.is_empty()).then(|| text.to_string())
+            }
+            _ => None,
+        }
+    }
+
+    fn rational(&self, tag: Tag) -> Option<f64> {
+        match &self.0.get_field(tag, In::PRIMARY)?.value {
+            Value::Rational(values) => Some(values.first()?.to_f64()).filter(|v| v.is_finite()),
+            _ => None,
+        }
+    }
+
+    /// 字段的显示文字，字段名见 [`EXIF_FIELDS`]，图片没有该字段时返回 None
+    ///
+    /// 如 `datetime` 为 `2024-05-01 10:30:00`，`exposure` 为 `1/250s f/2.8 ISO 100`
+    pub fn field(&self, name: &str) -> Option<String> {
+        let trim = |v: f64| format!("{:.1}", v).trim_end_matches(".0").to_string();
+        match name {
+            "datetime" | "date" => {
+                let dt = capture_datetime(&self.0)?;
+                let date = format!("{:04}-{:02}-{:02}", dt.year, dt.month, dt.day);
+                Some(match name {
+                    "date" => date,
+                    _ => format!("{} {:02}:{:02}:{:02}", date, dt.hour, dt.minute, dt.second),
+                })
+            }
+            "camera" => {
+                let model = self.ascii(Tag::Model);
+                match (self.ascii(Tag::Make), model) {
+                    // 型号通常已包含厂商名
+                    (Some(make), Some(model)) if !model.starts_with(&make) => {
+                        Some(format!("{} {}", make, model))
+                    }
+                    (make, model) => model.or(make),
+                }
+            }
+            "lens" => self.ascii(Tag::LensModel),
+            "shutter" => {
+                let t = self.rational(Tag::ExposureTime).filter(|&t| t > 0.0)?;
+                Some(match t < 1.0 {
+                    true => format!("1/{}s", (1.0 / t).round()),
+                    false => format!("{}s", trim(t)),
+                })
+            }
+            "aperture" => Some(format!("f/{}", trim(self.rational(Tag::FNumber)?))),
+            "iso" => {
+                let field = self
+                    .0
+                    .get_field(Tag::PhotographicSensitivity, In::PRIMARY)?;
+                Some(format!("ISO {}", field.value.get_uint(0)?))
+            }
+            "focal" => Some(format!("{}mm", trim(self.rational(Tag::FocalLength)?))),
+            "exposure" => {
+                let parts: Vec<String> = ["shutter", "aperture", "iso"]
+                    .iter()
+                    .filter_map(|name| self.field(name))
+                    .collect();
+                (!parts.is_empty()).then(|| parts.join(" "))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// 计算一组日期的范围，如 `2024-05-01` 或 `2024-05-01_2024-05-03`
+///
+/// 没有任何日期时返回 None
+pub fn date_range<'a>(dates: impl IntoIterator<Item = &'a str>) -> Option<String> {
+    let mut dates: Vec<&str> = dates.into_iter().collect();
+    dates.sort_unstable();
+    let (first, last) = (dates.first()?, dates.last()?);
+    if first == last {
+        Some(first.to_string())
+    } else {
+        Some(format!("{}_{}", first, last))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date_range() {
+        assert_eq!(date_range([]), None);
+        assert_eq!(
+            date_range(["2024-05-01", "2024-05-01"]),
+            Some("2024-05-01".to_string())
+        );
+        assert_eq!(
+            date_range(["2024-05-03", "2024-05-01", "2024-05-02"]),
+            Some("2024-05-01_2024-05-03".to_string())
+        );
+    }
+}
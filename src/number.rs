@@ -0,0 +1,143 @@
+//! 图片编号
+//!
+//! 按页序与页内排列顺序为每张图片编号（从 1 开始），印在图片一角，
+//! 客户可按编号挑选加印
+
+use image::{imageops, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::{
+    i18n::Msg,
+    layout::{Placement, Rect},
+    text::Font,
+    Config, Error,
+};
+
+/// 编号文字颜色
+const NUMBER_COLOR: Rgba<u8> = Rgba([30, 30, 30, 255]);
+
+/// 编号底色，保证在深色图片上也能看清
+const NUMBER_BACKGROUND: Rgba<u8> = Rgba([255, 255, 255, 220]);
+
+/// 编号字号（单位：pt）
+const NUMBER_SIZE: f64 = 8.0;
+
+/// 编号所在的角
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+/// 每页第一张图片的编号
+pub fn first_numbers(pages: &[Vec<Placement>]) -> Vec<usize> {
+    pages
+        .iter()
+        .scan(1, |next, placements| {
+            let first = *next;
+            *next += placements.len();
+            Some(first)
+        })
+        .collect()
+}
+
+/// 生成编号与文件名对照表，CSV 格式
+pub fn csv(pages: &[Vec<Placement>], inputs: &[PathBuf]) -> Result<String, Error> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let config_error = |e: csv::Error| Error::Config {
+        reason: Msg::NumberTableFailed(e.to_string()).to_string(),
+    };
+    writer
+        .write_record(["number", "file", "page"])
+        .map_err(config_error)?;
+    for (page, (placements, first)) in pages.iter().zip(first_numbers(pages)).enumerate() {
+        for (i, placement) in placements.iter().enumerate() {
+            let name = inputs[placement.index].to_string_lossy();
+            writer
+                .write_record([
+                    (first + i).to_string(),
+                    name.into_owned(),
+                    (page + 1).to_string(),
+                ])
+                .map_err(config_error)?;
+        }
+    }
+    let data = writer.into_inner().map_err(|e| Error::Config {
+        reason: e.to_string(),
+    })?;
+    Ok(String::from_utf8_lossy(&data).into_owned())
+}
+
+/// 在图片一角印上编号
+///
+/// rect 为图片的实际绘制区域，编号超出图片宽度时缩小
+pub fn stamp(
+    canvas: &mut RgbaImage,
+    font: &Font,
+    config: &Config,
+    number: usize,
+    rect: Rect,
+    corner: Corner,
+) {
+    let text = number.to_string();
+    let size = font.fit_size(
+        &text,
+        (NUMBER_SIZE / 72.0 * 2.54 * config.ppc) as f32,
+        rect.w / 2,
+    );
+    if size < 1.0 || rect.w == 0 || rect.h == 0 {
+        return;
+    }
+    let (text_w, _) = font.measure(&text, size);
+    let pad = (size / 3.0).ceil() as u32;
+    let (box_w, box_h) = (text_w + 2 * pad, (size.ceil() as u32 + pad).min(rect.h));
+    let x = match corner {
+        Corner::TopLeft | Corner::BottomLeft => rect.x,
+        Corner::TopRight | Corner::BottomRight => rect.x + rect.w.saturating_sub(box_w),
+    };
+    let y = match corner {
+        Corner::TopLeft | Corner::TopRight => rect.y,
+        Corner::BottomLeft | Corner::BottomRight => rect.y + rect.h.saturating_sub(box_h),
+    };
+    let background = RgbaImage::from_pixel(box_w, box_h, NUMBER_BACKGROUND);
+    imageops::overlay(canvas, &background, x as i64, y as i64);
+    font.draw(
+        canvas,
+        &text,
+        (x + pad) as i32,
+        (y + pad / 2) as i32,
+        size,
+        NUMBER_COLOR,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::Span;
+
+    #[test]
+    fn test_numbers() {
+        let placement = |index| Placement {
+            index,
+            row: 0,
+            col: index as u32,
+            span: Span::ONE,
+        };
+        let pages = vec![vec![placement(0), placement(1)], vec![placement(2)]];
+        assert_eq!(first_numbers(&pages), [1, 3]);
+        let inputs: Vec<PathBuf> = ["a.jpg", "b.jpg", "c,d.jpg"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        assert_eq!(
+            csv(&pages, &inputs).unwrap(),
+            "number,file,page\n1,a.jpg,1\n2,b.jpg,1\n3,\"c,d.jpg\",2\n"
+        );
+    }
+}
@@ -0,0 +1,204 @@
+//! 输出文件
+//!
+//! 各输出格式的保存、色彩配置文件嵌入及原子写入
+
+use image::{
+    codecs::{jpeg::JpegEncoder, webp::WebPEncoder},
+    DynamicImage, ImageFormat, RgbaImage,
+};
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+use std::{fs, io::BufWriter, path::Path};
+
+#[cfg(feature = "jxl")]
+use crate::jxl;
+use crate::{
+    colorspace::{self, Colorspace},
+    flatten,
+    i18n::Msg,
+    icc::{self, Profile},
+    Error, ImageSnafu, InputSnafu, IoSnafu,
+};
+
+/// 输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    Png,
+    /// 有损压缩，质量由 --quality 指定，透明背景合成为白色
+    Jpeg,
+    /// 无损压缩
+    Webp,
+    Tiff,
+    /// 所有页面合并为一个 PDF，带书签与图片链接
+    Pdf,
+    /// JPEG XL，需要 jxl 功能及 cjxl 工具
+    #[cfg(feature = "jxl")]
+    Jxl,
+}
+
+impl OutputFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Png => "png",
+            OutputFormat::Jpeg => "jpg",
+            OutputFormat::Webp => "webp",
+            OutputFormat::Tiff => "tiff",
+            OutputFormat::Pdf => "pdf",
+            #[cfg(feature = "jxl")]
+            OutputFormat::Jxl => "jxl",
+        }
+    }
+}
+
+/// 生成图片链接地址
+///
+/// - input: 源文件路径
+/// - template: 地址模板，为空时链接到源文件
+pub fn link_uri(input: &Path, template: Option<&str>) -> String {
+    let path = fs::canonicalize(input).unwrap_or_else(|_| input.to_path_buf());
+    match template {
+        Some(template) => template
+            .replace(
+                "{name}",
+                &input.file_name().unwrap_or_default().to_string_lossy(),
+            )
+            .replace("{path}", &path.to_string_lossy()),
+        None => format!("file://{}", path.to_string_lossy().replace('\\', "/")),
+    }
+}
+
+/// 以图片格式保存画布
+///
+/// - quality: JPEG 质量 1-100
+pub fn save_image(
+    canvas: &RgbaImage,
+    path: &Path,
+    format: OutputFormat,
+    quality: u8,
+) -> Result<(), Error> {
+    let writer = || fs::File::create(path).map(BufWriter::new).context(IoSnafu);
+    match format {
+        #[cfg(feature = "jxl")]
+        OutputFormat::Jxl => jxl::encode(canvas, path),
+        // JPEG 不支持透明通道，合成到白色背景
+        OutputFormat::Jpeg => flatten(canvas)
+            .write_with_encoder(JpegEncoder::new_with_quality(writer()?, quality))
+            .context(ImageSnafu),
+        OutputFormat::Webp => canvas
+            .write_with_encoder(WebPEncoder::new_lossless(writer()?))
+            .context(ImageSnafu),
+        OutputFormat::Tiff => canvas
+            .save_with_format(path, ImageFormat::Tiff)
+            .context(ImageSnafu),
+        _ => canvas
+            .save_with_format(path, ImageFormat::Png)
+            .context(ImageSnafu),
+    }
+}
+
+/// 保存 16 位、灰度或黑白页面
+///
+/// - canvas: 同一页面的 8 位 RGBA 版本，用于不支持该色彩格式的输出格式
+/// - colorspace: 黑白输出时 PNG 写为 1 位
+pub fn save_page(
+    page: &DynamicImage,
+    canvas: &RgbaImage,
+    path: &Path,
+    format: OutputFormat,
+    quality: u8,
+    colorspace: Colorspace,
+) -> Result<(), Error> {
+    let writer = || fs::File::create(path).map(BufWriter::new).context(IoSnafu);
+    match (format, colorspace) {
+        (OutputFormat::Png, Colorspace::Bilevel) => {
+            colorspace::write_bilevel_png(&page.to_luma8(), writer()?)
+        }
+        (OutputFormat::Png, _) => page
+            .save_with_format(path, ImageFormat::Png)
+            .context(ImageSnafu),
+        (OutputFormat::Tiff, _) => page
+            .save_with_format(path, ImageFormat::Tiff)
+            .context(ImageSnafu),
+        (OutputFormat::Jpeg, Colorspace::Gray | Colorspace::Bilevel) => page
+            .to_luma8()
+            .write_with_encoder(JpegEncoder::new_with_quality(writer()?, quality))
+            .context(ImageSnafu),
+        (OutputFormat::Webp, Colorspace::Gray | Colorspace::Bilevel) => page
+            .to_luma8()
+            .write_with_encoder(WebPEncoder::new_lossless(writer()?))
+            .context(ImageSnafu),
+        _ => save_image(canvas, path, format, quality),
+    }
+}
+
+/// 在已保存的图片文件中嵌入色彩配置文件，JPEG XL 不嵌入
+pub fn embed_profile(path: &Path, format: OutputFormat, profile: Profile) -> Result<(), Error> {
+    let format = match format {
+        OutputFormat::Png => ImageFormat::Png,
+        OutputFormat::Jpeg => ImageFormat::Jpeg,
+        OutputFormat::Tiff => ImageFormat::Tiff,
+        OutputFormat::Webp => ImageFormat::WebP,
+        _ => return Ok(()),
+    };
+    let bytes = fs::read(path).context(IoSnafu)?;
+    let bytes = icc::embed(bytes, format, &profile.icc())?;
+    fs::write(path, bytes).context(IoSnafu)
+}
+
+/// 先写入临时文件再重命名，避免中断时留下不完整的输出
+///
+/// 未指定 overwrite 时拒绝覆盖已存在的文件
+pub fn save_atomic(
+    output_path: &str,
+    overwrite: bool,
+    write: impl FnOnce(&Path) -> Result<(), Error>,
+) -> Result<(), Error> {
+    ensure!(
+        overwrite || !Path::new(output_path).exists(),
+        InputSnafu {
+            reason: Msg::OutputExists(output_path).to_string(),
+        }
+    );
+    let part_path = format!("{}.part", output_path);
+    if let Err(e) = write(Path::new(&part_path)) {
+        let _ = fs::remove_file(&part_path);
+        return Err(e);
+    }
+    fs::rename(&part_path, output_path).context(IoSnafu)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_atomic() {
+        let dir = std::env::temp_dir().join("image-typesetting-tool-save-atomic");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("output_0.png");
+        let output_path = path.to_string_lossy();
+        let write =
+            |bytes: &'static [u8]| move |path: &Path| fs::write(path, bytes).context(IoSnafu);
+        save_atomic(&output_path, false, write(b"a")).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"a");
+        // 未指定覆盖时保留已有文件
+        assert!(matches!(
+            save_atomic(&output_path, false, write(b"b")),
+            Err(Error::Input { .. })
+        ));
+        // 写入失败时不留下临时文件，也不改动已有文件
+        let failed = save_atomic(&output_path, true, |_| {
+            Err(Error::Input {
+                reason: String::new(),
+            })
+        });
+        assert!(failed.is_err());
+        assert!(!dir.join("output_0.png.part").exists());
+        assert_eq!(fs::read(&path).unwrap(), b"a");
+        save_atomic(&output_path, true, write(b"b")).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"b");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
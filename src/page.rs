@@ -0,0 +1,394 @@
+//! 单页绘制
+//!
+//! 读取一页中的图片并绘制到画布，再叠加说明文字、编码、编号、水印、页眉页脚等内容，
+//! 最后按输出色彩转换
+
+use image::{DynamicImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use crate::{
+    barcode::{self, Symbology},
+    caption::{self, Captions},
+    code::CodeValues,
+    colorspace::{self, CmykImage, Colorspace, Dither},
+    depth::{self, Depth},
+    draw,
+    header::HeaderFooter,
+    i18n::Msg,
+    layout::{Placement, Rect},
+    number::{self, Corner},
+    open_image_as, qr,
+    text::Font,
+    watermark::Watermark,
+    Error, RenderEvent, Typesetter,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OnError {
+    /// 跳过并在结束时列出，单元格留空
+    Skip,
+    /// 立即退出
+    Abort,
+    /// 绘制标有文件名的占位框
+    Placeholder,
+}
+
+/// 单页绘制所需的输入
+pub struct PageSource<'a> {
+    /// 本页图片位置
+    pub placements: &'a [Placement],
+    /// 全部输入图片
+    pub inputs: &'a [PathBuf],
+    /// 全部图片的目标高度 像素
+    pub target_hs: &'a [u32],
+    /// 页码（从 1 开始）与总页数
+    pub number: (usize, usize),
+    /// 本页第一张图片的编号
+    pub first_image: usize,
+}
+
+/// 单页绘制方式
+pub struct PageStyle<'a> {
+    /// 无法读取图片时的处理方式
+    pub on_error: OnError,
+    /// 占位图与说明文字所用字体
+    pub font: Option<&'a Font>,
+    /// 说明文字设置
+    pub captions: Option<&'a Captions>,
+    /// 页眉与页脚设置
+    pub header_footer: Option<&'a HeaderFooter>,
+    /// 页眉与页脚所用字体
+    pub header_font: Option<&'a Font>,
+    /// 水印设置
+    pub watermark: Option<&'a Watermark>,
+    /// 二维码内容
+    pub qr: Option<&'a CodeValues>,
+    /// 条形码内容与类型
+    pub barcode: Option<(&'a CodeValues, Symbology)>,
+    /// 图片编号所在的角，None 为不编号
+    pub number_corner: Option<Corner>,
+    /// 绘制裁切线
+    pub cut_marks: bool,
+    /// 绘制调试网格
+    pub debug_grid: bool,
+    /// 需要标出的有效 DPI 过低的图片及其 DPI，键为图片序号
+    pub low_dpi: Option<&'a BTreeMap<usize, f64>>,
+    /// 输出色彩
+    pub colorspace: Colorspace,
+    /// 黑白输出的抖动方式
+    pub dither: Dither,
+    /// CMYK 输出的配置文件
+    pub cmyk_profile: Option<&'a [u8]>,
+    /// 双面排版时背面页的整体偏移 像素
+    pub back_offset: Option<(i64, i64)>,
+    /// 居中最后一页
+    pub center_last: bool,
+    /// 水平翻转整页
+    pub mirror: bool,
+    /// 反相整页
+    pub invert: bool,
+    /// 输出详细程度
+    pub verbose: u8,
+}
+
+/// 绘制完成的单页
+pub struct RenderedPage {
+    /// 本页图片路径
+    pub inputs: Vec<PathBuf>,
+    /// 绘制完成的页面，灰度与黑白输出时已转换，供 PDF 与缩略图使用
+    pub canvas: RgbaImage,
+    /// 16 位、灰度或黑白输出时实际保存的页面
+    pub output: Option<DynamicImage>,
+    /// CMYK 输出时分色后的页面
+    pub cmyk: Option<CmykImage>,
+    /// 每张图片的实际绘制区域
+    pub rects: Vec<Rect>,
+    /// 每张图片所在的单元格区域
+    pub cells: Vec<Rect>,
+    /// 每张图片的旋转角度与缩放前截取的区域，未读取的图片为 None
+    pub framings: Vec<Option<(u32, Rect)>>,
+}
+
+/// 单页绘制进度
+#[derive(Debug, Clone)]
+pub enum PageEvent<'a> {
+    /// 开始读取图片
+    Read(&'a Path),
+    /// 图片的绘制进度
+    Render(RenderEvent),
+    /// 详细输出时的提示信息
+    Message(Msg<'a>),
+    /// 不影响输出的问题，如无法绘制的二维码
+    Warn(Msg<'a>),
+}
+
+/// 读取并绘制单页
+///
+/// 无法读取的图片按 on_error 处理并记入 skipped，
+/// 每个进度事件调用 progress，其返回错误时中止绘制
+pub fn render_page(
+    typesetter: &Typesetter,
+    page: PageSource,
+    style: &PageStyle,
+    skipped: &Mutex<Vec<(PathBuf, String)>>,
+    progress: impl Fn(PageEvent) -> Result<(), Error>,
+) -> Result<RenderedPage, Error> {
+    let PageSource {
+        placements,
+        inputs,
+        target_hs,
+        number: (number, total),
+        first_image,
+    } = page;
+    let PageStyle {
+        on_error,
+        font,
+        captions,
+        header_footer,
+        header_font,
+        watermark,
+        qr,
+        barcode,
+        number_corner,
+        cut_marks,
+        debug_grid,
+        low_dpi,
+        colorspace,
+        dither,
+        cmyk_profile,
+        back_offset,
+        center_last,
+        mirror,
+        invert,
+        verbose,
+    } = *style;
+    // 装订线交替时偶数页使用移动后的网格，最后一页居中时再调整网格，调试网格不随居中移动
+    let page_config = typesetter.config.for_page(number);
+    let grid_config = page_config.as_ref().unwrap_or(&typesetter.config);
+    let centered = match center_last && number == total {
+        true => grid_config.centered(placements),
+        false => None,
+    };
+    let shifted = centered
+        .or_else(|| page_config.clone())
+        .map(|config| Typesetter {
+            config,
+            ..*typesetter
+        });
+    let typesetter = shifted.as_ref().unwrap_or(typesetter);
+    let batch_inputs: Vec<PathBuf> = placements
+        .iter()
+        .map(|placement| inputs[placement.index].clone())
+        .collect();
+    let batch_target_hs: Vec<u32> = placements
+        .iter()
+        .map(|placement| target_hs[placement.index])
+        .collect();
+    let load = |i: usize| {
+        let input = &batch_inputs[i];
+        progress(PageEvent::Read(input))?;
+        let e = match open_image_as(input, typesetter.config.color_profile) {
+            Ok(image) => return Ok(Some(image)),
+            Err(e) if on_error == OnError::Abort => return Err(unreadable(input, e)),
+            Err(e) => e,
+        };
+        if let Ok(mut skipped) = skipped.lock() {
+            if !skipped.iter().any(|(path, _)| path == input) {
+                skipped.push((input.clone(), e.to_string()));
+            }
+        }
+        if on_error != OnError::Placeholder {
+            return Ok(None);
+        }
+        let config = &typesetter.config;
+        let area = config.image_area(&config.cell_rect(&placements[i]));
+        let label = input.file_name().unwrap_or_default().to_string_lossy();
+        let h = batch_target_hs[i].min(area.h);
+        Ok(Some(DynamicImage::ImageRgba8(draw::placeholder(
+            area.w, h, &label, font,
+        ))))
+    };
+    let mut framings = vec![None; placements.len()];
+    let (mut canvas, layer, rects) =
+        typesetter.render_page_deep(placements, &batch_target_hs, load, |event| {
+            if let RenderEvent::Resized {
+                index,
+                rotation,
+                crop,
+                ..
+            } = event
+            {
+                framings[index] = Some((rotation, crop));
+            }
+            match event {
+                RenderEvent::Resized {
+                    index,
+                    rotation,
+                    from,
+                    to,
+                    ..
+                } if verbose > 0 => {
+                    let name = batch_inputs[index].file_name().unwrap_or_default();
+                    let msg = Msg::Resized {
+                        file: &name.to_string_lossy(),
+                        rotation,
+                        percent: to.0.max(to.1) as f64 * 100.0 / from.0.max(from.1).max(1) as f64,
+                        from,
+                        to,
+                    };
+                    progress(PageEvent::Message(msg))?;
+                }
+                _ => {}
+            }
+            progress(PageEvent::Render(event))
+        })?;
+    if cut_marks {
+        // 裁切线位于单元格样式的装饰之外
+        let config = &typesetter.config;
+        let outer: Vec<Rect> = rects
+            .iter()
+            .map(|&rect| match rect.w {
+                0 => rect,
+                _ => config.outer_rect(rect),
+            })
+            .collect();
+        draw::cut_marks(&mut canvas, &outer, config.ppc, config.bleed_px);
+    }
+    if let (Some(captions), Some(font)) = (captions, font) {
+        for ((placement, &rect), input) in placements.iter().zip(&rects).zip(&batch_inputs) {
+            // 跳过的图片单元格留空，不绘制说明文字
+            let Some(text) = captions.text(input).filter(|_| rect.w > 0) else {
+                continue;
+            };
+            let cell = typesetter.config.cell_rect(placement);
+            let config = &typesetter.config;
+            caption::draw(
+                &mut canvas,
+                font,
+                &text,
+                config,
+                captions.position,
+                rect,
+                cell,
+            );
+        }
+    }
+    if qr.is_some() || barcode.is_some() {
+        let config = &typesetter.config;
+        for ((placement, &rect), input) in placements.iter().zip(&rects).zip(&batch_inputs) {
+            if rect.w == 0 {
+                continue;
+            }
+            let outer = config.outer_rect(rect);
+            let cell = config.cell_rect(placement);
+            let qr = qr
+                .and_then(|qr| qr.value(input))
+                .map(|value| qr::draw(&mut canvas, &value, config, outer, cell));
+            let barcode = barcode.and_then(|(barcode, symbology)| {
+                let value = barcode.value(input)?;
+                Some(barcode::draw(&mut canvas, &value, symbology, config, outer))
+            });
+            for e in [qr, barcode].into_iter().flatten().filter_map(Result::err) {
+                let msg = Msg::CodeSkipped {
+                    file: &input.to_string_lossy(),
+                    reason: &e.to_string(),
+                };
+                progress(PageEvent::Warn(msg))?;
+            }
+        }
+    }
+    if let (Some(corner), Some(font)) = (number_corner, font) {
+        for (i, &rect) in rects.iter().enumerate().filter(|(_, rect)| rect.w > 0) {
+            let number = first_image + i;
+            number::stamp(&mut canvas, font, &typesetter.config, number, rect, corner);
+        }
+    }
+    if let Some(low_dpi) = low_dpi {
+        for (placement, &rect) in placements.iter().zip(&rects) {
+            if let Some(dpi) = low_dpi.get(&placement.index) {
+                draw::low_dpi_mark(&mut canvas, rect, &format!("{:.0} DPI", dpi), font);
+            }
+        }
+    }
+    if let Some(watermark) = watermark {
+        watermark.apply(&mut canvas, &rects, &typesetter.config);
+    }
+    if let (Some(header_footer), Some(font)) = (header_footer, header_font) {
+        header_footer.draw(&mut canvas, font, &typesetter.config, number, total);
+    }
+    if debug_grid {
+        draw::debug_grid(&mut canvas, grid_config);
+    }
+    let deep = match (typesetter.config.depth, layer) {
+        (Depth::Sixteen, layer) => Some(depth::merge(&canvas, layer.as_ref())),
+        (Depth::Auto, Some(layer)) => Some(depth::merge(&canvas, Some(&layer))),
+        _ => None,
+    };
+    let page = match deep {
+        Some(deep) => DynamicImage::ImageRgba16(deep),
+        None => DynamicImage::ImageRgba8(canvas),
+    };
+    // 双面排版时偶数页为背面
+    let page = match back_offset {
+        Some((dx, dy)) if number % 2 == 0 => {
+            draw::translate(page, dx, dy, typesetter.config.background.0)
+        }
+        _ => page,
+    };
+    let mut page = match mirror {
+        true => page.fliph(),
+        false => page,
+    };
+    if invert {
+        page.invert();
+    }
+    let cmyk = match colorspace {
+        Colorspace::Cmyk => Some(colorspace::to_cmyk(
+            &page,
+            typesetter.config.color_profile,
+            cmyk_profile,
+        )?),
+        _ => None,
+    };
+    let output = match colorspace {
+        Colorspace::Rgb | Colorspace::Cmyk => page,
+        Colorspace::Gray => colorspace::to_gray(&page),
+        Colorspace::Bilevel => DynamicImage::ImageLuma8(colorspace::to_bilevel(
+            &colorspace::to_gray(&page).into_luma8(),
+            dither,
+        )),
+    };
+    let (canvas, output) = match output {
+        DynamicImage::ImageRgba8(canvas) => (canvas, None),
+        output => (output.to_rgba8(), Some(output)),
+    };
+    Ok(RenderedPage {
+        inputs: batch_inputs,
+        canvas,
+        output,
+        cmyk,
+        rects,
+        cells: placements
+            .iter()
+            .map(|placement| typesetter.config.cell_rect(placement))
+            .collect(),
+        framings,
+    })
+}
+
+/// 无法读取图片时立即退出的错误
+pub fn unreadable(path: &Path, e: Error) -> Error {
+    Error::Input {
+        reason: Msg::UnreadableFile {
+            path: &path.display().to_string(),
+            reason: e.to_string(),
+        }
+        .to_string(),
+    }
+}
@@ -0,0 +1,256 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+use crate::i18n::Msg;
+
+/// 纸张尺寸
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Paper {
+    /// 宽度 厘米
+    pub width_cm: f64,
+    /// 高度 厘米
+    pub height_cm: f64,
+}
+
+/// 预设纸张，竖向尺寸（宽, 高）厘米
+const PRESETS: &[(&str, f64, f64)] = &[
+    ("a3", 29.7, 42.0),
+    ("a4", 21.0, 29.7),
+    ("a5", 14.8, 21.0),
+    ("letter", 21.59, 27.94),
+    ("legal", 21.59, 35.56),
+];
+
+impl Paper {
+    /// 默认纸张 A4 横向
+    pub const A4_LANDSCAPE: Paper = Paper {
+        width_cm: 29.7,
+        height_cm: 21.0,
+    };
+}
+
+/// 纸张方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Orientation {
+    /// 竖向
+    Portrait,
+    /// 横向
+    Landscape,
+}
+
+impl Paper {
+    /// 按方向调整纸张，竖向时宽为短边，横向时宽为长边
+    pub fn oriented(self, orientation: Orientation) -> Paper {
+        let (short, long) = (
+            self.width_cm.min(self.height_cm),
+            self.width_cm.max(self.height_cm),
+        );
+        match orientation {
+            Orientation::Portrait => Paper {
+                width_cm: short,
+                height_cm: long,
+            },
+            Orientation::Landscape => Paper {
+                width_cm: long,
+                height_cm: short,
+            },
+        }
+    }
+}
+
+/// 纸张四边的边距 厘米
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "BorderValue", into = "String")]
+pub struct Border {
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+    pub left: f64,
+}
+
+/// 配置文件中的边距，可为数值或字符串
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BorderValue {
+    Uniform(f64),
+    Sides(String),
+}
+
+impl Border {
+    /// 四边相同的边距
+    pub fn uniform(cm: f64) -> Border {
+        Border {
+            top: cm,
+            right: cm,
+            bottom: cm,
+            left: cm,
+        }
+    }
+
+    /// 四边中最大的边距
+    pub fn max(&self) -> f64 {
+        self.top.max(self.right).max(self.bottom).max(self.left)
+    }
+}
+
+impl fmt::Display for Border {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self == Border::uniform(self.top) {
+            true => write!(f, "{}", self.top),
+            false => write!(
+                f,
+                "{},{},{},{}",
+                self.top, self.right, self.bottom, self.left
+            ),
+        }
+    }
+}
+
+impl From<Border> for String {
+    fn from(border: Border) -> Self {
+        border.to_string()
+    }
+}
+
+impl TryFrom<BorderValue> for Border {
+    type Error = String;
+
+    fn try_from(value: BorderValue) -> Result<Self, Self::Error> {
+        match value {
+            BorderValue::Uniform(cm) => cm.to_string().parse(),
+            BorderValue::Sides(s) => s.parse(),
+        }
+    }
+}
+
+impl FromStr for Border {
+    type Err = String;
+
+    /// 解析边距，一个值为四边相同，四个值依次为上、右、下、左（单位：cm），如 1.0,0.5,1.0,0.5
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values: Option<Vec<f64>> = s
+            .split(',')
+            .map(|value| value.trim().parse::<f64>().ok().filter(|&cm| cm >= 0.0))
+            .collect();
+        match values.as_deref() {
+            Some(&[cm]) => Ok(Border::uniform(cm)),
+            Some(&[top, right, bottom, left]) => Ok(Border {
+                top,
+                right,
+                bottom,
+                left,
+            }),
+            _ => Err(Msg::BorderInvalid(s).to_string()),
+        }
+    }
+}
+
+impl Default for Paper {
+    fn default() -> Self {
+        Paper::A4_LANDSCAPE
+    }
+}
+
+impl fmt::Display for Paper {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.width_cm, self.height_cm)
+    }
+}
+
+impl From<Paper> for String {
+    fn from(paper: Paper) -> Self {
+        paper.to_string()
+    }
+}
+
+impl TryFrom<String> for Paper {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl FromStr for Paper {
+    type Err = String;
+
+    /// 解析纸张，支持预设名称（横向）或自定义 `宽x高`（单位：cm，以 in 结尾时为英寸）
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_lowercase();
+        if let Some(&(_, short, long)) = PRESETS.iter().find(|(name, _, _)| *name == s) {
+            return Ok(Paper {
+                width_cm: long,
+                height_cm: short,
+            });
+        }
+        let (size, unit_cm) = match s.strip_suffix("in") {
+            Some(size) => (size, 2.54),
+            None => (s.trim_end_matches("cm"), 1.0),
+        };
+        let parsed = size.split_once(['x', '*']).and_then(|(w, h)| {
+            Some((w.trim().parse::<f64>().ok()?, h.trim().parse::<f64>().ok()?))
+        });
+        match parsed {
+            Some((w, h)) if w > 0.0 && h > 0.0 => Ok(Paper {
+                width_cm: w * unit_cm,
+                height_cm: h * unit_cm,
+            }),
+            _ => Err(Msg::PaperInvalid(&s).to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_paper() {
+        assert_eq!("A4".parse::<Paper>(), Ok(Paper::A4_LANDSCAPE));
+        assert_eq!(
+            "letter".parse::<Paper>(),
+            Ok(Paper {
+                width_cm: 27.94,
+                height_cm: 21.59
+            })
+        );
+        assert_eq!(
+            "15x10".parse::<Paper>(),
+            Ok(Paper {
+                width_cm: 15.0,
+                height_cm: 10.0
+            })
+        );
+        assert_eq!(
+            "10.2*15.2cm".parse::<Paper>(),
+            Ok(Paper {
+                width_cm: 10.2,
+                height_cm: 15.2
+            })
+        );
+        assert_eq!(
+            "6x4in".parse::<Paper>(),
+            Ok(Paper {
+                width_cm: 15.24,
+                height_cm: 10.16
+            })
+        );
+        assert_eq!(
+            Paper::A4_LANDSCAPE.oriented(Orientation::Portrait),
+            Paper {
+                width_cm: 21.0,
+                height_cm: 29.7
+            }
+        );
+        assert!("B7".parse::<Paper>().is_err());
+        assert_eq!("0.8".parse::<Border>(), Ok(Border::uniform(0.8)));
+        let border: Border = "1, 0.5, 2, 0".parse().unwrap();
+        assert_eq!((border.top, border.bottom, border.left), (1.0, 2.0, 0.0));
+        assert_eq!(border.to_string(), "1,0.5,2,0");
+        assert!("1,2".parse::<Border>().is_err());
+        assert!("-1".parse::<Border>().is_err());
+        assert!("0x10".parse::<Paper>().is_err());
+    }
+}
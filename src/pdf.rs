@@ -0,0 +1,207 @@
+use image::{DynamicImage, RgbaImage};
+use printpdf::lopdf::{self, Dictionary, Object, ObjectId, Stream, StringFormat};
+use printpdf::{
+    Actions, Image, ImageTransform, LinkAnnotation, Mm, PdfDocument, PdfDocumentReference,
+};
+use snafu::prelude::*;
+use std::{collections::HashSet, fs, path::Path};
+
+use crate::{flatten, icc::Profile, layout::Rect, Error, IoSnafu, PdfPagesSnafu, PdfSnafu};
+
+/// 页面上的链接区域
+pub struct PageLink {
+    /// 链接区域 像素
+    pub rect: Rect,
+    /// 链接地址
+    pub uri: String,
+}
+
+/// PDF 文档
+pub struct PdfBook {
+    title: String,
+    doc: Option<PdfDocumentReference>,
+    /// 作为输出意图嵌入的色彩配置文件
+    profile: Option<Profile>,
+}
+
+impl PdfBook {
+    pub fn new(title: &str) -> PdfBook {
+        PdfBook {
+            title: title.to_string(),
+            doc: None,
+            profile: None,
+        }
+    }
+
+    /// 嵌入色彩配置文件，页面 RGB 数值按该配置文件解释
+    pub fn set_profile(&mut self, profile: Option<Profile>) {
+        self.profile = profile;
+    }
+
+    /// 添加一页，页面物理尺寸由画布像素数与每厘米像素数决定
+    ///
+    /// - canvas: 页面画布
+    /// - ppc: 每厘米像素数
+    /// - bookmark: 页面书签
+    /// - links: 页面上的链接区域
+    pub fn add_page(&mut self, canvas: &RgbaImage, ppc: f64, bookmark: &str, links: &[PageLink]) {
+        let px_to_mm = |px: u32| Mm((px as f64 / ppc * 10.0) as f32);
+        let (page_w, page_h) = (px_to_mm(canvas.width()), px_to_mm(canvas.height()));
+        let (page, layer) = match &self.doc {
+            Some(doc) => doc.add_page(page_w, page_h, "page"),
+            None => {
+                let (doc, page, layer) = PdfDocument::new(&self.title, page_w, page_h, "page");
+                self.doc = Some(doc);
+                (page, layer)
+            }
+        };
+        let doc = self.doc.as_ref().expect("document initialized above");
+        let layer = doc.get_page(page).get_layer(layer);
+
+        Image::from_dynamic_image(&DynamicImage::ImageRgb8(flatten(canvas))).add_to_layer(
+            layer.clone(),
+            ImageTransform {
+                dpi: Some((ppc * 2.54) as f32),
+                ..Default::default()
+            },
+        );
+        // PDF 坐标原点在左下角
+        for link in links {
+            let rect = printpdf::Rect::new(
+                px_to_mm(link.rect.x),
+                Mm(page_h.0 - px_to_mm(link.rect.y + link.rect.h).0),
+                px_to_mm(link.rect.x + link.rect.w),
+                Mm(page_h.0 - px_to_mm(link.rect.y).0),
+            );
+            layer.add_link_annotation(LinkAnnotation::new(
+                rect,
+                None,
+                None,
+                Actions::uri(link.uri.clone()),
+                None,
+            ));
+        }
+        doc.add_bookmark(bookmark, page);
+    }
+
+    /// 保存文档，没有页面时不输出
+    ///
+    /// - path: 输出路径
+    /// - order: 页面输出顺序，同一页可出现多次，为空时按添加顺序输出
+    pub fn save(self, path: &Path, order: &[usize]) -> Result<(), Error> {
+        match self.to_bytes(order)? {
+            Some(bytes) => fs::write(path, bytes).context(IoSnafu),
+            None => Ok(()),
+        }
+    }
+
+    /// 生成文档内容，没有页面时返回 None
+    ///
+    /// - order: 页面输出顺序，同 [`PdfBook::save`]
+    pub fn to_bytes(self, order: &[usize]) -> Result<Option<Vec<u8>>, Error> {
+        let Some(doc) = self.doc else {
+            return Ok(None);
+        };
+        let mut bytes = doc.save_to_bytes().context(PdfSnafu)?;
+        if !order.is_empty() {
+            bytes = reorder_pages(&bytes, order).context(PdfPagesSnafu)?;
+        }
+        if let Some(profile) = self.profile {
+            bytes = add_output_intent(&bytes, profile).context(PdfPagesSnafu)?;
+        }
+        Ok(Some(bytes))
+    }
+}
+
+/// 按给定顺序重排页面
+///
+/// 重复出现的页面复制页面对象，与原页面共享内容与资源，不会重复嵌入图片
+fn reorder_pages(bytes: &[u8], order: &[usize]) -> Result<Vec<u8>, lopdf::Error> {
+    let mut doc = lopdf::Document::load_mem(bytes)?;
+    let pages: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    let pages_id = doc.catalog()?.get(b"Pages")?.as_reference()?;
+
+    let mut used = HashSet::new();
+    let mut kids = Vec::with_capacity(order.len());
+    for &i in order {
+        let id = pages[i];
+        if used.insert(id) {
+            kids.push(Object::Reference(id));
+        } else {
+            let page = doc.get_object(id)?.clone();
+            kids.push(Object::Reference(doc.add_object(page)));
+        }
+    }
+    let pages_dict = doc.get_object_mut(pages_id)?.as_dict_mut()?;
+    pages_dict.set("Count", kids.len() as i64);
+    pages_dict.set("Kids", kids);
+
+    let mut out = Vec::new();
+    doc.save_to(&mut out)?;
+    Ok(out)
+}
+
+/// 在文档目录中加入以色彩配置文件为目标的输出意图
+fn add_output_intent(bytes: &[u8], profile: Profile) -> Result<Vec<u8>, lopdf::Error> {
+    let mut doc = lopdf::Document::load_mem(bytes)?;
+    let mut icc = Dictionary::new();
+    icc.set("N", 3);
+    icc.set("Alternate", Object::Name(b"DeviceRGB".to_vec()));
+    let icc_id = doc.add_object(Stream::new(icc, profile.icc()));
+    let name = || Object::String(profile.name().into(), StringFormat::Literal);
+
+    let mut intent = Dictionary::new();
+    intent.set("Type", Object::Name(b"OutputIntent".to_vec()));
+    intent.set("S", Object::Name(b"GTS_PDFA1".to_vec()));
+    intent.set("OutputConditionIdentifier", name());
+    intent.set("Info", name());
+    intent.set("DestinationOutputProfile", icc_id);
+    doc.catalog_mut()?
+        .set("OutputIntents", vec![Object::Dictionary(intent)]);
+
+    let mut out = Vec::new();
+    doc.save_to(&mut out)?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{paper::Paper, Config, LayoutOptions};
+
+    #[test]
+    fn test_two_pages() {
+        assert!(PdfBook::new("empty").to_bytes(&[]).unwrap().is_none());
+        let paper: Paper = "A5".parse().unwrap();
+        let config = Config::from_options(&LayoutOptions {
+            paper: Some(paper),
+            ppc: Some(10.0),
+            ..Default::default()
+        })
+        .unwrap();
+        let canvas = RgbaImage::new(config.paper_w_px, config.paper_h_px);
+        let mut book = PdfBook::new("test");
+        book.add_page(&canvas, config.ppc, "output_0", &[]);
+        book.add_page(&canvas, config.ppc, "output_1", &[]);
+        let bytes = book.to_bytes(&[]).unwrap().unwrap();
+
+        let doc = lopdf::Document::load_mem(&bytes).unwrap();
+        let pages = doc.get_pages();
+        assert_eq!(pages.len(), 2);
+        // 页面尺寸为纸张尺寸，1 cm = 72 / 2.54 点
+        let pt = |cm: f64| cm * 72.0 / 2.54;
+        for &id in pages.values() {
+            let page = doc.get_dictionary(id).unwrap();
+            let media_box: Vec<f64> = page
+                .get(b"MediaBox")
+                .and_then(Object::as_array)
+                .unwrap()
+                .iter()
+                .map(|value| value.as_float().map(f64::from).unwrap())
+                .collect();
+            let (w, h) = (media_box[2] - media_box[0], media_box[3] - media_box[1]);
+            assert!((w - pt(paper.width_cm)).abs() < 0.5, "{}", w);
+            assert!((h - pt(paper.height_cm)).abs() < 0.5, "{}", h);
+        }
+    }
+}
@@ -0,0 +1,611 @@
+//! 排版计划
+//!
+//! 分组、排列清单展开、页面与多份输出的命名及试运行报告，
+//! 命令行程序与其他前端共用
+
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    caption::CaptionFile,
+    i18n::Msg,
+    image_dimensions,
+    input::OrderEntry,
+    layout::{self, Placement, Span},
+    manifest::{Manifest, Scale},
+    metadata, Config, Error, InputSnafu, Typesetter,
+};
+
+/// 分组方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GroupBy {
+    /// 按 EXIF 拍摄日期分组
+    Date,
+}
+
+/// 按拍摄日期分组，日期变化处分组编号递增
+pub fn date_groups(dates: &[Option<String>]) -> Vec<usize> {
+    dates
+        .iter()
+        .enumerate()
+        .scan(0, |group, (i, date)| {
+            if i > 0 && dates[i - 1] != *date {
+                *group += 1;
+            }
+            Some(*group)
+        })
+        .collect()
+}
+
+/// 单张图片的份数，份数文件中的条目优先于默认份数
+///
+/// - default: 默认份数
+/// - csv: 份数文件，每行 `文件名,份数`，份数为 0 时不排入该图片
+pub fn image_copies(path: &Path, default: u32, csv: Option<&CaptionFile>) -> Result<usize, Error> {
+    let Some(value) = csv.and_then(|csv| csv.caption_for(path)) else {
+        return Ok(default as usize);
+    };
+    value.parse::<usize>().ok().context(InputSnafu {
+        reason: Msg::CopiesInvalid {
+            path: &path.display().to_string(),
+            value,
+        }
+        .to_string(),
+    })
+}
+
+/// 按排列清单展开排版位置
+///
+/// 返回每个位置对应的图片索引（空白为 None）、占用单元格与分组编号，
+/// 换页处分组编号递增
+///
+/// - order: 排列清单
+/// - spans: 每张图片占用的单元格数
+/// - groups: 每张图片的分组编号
+pub fn order_slots(
+    order: &[OrderEntry],
+    spans: &[Span],
+    groups: &[usize],
+) -> (Vec<Option<usize>>, Vec<Span>, Vec<usize>) {
+    let mut slots = Vec::with_capacity(order.len());
+    let mut slot_spans = Vec::with_capacity(order.len());
+    let mut slot_groups = Vec::with_capacity(order.len());
+    let mut next = 0;
+    let mut group = 0;
+    let mut prev_key = None;
+    for entry in order {
+        let (slot, span, key) = match entry {
+            OrderEntry::PageBreak => {
+                // 下一个位置必然另起一页
+                prev_key = None;
+                continue;
+            }
+            // 空白与前一张图片同组，页首的空白与下一张图片同组
+            OrderEntry::Blank => (
+                None,
+                Span::ONE,
+                prev_key.or(groups.get(next).copied()).unwrap_or(0),
+            ),
+            OrderEntry::Image(..) => {
+                next += 1;
+                (Some(next - 1), spans[next - 1], groups[next - 1])
+            }
+        };
+        if prev_key != Some(key) && !slots.is_empty() {
+            group += 1;
+        }
+        prev_key = Some(key);
+        slots.push(slot);
+        slot_spans.push(span);
+        slot_groups.push(group);
+    }
+    (slots, slot_spans, slot_groups)
+}
+
+/// 排入页面的方式
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ArrangeOptions {
+    /// 按原始尺寸排版，目标高度即图片高度
+    pub true_size: bool,
+    /// 分组方式
+    pub group_by: Option<GroupBy>,
+    /// 第一页开头跳过的单元格数量，优先于起始行列
+    pub skip_cells: Option<u32>,
+    /// 第一张图片所在的行与列（从 1 开始）
+    pub start: (Option<u32>, Option<u32>),
+}
+
+/// 排版结果
+pub struct Arrangement {
+    pub typesetter: Typesetter,
+    /// 全部图片的目标高度 像素
+    pub target_hs: Vec<u32>,
+    /// 全部图片占用的单元格数
+    pub spans: Vec<Span>,
+    /// 每张图片的拍摄日期，按日期分组时使用
+    pub dates: Vec<Option<String>>,
+    /// 每页图片位置
+    pub pages: Vec<Vec<Placement>>,
+}
+
+/// 计算每张图片的目标高度、占用单元格与分组，并排入页面
+///
+/// - typesetter: 排版器，其中的 skip_cells 由 options 重新计算
+/// - manifest: 图片清单，指定单张图片的尺寸
+/// - inputs: 全部输入图片
+/// - order: 排列清单，其中的图片与 inputs 一一对应
+pub fn arrange(
+    typesetter: Typesetter,
+    manifest: &Manifest,
+    options: &ArrangeOptions,
+    inputs: &[PathBuf],
+    order: Option<&[OrderEntry]>,
+) -> Result<Arrangement, Error> {
+    let config = &typesetter.config;
+    // 排列清单中指定的尺寸优先于图片清单
+    let order_scales: Vec<Option<Scale>> = order
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|entry| match entry {
+            OrderEntry::Image(_, scale) => Some(*scale),
+            _ => None,
+        })
+        .collect();
+    // 计算每张图片的目标高度与占用单元格
+    let mut target_hs = Vec::with_capacity(inputs.len());
+    let mut spans = Vec::with_capacity(inputs.len());
+    for (i, input) in inputs.iter().enumerate() {
+        if options.true_size {
+            // 目标高度即原始高度，无法读取的图片使用占位图
+            let Ok(dimensions) = image_dimensions(input) else {
+                target_hs.push(config.target_h_px);
+                spans.push(Span::ONE);
+                continue;
+            };
+            let target_h = config.oriented(dimensions).1;
+            let span = config.span_for(target_h, dimensions);
+            ensure!(
+                span.cols <= config.n_h && span.rows <= config.n_v,
+                InputSnafu {
+                    reason: Msg::OriginalTooLarge(&input.display().to_string()).to_string(),
+                }
+            );
+            target_hs.push(target_h);
+            spans.push(span);
+            continue;
+        }
+        let scale = order_scales.get(i).copied().flatten();
+        match scale.or_else(|| manifest.scale_for(input)) {
+            Some(scale) => {
+                let dimensions = image_dimensions(input).ok();
+                let target_h = scale.target_h_px(
+                    config.target_h_px,
+                    config.ppc,
+                    dimensions.map(|dimensions| config.oriented(dimensions)),
+                );
+                target_hs.push(target_h);
+                // 无法读取的图片使用占位图，占用一个单元格
+                spans.push(dimensions.map_or(Span::ONE, |dimensions| {
+                    config.span_for(target_h, dimensions)
+                }));
+            }
+            None => {
+                target_hs.push(config.target_h_px);
+                spans.push(Span::ONE);
+            }
+        }
+    }
+    // 分组
+    let dates: Vec<Option<String>> = match options.group_by {
+        Some(GroupBy::Date) => inputs
+            .iter()
+            .map(|input| metadata::capture_date(input))
+            .collect(),
+        None => vec![None; inputs.len()],
+    };
+    let groups = date_groups(&dates);
+    // 起始位置按填充顺序换算为跳过的单元格数量，只指定行或列时取该行或该列最先填充的单元格
+    let skip_cells = match (options.skip_cells, options.start.0, options.start.1) {
+        (Some(skip), _, _) => skip,
+        (None, None, None) => 0,
+        (None, row, col) => {
+            let rows = row.map_or(0..config.n_v, |row| row - 1..row);
+            let cols = col.map_or(0..config.n_h, |col| col - 1..col);
+            rows.flat_map(|r| cols.clone().map(move |c| (r, c)))
+                .filter_map(|cell| typesetter.fill_order.position(config.n_h, config.n_v, cell))
+                .min()
+                .context(InputSnafu {
+                    reason: Msg::StartOutOfRange {
+                        grid: (config.n_h, config.n_v),
+                    }
+                    .to_string(),
+                })?
+        }
+    };
+    let typesetter = Typesetter {
+        skip_cells,
+        ..typesetter
+    };
+    let pages = match order {
+        Some(order) => {
+            let (slots, spans, groups) = order_slots(order, &spans, &groups);
+            // 先去掉空白位置再拼版，避免全为空白的半页被去掉后打乱页序
+            typesetter.impose(layout::fill_slots(
+                typesetter.pack(&spans, &groups)?,
+                &slots,
+            ))
+        }
+        None => typesetter.layout(&spans, &groups)?,
+    };
+    Ok(Arrangement {
+        typesetter,
+        target_hs,
+        spans,
+        dates,
+        pages,
+    })
+}
+
+/// 生成每页的输出文件名（不含扩展名）
+///
+/// 按日期分组时以页面内容的日期范围命名，如 `2024-05-01_p1`，
+/// 否则为 `output_0`、`output_1`...
+pub fn page_names(
+    pages: &[Vec<Placement>],
+    dates: &[Option<String>],
+    group_by: Option<GroupBy>,
+) -> Vec<String> {
+    let Some(GroupBy::Date) = group_by else {
+        return (0..pages.len()).map(|i| format!("output_{}", i)).collect();
+    };
+    let mut names = Vec::with_capacity(pages.len());
+    let mut prev_range = None;
+    let mut n = 0;
+    for placements in pages {
+        let range = metadata::date_range(
+            placements
+                .iter()
+                .filter_map(|placement| dates[placement.index].as_deref()),
+        )
+        .unwrap_or_else(|| "undated".to_string());
+        n = if prev_range.as_ref() == Some(&range) {
+            n + 1
+        } else {
+            1
+        };
+        names.push(format!("{}_p{}", range, n));
+        prev_range = Some(range);
+    }
+    names
+}
+
+/// 计算多份输出时每页各份的输出序号
+///
+/// 逐份输出（collate）时为 1,2,3,1,2,3，否则为 1,1,2,2,3,3
+///
+/// - n_pages: 页数
+/// - copies: 份数
+/// - collate: 是否逐份输出
+pub fn copy_sequences(n_pages: usize, copies: u32, collate: bool) -> Vec<Vec<usize>> {
+    let copies = copies.max(1) as usize;
+    (0..n_pages)
+        .map(|i| {
+            (0..copies)
+                .map(|k| {
+                    if collate {
+                        k * n_pages + i
+                    } else {
+                        i * copies + k
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// 生成多份输出时每页各份的文件名（不含扩展名）
+///
+/// - page_names: 每页的文件名
+/// - sequences: 每页各份的输出序号
+/// - group_by: 分组方式，按日期分组时保留日期文件名并加序号前缀
+pub fn copy_names(
+    page_names: &[String],
+    sequences: &[Vec<usize>],
+    group_by: Option<GroupBy>,
+) -> Vec<Vec<String>> {
+    if sequences.iter().all(|seqs| seqs.len() <= 1) {
+        return page_names.iter().map(|name| vec![name.clone()]).collect();
+    }
+    let n_total: usize = sequences.iter().map(|seqs| seqs.len()).sum();
+    let width = n_total.saturating_sub(1).to_string().len();
+    page_names
+        .iter()
+        .zip(sequences)
+        .map(|(name, seqs)| {
+            seqs.iter()
+                .map(|seq| match group_by {
+                    Some(GroupBy::Date) => format!("{:0width$}_{}", seq, name),
+                    None => format!("output_{}", seq),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// 排版结果
+pub struct Plan<'a> {
+    pub config: &'a Config,
+    /// 每页图片位置
+    pub pages: &'a [Vec<Placement>],
+    /// 每页的输出文件名
+    pub page_names: &'a [String],
+    /// 全部输入图片
+    pub inputs: &'a [PathBuf],
+    /// 全部图片的目标高度 像素
+    pub target_hs: &'a [u32],
+}
+
+/// 生成试运行报告：页数、单元格尺寸及每页图片的打印尺寸与有效 DPI
+pub fn dry_run_report(plan: &Plan) -> String {
+    let cfg = plan.config;
+    let cm = |px: u32| px as f64 / cfg.ppc;
+    let mut report = String::new();
+    let paper = Msg::PlanPaper {
+        size_cm: (cm(cfg.paper_w_px), cm(cfg.paper_h_px)),
+        size_px: (cfg.paper_w_px, cfg.paper_h_px),
+        grid: (cfg.n_h, cfg.n_v),
+        pages: plan.pages.len(),
+    };
+    let _ = writeln!(report, "{}", paper);
+    let cell = Msg::PlanCell {
+        size_cm: (cm(cfg.max_w_px), cm(cfg.max_h_px)),
+        size_px: (cfg.max_w_px, cfg.max_h_px),
+        target_cm: cm(cfg.target_h_px),
+        target_px: cfg.target_h_px,
+    };
+    let _ = writeln!(report, "{}", cell);
+    for (placements, name) in plan.pages.iter().zip(plan.page_names) {
+        let _ = writeln!(report, "== {} ==", name);
+        for placement in placements {
+            let input = &plan.inputs[placement.index];
+            let _ = write!(
+                report,
+                "  [{},{}] {}",
+                placement.row,
+                placement.col,
+                input.display()
+            );
+            let target_h = plan.target_hs[placement.index];
+            match image_dimensions(input) {
+                Ok(dimensions) => {
+                    let (w, h) = cfg.fitted_size(placement, target_h, dimensions);
+                    let dpi = cfg.effective_dpi(placement, target_h, dimensions);
+                    let size_cm = (cm(w), cm(h));
+                    let _ = writeln!(report, "  {}", Msg::PlanImage { size_cm, dpi });
+                }
+                Err(_) => {
+                    let _ = writeln!(report, "  {}", Msg::Unreadable);
+                }
+            }
+        }
+    }
+    report
+}
+
+/// 找出有效 DPI 低于 min_dpi 的图片，返回图片序号与有效 DPI，无法读取的图片忽略
+pub fn low_dpi_images(
+    config: &Config,
+    pages: &[Vec<Placement>],
+    inputs: &[PathBuf],
+    target_hs: &[u32],
+    min_dpi: f64,
+) -> BTreeMap<usize, f64> {
+    let mut low = BTreeMap::new();
+    if min_dpi <= 0.0 {
+        return low;
+    }
+    for placement in pages.iter().flatten() {
+        let Ok(dimensions) = image_dimensions(&inputs[placement.index]) else {
+            continue;
+        };
+        let dpi = config.effective_dpi(placement, target_hs[placement.index], dimensions);
+        if dpi < min_dpi {
+            low.insert(placement.index, dpi);
+        }
+    }
+    low
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copy_sequences() {
+        assert_eq!(copy_sequences(2, 0, false), [vec![0], vec![1]]);
+        assert_eq!(copy_sequences(3, 2, false), [[0, 1], [2, 3], [4, 5]]);
+        assert_eq!(copy_sequences(3, 2, true), [[0, 3], [1, 4], [2, 5]]);
+    }
+
+    #[test]
+    fn test_copy_names() {
+        let names = ["output_0".to_string(), "output_1".to_string()];
+        // 单份时沿用页面文件名
+        let single = copy_names(&names, &copy_sequences(2, 1, false), None);
+        assert_eq!(single, [["output_0"], ["output_1"]]);
+        let copies = copy_names(&names, &copy_sequences(2, 2, true), None);
+        assert_eq!(copies, [["output_0", "output_2"], ["output_1", "output_3"]]);
+        // 按日期分组时保留日期文件名，序号按总份数补零
+        let names = ["2024-05-01_p1".to_string()];
+        let dated = copy_names(&names, &copy_sequences(1, 12, false), Some(GroupBy::Date));
+        assert_eq!(dated[0][0], "00_2024-05-01_p1");
+        assert_eq!(dated[0][11], "11_2024-05-01_p1");
+    }
+
+    #[test]
+    fn test_page_names() {
+        let page = |indices: &[usize]| -> Vec<Placement> {
+            indices
+                .iter()
+                .map(|&index| Placement {
+                    index,
+                    row: 0,
+                    col: index as u32,
+                    span: Span::ONE,
+                })
+                .collect()
+        };
+        let pages = [page(&[0, 1]), page(&[2, 3]), page(&[4]), page(&[5])];
+        let dates: Vec<Option<String>> = [
+            Some("2024-05-01"),
+            Some("2024-05-03"),
+            Some("2024-05-03"),
+            Some("2024-05-01"),
+            None,
+            Some("2024-05-02"),
+        ]
+        .iter()
+        .map(|date| date.map(str::to_string))
+        .collect();
+        assert_eq!(
+            page_names(&pages, &dates, None),
+            ["output_0", "output_1", "output_2", "output_3"]
+        );
+        // 日期范围相同的连续页面依次编号，没有日期的页面为 undated
+        assert_eq!(
+            page_names(&pages, &dates, Some(GroupBy::Date)),
+            [
+                "2024-05-01_2024-05-03_p1",
+                "2024-05-01_2024-05-03_p2",
+                "undated_p1",
+                "2024-05-02_p1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_order_slots() {
+        let image = || OrderEntry::Image(PathBuf::from("a.jpg"), None);
+        let order = [
+            image(),
+            OrderEntry::Blank,
+            image(),
+            OrderEntry::PageBreak,
+            OrderEntry::Blank,
+            image(),
+            OrderEntry::PageBreak,
+        ];
+        let wide = Span { cols: 2, rows: 1 };
+        let spans = [Span::ONE, wide, Span::ONE];
+        let (slots, slot_spans, groups) = order_slots(&order, &spans, &[0, 0, 0]);
+        assert_eq!(slots, [Some(0), None, Some(1), None, Some(2)]);
+        assert_eq!(
+            slot_spans,
+            [Span::ONE, Span::ONE, wide, Span::ONE, Span::ONE]
+        );
+        // 换页处分组递增，空白与相邻图片同组，末尾的换页不产生空的分组
+        assert_eq!(groups, [0, 0, 0, 1, 1]);
+        // 图片本身的分组变化时同样另起一组
+        let (_, _, groups) = order_slots(&order, &spans, &[0, 1, 1]);
+        assert_eq!(groups, [0, 0, 1, 2, 2]);
+    }
+
+    #[test]
+    fn test_date_groups() {
+        let date = |date: &str| Some(date.to_string());
+        let dates = [
+            date("2024-05-01"),
+            date("2024-05-01"),
+            None,
+            None,
+            date("2024-05-02"),
+            date("2024-05-01"),
+        ];
+        // 没有日期的图片自成一组，不并入前后的日期
+        assert_eq!(date_groups(&dates), [0, 0, 1, 1, 2, 3]);
+        assert_eq!(date_groups(&[None, None]), [0, 0]);
+        assert!(date_groups(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_image_copies() {
+        let path = |name: &str| PathBuf::from("photos").join(name);
+        assert_eq!(image_copies(&path("a.jpg"), 2, None).unwrap(), 2);
+        let csv = CaptionFile::parse("a.jpg,3\nb.jpg,0\nc.jpg,two\n", b',').unwrap();
+        let copies = |name: &str| image_copies(&path(name), 1, Some(&csv));
+        assert_eq!(copies("a.jpg").unwrap(), 3);
+        assert_eq!(copies("b.jpg").unwrap(), 0);
+        // 份数文件中没有的图片使用默认份数
+        assert_eq!(copies("d.jpg").unwrap(), 1);
+        assert!(matches!(copies("c.jpg"), Err(Error::Input { .. })));
+    }
+
+    #[test]
+    fn test_dry_run_report() {
+        let dir = std::env::temp_dir().join("image-typesetting-tool-dry-run");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let inputs = [
+            dir.join("a.png"),
+            dir.join("b.png"),
+            dir.join("missing.png"),
+        ];
+        image::RgbImage::new(400, 300).save(&inputs[0]).unwrap();
+        image::RgbImage::new(300, 400).save(&inputs[1]).unwrap();
+        let config = Config::from_options(&crate::LayoutOptions {
+            ppc: Some(10.0),
+            nh: Some(2),
+            nv: Some(1),
+            ..Default::default()
+        })
+        .unwrap();
+        let target_hs = [config.target_h_px; 3];
+        let report = |typesetter: Typesetter| {
+            let pages = typesetter.layout(&[Span::ONE; 3], &[0; 3]).unwrap();
+            let page_names = page_names(&pages, &[None, None, None], None);
+            let report = dry_run_report(&Plan {
+                config: &typesetter.config,
+                pages: &pages,
+                page_names: &page_names,
+                inputs: &inputs,
+                target_hs: &target_hs,
+            });
+            report.lines().map(str::to_string).collect::<Vec<_>>()
+        };
+        let entry = |line: &String, position: &str, index: usize| {
+            let prefix = format!("  {} {}  ", position, inputs[index].display());
+            line.starts_with(&prefix)
+        };
+        // 跳过第一个单元格：第一页只有一张图片，位于第二列
+        let skipped = report(Typesetter {
+            skip_cells: 1,
+            ..Typesetter::new(config.clone())
+        });
+        assert_eq!(skipped.len(), 2 + 2 + 3);
+        assert_eq!(skipped[2], "== output_0 ==");
+        assert!(entry(&skipped[3], "[0,1]", 0));
+        assert!(skipped[3].ends_with("DPI"));
+        assert_eq!(skipped[4], "== output_1 ==");
+        assert!(entry(&skipped[5], "[0,0]", 1));
+        // 无法读取的图片不报告尺寸
+        assert!(entry(&skipped[6], "[0,1]", 2));
+        assert!(!skipped[6].ends_with("DPI"));
+        // 双面排版：背面页按列镜像，b.png 位于 a.png 的背面
+        let duplex = report(Typesetter {
+            duplex: true,
+            ..Typesetter::new(config)
+        });
+        assert_eq!(duplex.len(), 2 + 3 + 2);
+        assert!(entry(&duplex[3], "[0,0]", 0));
+        assert!(entry(&duplex[4], "[0,1]", 2));
+        assert_eq!(duplex[5], "== output_1 ==");
+        assert!(entry(&duplex[6], "[0,1]", 1));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,224 @@
+//! 海报拼接
+//!
+//! 将一张大图放大到 N×M 张纸拼成的尺寸，分割为每张纸一页。
+//! 相邻页面重叠一段相同的内容便于粘贴，重叠区域的边界在纸张边距内画出对齐标记
+
+use image::{imageops, imageops::FilterType, DynamicImage, GenericImageView, Rgba, RgbaImage};
+use snafu::prelude::*;
+
+use crate::{
+    i18n::Msg,
+    paper::{Orientation, Paper},
+    Error, InputSnafu,
+};
+
+/// 对齐标记颜色
+const MARK_COLOR: Rgba<u8> = Rgba([0, 0, 0, 255]);
+/// 对齐标记长度上限 厘米
+const MARK_LENGTH: f64 = 0.5;
+/// 对齐标记线宽 厘米
+const MARK_WIDTH: f64 = 0.02;
+
+/// 海报参数
+#[derive(Debug, Clone, Copy)]
+pub struct Poster {
+    /// 每页纸张
+    pub paper: Paper,
+    /// 横向页数
+    pub cols: u32,
+    /// 纵向页数
+    pub rows: u32,
+    /// 相邻页面的重叠宽度（单位：cm）
+    pub overlap_cm: f64,
+    /// 纸张边距（单位：cm），打印机无法打印的区域
+    pub border_cm: f64,
+    /// 每厘米像素数
+    pub ppc: f64,
+}
+
+impl Poster {
+    /// 按方向调整纸张，未指定方向时取海报宽高比最接近图片的方向
+    ///
+    /// - dimensions: 图片尺寸
+    pub fn oriented(self, orientation: Option<Orientation>, (w, h): (u32, u32)) -> Poster {
+        let oriented = |orientation| Poster {
+            paper: self.paper.oriented(orientation),
+            ..self
+        };
+        if let Some(orientation) = orientation {
+            return oriented(orientation);
+        }
+        let aspect = (w.max(1) as f64 / h.max(1) as f64).ln();
+        [Orientation::Landscape, Orientation::Portrait]
+            .map(oriented)
+            .into_iter()
+            .min_by(|a, b| {
+                let diff = |poster: &Poster| {
+                    let (pw, ph) = poster.size_cm();
+                    ((pw / ph).ln() - aspect).abs()
+                };
+                diff(a).total_cmp(&diff(b))
+            })
+            .unwrap_or(self)
+    }
+
+    /// 每页可打印区域（单位：cm）
+    fn area_cm(&self) -> (f64, f64) {
+        (
+            self.paper.width_cm - 2.0 * self.border_cm,
+            self.paper.height_cm - 2.0 * self.border_cm,
+        )
+    }
+
+    /// 拼接后的海报尺寸（单位：cm），不含重叠部分
+    pub fn size_cm(&self) -> (f64, f64) {
+        let (aw, ah) = self.area_cm();
+        (
+            self.cols as f64 * aw - (self.cols - 1) as f64 * self.overlap_cm,
+            self.rows as f64 * ah - (self.rows - 1) as f64 * self.overlap_cm,
+        )
+    }
+
+    /// 分割图片，按行依次返回每页
+    ///
+    /// 图片按原宽高比缩放到海报尺寸内并居中，多余部分留白
+    pub fn render(
+        &self,
+        image: &DynamicImage,
+        filter: FilterType,
+    ) -> Result<Vec<RgbaImage>, Error> {
+        let (aw, ah) = self.area_cm();
+        ensure!(
+            self.cols > 0 && self.rows > 0 && aw > 0.0 && ah > 0.0,
+            InputSnafu {
+                reason: Msg::NoPrintableArea.to_string(),
+            }
+        );
+        ensure!(
+            self.overlap_cm >= 0.0 && self.overlap_cm < aw.min(ah) / 2.0,
+            InputSnafu {
+                reason: Msg::PosterOverlap {
+                    max_cm: aw.min(ah) / 2.0,
+                }
+                .to_string(),
+            }
+        );
+        let px = |cm: f64| (cm * self.ppc).round() as u32;
+        let (area_w, area_h) = (px(aw), px(ah));
+        let (step_w, step_h) = (px(aw - self.overlap_cm), px(ah - self.overlap_cm));
+        let overlap = px(self.overlap_cm);
+        let border = px(self.border_cm);
+        let (poster_w, poster_h) = (
+            step_w * (self.cols - 1) + area_w,
+            step_h * (self.rows - 1) + area_h,
+        );
+
+        let fitted = image.resize(poster_w, poster_h, filter).into_rgba8();
+        let mut poster = RgbaImage::from_pixel(poster_w, poster_h, Rgba([255; 4]));
+        let (fw, fh) = fitted.dimensions();
+        imageops::overlay(
+            &mut poster,
+            &fitted,
+            ((poster_w - fw) / 2) as i64,
+            ((poster_h - fh) / 2) as i64,
+        );
+
+        let mut pages = Vec::with_capacity((self.cols * self.rows) as usize);
+        for row in 0..self.rows {
+            for col in 0..self.cols {
+                let mut page = RgbaImage::from_pixel(
+                    px(self.paper.width_cm),
+                    px(self.paper.height_cm),
+                    Rgba([255; 4]),
+                );
+                let tile = poster.view(col * step_w, row * step_h, area_w, area_h);
+                imageops::replace(&mut page, &*tile, border as i64, border as i64);
+                // 与相邻页面重叠区域的边界
+                let mut xs = Vec::new();
+                let mut ys = Vec::new();
+                if col > 0 {
+                    xs.extend([border, border + overlap]);
+                }
+                if col + 1 < self.cols {
+                    xs.extend([border + area_w - overlap, border + area_w]);
+                }
+                if row > 0 {
+                    ys.extend([border, border + overlap]);
+                }
+                if row + 1 < self.rows {
+                    ys.extend([border + area_h - overlap, border + area_h]);
+                }
+                self.marks(&mut page, &xs, &ys, border, (area_w, area_h));
+                pages.push(page);
+            }
+        }
+        Ok(pages)
+    }
+
+    /// 在纸张边距内画出对齐标记
+    ///
+    /// - xs: 竖直边界的横坐标，标记画在上下边距内
+    /// - ys: 水平边界的纵坐标，标记画在左右边距内
+    fn marks(&self, page: &mut RgbaImage, xs: &[u32], ys: &[u32], border: u32, area: (u32, u32)) {
+        let length = ((MARK_LENGTH * self.ppc).round() as u32).min(border);
+        let width = ((MARK_WIDTH * self.ppc).round() as u32).max(1);
+        let (w, h) = page.dimensions();
+        let mut fill = |x0: u32, y0: u32, x1: u32, y1: u32| {
+            for y in y0..y1.min(h) {
+                for x in x0..x1.min(w) {
+                    page.put_pixel(x, y, MARK_COLOR);
+                }
+            }
+        };
+        for &x in xs {
+            let x = x.saturating_sub(width / 2);
+            fill(x, border - length, x + width, border);
+            fill(x, border + area.1, x + width, border + area.1 + length);
+        }
+        for &y in ys {
+            let y = y.saturating_sub(width / 2);
+            fill(border - length, y, border, y + width);
+            fill(border + area.0, y, border + area.0 + length, y + width);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poster() {
+        let poster = Poster {
+            paper: "10x8".parse().unwrap(),
+            cols: 2,
+            rows: 3,
+            overlap_cm: 1.0,
+            border_cm: 1.0,
+            ppc: 10.0,
+        }
+        .oriented(None, (100, 200));
+        // 竖向纸张 6×8 cm 可打印，拼接后 11×22 cm，接近图片宽高比
+        assert_eq!(poster.size_cm(), (11.0, 22.0));
+        // 左半黑右半白的图片
+        let image = RgbaImage::from_fn(100, 200, |x, _| match x < 50 {
+            true => Rgba([0, 0, 0, 255]),
+            false => Rgba([255; 4]),
+        });
+        let pages = poster
+            .render(&DynamicImage::ImageRgba8(image), FilterType::Triangle)
+            .unwrap();
+        assert_eq!(pages.len(), 6);
+        assert_eq!(pages[0].dimensions(), (80, 100));
+        assert_eq!(pages[0].get_pixel(15, 50).0, [0, 0, 0, 255]);
+        assert_eq!(pages[1].get_pixel(60, 50).0, [255; 4]);
+        // 第二列左侧边距内的对齐标记
+        assert_eq!(pages[1].get_pixel(10, 5).0, [0, 0, 0, 255]);
+        assert!(Poster {
+            overlap_cm: 5.0,
+            ..poster
+        }
+        .render(&DynamicImage::new_rgba8(1, 1), FilterType::Triangle)
+        .is_err());
+    }
+}
@@ -0,0 +1,294 @@
+//! Python 模块
+//!
+//! 构建：`maturin build --release`，见 `pyproject.toml`
+//!
+//! ```python
+//! import image_typesetting_tool as itt
+//!
+//! config = itt.Config(paper="a4", nh=4, nv=3, format="jpeg")
+//! pages = itt.typeset(["a.jpg", open("b.png", "rb").read()], config)
+//! arrays = itt.typeset_array(["a.jpg"], config)  # numpy 数组，形状为 (高, 宽, 3)
+//! ```
+
+use std::path::PathBuf;
+
+use image::{DynamicImage, RgbImage};
+use numpy::{PyArray, PyArray3, PyArrayMethods};
+use pyo3::{
+    exceptions::{PyRuntimeError, PyTypeError, PyValueError},
+    prelude::*,
+    types::{PyBool, PyBytes, PyDict, PyFloat, PyInt, PyString},
+};
+
+use crate::{
+    flatten,
+    i18n::Msg,
+    icc,
+    memory::{encode_page, MemoryOptions},
+    open_image_as, Config, Error, Typesetter,
+};
+
+/// 可设置的参数，与 [`MemoryOptions`] 的键相同
+const KEYS: &[&str] = &[
+    "paper",
+    "orientation",
+    "template",
+    "height",
+    "border",
+    "margin",
+    "gutter",
+    "gutter_side",
+    "ppc",
+    "ppi",
+    "nh",
+    "nv",
+    "scale",
+    "scale_margins",
+    "auto_grid",
+    "justify",
+    "bleed",
+    "frame",
+    "frame_color",
+    "corner_radius",
+    "style",
+    "rotate",
+    "align",
+    "fit",
+    "no_upscale",
+    "crop",
+    "filter",
+    "linear_light",
+    "sharpen",
+    "deskew",
+    "autocrop",
+    "brightness",
+    "contrast",
+    "auto_levels",
+    "color_profile",
+    "background",
+    "matte",
+    "qr_size",
+    "barcode_height",
+    "shadow",
+    "shadow_offset",
+    "shadow_blur",
+    "shadow_opacity",
+    "format",
+    "quality",
+];
+
+/// 转换为 Python 异常，参数与输入错误为 ValueError
+fn py_err(e: Error) -> PyErr {
+    match e {
+        Error::Config { .. } | Error::Input { .. } => PyValueError::new_err(e.to_string()),
+        _ => PyRuntimeError::new_err(e.to_string()),
+    }
+}
+
+/// 排版参数
+///
+/// 关键字参数与命令行中的同名参数相同，另有 `format`（png/jpeg）与 `quality`
+#[pyclass(name = "Config", module = "image_typesetting_tool")]
+struct PyConfig {
+    options: MemoryOptions,
+    config: Config,
+}
+
+#[pymethods]
+impl PyConfig {
+    #[new]
+    #[pyo3(signature = (**options))]
+    fn new(options: Option<&Bound<'_, PyDict>>) -> PyResult<PyConfig> {
+        let mut json = serde_json::Map::new();
+        for (key, value) in options.into_iter().flatten() {
+            let key: String = key.extract()?;
+            if !KEYS.contains(&key.as_str()) {
+                return Err(PyTypeError::new_err(Msg::OptionUnknown(&key).to_string()));
+            }
+            let value = if value.is_none() {
+                continue;
+            } else if value.is_instance_of::<PyBool>() {
+                serde_json::Value::from(value.extract::<bool>()?)
+            } else if value.is_instance_of::<PyInt>() {
+                serde_json::Value::from(value.extract::<i64>()?)
+            } else if value.is_instance_of::<PyFloat>() {
+                serde_json::Value::from(value.extract::<f64>()?)
+            } else if value.is_instance_of::<PyString>() {
+                serde_json::Value::from(value.extract::<String>()?)
+            } else {
+                return Err(PyTypeError::new_err(
+                    Msg::OptionTypeInvalid(&key).to_string(),
+                ));
+            };
+            json.insert(key, value);
+        }
+        let options = MemoryOptions::from_json(&serde_json::Value::Object(json).to_string())
+            .map_err(py_err)?;
+        Ok(PyConfig {
+            config: Config::from_options(&options.layout).map_err(py_err)?,
+            options,
+        })
+    }
+
+    /// 纸张尺寸 (宽, 高) 像素
+    #[getter]
+    fn paper_size(&self) -> (u32, u32) {
+        (self.config.paper_w_px, self.config.paper_h_px)
+    }
+
+    /// 网格 (横向数量, 纵向数量)
+    #[getter]
+    fn grid(&self) -> (u32, u32) {
+        (self.config.n_h, self.config.n_v)
+    }
+
+    /// 单张图片目标高度 像素
+    #[getter]
+    fn target_height(&self) -> u32 {
+        self.config.target_h_px
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Config(paper_size={:?}, grid={:?}, target_height={})",
+            self.paper_size(),
+            self.grid(),
+            self.target_height()
+        )
+    }
+}
+
+/// 图片来源：文件内容或文件路径
+#[derive(FromPyObject)]
+enum ImageSource {
+    Bytes(Vec<u8>),
+    Path(PathBuf),
+}
+
+/// 读取全部图片并排版，返回每页画布
+fn render(
+    py: Python<'_>,
+    images: Vec<ImageSource>,
+    config: Option<PyRef<'_, PyConfig>>,
+) -> PyResult<(Vec<RgbImage>, MemoryOptions)> {
+    let (options, config) = match config {
+        Some(config) => (config.options.clone(), config.config.clone()),
+        None => {
+            let options = MemoryOptions::from_json("").map_err(py_err)?;
+            let config = Config::from_options(&options.layout).map_err(py_err)?;
+            (options, config)
+        }
+    };
+    let pages = py.detach(|| {
+        let images = images
+            .iter()
+            .enumerate()
+            .map(|(i, source)| match source {
+                ImageSource::Bytes(bytes) => {
+                    let image = image::load_from_memory(bytes).map_err(|e| Error::Input {
+                        reason: Msg::MemoryImageUnreadable {
+                            index: i + 1,
+                            reason: e.to_string(),
+                        }
+                        .to_string(),
+                    })?;
+                    // 内存中的图片不读取内嵌配置文件，视为 sRGB
+                    match config.color_profile {
+                        Some(profile) => icc::convert(image, None, profile),
+                        None => Ok(image),
+                    }
+                }
+                ImageSource::Path(path) => open_image_as(path, config.color_profile),
+            })
+            .collect::<Result<Vec<DynamicImage>, Error>>()?;
+        Typesetter::new(config)
+            .render_pages(&images)
+            .map(|pages| pages.iter().map(flatten).collect())
+    });
+    Ok((pages.map_err(py_err)?, options))
+}
+
+/// 排版图片，返回每页编码后的图片 bytes
+///
+/// - images: 图片列表，每项为文件内容 bytes 或文件路径
+/// - config: 排版参数，省略时使用默认值
+#[pyfunction]
+#[pyo3(signature = (images, config = None))]
+fn typeset<'py>(
+    py: Python<'py>,
+    images: Vec<ImageSource>,
+    config: Option<PyRef<'py, PyConfig>>,
+) -> PyResult<Vec<Bound<'py, PyBytes>>> {
+    let (pages, options) = render(py, images, config)?;
+    pages
+        .iter()
+        .map(|page| {
+            let bytes = encode_page(page, &options).map_err(py_err)?;
+            Ok(PyBytes::new(py, &bytes))
+        })
+        .collect()
+}
+
+/// 排版图片，返回每页 RGB 像素，numpy 数组形状为 (高, 宽, 3)
+///
+/// - images: 图片列表，每项为文件内容 bytes 或文件路径
+/// - config: 排版参数，省略时使用默认值
+#[pyfunction]
+#[pyo3(signature = (images, config = None))]
+fn typeset_array<'py>(
+    py: Python<'py>,
+    images: Vec<ImageSource>,
+    config: Option<PyRef<'py, PyConfig>>,
+) -> PyResult<Vec<Bound<'py, PyArray3<u8>>>> {
+    let (pages, _) = render(py, images, config)?;
+    pages
+        .into_iter()
+        .map(|page| {
+            let shape = [page.height() as usize, page.width() as usize, 3];
+            PyArray::from_vec(py, page.into_raw()).reshape(shape)
+        })
+        .collect()
+}
+
+#[pymodule]
+fn image_typesetting_tool(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyConfig>()?;
+    m.add_function(wrap_pyfunction!(typeset, m)?)?;
+    m.add_function(wrap_pyfunction!(typeset_array, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::types::PyList;
+
+    #[test]
+    fn test_py_errors() {
+        Python::initialize();
+        Python::attach(|py| {
+            let config = |key: &str, value: &Bound<'_, PyAny>| {
+                let options = PyDict::new(py);
+                options.set_item(key, value).unwrap();
+                PyConfig::new(Some(&options))
+            };
+            // 参数无效时为 ValueError，不会 panic
+            let zero = 0u32.into_pyobject(py).unwrap().into_any();
+            let e = config("nh", &zero).err().unwrap();
+            assert!(e.is_instance_of::<PyValueError>(py));
+            let e = config("nv", &zero).err().unwrap();
+            assert!(e.is_instance_of::<PyValueError>(py));
+            // 未知参数与类型错误为 TypeError
+            let e = config("colour", &zero).err().unwrap();
+            assert!(e.is_instance_of::<PyTypeError>(py));
+            let list = PyList::empty(py).into_any();
+            let e = config("nh", &list).err().unwrap();
+            assert!(e.is_instance_of::<PyTypeError>(py));
+            // 读写错误为 RuntimeError
+            let io = std::io::Error::other("disk");
+            let e = py_err(Error::Io { source: io });
+            assert!(e.is_instance_of::<PyRuntimeError>(py));
+            assert!(PyConfig::new(None).is_ok());
+        });
+    }
+}
@@ -0,0 +1,80 @@
+//! 二维码
+//!
+//! 绘制在每张图片右侧排版时预留的空间内，M 级纠错
+
+use image::{imageops, Rgba, RgbaImage};
+use qrcode::{Color as Module, EcLevel, QrCode};
+
+use crate::{i18n::Msg, layout::Rect, Config, Error};
+
+/// 二维码四周空白的模块数
+const QUIET_ZONE: u32 = 4;
+
+/// 绘制为边长 side 像素的图像，含四周空白，模块按整数像素对齐
+///
+/// side 小于模块数时每个模块占 1 像素，图像随之变大
+pub fn render(value: &str, side: u32) -> Result<RgbaImage, Error> {
+    let code =
+        QrCode::with_error_correction_level(value, EcLevel::M).map_err(|e| Error::Input {
+            reason: Msg::QrFailed(e.to_string()).to_string(),
+        })?;
+    let width = code.width() as u32;
+    let n = width + 2 * QUIET_ZONE;
+    let scale = (side / n).max(1);
+    let side = side.max(n);
+    let offset = (side - n * scale) / 2 + QUIET_ZONE * scale;
+    let mut image = RgbaImage::from_pixel(side, side, Rgba([255, 255, 255, 255]));
+    for (i, module) in code.to_colors().into_iter().enumerate() {
+        if module != Module::Dark {
+            continue;
+        }
+        let (x, y) = (i as u32 % width, i as u32 / width);
+        for dy in 0..scale {
+            for dx in 0..scale {
+                image.put_pixel(
+                    offset + x * scale + dx,
+                    offset + y * scale + dy,
+                    Rgba([0, 0, 0, 255]),
+                );
+            }
+        }
+    }
+    Ok(image)
+}
+
+/// 在图片右侧预留的空间内绘制二维码，与图片底边对齐
+///
+/// - rect: 图片加上单元格样式装饰后的区域
+/// - cell: 图片所在单元格
+pub fn draw(
+    canvas: &mut RgbaImage,
+    value: &str,
+    config: &Config,
+    rect: Rect,
+    cell: Rect,
+) -> Result<(), Error> {
+    if config.qr_px == 0 {
+        return Ok(());
+    }
+    let qr = render(value, config.qr_px)?;
+    let x = rect.x + rect.w + config.qr_gap_px();
+    let y = (rect.y + rect.h).saturating_sub(qr.height()).max(cell.y);
+    imageops::overlay(canvas, &qr, x as i64, y as i64);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        // 版本 1 为 21 个模块，加上空白共 29 个，每个 3 像素
+        let image = render("IMG_0001", 90).unwrap();
+        assert_eq!(image.dimensions(), (90, 90));
+        assert_eq!(*image.get_pixel(12, 12), Rgba([255, 255, 255, 255]));
+        assert_eq!(*image.get_pixel(13, 13), Rgba([0, 0, 0, 255]));
+        assert_eq!(render("IMG_0001", 10).unwrap().width(), 29);
+        assert!(render(&"x".repeat(3000), 90).is_err());
+    }
+}
@@ -0,0 +1,157 @@
+//! 按内容裁切
+//!
+//! 铺满单元格时需要裁去图片的一部分，居中裁切容易切掉人物头部等主体。
+//! 以缩小后灰度图的梯度强度作为细节量，在可移动的方向上选取细节最多的窗口，
+//! 或以最大肤色区域为中心裁切
+
+use image::{imageops::FilterType, DynamicImage, GenericImageView};
+use imageproc::gradients::sobel_gradients;
+use serde::{Deserialize, Serialize};
+
+use crate::{layout::Rect, skin};
+
+/// 铺满时的裁切位置
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Crop {
+    /// 保留细节最多的区域
+    #[default]
+    Detail,
+    /// 以最大肤色区域的顶部为中心，找不到肤色区域时同 detail
+    #[value(alias = "face")]
+    #[serde(alias = "face")]
+    Skin,
+    /// 居中
+    Center,
+}
+
+/// 计算细节量前将图片缩小到的最大边长 像素
+const ENERGY_SIZE: u32 = 256;
+
+/// 缩放并裁切为 w×h，按 crop 选取裁切位置
+///
+/// 与 [`DynamicImage::resize_to_fill`] 相同，只是裁切位置不固定为居中
+pub fn resize_to_fill(
+    image: &DynamicImage,
+    w: u32,
+    h: u32,
+    filter: FilterType,
+    crop: Crop,
+) -> DynamicImage {
+    let (iw, ih) = image.dimensions();
+    if iw == 0 || ih == 0 || w == 0 || h == 0 {
+        return image.resize_to_fill(w, h, filter);
+    }
+    let window = fill_window(image, w, h, crop);
+    image
+        .crop_imm(window.x, window.y, window.w, window.h)
+        .resize_exact(w, h, filter)
+}
+
+/// 原图中缩放后铺满 w×h 的区域，按 crop 选取位置
+pub fn fill_window(image: &DynamicImage, w: u32, h: u32, crop: Crop) -> Rect {
+    let (iw, ih) = image.dimensions();
+    if iw == 0 || ih == 0 || w == 0 || h == 0 {
+        return Rect {
+            x: 0,
+            y: 0,
+            w: iw,
+            h: ih,
+        };
+    }
+    // 原图中与目标宽高比一致的最大窗口
+    let scale = (w as f64 / iw as f64).max(h as f64 / ih as f64);
+    let cw = ((w as f64 / scale).round() as u32).clamp(1, iw);
+    let ch = ((h as f64 / scale).round() as u32).clamp(1, ih);
+    let focus = match crop {
+        Crop::Skin => skin::locate(image),
+        _ => None,
+    };
+    // 窗口中心对准肤色区域，超出图片时靠边；没有时按细节量
+    let centered = |at: f64, full: u32, window: u32| {
+        ((at * full as f64 - window as f64 / 2.0).round().max(0.0) as u32).min(full - window)
+    };
+    let (x, y) = match (cw < iw, ch < ih, focus) {
+        _ if crop == Crop::Center => ((iw - cw) / 2, (ih - ch) / 2),
+        (true, _, Some((fx, _))) => (centered(fx, iw, cw), 0),
+        (_, true, Some((_, fy))) => (0, centered(fy, ih, ch)),
+        (true, _, None) => (best_offset(image, iw - cw, cw, true), 0),
+        (_, true, None) => (0, best_offset(image, ih - ch, ch, false)),
+        _ => (0, 0),
+    };
+    Rect { x, y, w: cw, h: ch }
+}
+
+/// 窗口在可移动方向上细节最多的起点，细节量相同时取最靠近居中的位置
+///
+/// - slack: 窗口可移动的范围 原图像素
+/// - window: 窗口长度 原图像素
+/// - horizontal: 窗口是否沿水平方向移动
+fn best_offset(image: &DynamicImage, slack: u32, window: u32, horizontal: bool) -> u32 {
+    let small = image
+        .resize(ENERGY_SIZE, ENERGY_SIZE, FilterType::Triangle)
+        .into_luma8();
+    let gradients = sobel_gradients(&small);
+    let (sw, sh) = gradients.dimensions();
+    let len = if horizontal { sw } else { sh };
+    // 每列或每行的细节量
+    let mut energy = vec![0u64; len as usize];
+    for (x, y, pixel) in gradients.enumerate_pixels() {
+        energy[if horizontal { x } else { y } as usize] += pixel.0[0] as u64;
+    }
+    let full = if horizontal {
+        image.width()
+    } else {
+        image.height()
+    };
+    let small_window =
+        ((window as f64 * len as f64 / full as f64).round() as usize).clamp(1, len as usize);
+    let positions = len as usize - small_window;
+    if positions == 0 {
+        return slack / 2;
+    }
+    let mut sums = Vec::with_capacity(positions + 1);
+    let mut sum: u64 = energy[..small_window].iter().sum();
+    sums.push(sum);
+    for i in 0..positions {
+        sum = sum + energy[i + small_window] - energy[i];
+        sums.push(sum);
+    }
+    let center = positions as f64 / 2.0;
+    let best = (0..=positions)
+        .max_by(|&a, &b| {
+            sums[a].cmp(&sums[b]).then(
+                (b as f64 - center)
+                    .abs()
+                    .total_cmp(&(a as f64 - center).abs()),
+            )
+        })
+        .unwrap_or(0);
+    ((best as f64 / positions as f64 * slack as f64).round() as u32).min(slack)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_resize_to_fill() {
+        // 细节集中在左侧的横幅图片裁成正方形时保留左侧
+        let banner =
+            RgbaImage::from_fn(400, 100, |x, y| match x < 100 && (x / 5 + y / 5) % 2 == 0 {
+                true => Rgba([0, 0, 0, 255]),
+                false => Rgba([255, 255, 255, 255]),
+            });
+        let banner = DynamicImage::ImageRgba8(banner);
+        let square =
+            resize_to_fill(&banner, 50, 50, FilterType::Triangle, Crop::Detail).into_rgba8();
+        assert_eq!(square.dimensions(), (50, 50));
+        assert!(square.pixels().any(|pixel| pixel.0[0] == 0));
+        // 没有细节时居中裁切
+        let plain = DynamicImage::ImageRgba8(RgbaImage::from_pixel(400, 100, Rgba([9; 4])));
+        let square = resize_to_fill(&plain, 50, 50, FilterType::Triangle, Crop::Skin);
+        assert_eq!(square.dimensions(), (50, 50));
+        assert_eq!(best_offset(&plain, 300, 100, true), 150);
+    }
+}
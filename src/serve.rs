@@ -0,0 +1,427 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::Cursor,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::atomic::Ordering,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use clap::ArgMatches;
+use image_typesetting_tool::{
+    i18n::Msg, panic_reason, template::LabelTemplate, Config, ConfigSnafu, Error, IoSnafu,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use snafu::prelude::*;
+use tiny_http::{Header, Method, Request, Response, Server, StatusCode};
+
+use crate::{config_file, typeset, watch::Rendered, Cli, PBData, INTERRUPTED};
+
+type Reply = Response<Cursor<Vec<u8>>>;
+
+/// 任务状态
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum JobState {
+    /// 等待上传图片与提交排版
+    Pending,
+    /// 正在排版
+    Running,
+    /// 已完成
+    Done,
+    /// 出错
+    Failed,
+}
+
+/// 排版任务
+#[derive(Serialize)]
+struct Job {
+    id: String,
+    state: JobState,
+    /// 已排版图片数
+    current: u64,
+    /// 图片总数
+    total: u64,
+    /// 已输出页数
+    pages_written: u64,
+    /// 总页数
+    pages_total: u64,
+    /// 出错原因
+    error: Option<String>,
+    /// 警告信息，如无法读取的图片
+    warnings: Vec<String>,
+    /// 输出文件名
+    files: Vec<String>,
+    /// 任务目录，包含 input 与 output
+    #[serde(skip)]
+    dir: PathBuf,
+}
+
+type Jobs = Arc<Mutex<HashMap<String, Job>>>;
+
+/// 指向服务器本地文件的参数，请求中不可指定
+const LOCAL_PATH_KEYS: &[&str] = &[
+    "config",
+    "input",
+    "files",
+    "files_from",
+    "output",
+    "font",
+    "header_font",
+    "captions_file",
+    "cmyk_profile",
+    "watermark",
+    "qr_csv",
+    "barcode_csv",
+    "image_copies_csv",
+    "order",
+    "manifest",
+];
+
+/// 启动 HTTP 服务，按 Ctrl+C 退出
+///
+/// 接口：
+/// - `POST /jobs` 新建任务
+/// - `PUT /jobs/{id}/images/{name}` 上传图片，请求体为图片文件内容
+/// - `POST /jobs/{id}/render` 开始排版，请求体为 JSON 对象，键与配置文件相同
+/// - `GET /jobs/{id}` 查询任务状态与进度
+/// - `GET /jobs/{id}/files/{name}` 下载输出文件
+/// - `DELETE /jobs/{id}` 删除任务及其文件
+///
+/// - cli: 命令行参数，作为每个任务的默认参数
+/// - addr: 监听地址
+/// - workspace: 任务文件存放目录
+pub fn serve(cli: &Cli, addr: &str, workspace: &Path) -> Result<(), Error> {
+    let server = Server::http(addr).map_err(|e| Error::Config {
+        reason: Msg::ListenFailed {
+            addr,
+            reason: e.to_string(),
+        }
+        .to_string(),
+    })?;
+    fs::create_dir_all(workspace).context(IoSnafu)?;
+    println!("{}", Msg::Serving(addr));
+    let jobs: Jobs = Arc::default();
+    while !INTERRUPTED.load(Ordering::SeqCst) {
+        let Some(mut request) = server
+            .recv_timeout(Duration::from_millis(200))
+            .context(IoSnafu)?
+        else {
+            continue;
+        };
+        let reply = handle(cli, workspace, &jobs, &mut request);
+        let cors = Header::from_bytes("Access-Control-Allow-Origin", "*").unwrap();
+        let _ = request.respond(reply.with_header(cors));
+    }
+    Ok(())
+}
+
+/// JSON 响应
+fn reply_json(status: u16, value: serde_json::Value) -> Reply {
+    let header = Header::from_bytes("Content-Type", "application/json").unwrap();
+    Response::from_data(value.to_string().into_bytes())
+        .with_status_code(StatusCode(status))
+        .with_header(header)
+}
+
+/// 错误响应
+fn reply_error(status: u16, reason: impl ToString) -> Reply {
+    reply_json(status, json!({ "error": reason.to_string() }))
+}
+
+/// 按扩展名判断文件类型
+fn content_type(name: &str) -> &'static str {
+    let ext = name
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase());
+    match ext.as_deref() {
+        Some("png") => "image/png",
+        Some("jpg") => "image/jpeg",
+        Some("webp") => "image/webp",
+        Some("tiff") => "image/tiff",
+        Some("pdf") => "application/pdf",
+        Some("json") => "application/json",
+        Some("html") => "text/html; charset=utf-8",
+        Some("txt") => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+/// 解码 URL 中的文件名，拒绝包含路径的名称
+fn file_name(segment: &str) -> Option<String> {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    let name = String::from_utf8(decoded).ok()?;
+    let valid = !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\']);
+    valid.then_some(name)
+}
+
+/// 新建任务编号
+fn new_job_id(jobs: &HashMap<String, Job>) -> String {
+    let mut seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64);
+    loop {
+        let id = format!("{:x}", seed);
+        if !jobs.contains_key(&id) {
+            return id;
+        }
+        seed += 1;
+    }
+}
+
+/// 处理单个请求
+fn handle(cli: &Cli, workspace: &Path, jobs: &Jobs, request: &mut Request) -> Reply {
+    let url = request.url().to_string();
+    let url = url.split(['?', '#']).next().unwrap_or_default();
+    let segments: Vec<&str> = url.split('/').filter(|s| !s.is_empty()).collect();
+    let method = request.method().clone();
+    // 先读取请求体再锁定任务表，避免慢速上传阻塞其他请求
+    let mut body = Vec::new();
+    if let Err(e) = request.as_reader().read_to_end(&mut body) {
+        return reply_error(400, e);
+    }
+    let Ok(mut jobs_guard) = jobs.lock() else {
+        return reply_error(500, Msg::JobsUnavailable);
+    };
+    match (&method, segments.as_slice()) {
+        (Method::Post, ["jobs"]) => {
+            let id = new_job_id(&jobs_guard);
+            let dir = workspace.join(&id);
+            if let Err(e) = fs::create_dir_all(dir.join("input")) {
+                return reply_error(500, e);
+            }
+            let job = Job {
+                id: id.clone(),
+                state: JobState::Pending,
+                current: 0,
+                total: 0,
+                pages_written: 0,
+                pages_total: 0,
+                error: None,
+                warnings: Vec::new(),
+                files: Vec::new(),
+                dir,
+            };
+            let reply = reply_json(201, json!(job));
+            jobs_guard.insert(id, job);
+            reply
+        }
+        (_, ["jobs", id, ..]) if !jobs_guard.contains_key(*id) => {
+            reply_error(404, Msg::JobMissing(id))
+        }
+        (Method::Get, ["jobs", id]) => reply_json(200, json!(jobs_guard[*id])),
+        (Method::Delete, ["jobs", id]) => {
+            if jobs_guard[*id].state == JobState::Running {
+                return reply_error(409, Msg::JobRunning);
+            }
+            if let Some(job) = jobs_guard.remove(*id) {
+                let _ = fs::remove_dir_all(job.dir);
+            }
+            reply_json(200, json!({ "deleted": id }))
+        }
+        (Method::Put | Method::Post, ["jobs", id, "images", name]) => {
+            let job = &jobs_guard[*id];
+            if job.state == JobState::Running {
+                return reply_error(409, Msg::JobRunning);
+            }
+            let Some(name) = file_name(name) else {
+                return reply_error(400, Msg::FileNameInvalid);
+            };
+            match fs::write(job.dir.join("input").join(&name), body) {
+                Ok(_) => reply_json(201, json!({ "uploaded": name })),
+                Err(e) => reply_error(500, e),
+            }
+        }
+        (Method::Post, ["jobs", id, "render"]) => {
+            if jobs_guard[*id].state == JobState::Running {
+                return reply_error(409, Msg::JobRunning);
+            }
+            let body = match String::from_utf8(body) {
+                Ok(body) => body,
+                Err(e) => return reply_error(400, e),
+            };
+            let dir = jobs_guard[*id].dir.clone();
+            // 参数无效时直接拒绝，不启动任务
+            let job_cli = job_options(cli, &body, &dir).and_then(|job_cli| {
+                Config::from_options(&job_cli.layout_options())?;
+                Ok(job_cli)
+            });
+            let job_cli = match job_cli {
+                Ok(job_cli) => job_cli,
+                Err(e) => return reply_error(400, e),
+            };
+            let job = jobs_guard.get_mut(*id).unwrap();
+            job.state = JobState::Running;
+            job.error = None;
+            job.warnings.clear();
+            job.files.clear();
+            spawn_job(jobs.clone(), id.to_string(), job_cli);
+            reply_json(202, json!(job))
+        }
+        (Method::Get, ["jobs", id, "files", name]) => {
+            let Some(name) = file_name(name) else {
+                return reply_error(400, Msg::FileNameInvalid);
+            };
+            match fs::read(jobs_guard[*id].dir.join("output").join(&name)) {
+                Ok(data) => {
+                    let header = Header::from_bytes("Content-Type", content_type(&name)).unwrap();
+                    Response::from_data(data).with_header(header)
+                }
+                Err(_) => reply_error(404, Msg::FileMissing(&name)),
+            }
+        }
+        _ => reply_error(404, Msg::UnknownEndpoint),
+    }
+}
+
+/// 合并请求中的排版参数，输入输出固定为任务目录
+///
+/// 请求指定服务器本地文件（见 [`LOCAL_PATH_KEYS`]）或模板文件时返回错误，
+/// 这些参数只能由服务器的命令行或配置文件给出
+///
+/// - cli: 默认参数
+/// - body: 请求体，JSON 对象，为空时使用默认参数
+/// - dir: 任务目录
+fn job_options(cli: &Cli, body: &str, dir: &Path) -> Result<Cli, Error> {
+    let body = if body.trim().is_empty() { "{}" } else { body };
+    let table = serde_json::from_str::<serde_json::Value>(body)
+        .map_err(|e| e.to_string())
+        .and_then(|value| toml::Table::deserialize(value).map_err(|e| e.to_string()))
+        .map_err(|reason| Error::Config { reason })?;
+    for (key, value) in &table {
+        let id = key.replace('-', "_");
+        // 模板只能使用预设，不读取模板文件
+        let local = match id.as_str() {
+            "template" => !value.as_str().is_some_and(|name| {
+                LabelTemplate::preset_names().any(|preset| name.eq_ignore_ascii_case(preset))
+            }),
+            id => LOCAL_PATH_KEYS.contains(&id),
+        };
+        ensure!(
+            !local,
+            ConfigSnafu {
+                reason: Msg::RequestLocalPath(key).to_string(),
+            }
+        );
+    }
+    let mut job_cli = config_file::merge(cli, &ArgMatches::default(), table, Path::new("request"))?;
+    job_cli.command = None;
+    job_cli.input = vec![dir.join("input").to_string_lossy().into_owned()];
+    job_cli.files = Vec::new();
+    job_cli.files_from = None;
+    job_cli.recursive = false;
+    job_cli.output = Some(dir.join("output").to_string_lossy().into_owned());
+    job_cli.overwrite = true;
+    job_cli.clean = false;
+    // 排列清单、图片清单与说明文字文件按服务器的输入编写，不用于任务
+    job_cli.order = None;
+    job_cli.manifest = None;
+    job_cli.captions_file = None;
+    job_cli.dry_run = false;
+    job_cli.watch = false;
+    job_cli.quiet = true;
+    Ok(job_cli)
+}
+
+/// 在后台排版，并将进度写入任务状态
+fn spawn_job(jobs: Jobs, id: String, cli: Cli) {
+    thread::spawn(move || {
+        let (tx, rx) = mpsc::channel::<PBData>();
+        let progress = {
+            let jobs = jobs.clone();
+            let id = id.clone();
+            thread::spawn(move || {
+                while let Ok(data) = rx.recv() {
+                    let Ok(mut jobs) = jobs.lock() else { break };
+                    let Some(job) = jobs.get_mut(&id) else { break };
+                    match data {
+                        PBData::Stop => break,
+                        PBData::NewTotal(n) => (job.current, job.total) = (0, n),
+                        PBData::NextTotal => job.current += 1,
+                        PBData::NewOutput(n) => (job.pages_written, job.pages_total) = (0, n),
+                        PBData::NextOutput => job.pages_written += 1,
+                        PBData::Warn(message) => job.warnings.push(message),
+                        _ => {}
+                    }
+                }
+            })
+        };
+        let output = PathBuf::from(cli.output.clone().unwrap_or_default());
+        let _ = fs::remove_dir_all(&output);
+        // 排版中的意外错误同样记为任务失败，避免任务一直处于排版状态
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            typeset(&cli, &mut Rendered::default(), Some(tx)).map_err(|e| e.to_string())
+        }))
+        .unwrap_or_else(|payload| Err(panic_reason(payload.as_ref())));
+        let _ = progress.join();
+        let mut files: Vec<String> = fs::read_dir(&output)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect();
+        files.sort();
+        let Ok(mut jobs) = jobs.lock() else { return };
+        let Some(job) = jobs.get_mut(&id) else { return };
+        job.files = files;
+        match result {
+            Ok(_) => job.state = JobState::Done,
+            Err(e) => {
+                job.state = JobState::Failed;
+                job.error = Some(e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(file_name("a.png").as_deref(), Some("a.png"));
+        assert_eq!(file_name("%E5%9B%BE%201.jpg").as_deref(), Some("图 1.jpg"));
+        for segment in [
+            "", ".", "..", "%2E%2E", "%2F", "a%2Fb", "a%5Cb", "a\\b", "%zz", "a%4",
+        ] {
+            assert_eq!(file_name(segment), None, "{}", segment);
+        }
+    }
+
+    #[test]
+    fn test_job_options() {
+        let cli = Cli::parse_from(["image-typesetting-tool", "--font", "server.ttf"]);
+        let dir = Path::new("jobs").join("1");
+        let job_cli = job_options(&cli, r#"{"nh": 2, "template": "avery-5160"}"#, &dir).unwrap();
+        assert_eq!(job_cli.nh, Some(2));
+        assert_eq!(job_cli.font.as_deref(), Some("server.ttf"));
+        assert_eq!(job_cli.input, [dir.join("input").to_string_lossy()]);
+        // 指向服务器本地文件的参数一律拒绝
+        for key in LOCAL_PATH_KEYS.iter().chain(&["template", "qr-csv"]) {
+            let body = json!({ *key: "/etc/passwd" }).to_string();
+            assert!(
+                matches!(job_options(&cli, &body, &dir), Err(Error::Config { .. })),
+                "{}",
+                key
+            );
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! 肤色区域定位
+//!
+//! 在缩小的图片中按 YCbCr 肤色范围标出肤色像素，取面积最大的连通区域，
+//! 返回其顶部的中心。这只是颜色启发式，并不识别人脸：肤色相近的背景、
+//! 木纹或暖色调光线都会被当作肤色，多人合影也只取最大的一块。
+//! 找不到足够大的肤色区域时返回 None
+
+use image::{imageops::FilterType, DynamicImage, GrayImage, Luma};
+use imageproc::region_labelling::{connected_components, Connectivity};
+
+/// 检测前将图片缩小到的最大边长 像素
+const DETECT_SIZE: u32 = 256;
+/// 肤色区域至少占图片面积的比例
+const MIN_AREA: f64 = 0.005;
+
+/// 是否为肤色，按 Chai 与 Ngan 的 Cb、Cr 范围判断
+fn is_skin([r, g, b]: [u8; 3]) -> bool {
+    let (r, g, b) = (r as f32, g as f32, b as f32);
+    let cb = 128.0 - 0.168736 * r - 0.331264 * g + 0.5 * b;
+    let cr = 128.0 + 0.5 * r - 0.418688 * g - 0.081312 * b;
+    (77.0..=127.0).contains(&cb) && (133.0..=173.0).contains(&cr)
+}
+
+/// 最大肤色区域顶部的中心，坐标为相对宽高的比例 0~1
+pub fn locate(image: &DynamicImage) -> Option<(f64, f64)> {
+    let small = image
+        .resize(DETECT_SIZE, DETECT_SIZE, FilterType::Triangle)
+        .into_rgba8();
+    let (w, h) = small.dimensions();
+    let mask = GrayImage::from_fn(w, h, |x, y| {
+        let [r, g, b, a] = small.get_pixel(x, y).0;
+        Luma([u8::from(a > 0 && is_skin([r, g, b]))])
+    });
+    let labels = connected_components(&mask, Connectivity::Eight, Luma([0]));
+
+    // 每个区域的面积与外接矩形
+    let mut regions: Vec<(u32, u32, u32, u32, u32)> = Vec::new();
+    for (x, y, label) in labels.enumerate_pixels() {
+        let label = label.0[0] as usize;
+        if label == 0 {
+            continue;
+        }
+        if regions.len() < label {
+            regions.resize(label, (0, u32::MAX, u32::MAX, 0, 0));
+        }
+        let (area, x0, y0, x1, y1) = &mut regions[label - 1];
+        *area += 1;
+        (*x0, *y0) = ((*x0).min(x), (*y0).min(y));
+        (*x1, *y1) = ((*x1).max(x), (*y1).max(y));
+    }
+    let &(area, x0, y0, x1, y1) = regions.iter().max_by_key(|region| region.0)?;
+    (area as f64 >= (w * h) as f64 * MIN_AREA).then_some(())?;
+    // 区域向下延伸时（脖子、手臂）只取顶部，高度不超过宽度的 1.3 倍
+    let top_w = (x1 - x0 + 1) as f64;
+    let top_h = ((y1 - y0 + 1) as f64).min(top_w * 1.3);
+    let cx = (x0 as f64 + top_w / 2.0) / w as f64;
+    let cy = (y0 as f64 + top_h / 2.0) / h as f64;
+    Some((cx, cy))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+    use imageproc::drawing::draw_filled_ellipse_mut;
+
+    #[test]
+    fn test_locate() {
+        // 蓝色背景右侧的肤色椭圆
+        let mut photo = RgbaImage::from_pixel(400, 200, Rgba([40, 60, 160, 255]));
+        draw_filled_ellipse_mut(&mut photo, (320, 80), 30, 40, Rgba([224, 172, 140, 255]));
+        let (x, y) = locate(&DynamicImage::ImageRgba8(photo)).unwrap();
+        assert!(
+            (x - 0.8).abs() < 0.03 && (y - 0.4).abs() < 0.05,
+            "{} {}",
+            x,
+            y
+        );
+        let plain = RgbaImage::from_pixel(40, 20, Rgba([40, 60, 160, 255]));
+        assert_eq!(locate(&DynamicImage::ImageRgba8(plain)), None);
+    }
+}
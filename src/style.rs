@@ -0,0 +1,110 @@
+//! 单元格样式
+//!
+//! 在缩放后的图片外加装饰，得到实际放置在单元格中的图片。
+//! 装饰占用的空间在排版时从单元格中预留
+
+use image::{imageops, DynamicImage, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+/// 拍立得相框颜色
+const POLAROID_COLOR: Rgba<u8> = Rgba([252, 252, 250, 255]);
+
+/// 拍立得相框左、右、上边宽度与单元格高度之比
+const POLAROID_SIDE: f64 = 0.05;
+
+/// 拍立得相框下边宽度与单元格高度之比
+const POLAROID_BOTTOM: f64 = 0.18;
+
+/// 单元格样式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CellStyle {
+    /// 仅图片
+    #[default]
+    Plain,
+    /// 拍立得：白色相框，下边较宽，说明文字写在下边内
+    Polaroid,
+}
+
+/// 四周占用的宽度 像素，用于单元格样式的装饰与纸张边距
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Insets {
+    pub left: u32,
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+}
+
+impl Insets {
+    /// 横向合计
+    pub fn horizontal(&self) -> u32 {
+        self.left + self.right
+    }
+
+    /// 纵向合计
+    pub fn vertical(&self) -> u32 {
+        self.top + self.bottom
+    }
+}
+
+impl CellStyle {
+    /// 计算装饰占用的宽度
+    ///
+    /// - cell_h: 单元格高度 像素
+    /// - caption_h: 说明文字所需高度 像素，样式可将说明文字放在装饰内
+    pub fn insets(self, cell_h: u32, caption_h: u32) -> Insets {
+        match self {
+            CellStyle::Plain => Insets::default(),
+            CellStyle::Polaroid => {
+                let side = (cell_h as f64 * POLAROID_SIDE).round() as u32;
+                let bottom = (cell_h as f64 * POLAROID_BOTTOM).round() as u32;
+                Insets {
+                    left: side,
+                    top: side,
+                    right: side,
+                    bottom: bottom.max(caption_h + side),
+                }
+            }
+        }
+    }
+
+    /// 说明文字是否写在装饰内，不另外预留空间
+    pub fn holds_caption(self) -> bool {
+        self == CellStyle::Polaroid
+    }
+
+    /// 为缩放后的图片加上装饰
+    pub fn decorate(self, image: DynamicImage, insets: Insets) -> DynamicImage {
+        match self {
+            CellStyle::Plain => image,
+            CellStyle::Polaroid => {
+                let mut card = RgbaImage::from_pixel(
+                    image.width() + insets.horizontal(),
+                    image.height() + insets.vertical(),
+                    POLAROID_COLOR,
+                );
+                imageops::overlay(&mut card, &image, insets.left as i64, insets.top as i64);
+                DynamicImage::ImageRgba8(card)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_polaroid() {
+        let insets = CellStyle::Polaroid.insets(200, 0);
+        assert_eq!((insets.left, insets.top, insets.bottom), (10, 10, 36));
+        assert_eq!(CellStyle::Polaroid.insets(200, 40).bottom, 50);
+        assert_eq!(CellStyle::Plain.insets(200, 40), Insets::default());
+
+        let photo = DynamicImage::ImageRgba8(RgbaImage::from_pixel(30, 20, Rgba([0, 0, 0, 255])));
+        let card = CellStyle::Polaroid.decorate(photo, insets).into_rgba8();
+        assert_eq!(card.dimensions(), (50, 66));
+        assert_eq!(*card.get_pixel(5, 5), POLAROID_COLOR);
+        assert_eq!(*card.get_pixel(15, 15), Rgba([0, 0, 0, 255]));
+    }
+}
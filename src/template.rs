@@ -0,0 +1,333 @@
+//! 标签纸模板
+//!
+//! 预先模切的不干胶标签纸上每个标签的位置固定，按模板指定的标签尺寸、
+//! 起始位置与间距排版，替代按纸张与数量计算的均匀网格
+
+use serde::{Deserialize, Serialize};
+use std::{fmt, fs, path::Path, str::FromStr};
+
+use crate::{
+    i18n::Msg,
+    paper::{Orientation, Paper},
+};
+
+/// 标签形状
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LabelShape {
+    /// 矩形
+    #[default]
+    Rect,
+    /// 圆形或椭圆，图片裁为对应形状
+    Round,
+}
+
+/// 标签纸模板，长度单位均为毫米
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct LabelTemplate {
+    /// 预设名称或模板文件路径
+    pub name: String,
+    /// 纸张
+    pub paper: Paper,
+    /// 横向标签数量
+    pub cols: u32,
+    /// 纵向标签数量
+    pub rows: u32,
+    /// 标签宽度
+    pub width: f64,
+    /// 标签高度
+    pub height: f64,
+    /// 第一列标签左边缘到纸张左边缘的距离
+    pub left: f64,
+    /// 第一行标签上边缘到纸张上边缘的距离
+    pub top: f64,
+    /// 相邻两列标签左边缘的距离
+    pub pitch_x: f64,
+    /// 相邻两行标签上边缘的距离
+    pub pitch_y: f64,
+    /// 标签形状
+    pub shape: LabelShape,
+}
+
+/// 模板文件内容，纸张默认竖向，间距默认与标签尺寸相同（标签紧挨排列）
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TemplateFile {
+    paper: Paper,
+    #[serde(default)]
+    orientation: Option<Orientation>,
+    cols: u32,
+    rows: u32,
+    width: f64,
+    height: f64,
+    left: f64,
+    top: f64,
+    pitch_x: Option<f64>,
+    pitch_y: Option<f64>,
+    #[serde(default)]
+    shape: LabelShape,
+}
+
+const INCH: f64 = 25.4;
+
+/// 预设模板，纸张竖向
+struct Preset {
+    name: &'static str,
+    paper: Paper,
+    /// 列数与行数
+    grid: (u32, u32),
+    /// 标签宽高
+    size: (f64, f64),
+    /// 第一个标签左上角位置
+    origin: (f64, f64),
+    /// 横向与纵向间距
+    pitch: (f64, f64),
+    shape: LabelShape,
+}
+
+const LETTER: Paper = Paper {
+    width_cm: 21.59,
+    height_cm: 27.94,
+};
+const A4: Paper = Paper {
+    width_cm: 21.0,
+    height_cm: 29.7,
+};
+
+const PRESETS: &[Preset] = &[
+    // Avery 5160/8160 地址标签 1" × 2-5/8"，30 枚
+    Preset {
+        name: "avery-5160",
+        paper: LETTER,
+        grid: (3, 10),
+        size: (2.625 * INCH, INCH),
+        origin: (0.1875 * INCH, 0.5 * INCH),
+        pitch: (2.75 * INCH, INCH),
+        shape: LabelShape::Rect,
+    },
+    // Avery 5163/8163 运输标签 2" × 4"，10 枚
+    Preset {
+        name: "avery-5163",
+        paper: LETTER,
+        grid: (2, 5),
+        size: (4.0 * INCH, 2.0 * INCH),
+        origin: (0.15625 * INCH, 0.5 * INCH),
+        pitch: (4.1875 * INCH, 2.0 * INCH),
+        shape: LabelShape::Rect,
+    },
+    // Avery 5167 回邮标签 1/2" × 1-3/4"，80 枚
+    Preset {
+        name: "avery-5167",
+        paper: LETTER,
+        grid: (4, 20),
+        size: (1.75 * INCH, 0.5 * INCH),
+        origin: (0.3 * INCH, 0.5 * INCH),
+        pitch: (2.05 * INCH, 0.5 * INCH),
+        shape: LabelShape::Rect,
+    },
+    // Avery 22807 圆形标签 直径 2"，12 枚
+    Preset {
+        name: "avery-22807",
+        paper: LETTER,
+        grid: (3, 4),
+        size: (2.0 * INCH, 2.0 * INCH),
+        origin: (0.75 * INCH, 0.625 * INCH),
+        pitch: (2.5 * INCH, 2.5 * INCH),
+        shape: LabelShape::Round,
+    },
+    // Avery L7160 63.5 × 38.1 mm，21 枚
+    Preset {
+        name: "avery-l7160",
+        paper: A4,
+        grid: (3, 7),
+        size: (63.5, 38.1),
+        origin: (7.2, 15.15),
+        pitch: (66.0, 38.1),
+        shape: LabelShape::Rect,
+    },
+    // Avery L7163 99.1 × 38.1 mm，14 枚
+    Preset {
+        name: "avery-l7163",
+        paper: A4,
+        grid: (2, 7),
+        size: (99.1, 38.1),
+        origin: (4.65, 15.15),
+        pitch: (101.6, 38.1),
+        shape: LabelShape::Rect,
+    },
+    // 通用 A4 圆形贴纸 直径 40 mm，24 枚
+    Preset {
+        name: "round-24",
+        paper: A4,
+        grid: (4, 6),
+        size: (40.0, 40.0),
+        origin: (13.75, 13.5),
+        pitch: (47.5, 46.0),
+        shape: LabelShape::Round,
+    },
+];
+
+impl LabelTemplate {
+    /// 预设模板名称
+    pub fn preset_names() -> impl Iterator<Item = &'static str> {
+        PRESETS.iter().map(|preset| preset.name)
+    }
+
+    /// 读取 TOML 模板文件
+    pub fn load(path: &Path) -> Result<LabelTemplate, String> {
+        let display = path.display().to_string();
+        let text = fs::read_to_string(path).map_err(|e| {
+            let reason = e.to_string();
+            Msg::TemplateUnreadable {
+                path: &display,
+                reason,
+            }
+            .to_string()
+        })?;
+        let file: TemplateFile = toml::from_str(&text).map_err(|e| {
+            let reason = e.to_string();
+            Msg::TemplateInvalid {
+                path: &display,
+                reason,
+            }
+            .to_string()
+        })?;
+        LabelTemplate {
+            name: path.display().to_string(),
+            paper: file
+                .paper
+                .oriented(file.orientation.unwrap_or(Orientation::Portrait)),
+            cols: file.cols,
+            rows: file.rows,
+            width: file.width,
+            height: file.height,
+            left: file.left,
+            top: file.top,
+            pitch_x: file.pitch_x.unwrap_or(file.width),
+            pitch_y: file.pitch_y.unwrap_or(file.height),
+            shape: file.shape,
+        }
+        .validate()
+    }
+
+    /// 检查标签数量与尺寸，标签不能重叠或超出纸张
+    fn validate(self) -> Result<LabelTemplate, String> {
+        let fits = |start: f64, size: f64, pitch: f64, n: u32, paper_mm: f64| {
+            n > 0
+                && size > 0.0
+                && start >= 0.0
+                && pitch >= size
+                && start + (n - 1) as f64 * pitch + size <= paper_mm + 0.01
+        };
+        match fits(
+            self.left,
+            self.width,
+            self.pitch_x,
+            self.cols,
+            self.paper.width_cm * 10.0,
+        ) && fits(
+            self.top,
+            self.height,
+            self.pitch_y,
+            self.rows,
+            self.paper.height_cm * 10.0,
+        ) {
+            true => Ok(self),
+            false => Err(Msg::TemplateOverlap {
+                name: &self.name,
+                paper: &self.paper.to_string(),
+            }
+            .to_string()),
+        }
+    }
+}
+
+impl fmt::Display for LabelTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+impl From<LabelTemplate> for String {
+    fn from(template: LabelTemplate) -> Self {
+        template.name
+    }
+}
+
+impl TryFrom<String> for LabelTemplate {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl FromStr for LabelTemplate {
+    type Err = String;
+
+    /// 解析模板，支持预设名称或 TOML 模板文件路径
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+        if let Some(preset) = PRESETS.iter().find(|preset| preset.name == name) {
+            return Ok(LabelTemplate {
+                name: preset.name.to_string(),
+                paper: preset.paper,
+                cols: preset.grid.0,
+                rows: preset.grid.1,
+                width: preset.size.0,
+                height: preset.size.1,
+                left: preset.origin.0,
+                top: preset.origin.1,
+                pitch_x: preset.pitch.0,
+                pitch_y: preset.pitch.1,
+                shape: preset.shape,
+            });
+        }
+        let path = Path::new(s.trim());
+        match path.is_file() {
+            true => LabelTemplate::load(path),
+            false => Err(Msg::TemplateUnknown {
+                name: s,
+                presets: &LabelTemplate::preset_names().collect::<Vec<_>>(),
+            }
+            .to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_label_template() {
+        for name in LabelTemplate::preset_names() {
+            let template: LabelTemplate = name.parse().unwrap();
+            assert!(template.clone().validate().is_ok(), "{}", name);
+        }
+        let template: LabelTemplate = "Avery-5160".parse().unwrap();
+        assert_eq!((template.cols, template.rows), (3, 10));
+        assert!(template.paper.width_cm < template.paper.height_cm);
+
+        let dir = std::env::temp_dir().join("image-typesetting-tool-template");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("labels.toml");
+        fs::write(
+            &path,
+            "paper = \"a4\"\ncols = 2\nrows = 2\nwidth = 90\nheight = 50\nleft = 10\ntop = 10\npitch_x = 100\nshape = \"round\"\n",
+        )
+        .unwrap();
+        let template = LabelTemplate::load(&path).unwrap();
+        assert_eq!(template.pitch_y, 50.0);
+        assert_eq!(template.shape, LabelShape::Round);
+        fs::write(
+            &path,
+            "paper = \"a4\"\ncols = 3\nrows = 1\nwidth = 90\nheight = 50\nleft = 10\ntop = 10\n",
+        )
+        .unwrap();
+        assert!(LabelTemplate::load(&path).is_err());
+        let _ = fs::remove_dir_all(&dir);
+        assert!("avery-0000".parse::<LabelTemplate>().is_err());
+    }
+}
@@ -0,0 +1,90 @@
+//! 文字绘制
+//!
+//! 未指定字体时依次尝试常见的系统字体，优先选择支持中文的字体
+
+use image::{Rgba, RgbaImage};
+use imageproc::drawing::{draw_text_mut, text_size};
+use rusttype::Scale;
+use std::{fs, path::Path};
+
+use crate::{i18n::Msg, Error};
+
+/// 常见系统字体路径
+const SYSTEM_FONTS: &[&str] = &[
+    "C:/Windows/Fonts/msyh.ttc",
+    "C:/Windows/Fonts/simhei.ttf",
+    "/System/Library/Fonts/PingFang.ttc",
+    "/System/Library/Fonts/STHeiti Medium.ttc",
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+];
+
+/// 已加载的字体
+pub struct Font(rusttype::Font<'static>);
+
+impl Font {
+    /// 读取字体文件，字体集合取第一个字体
+    pub fn open(path: &Path) -> Result<Font, Error> {
+        let data = fs::read(path).map_err(|e| Error::Input {
+            reason: Msg::FontUnreadable {
+                path: &path.display().to_string(),
+                reason: e.to_string(),
+            }
+            .to_string(),
+        })?;
+        rusttype::Font::try_from_vec(data)
+            .map(Font)
+            .ok_or_else(|| Error::Input {
+                reason: Msg::FontInvalid(&path.display().to_string()).to_string(),
+            })
+    }
+
+    /// 读取指定字体，未指定时查找系统字体，均不可用时返回 None
+    pub fn load(path: Option<&Path>) -> Result<Option<Font>, Error> {
+        match path {
+            Some(path) => Font::open(path).map(Some),
+            None => Ok(SYSTEM_FONTS
+                .iter()
+                .map(Path::new)
+                .filter(|path| path.is_file())
+                .find_map(|path| Font::open(path).ok())),
+        }
+    }
+
+    /// 计算文字尺寸 像素
+    ///
+    /// - size: 字号 像素
+    pub fn measure(&self, text: &str, size: f32) -> (u32, u32) {
+        let (w, h) = text_size(Scale::uniform(size), &self.0, text);
+        (w.max(0) as u32, h.max(0) as u32)
+    }
+
+    /// 在画布上绘制单行文字
+    ///
+    /// - x, y: 文字左上角 像素
+    /// - size: 字号 像素
+    pub fn draw(
+        &self,
+        canvas: &mut RgbaImage,
+        text: &str,
+        x: i32,
+        y: i32,
+        size: f32,
+        color: Rgba<u8>,
+    ) {
+        draw_text_mut(canvas, color, x, y, Scale::uniform(size), &self.0, text);
+    }
+
+    /// 计算文字在限定宽度内的最大字号，不超过 size
+    pub fn fit_size(&self, text: &str, size: f32, max_w: u32) -> f32 {
+        let (w, _) = self.measure(text, size);
+        if w <= max_w || w == 0 {
+            size
+        } else {
+            size * max_w as f32 / w as f32
+        }
+    }
+}
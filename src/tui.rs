@@ -0,0 +1,275 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use image_typesetting_tool::{
+    i18n::Msg,
+    input::{read_order, OrderEntry},
+    layout::Placement,
+    Error, IoSnafu,
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Layout, Rect},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, Paragraph},
+    Frame, Terminal,
+};
+use snafu::prelude::*;
+
+use crate::{arrange, date_groups, scan_sources, typeset, watch::Rendered, Arrangement, Cli};
+
+/// 界面状态
+struct App {
+    /// 全部输入图片
+    inputs: Vec<PathBuf>,
+    /// 当前排列，元素为 inputs 中的索引
+    items: Vec<usize>,
+    /// 已移除的图片及其原位置，用于撤销
+    dropped: Vec<(usize, usize)>,
+    /// 选中图片在 items 中的位置
+    selected: usize,
+    /// 按当前排列计算的每页图片位置，索引对应 items
+    pages: Vec<Vec<Placement>>,
+}
+
+impl App {
+    /// 选中图片所在页
+    fn page(&self) -> usize {
+        self.pages
+            .iter()
+            .position(|page| page.iter().any(|p| p.index == self.selected))
+            .unwrap_or(0)
+    }
+
+    /// 移动选中位置
+    fn select(&mut self, delta: isize) {
+        if self.items.is_empty() {
+            return;
+        }
+        let last = self.items.len() as isize - 1;
+        self.selected = (self.selected as isize + delta).clamp(0, last) as usize;
+    }
+
+    /// 选中上一页或下一页的第一张图片
+    fn select_page(&mut self, delta: isize) {
+        let last = self.pages.len().saturating_sub(1) as isize;
+        let page = (self.page() as isize + delta).clamp(0, last) as usize;
+        if let Some(first) = self.pages.get(page).and_then(|page| page.first()) {
+            self.selected = first.index;
+        }
+    }
+
+    /// 将选中图片前移或后移一位
+    fn move_item(&mut self, delta: isize) {
+        let target = self.selected as isize + delta;
+        if target < 0 || target >= self.items.len() as isize {
+            return;
+        }
+        self.items.swap(self.selected, target as usize);
+        self.selected = target as usize;
+    }
+
+    /// 移除选中图片
+    fn drop_item(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
+        let item = self.items.remove(self.selected);
+        self.dropped.push((self.selected, item));
+        self.selected = self.selected.min(self.items.len().saturating_sub(1));
+    }
+
+    /// 撤销最近一次移除
+    fn undo_drop(&mut self) {
+        if let Some((position, item)) = self.dropped.pop() {
+            let position = position.min(self.items.len());
+            self.items.insert(position, item);
+            self.selected = position;
+        }
+    }
+
+    /// 当前排列的图片路径
+    fn paths(&self) -> Vec<PathBuf> {
+        self.items.iter().map(|&i| self.inputs[i].clone()).collect()
+    }
+}
+
+/// 交互调整排列后绘制页面
+///
+/// 界面中按网格显示每页的图片文件名，可调整顺序或移除图片，确认后按调整后的顺序输出。
+/// 排列清单中的空白与换页不保留。
+pub fn tui(cli: &Cli) -> Result<(), Error> {
+    let inputs = match &cli.order {
+        Some(path) => read_order(Path::new(path))?
+            .into_iter()
+            .filter_map(|entry| match entry {
+                OrderEntry::Image(path, _) => Some(path),
+                _ => None,
+            })
+            .collect(),
+        None => scan_sources(cli)?,
+    };
+    let Arrangement {
+        typesetter,
+        spans,
+        dates,
+        ..
+    } = arrange(cli, &inputs, None)?;
+    let mut app = App {
+        items: (0..inputs.len()).collect(),
+        inputs,
+        dropped: Vec::new(),
+        selected: 0,
+        pages: Vec::new(),
+    };
+
+    enable_raw_mode().context(IoSnafu)?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen).context(IoSnafu)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout)).context(IoSnafu)?;
+    let confirmed = (|| loop {
+        let item_spans: Vec<_> = app.items.iter().map(|&i| spans[i]).collect();
+        let item_dates: Vec<_> = app.items.iter().map(|&i| dates[i].clone()).collect();
+        app.pages = typesetter.layout(&item_spans, &date_groups(&item_dates))?;
+        let (n_h, n_v) = (typesetter.config.n_h, typesetter.config.n_v);
+        terminal
+            .draw(|frame| draw(frame, &app, n_h, n_v))
+            .context(IoSnafu)?;
+        let Event::Key(key) = event::read().context(IoSnafu)? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Ok(false)
+            }
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+            KeyCode::Enter => return Ok(true),
+            KeyCode::Left if shift => app.move_item(-1),
+            KeyCode::Right if shift => app.move_item(1),
+            KeyCode::Char('H') | KeyCode::Char('<') => app.move_item(-1),
+            KeyCode::Char('L') | KeyCode::Char('>') => app.move_item(1),
+            KeyCode::Left | KeyCode::Char('h') => app.select(-1),
+            KeyCode::Right | KeyCode::Char('l') => app.select(1),
+            KeyCode::Up | KeyCode::Char('k') => app.select(-(n_h as isize)),
+            KeyCode::Down | KeyCode::Char('j') => app.select(n_h as isize),
+            KeyCode::PageUp => app.select_page(-1),
+            KeyCode::PageDown => app.select_page(1),
+            KeyCode::Char('d') | KeyCode::Delete => app.drop_item(),
+            KeyCode::Char('u') => app.undo_drop(),
+            _ => {}
+        }
+    })();
+    let _ = disable_raw_mode();
+    let _ = execute!(terminal.backend_mut(), LeaveAlternateScreen);
+    let _ = terminal.show_cursor();
+
+    if !confirmed? {
+        return Ok(());
+    }
+    // 按调整后的顺序作为显式输入文件重新排版
+    let mut cli = cli.clone();
+    cli.command = None;
+    cli.input = Vec::new();
+    cli.files = app
+        .paths()
+        .iter()
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect();
+    cli.files_from = None;
+    cli.order = None;
+    cli.sort = None;
+    cli.shuffle = false;
+    cli.reverse = false;
+    typeset(&cli, &mut Rendered::default(), None)
+}
+
+/// 绘制当前页的网格与状态栏
+fn draw(frame: &mut Frame, app: &App, n_h: u32, n_v: u32) {
+    let [grid_area, status_area, help_area] = Layout::vertical([
+        Constraint::Min(3),
+        Constraint::Length(1),
+        Constraint::Length(1),
+    ])
+    .areas(frame.size());
+    let page = app.page();
+    let title = format!(" {}/{} ", page + 1, app.pages.len().max(1));
+    let outer = Block::default().borders(Borders::ALL).title(title);
+    let inner = outer.inner(grid_area);
+    frame.render_widget(outer, grid_area);
+
+    let cell_w = inner.width / n_h.max(1) as u16;
+    let cell_h = inner.height / n_v.max(1) as u16;
+    for placement in app.pages.get(page).into_iter().flatten() {
+        let area = Rect {
+            x: inner.x + placement.col as u16 * cell_w,
+            y: inner.y + placement.row as u16 * cell_h,
+            width: cell_w * placement.span.cols as u16,
+            height: cell_h * placement.span.rows as u16,
+        };
+        let path = &app.inputs[app.items[placement.index]];
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let mut block = Block::default().borders(Borders::ALL);
+        if placement.index == app.selected {
+            block = block.border_style(Style::default().add_modifier(Modifier::REVERSED));
+        }
+        let label = Paragraph::new(format!("{}\n{}", placement.index + 1, name)).block(block);
+        frame.render_widget(label, area);
+    }
+
+    let status = match app.items.get(app.selected) {
+        Some(&item) => Msg::TuiStatus {
+            position: app.selected + 1,
+            total: app.items.len(),
+            dropped: app.dropped.len(),
+            path: &app.inputs[item].display().to_string(),
+        }
+        .to_string(),
+        None => String::new(),
+    };
+    frame.render_widget(Paragraph::new(Line::from(status)), status_area);
+    let help = Paragraph::new(Msg::TuiHelp.to_string())
+        .style(Style::default().add_modifier(Modifier::DIM));
+    frame.render_widget(help, help_area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_app_reorder_and_drop() {
+        let mut app = App {
+            inputs: ["a", "b", "c"].iter().map(PathBuf::from).collect(),
+            items: vec![0, 1, 2],
+            dropped: Vec::new(),
+            selected: 0,
+            pages: Vec::new(),
+        };
+        app.move_item(1);
+        assert_eq!((app.items.clone(), app.selected), (vec![1, 0, 2], 1));
+        app.move_item(-1);
+        app.move_item(-1);
+        assert_eq!((app.items.clone(), app.selected), (vec![0, 1, 2], 0));
+
+        app.select(2);
+        app.drop_item();
+        assert_eq!((app.items.clone(), app.selected), (vec![0, 1], 1));
+        app.undo_drop();
+        assert_eq!(
+            app.paths(),
+            vec![PathBuf::from("a"), "b".into(), "c".into()]
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use std::fmt::Write;
+
+use crate::{i18n::Msg, layout::Rect};
+
+/// 像素换算为厘米
+fn cm(px: u32, ppc: f64) -> f64 {
+    px as f64 / ppc
+}
+
+/// 计算同一行/列中相邻图片之间的间距 像素
+///
+/// 返回 (前一张索引, 后一张索引, 间距)
+fn gaps(rects: &[Rect], horizontal: bool) -> Vec<(usize, usize, u32)> {
+    let mut order: Vec<usize> = (0..rects.len()).collect();
+    let key = |r: &Rect| if horizontal { (r.y, r.x) } else { (r.x, r.y) };
+    order.sort_by_key(|&i| key(&rects[i]));
+
+    order
+        .windows(2)
+        .filter_map(|pair| {
+            let (a, b) = (&rects[pair[0]], &rects[pair[1]]);
+            let (lane_a, start_a, len_a) = if horizontal {
+                (a.y, a.x, a.w)
+            } else {
+                (a.x, a.y, a.h)
+            };
+            let (lane_b, start_b) = if horizontal { (b.y, b.x) } else { (b.x, b.y) };
+            (lane_a == lane_b && start_b >= start_a + len_a)
+                .then(|| (pair[0], pair[1], start_b - start_a - len_a))
+        })
+        .collect()
+}
+
+/// 生成单页的实际打印尺寸报告
+///
+/// - page: 页码
+/// - names: 本页图片文件名，与 rects 一一对应
+/// - rects: 本页图片实际绘制区域
+/// - ppc: 每厘米像素数
+pub fn page_report(page: usize, names: &[String], rects: &[Rect], ppc: f64) -> String {
+    let mut report = String::new();
+    let _ = writeln!(report, "{}", Msg::VerifyPage(page));
+    for (name, rect) in names.iter().zip(rects) {
+        let _ = writeln!(
+            report,
+            "{}: {:.2}x{:.2}cm @ ({:.2}, {:.2})cm [{}x{}px]",
+            name,
+            cm(rect.w, ppc),
+            cm(rect.h, ppc),
+            cm(rect.x, ppc),
+            cm(rect.y, ppc),
+            rect.w,
+            rect.h
+        );
+    }
+    for horizontal in [true, false] {
+        for (a, b, gap) in gaps(rects, horizontal) {
+            let _ = writeln!(
+                report,
+                "{} {} | {}: {:.2}cm",
+                Msg::VerifyGap(horizontal),
+                names[a],
+                names[b],
+                cm(gap, ppc)
+            );
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gaps() {
+        let rects = [
+            Rect {
+                x: 130,
+                y: 0,
+                w: 100,
+                h: 50,
+            },
+            Rect {
+                x: 0,
+                y: 0,
+                w: 100,
+                h: 50,
+            },
+            Rect {
+                x: 0,
+                y: 80,
+                w: 100,
+                h: 50,
+            },
+        ];
+        assert_eq!(gaps(&rects, true), vec![(1, 0, 30)]);
+        assert_eq!(gaps(&rects, false), vec![(1, 2, 30)]);
+    }
+
+    #[test]
+    fn test_page_report() {
+        let rects = [Rect {
+            x: 100,
+            y: 100,
+            w: 500,
+            h: 350,
+        }];
+        let report = page_report(0, &["a.jpg".to_string()], &rects, 100.0);
+        assert!(report.contains("a.jpg: 5.00x3.50cm @ (1.00, 1.00)cm"));
+    }
+}
@@ -0,0 +1,29 @@
+//! WebAssembly 接口
+//!
+//! 构建：`cargo build --lib --release --target wasm32-unknown-unknown`，
+//! 再用 `wasm-bindgen` 生成 JavaScript 绑定
+
+use js_sys::{Array, Uint8Array};
+use wasm_bindgen::prelude::*;
+
+use crate::memory::typeset_bytes;
+
+/// 排版编码后的图片
+///
+/// - images: `Uint8Array` 数组，每项为一张图片的文件内容
+/// - options: JSON 参数，见 [`crate::memory::MemoryOptions`]
+///
+/// 返回 `Uint8Array` 数组，每项为一页编码后的图片；出错时抛出错误信息字符串
+#[wasm_bindgen]
+pub fn typeset(images: Array, options: &str) -> Result<Array, JsValue> {
+    let images: Vec<Vec<u8>> = images
+        .iter()
+        .map(|image| Uint8Array::new(&image).to_vec())
+        .collect();
+    let images: Vec<&[u8]> = images.iter().map(Vec::as_slice).collect();
+    let pages = typeset_bytes(&images, options).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    Ok(pages
+        .iter()
+        .map(|page| Uint8Array::from(page.as_slice()))
+        .collect())
+}
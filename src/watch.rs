@@ -0,0 +1,182 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    collections::HashSet,
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::atomic::Ordering,
+    sync::mpsc,
+    time::Duration,
+};
+
+use image_typesetting_tool::{i18n::Msg, input::read_file_list, layout::Placement, Error, IoSnafu};
+use notify::{event::EventKind, RecursiveMode, Watcher};
+use snafu::prelude::*;
+
+use crate::{typeset, Cli, INTERRUPTED};
+
+/// 文件变化后等待后续事件的时间，合并连续写入
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// 上一轮的输出，用于只重绘内容变化的页面
+#[derive(Default)]
+pub struct Rendered {
+    /// 每页的内容签名
+    pub signatures: Vec<u64>,
+    /// 每页输出的文件
+    pub files: Vec<Vec<String>>,
+}
+
+impl Rendered {
+    /// 本页内容与上一轮相同，可以沿用已输出的文件
+    pub fn unchanged(&self, page: usize, signature: u64, files: &[String]) -> bool {
+        self.signatures.get(page) == Some(&signature)
+            && self.files.get(page).is_some_and(|old| old == files)
+            && files.iter().all(|file| Path::new(file).exists())
+    }
+
+    /// 记录本轮输出，删除上一轮输出中已不再使用的文件
+    pub fn update(&mut self, signatures: Vec<u64>, files: Vec<Vec<String>>) {
+        let current: HashSet<&String> = files.iter().flatten().collect();
+        for stale in self.files.iter().flatten() {
+            if !current.contains(stale) {
+                let _ = fs::remove_file(stale);
+            }
+        }
+        self.signatures = signatures;
+        self.files = files;
+    }
+}
+
+/// 计算单页的内容签名
+///
+/// 包括图片位置、目标高度，以及图片文件的路径、大小与修改时间
+///
+/// - placements: 本页图片位置
+/// - inputs: 全部输入图片
+/// - target_hs: 全部图片的目标高度 像素
+/// - extra: 页面上的其他内容，如页眉与页脚
+pub fn page_signature(
+    placements: &[Placement],
+    inputs: &[PathBuf],
+    target_hs: &[u32],
+    extra: impl Hash,
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    extra.hash(&mut hasher);
+    for placement in placements {
+        let input = &inputs[placement.index];
+        input.hash(&mut hasher);
+        (placement.row, placement.col, placement.span.cols).hash(&mut hasher);
+        (placement.span.rows, target_hs[placement.index]).hash(&mut hasher);
+        if let Ok(metadata) = fs::metadata(input) {
+            metadata.len().hash(&mut hasher);
+            metadata.modified().ok().hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+/// 需要监视的路径及是否递归
+fn watch_paths(cli: &Cli) -> Result<Vec<(PathBuf, RecursiveMode)>, Error> {
+    let listed = match cli.files_from.as_deref() {
+        Some("-") => {
+            return Err(Error::Config {
+                reason: Msg::WatchStdin.to_string(),
+            })
+        }
+        Some(path) => read_file_list(fs::File::open(path).context(IoSnafu)?, cli.null)?,
+        None => Vec::new(),
+    };
+    let mut sources: Vec<&str> = cli
+        .input
+        .iter()
+        .chain(&cli.files)
+        .chain(&listed)
+        .map(String::as_str)
+        .collect();
+    if sources.is_empty() {
+        sources.push("input");
+    }
+    let mode = match cli.recursive {
+        true => RecursiveMode::Recursive,
+        false => RecursiveMode::NonRecursive,
+    };
+    let mut paths: Vec<(PathBuf, RecursiveMode)> = sources
+        .into_iter()
+        .map(|source| (PathBuf::from(source), mode))
+        .collect();
+    // 排列清单、缩放清单、输入列表、说明文字文件修改后同样需要重新排版
+    for file in [
+        &cli.order,
+        &cli.manifest,
+        &cli.files_from,
+        &cli.captions_file,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        paths.push((PathBuf::from(file), RecursiveMode::NonRecursive));
+    }
+    Ok(paths)
+}
+
+/// 监视输入，文件新增或修改时重新排版，按 Ctrl+C 退出
+///
+/// 输出图片时只重绘内容变化的页面；输出 PDF、验证报告、layout.json 或网页时，
+/// 这些文件汇总了全部页面，每轮都完整重绘。
+pub fn watch(cli: &Cli) -> Result<(), Error> {
+    let paths = watch_paths(cli)?;
+    let output_dir = cli.output.clone().unwrap_or("output".to_string());
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).map_err(|e| Error::Config {
+        reason: e.to_string(),
+    })?;
+    for (path, mode) in &paths {
+        watcher.watch(path, *mode).map_err(|e| Error::Input {
+            reason: Msg::WatchFailed {
+                path: &path.display().to_string(),
+                reason: e.to_string(),
+            }
+            .to_string(),
+        })?;
+    }
+
+    let mut cli = cli.clone();
+    let mut rendered = Rendered::default();
+    loop {
+        match typeset(&cli, &mut rendered, None) {
+            Err(Error::Interrupted) => return Err(Error::Interrupted),
+            Err(e) => eprintln!("{e}"),
+            // 之后每轮覆盖本程序输出的文件
+            Ok(_) => {
+                cli.overwrite = true;
+                cli.clean = false;
+            }
+        }
+        if !cli.quiet {
+            println!("{}", Msg::Watching);
+        }
+        // 等待输入变化，输出目录中的变化忽略
+        let output_dir = fs::canonicalize(&output_dir).unwrap_or(PathBuf::from(&output_dir));
+        let relevant = |event: &notify::Event| {
+            !matches!(event.kind, EventKind::Access(_))
+                && event.paths.iter().any(|path| {
+                    !path.starts_with(&output_dir)
+                        && path.extension().is_none_or(|ext| ext != "part")
+                })
+        };
+        loop {
+            if INTERRUPTED.load(Ordering::SeqCst) {
+                return Ok(());
+            }
+            match rx.recv_timeout(Duration::from_millis(200)) {
+                Ok(Ok(event)) if relevant(&event) => break,
+                Ok(_) | Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+        }
+        while rx.recv_timeout(DEBOUNCE).is_ok() {}
+    }
+}
@@ -0,0 +1,177 @@
+//! 水印
+//!
+//! 将半透明的水印图片叠加在整页或每张图片上，用于交给客户确认的样张
+
+use image::{imageops, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::{i18n::Msg, layout::Rect, Config, Error};
+
+/// 居中放置时水印占区域的比例
+const CENTER_SCALE: f64 = 0.5;
+
+/// 平铺时每行水印数量
+const TILES_PER_ROW: u32 = 4;
+
+/// 水印位置
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WatermarkPos {
+    /// 居中放置一个
+    #[default]
+    Center,
+    /// 错位平铺满整个区域
+    Tile,
+}
+
+/// 水印作用范围
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WatermarkScope {
+    /// 整页一个水印
+    #[default]
+    Page,
+    /// 每张图片各一个水印
+    Image,
+}
+
+/// 水印设置
+#[derive(Debug, Clone)]
+pub struct Watermark {
+    /// 水印图片
+    pub image: RgbaImage,
+    /// 不透明度，0~1
+    pub opacity: f32,
+    pub pos: WatermarkPos,
+    pub scope: WatermarkScope,
+}
+
+impl Watermark {
+    /// 读取水印图片
+    pub fn open(
+        path: &Path,
+        opacity: f32,
+        pos: WatermarkPos,
+        scope: WatermarkScope,
+    ) -> Result<Watermark, Error> {
+        let image = image::open(path).map_err(|e| Error::Input {
+            reason: Msg::WatermarkUnreadable {
+                path: &path.display().to_string(),
+                reason: e.to_string(),
+            }
+            .to_string(),
+        })?;
+        Ok(Watermark {
+            image: image.into_rgba8(),
+            opacity: opacity.clamp(0.0, 1.0),
+            pos,
+            scope,
+        })
+    }
+
+    /// 按作用范围在页面上绘制水印
+    ///
+    /// rects 为每张图片的实际绘制区域，宽度为 0 的区域（跳过的图片）不绘制
+    pub fn apply(&self, canvas: &mut RgbaImage, rects: &[Rect], config: &Config) {
+        match self.scope {
+            WatermarkScope::Page => self.draw(
+                canvas,
+                Rect {
+                    x: 0,
+                    y: 0,
+                    w: config.paper_w_px,
+                    h: config.paper_h_px,
+                },
+            ),
+            WatermarkScope::Image => {
+                for &rect in rects.iter().filter(|rect| rect.w > 0 && rect.h > 0) {
+                    self.draw(canvas, rect);
+                }
+            }
+        }
+    }
+
+    /// 在区域内绘制水印，超出区域的部分裁去
+    fn draw(&self, canvas: &mut RgbaImage, area: Rect) {
+        let (w, h) = self.image.dimensions();
+        if w == 0 || h == 0 || area.w == 0 || area.h == 0 || self.opacity == 0.0 {
+            return;
+        }
+        // 居中时按区域较短边缩放，平铺时按每行数量缩放
+        let scale = match self.pos {
+            WatermarkPos::Center => {
+                (area.w as f64 / w as f64).min(area.h as f64 / h as f64) * CENTER_SCALE
+            }
+            WatermarkPos::Tile => area.w as f64 / TILES_PER_ROW as f64 / w as f64 / 1.5,
+        };
+        let tile_w = ((w as f64 * scale).round() as u32).max(1);
+        let tile_h = ((h as f64 * scale).round() as u32).max(1);
+        let mut tile =
+            imageops::resize(&self.image, tile_w, tile_h, imageops::FilterType::Triangle);
+        for pixel in tile.pixels_mut() {
+            pixel.0[3] = (pixel.0[3] as f32 * self.opacity).round() as u8;
+        }
+
+        let mut layer = RgbaImage::new(area.w, area.h);
+        match self.pos {
+            WatermarkPos::Center => imageops::overlay(
+                &mut layer,
+                &tile,
+                (area.w as i64 - tile_w as i64) / 2,
+                (area.h as i64 - tile_h as i64) / 2,
+            ),
+            WatermarkPos::Tile => {
+                // 水印之间留出半个水印的间距，奇数行错开半格
+                let (step_x, step_y) = (tile_w + tile_w / 2, tile_h + tile_h / 2);
+                for (row, y) in (0..area.h).step_by(step_y as usize).enumerate() {
+                    let shift = (row % 2) as i64 * step_x as i64 / 2;
+                    let mut x = -shift;
+                    while x < area.w as i64 {
+                        imageops::overlay(&mut layer, &tile, x, y as i64);
+                        x += step_x as i64;
+                    }
+                }
+            }
+        }
+        imageops::overlay(canvas, &layer, area.x as i64, area.y as i64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn test_watermark() {
+        let watermark = |pos| Watermark {
+            image: RgbaImage::from_pixel(10, 10, Rgba([0, 0, 0, 255])),
+            opacity: 0.5,
+            pos,
+            scope: WatermarkScope::Image,
+        };
+        let white = Rgba([255, 255, 255, 255]);
+        let area = Rect {
+            x: 10,
+            y: 10,
+            w: 40,
+            h: 20,
+        };
+
+        let mut canvas = RgbaImage::from_pixel(60, 40, white);
+        watermark(WatermarkPos::Center).draw(&mut canvas, area);
+        // 按较短边缩放为 10x10，居中于区域
+        let gray = canvas.get_pixel(30, 20).0[0];
+        assert!((120..=135).contains(&gray), "{}", gray);
+        assert_eq!(*canvas.get_pixel(24, 20), white);
+        assert_eq!(*canvas.get_pixel(30, 14), white);
+
+        let mut canvas = RgbaImage::from_pixel(60, 40, white);
+        watermark(WatermarkPos::Tile).draw(&mut canvas, area);
+        assert_ne!(*canvas.get_pixel(10, 10), white);
+        // 区域外不绘制
+        assert_eq!(*canvas.get_pixel(5, 5), white);
+        assert_eq!(*canvas.get_pixel(55, 35), white);
+    }
+}